@@ -0,0 +1,101 @@
+#ifndef CLIPBOARD_RS_H
+#define CLIPBOARD_RS_H
+
+/* Generated with cbindgen, see cbindgen.toml */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum ClipboardFormat {
+  CLIPBOARD_FORMAT_TEXT = 0,
+  CLIPBOARD_FORMAT_RTF = 1,
+  CLIPBOARD_FORMAT_HTML = 2,
+  CLIPBOARD_FORMAT_IMAGE = 3,
+  CLIPBOARD_FORMAT_FILES = 4,
+  CLIPBOARD_FORMAT_SVG = 5,
+  CLIPBOARD_FORMAT_MARKDOWN = 6,
+  CLIPBOARD_FORMAT_URL = 7,
+} ClipboardFormat;
+
+// The clipboard handle of the C API
+typedef struct ClipboardRs ClipboardRs;
+
+// A running watcher, stopped and freed by `clipboard_watch_stop`
+typedef struct ClipboardWatchHandle ClipboardWatchHandle;
+
+// Called on the watcher thread after every clipboard change, with the `user_data` given to `clipboard_watch`
+typedef void (*ClipboardChangeCallback)(void *user_data);
+
+// Create a clipboard handle, null on failure. Free it with `clipboard_free`
+ClipboardRs *clipboard_new(void);
+
+// # Safety
+// `clipboard` must come from `clipboard_new` and not be used afterwards
+void clipboard_free(ClipboardRs *clipboard);
+
+// The message of the last failure on this thread, null when nothing failed.
+// The string stays valid until the next failing call on this thread, don't free it
+const char *clipboard_last_error(void);
+
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`
+bool clipboard_has(const ClipboardRs *clipboard, ClipboardFormat format);
+
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`
+int32_t clipboard_clear(const ClipboardRs *clipboard);
+
+// The text in the clipboard, null when there is none. Free it with `clipboard_string_free`
+//
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`
+char *clipboard_get_text(const ClipboardRs *clipboard);
+
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`, `text` a NUL terminated UTF-8 string
+int32_t clipboard_set_text(const ClipboardRs *clipboard, const char *text);
+
+// The html in the clipboard, null when there is none. Free it with `clipboard_string_free`
+//
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`
+char *clipboard_get_html(const ClipboardRs *clipboard);
+
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`, `html` a NUL terminated UTF-8 string
+int32_t clipboard_set_html(const ClipboardRs *clipboard, const char *html);
+
+// The image in the clipboard as PNG, null when there is none. The length goes to `len`,
+// free the bytes with `clipboard_bytes_free`
+//
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`, `len` a valid pointer
+uint8_t *clipboard_get_image_png(const ClipboardRs *clipboard, size_t *len);
+
+// # Safety
+// `clipboard` must be a live handle from `clipboard_new`, `png` must point to `len` bytes
+int32_t clipboard_set_image_png(const ClipboardRs *clipboard, const uint8_t *png, size_t len);
+
+// # Safety
+// `text` must come from this library and not be used afterwards
+void clipboard_string_free(char *text);
+
+// # Safety
+// `bytes` and `len` must come from this library and not be used afterwards
+void clipboard_bytes_free(uint8_t *bytes, size_t len);
+
+// Watch the clipboard on a new thread, `callback` runs there after every change.
+// Returns null on failure, stop the watcher with `clipboard_watch_stop`
+//
+// # Safety
+// `user_data` must be usable from the watcher thread until the watcher is stopped
+ClipboardWatchHandle *clipboard_watch(ClipboardChangeCallback callback, void *user_data);
+
+// Stop a watcher and wait for its thread, no callback runs once this returns
+//
+// # Safety
+// `handle` must come from `clipboard_watch` and not be used afterwards
+void clipboard_watch_stop(ClipboardWatchHandle *handle);
+
+#endif  /* CLIPBOARD_RS_H */
@@ -0,0 +1,163 @@
+//! `clip`, a small command line front end for clipboard-rs.
+//!
+//! Only uses the public API, build it with `cargo build --features cli`.
+
+use clipboard_rs::common::RustImage;
+use clipboard_rs::event::{EventOptions, EventWriter};
+use clipboard_rs::{
+	Clipboard, ClipboardContext, ClipboardHandler, ClipboardWatcher, ClipboardWatcherContext,
+	Result, RustImageData,
+};
+use std::io::{Read, Write};
+use std::time::Duration;
+
+const USAGE: &str = "usage:
+  clip get text|html|rtf|files|image [--out FILE]
+  clip set text|html|rtf [VALUE]    (reads stdin when VALUE is missing)
+  clip set image FILE
+  clip set files PATH...
+  clip formats
+  clip clear
+  clip watch [--json]";
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	if let Err(e) = run(&args) {
+		eprintln!("clip: {}", e);
+		std::process::exit(1);
+	}
+}
+
+fn run(args: &[String]) -> Result<()> {
+	let command = match args.first() {
+		Some(c) => c.as_str(),
+		None => return Err(USAGE.into()),
+	};
+	let rest = &args[1..];
+	match command {
+		"get" => get(rest),
+		"set" => set(rest),
+		"formats" => {
+			let ctx = ClipboardContext::new()?;
+			for format in ctx.available_formats()? {
+				println!("{}", format);
+			}
+			Ok(())
+		}
+		"clear" => ClipboardContext::new()?.clear(),
+		"watch" => watch(rest.iter().any(|a| a == "--json")),
+		"help" | "-h" | "--help" => {
+			println!("{}", USAGE);
+			Ok(())
+		}
+		other => Err(format!("unknown command `{}`\n{}", other, USAGE).into()),
+	}
+}
+
+fn get(args: &[String]) -> Result<()> {
+	let kind = args.first().map(|s| s.as_str()).unwrap_or("text");
+	let out = flag_value(args, "--out");
+	let ctx = ClipboardContext::new()?;
+	let bytes = match kind {
+		"text" => ctx.get_text()?.into_bytes(),
+		"html" => ctx.get_html()?.into_bytes(),
+		"rtf" => ctx.get_rich_text()?.into_bytes(),
+		"files" => {
+			let mut files = ctx.get_files()?.join("\n");
+			files.push('\n');
+			files.into_bytes()
+		}
+		"image" => {
+			let image = ctx.get_image()?;
+			if let Some(path) = &out {
+				// the extension of --out picks the encoding
+				return image.save_to_path(path);
+			}
+			image.to_png()?.get_bytes().to_vec()
+		}
+		other => return Err(format!("can't get `{}`\n{}", other, USAGE).into()),
+	};
+	match out {
+		Some(path) => std::fs::write(path, bytes)?,
+		None => std::io::stdout().write_all(&bytes)?,
+	}
+	Ok(())
+}
+
+fn set(args: &[String]) -> Result<()> {
+	let kind = match args.first() {
+		Some(k) => k.as_str(),
+		None => return Err(USAGE.into()),
+	};
+	let ctx = ClipboardContext::new()?;
+	match kind {
+		"text" | "html" | "rtf" => {
+			let value = match args.get(1) {
+				Some(v) => v.clone(),
+				None => {
+					let mut buf = String::new();
+					std::io::stdin().read_to_string(&mut buf)?;
+					buf
+				}
+			};
+			match kind {
+				"text" => ctx.set_text(value),
+				"html" => ctx.set_html(value),
+				_ => ctx.set_rich_text(value),
+			}
+		}
+		"image" => {
+			let path = args.get(1).ok_or("set image needs a FILE")?;
+			ctx.set_image(RustImageData::from_path(path)?)
+		}
+		"files" => {
+			if args.len() < 2 {
+				return Err("set files needs at least one PATH".into());
+			}
+			let files = args[1..]
+				.iter()
+				.map(|p| {
+					std::fs::canonicalize(p)
+						.map(|p| p.to_string_lossy().into_owned())
+						.map_err(|e| format!("{}: {}", p, e))
+				})
+				.collect::<std::result::Result<Vec<_>, _>>()?;
+			ctx.set_files(files)
+		}
+		other => Err(format!("can't set `{}`\n{}", other, USAGE).into()),
+	}
+}
+
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+	let pos = args.iter().position(|a| a == flag)?;
+	args.get(pos + 1).cloned()
+}
+
+struct Printer {
+	ctx: ClipboardContext,
+}
+
+impl ClipboardHandler for Printer {
+	fn on_clipboard_change(&mut self) {
+		let formats = self.ctx.available_formats().unwrap_or_default();
+		println!("formats: {}", formats.join(", "));
+		if let Ok(text) = self.ctx.get_text() {
+			println!("text: {}", text);
+		}
+	}
+}
+
+fn watch(json: bool) -> Result<()> {
+	let ctx = ClipboardContext::new()?;
+	if json {
+		let writer = EventWriter::new(ctx, std::io::stdout(), EventOptions::default());
+		let mut watcher = ClipboardWatcherContext::new()?;
+		watcher.add_handler(writer);
+		watcher.start_watch(Duration::from_millis(500));
+	} else {
+		let mut watcher = ClipboardWatcherContext::new()?;
+		watcher.add_handler(Printer { ctx });
+		watcher.start_watch(Duration::from_millis(500));
+	}
+	Ok(())
+}
@@ -1,7 +1,41 @@
+pub mod attributed;
+pub mod cf_html;
+pub mod codec;
+#[cfg(feature = "compress")]
+pub mod compress;
+pub mod convert;
+pub(crate) mod dpi;
+#[cfg(not(feature = "image"))]
+mod encoded_image;
+pub mod snapshot;
+
+#[cfg(not(feature = "image"))]
+pub use encoded_image::RustImageData;
+#[cfg(feature = "image")]
+use image::codecs::gif::GifDecoder;
+#[cfg(feature = "image")]
+use image::codecs::jpeg::JpegEncoder;
+#[cfg(feature = "image")]
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+#[cfg(feature = "image")]
 use image::imageops::FilterType;
-use image::{DynamicImage, GenericImageView, ImageFormat, RgbaImage};
+#[cfg(feature = "image")]
+use image::{
+	metadata::Orientation, AnimationDecoder, DynamicImage, Frame, GenericImageView, ImageDecoder,
+	ImageFormat, ImageReader, RgbaImage,
+};
 use std::error::Error;
-use std::io::Cursor;
+use std::fmt;
+#[cfg(feature = "image")]
+use std::io::{BufRead, Seek};
+use std::io::{Cursor, Read};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync + 'static>>;
 
 pub trait ContentData {
@@ -10,19 +44,443 @@ pub trait ContentData {
 	fn as_bytes(&self) -> &[u8];
 
 	fn as_str(&self) -> Result<&str>;
+
+	/// en: The entries of the content: every path or url of a list, the string of a text format, nothing for binary data.
+	/// Unlike `as_str`, lists keep all their entries
+	/// zh: 内容的所有条目：列表中的每个路径或 url、文本格式的字符串，二进制数据则为空。
+	/// 与 `as_str` 不同，列表会保留所有条目
+	fn as_lines(&self) -> Vec<&str> {
+		self.as_str().map(|s| vec![s]).unwrap_or_default()
+	}
+
+	/// en: The whole content encoded as its format's MIME type, e.g. files as a `text/uri-list`.
+	/// Unlike `as_bytes`, lists keep all their entries
+	/// zh: 按格式的 MIME 类型编码的完整内容，例如文件编码为 `text/uri-list`。
+	/// 与 `as_bytes` 不同，列表会保留所有条目
+	fn to_bytes(&self) -> Result<Vec<u8>> {
+		Ok(self.as_bytes().to_vec())
+	}
 }
 
 pub trait ClipboardHandler {
 	fn on_clipboard_change(&mut self);
+
+	/// en: Called by watchers that follow several selections, e.g. the X11 watcher with `with_primary_selection`.
+	/// By default clipboard changes go to `on_clipboard_change` and the other selections are ignored
+	/// zh: 由监视多个选区的监视器调用，例如启用了 `with_primary_selection` 的 X11 监视器。
+	/// 默认将剪贴板的变化交给 `on_clipboard_change`，忽略其他选区
+	fn on_selection_change(&mut self, selection: Selection) {
+		if selection == Selection::Clipboard {
+			self.on_clipboard_change();
+		}
+	}
+
+	/// en: Called when a watcher lost its change notifications and registered for them again, e.g. on Windows
+	/// after fast user switching, an RDP reconnect or explorer restarting. A change found missing is delivered
+	/// to `on_clipboard_change` right after
+	/// zh: 监视器丢失变化通知并重新注册后调用，例如 Windows 上快速切换用户、RDP 重连或 explorer 重启之后。
+	/// 随后会将期间遗漏的变化交给 `on_clipboard_change`
+	fn on_reconnected(&mut self) {}
+
+	/// en: Called by watchers for every change, by default it goes to `on_selection_change`.
+	/// Return `ControlFlow::Break` to stop the watcher once all handlers saw the change, e.g. after
+	/// the first copy, without taking its `WatcherShutdown` into the handler
+	/// zh: 每次变化时由监视器调用，默认交给 `on_selection_change`。
+	/// 返回 `ControlFlow::Break` 可在所有处理器处理完该变化后停止监视器，例如在第一次复制之后，无需将 `WatcherShutdown` 传入处理器
+	fn on_change(&mut self, selection: Selection) -> ControlFlow<()> {
+		self.on_selection_change(selection);
+		ControlFlow::Continue(())
+	}
+}
+
+/// Hand a change to every handler, `Break` when any of them asked the watcher to stop
+pub(crate) fn notify_handlers<T: ClipboardHandler>(
+	handlers: &mut [T],
+	selection: Selection,
+) -> ControlFlow<()> {
+	let mut flow = ControlFlow::Continue(());
+	for handler in handlers {
+		if handler.on_change(selection).is_break() {
+			flow = ControlFlow::Break(());
+		}
+	}
+	flow
+}
+
+/// en: The selection a change happened in
+/// zh: 发生变化的选区
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Selection {
+	/// en: The clipboard of copy and paste
+	/// zh: 复制粘贴使用的剪贴板
+	Clipboard,
+	/// en: The X11 primary selection, the text last highlighted in any app
+	/// zh: X11 的 primary 选区，即任意应用中最后选中的文本
+	Primary,
+}
+
+/// en: Stops the watcher it was taken from when dropped or stopped
+/// zh: 被释放或调用 stop 时停止对应的监视器
+pub struct WatcherShutdown {
+	stop_signal: Sender<()>,
+}
+
+impl WatcherShutdown {
+	pub(crate) fn new(stop_signal: Sender<()>) -> Self {
+		WatcherShutdown { stop_signal }
+	}
+}
+
+impl Drop for WatcherShutdown {
+	fn drop(&mut self) {
+		let _ = self.stop_signal.send(());
+	}
+}
+
+/// en: A watcher running on a thread of its own, from `ClipboardWatcher::start_watch_thread`.
+/// Dropping the handle stops the watcher without waiting for its thread
+/// zh: 在独立线程上运行的监视器，由 `ClipboardWatcher::start_watch_thread` 返回。
+/// 释放句柄会停止监视器，但不等待其线程结束
+pub struct WatchHandle {
+	shutdown: WatcherShutdown,
+	thread: thread::JoinHandle<()>,
 }
 
+impl WatchHandle {
+	pub(crate) fn new(shutdown: WatcherShutdown, thread: thread::JoinHandle<()>) -> Self {
+		WatchHandle { shutdown, thread }
+	}
+
+	/// en: Whether the watcher has returned, e.g. after failing to start
+	/// zh: 监视器是否已返回，例如启动失败后
+	pub fn is_finished(&self) -> bool {
+		self.thread.is_finished()
+	}
+
+	/// en: Stop the watcher and wait for its thread, no handler runs once this returns
+	/// zh: 停止监视器并等待其线程结束，返回后不会再有处理器被调用
+	pub fn stop(self) {
+		self.shutdown.stop();
+		let _ = self.thread.join();
+	}
+}
+
+/// en: The pending clear of `set_with_expiry`. Dropping the handle leaves the timer running, `cancel` stops it.
+/// The clear needs the process to live until the timeout
+/// zh: `set_with_expiry` 等待中的清空。释放句柄不会停止计时，调用 `cancel` 才会停止。进程需要存活到超时才会清空
+pub struct ClipboardExpiry {
+	cancel_signal: Sender<()>,
+}
+
+impl ClipboardExpiry {
+	/// Clear `clipboard` after `ttl` if the contents in `formats` still hash to `hash`
+	pub(crate) fn spawn<C: crate::Clipboard + 'static>(
+		clipboard: C,
+		formats: Vec<ContentFormat>,
+		hash: u64,
+		ttl: Duration,
+	) -> Self {
+		let (cancel_signal, cancel_receiver) = mpsc::channel();
+		thread::spawn(move || {
+			// a timeout too far away to represent never comes
+			let deadline = match Instant::now().checked_add(ttl) {
+				Some(deadline) => deadline,
+				None => return,
+			};
+			match cancel_receiver.recv_timeout(ttl) {
+				Ok(()) => return,
+				// the handle was dropped, wait out the rest of the timeout
+				Err(RecvTimeoutError::Disconnected) => {
+					thread::sleep(deadline.saturating_duration_since(Instant::now()))
+				}
+				Err(RecvTimeoutError::Timeout) => {}
+			}
+			// newer contents from the user or another app are left alone
+			if clipboard.current_hash(&formats).ok() == Some(hash) {
+				let _ = clipboard.clear();
+			}
+		});
+		ClipboardExpiry { cancel_signal }
+	}
+
+	/// en: Keep the contents, the clipboard won't be cleared
+	/// zh: 保留内容，不再清空剪贴板
+	pub fn cancel(self) {
+		let _ = self.cancel_signal.send(());
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ClipboardContent {
 	Text(String),
 	Rtf(String),
 	Html(String),
+	Svg(String),
+	Markdown(String),
 	Image(RustImageData),
 	Files(Vec<String>),
+	Urls(Vec<Url>),
+	Color(Rgba),
+	Pdf(Vec<u8>),
 	Other(String, Vec<u8>),
+	/// en: Data in a format registered with `Clipboard::register_format`, written without looking the name up again
+	/// zh: 通过 `Clipboard::register_format` 注册的格式的数据，写入时不再查找名称
+	Custom(FormatHandle, Vec<u8>),
+}
+
+impl ClipboardContent {
+	/// Bytes the content takes in memory, images count as decoded RGBA
+	pub(crate) fn size(&self) -> usize {
+		match self {
+			ClipboardContent::Image(image) => {
+				let (width, height) = image.get_size();
+				width as usize * height as usize * 4
+			}
+			ClipboardContent::Files(files) => files.iter().map(String::len).sum(),
+			ClipboardContent::Color(_) => std::mem::size_of::<Rgba>(),
+			ClipboardContent::Pdf(data) => data.len(),
+			ClipboardContent::Urls(urls) => urls
+				.iter()
+				.map(|url| url.url.len() + url.title.as_ref().map_or(0, String::len))
+				.sum(),
+			ClipboardContent::Text(text)
+			| ClipboardContent::Rtf(text)
+			| ClipboardContent::Html(text)
+			| ClipboardContent::Svg(text)
+			| ClipboardContent::Markdown(text) => text.len(),
+			ClipboardContent::Other(_, data) | ClipboardContent::Custom(_, data) => data.len(),
+		}
+	}
+
+	/// en: A custom format holding `text` as NUL terminated UTF-16LE, the usual layout of wide strings on Windows
+	/// zh: 以 NUL 结尾的 UTF-16LE 保存 `text` 的自定义格式，即 Windows 宽字符串的常见布局
+	pub fn other_utf16(format: &str, text: &str) -> Self {
+		ClipboardContent::Other(format.to_owned(), string_to_utf16le(text, true))
+	}
+
+	/// en: Decode a custom format buffer as UTF-16LE, stopping at the first NUL and skipping a leading BOM
+	/// zh: 将自定义格式的数据按 UTF-16LE 解码，遇到第一个 NUL 停止并跳过开头的 BOM
+	pub fn as_utf16_str(&self) -> Result<String> {
+		match self {
+			ClipboardContent::Other(_, data) | ClipboardContent::Custom(_, data) => {
+				let text = decode_utf16le(data, TextDecoding::Strict)?;
+				Ok(text
+					.strip_prefix('\u{feff}')
+					.map(str::to_owned)
+					.unwrap_or(text))
+			}
+			_ => Err("only custom formats hold UTF-16 buffers".into()),
+		}
+	}
+
+	/// en: The MIME type of the content. Custom formats use their name when it maps to a MIME type,
+	/// otherwise the bytes are sniffed with `detect_mime`, e.g. to label what `get` returned for an unknown format
+	/// zh: 内容的 MIME 类型。自定义格式的名称可映射为 MIME 类型时使用名称，否则通过 `detect_mime` 嗅探数据，
+	/// 例如为 `get` 返回的未知格式标注类型
+	pub fn mime(&self) -> Option<String> {
+		match self {
+			ClipboardContent::Other(name, data) => {
+				format_mime(name).or_else(|| detect_mime(data).map(str::to_owned))
+			}
+			ClipboardContent::Custom(handle, data) => {
+				format_mime(handle.name()).or_else(|| detect_mime(data).map(str::to_owned))
+			}
+			content => Some(content.get_format().to_string()),
+		}
+	}
+
+	/// en: Decode bytes encoded as the MIME type of `format`, the reverse of `to_bytes`
+	/// zh: 解码按 `format` 的 MIME 类型编码的数据，与 `to_bytes` 互逆
+	pub fn from_bytes(format: ContentFormat, bytes: &[u8]) -> Result<Self> {
+		let text = || std::str::from_utf8(bytes).map(str::to_owned);
+		Ok(match format {
+			ContentFormat::Text => ClipboardContent::Text(text()?),
+			ContentFormat::Rtf => ClipboardContent::Rtf(text()?),
+			ContentFormat::Html => ClipboardContent::Html(text()?),
+			ContentFormat::Svg => ClipboardContent::Svg(text()?),
+			ContentFormat::Markdown => ClipboardContent::Markdown(text()?),
+			ContentFormat::Image => ClipboardContent::Image(RustImageData::from_bytes(bytes)?),
+			ContentFormat::Files => ClipboardContent::Files(uri_list_to_files(&text()?)),
+			ContentFormat::Url => ClipboardContent::Urls(moz_url_to_urls(&text()?)),
+			ContentFormat::Color => ClipboardContent::Color(Rgba::from_x_color(bytes)?),
+			ContentFormat::Pdf => ClipboardContent::Pdf(bytes.to_vec()),
+			ContentFormat::Other(format) => ClipboardContent::Other(format, bytes.to_vec()),
+			ContentFormat::Custom(handle) => ClipboardContent::Custom(handle, bytes.to_vec()),
+		})
+	}
+
+	/// en: A stable 64-bit hash of the content, the same on every platform and run.
+	/// Text is hashed without `\r` line endings and trailing NULs, images by their pixels,
+	/// or by their encoded bytes when the pixels can't be decoded
+	/// zh: 内容的稳定 64 位哈希，在所有平台和每次运行中均相同。
+	/// 文本在去除 `\r` 换行与末尾 NUL 后计算，图片按像素计算，无法解码像素时按编码后的字节计算
+	pub fn content_hash(&self) -> u64 {
+		let mut hasher = Fnv64::new();
+		hasher.write(self.get_format().to_string().as_bytes());
+		hasher.write(&[0]);
+		match self {
+			ClipboardContent::Text(text)
+			| ClipboardContent::Rtf(text)
+			| ClipboardContent::Html(text)
+			| ClipboardContent::Svg(text)
+			| ClipboardContent::Markdown(text) => {
+				hasher.write(text.trim_end_matches('\0').replace("\r\n", "\n").as_bytes())
+			}
+			ClipboardContent::Image(image) => {
+				// png, dib and tiff copies of the same picture hash alike
+				if let Ok(rgba) = image.to_rgba_pixels() {
+					hasher.write(&rgba.width.to_le_bytes());
+					hasher.write(&rgba.height.to_le_bytes());
+					hasher.write(&rgba.data);
+				} else {
+					// without a codec there are no pixels, the encoded bytes still tell images apart
+					#[cfg(not(feature = "image"))]
+					if let Some(bytes) = image.encoded_bytes() {
+						hasher.write(detect_mime(bytes).unwrap_or_default().as_bytes());
+						hasher.write(&[0]);
+						hasher.write(bytes);
+					}
+				}
+			}
+			ClipboardContent::Files(files) => {
+				for file in files {
+					hasher.write(file.as_bytes());
+					hasher.write(&[0]);
+				}
+			}
+			ClipboardContent::Urls(urls) => {
+				for url in urls {
+					hasher.write(url.url.as_bytes());
+					hasher.write(&[0]);
+					hasher.write(url.title.as_deref().unwrap_or_default().as_bytes());
+					hasher.write(&[0]);
+				}
+			}
+			ClipboardContent::Color(color) => {
+				// platforms keep colors at different precisions, 8 bits survive all of them
+				hasher.write(&color.to_rgba8())
+			}
+			ClipboardContent::Pdf(data)
+			| ClipboardContent::Other(_, data)
+			| ClipboardContent::Custom(_, data) => hasher.write(data),
+		}
+		hasher.finish()
+	}
+}
+
+/// 64-bit FNV-1a, unlike `DefaultHasher` its output never changes between Rust releases
+pub(crate) struct Fnv64(u64);
+
+impl Fnv64 {
+	pub(crate) fn new() -> Self {
+		Fnv64(0xcbf2_9ce4_8422_2325)
+	}
+
+	pub(crate) fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= u64::from(*byte);
+			self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+
+	pub(crate) fn finish(&self) -> u64 {
+		self.0
+	}
+}
+
+/// `text` appended to `current`, the separator only goes between two non-empty texts
+pub(crate) fn appended_text(current: &str, separator: &str, text: &str) -> String {
+	if current.is_empty() {
+		text.to_owned()
+	} else {
+		format!("{}{}{}", current, separator, text)
+	}
+}
+
+/// en: Whether an error comes from a `try_get*` call that found the clipboard held by another process
+/// zh: 错误是否来自 `try_get*` 调用时剪贴板正被其他进程占用
+pub fn is_would_block(error: &(dyn Error + Send + Sync + 'static)) -> bool {
+	error
+		.downcast_ref::<std::io::Error>()
+		.map_or(false, |e| e.kind() == std::io::ErrorKind::WouldBlock)
+}
+
+pub(crate) fn would_block() -> Box<dyn Error + Send + Sync + 'static> {
+	std::io::Error::new(
+		std::io::ErrorKind::WouldBlock,
+		"clipboard is held by another process",
+	)
+	.into()
+}
+
+/// en: Whether an error from `get_detailed` means the format isn't in the clipboard at all,
+/// rather than being there but failing to read or decode
+/// zh: `get_detailed` 返回的错误是否表示剪贴板中根本没有该格式，而不是存在但读取或解码失败
+pub fn is_missing_format(error: &(dyn Error + Send + Sync + 'static)) -> bool {
+	error
+		.downcast_ref::<std::io::Error>()
+		.map_or(false, |e| e.kind() == std::io::ErrorKind::NotFound)
+}
+
+pub(crate) fn missing_format(format: &ContentFormat) -> Box<dyn Error + Send + Sync + 'static> {
+	std::io::Error::new(
+		std::io::ErrorKind::NotFound,
+		format!("{} is not in the clipboard", format),
+	)
+	.into()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+	let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+	for chunk in bytes.chunks(3) {
+		let n = (u32::from(chunk[0]) << 16)
+			| (u32::from(*chunk.get(1).unwrap_or(&0)) << 8)
+			| u32::from(*chunk.get(2).unwrap_or(&0));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(BASE64[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	out
+}
+
+pub(crate) fn base64_decode(text: &str) -> Result<Vec<u8>> {
+	let mut out = Vec::with_capacity(text.len() / 4 * 3);
+	let mut n = 0u32;
+	let mut bits = 0;
+	for c in text
+		.bytes()
+		.filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+	{
+		let value = BASE64
+			.iter()
+			.position(|b| *b == c)
+			.ok_or("invalid base64")?;
+		// only the bits not yet written out are kept
+		n = ((n << 6) | value as u32) & 0xffff;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((n >> bits) as u8);
+		}
+	}
+	Ok(out)
+}
+
+/// Combine content hashes into one that doesn't depend on their order
+pub(crate) fn combine_hashes(mut hashes: Vec<u64>) -> u64 {
+	hashes.sort_unstable();
+	let mut hasher = Fnv64::new();
+	for hash in hashes {
+		hasher.write(&hash.to_le_bytes());
+	}
+	hasher.finish()
 }
 
 impl ContentData for ClipboardContent {
@@ -31,9 +489,15 @@ impl ContentData for ClipboardContent {
 			ClipboardContent::Text(_) => ContentFormat::Text,
 			ClipboardContent::Rtf(_) => ContentFormat::Rtf,
 			ClipboardContent::Html(_) => ContentFormat::Html,
+			ClipboardContent::Svg(_) => ContentFormat::Svg,
+			ClipboardContent::Markdown(_) => ContentFormat::Markdown,
 			ClipboardContent::Image(_) => ContentFormat::Image,
 			ClipboardContent::Files(_) => ContentFormat::Files,
+			ClipboardContent::Urls(_) => ContentFormat::Url,
+			ClipboardContent::Color(_) => ContentFormat::Color,
+			ClipboardContent::Pdf(_) => ContentFormat::Pdf,
 			ClipboardContent::Other(format, _) => ContentFormat::Other(format.clone()),
+			ClipboardContent::Custom(handle, _) => ContentFormat::Custom(handle.clone()),
 		}
 	}
 
@@ -42,8 +506,12 @@ impl ContentData for ClipboardContent {
 			ClipboardContent::Text(data) => data.as_bytes(),
 			ClipboardContent::Rtf(data) => data.as_bytes(),
 			ClipboardContent::Html(data) => data.as_bytes(),
+			ClipboardContent::Svg(data) => data.as_bytes(),
+			ClipboardContent::Markdown(data) => data.as_bytes(),
 			// dynamic image is not supported to as bytes
 			ClipboardContent::Image(_) => &[],
+			ClipboardContent::Color(_) => &[],
+			ClipboardContent::Pdf(data) => data.as_slice(),
 			ClipboardContent::Files(data) => {
 				// use first file path as data
 				if let Some(path) = data.first() {
@@ -52,7 +520,15 @@ impl ContentData for ClipboardContent {
 					&[]
 				}
 			}
-			ClipboardContent::Other(_, data) => data.as_slice(),
+			ClipboardContent::Urls(data) => {
+				// use first url as data
+				if let Some(url) = data.first() {
+					url.url.as_bytes()
+				} else {
+					&[]
+				}
+			}
+			ClipboardContent::Other(_, data) | ClipboardContent::Custom(_, data) => data.as_slice(),
 		}
 	}
 
@@ -61,7 +537,11 @@ impl ContentData for ClipboardContent {
 			ClipboardContent::Text(data) => Ok(data),
 			ClipboardContent::Rtf(data) => Ok(data),
 			ClipboardContent::Html(data) => Ok(data),
+			ClipboardContent::Svg(data) => Ok(data),
+			ClipboardContent::Markdown(data) => Ok(data),
 			ClipboardContent::Image(_) => Err("can't convert image to string".into()),
+			ClipboardContent::Color(_) => Err("can't convert color to string".into()),
+			ClipboardContent::Pdf(_) => Err("can't convert pdf to string".into()),
 			ClipboardContent::Files(data) => {
 				// use first file path as data
 				if let Some(path) = data.first() {
@@ -70,44 +550,1006 @@ impl ContentData for ClipboardContent {
 					Err("content is empty".into())
 				}
 			}
-			ClipboardContent::Other(_, data) => std::str::from_utf8(data).map_err(|e| e.into()),
+			ClipboardContent::Urls(data) => {
+				// use first url as data
+				if let Some(url) = data.first() {
+					Ok(&url.url)
+				} else {
+					Err("content is empty".into())
+				}
+			}
+			ClipboardContent::Other(_, data) | ClipboardContent::Custom(_, data) => {
+				std::str::from_utf8(data).map_err(|e| e.into())
+			}
+		}
+	}
+
+	fn as_lines(&self) -> Vec<&str> {
+		match self {
+			ClipboardContent::Files(files) => files.iter().map(String::as_str).collect(),
+			ClipboardContent::Urls(urls) => urls.iter().map(|url| url.url.as_str()).collect(),
+			_ => self.as_str().map(|s| vec![s]).unwrap_or_default(),
+		}
+	}
+
+	fn to_bytes(&self) -> Result<Vec<u8>> {
+		match self {
+			ClipboardContent::Image(image) => Ok(image.to_png()?.get_bytes().to_vec()),
+			ClipboardContent::Color(color) => Ok(color.to_x_color()),
+			ClipboardContent::Files(files) => Ok(files_to_uri_list(files).into_bytes()),
+			ClipboardContent::Urls(urls) => Ok(urls_to_moz_url(urls).into_bytes()),
+			_ => Ok(self.as_bytes().to_vec()),
 		}
 	}
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ContentFormat {
 	Text,
 	Rtf,
 	Html,
+	Svg,
+	Markdown,
 	Image,
 	Files,
+	Url,
+	Color,
+	Pdf,
 	Other(String),
+	/// en: A format registered with `Clipboard::register_format`, `get`, `set` and `has` use its platform id
+	/// instead of resolving the name on every call
+	/// zh: 通过 `Clipboard::register_format` 注册的格式，`get`、`set` 与 `has` 直接使用其平台 id，
+	/// 不必每次调用都解析名称
+	Custom(FormatHandle),
+}
+
+/// en: The platforms with their own clipboard format names
+/// zh: 拥有各自剪贴板格式名称的平台
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Platform {
+	Windows,
+	MacOs,
+	/// en: X11 atom names, also used on the BSDs
+	/// zh: X11 atom 名称，BSD 系统同样使用
+	Linux,
+}
+
+impl Platform {
+	/// en: The platform this build runs on
+	/// zh: 当前构建运行的平台
+	pub fn current() -> Self {
+		if cfg!(target_os = "windows") {
+			Platform::Windows
+		} else if cfg!(target_os = "macos") {
+			Platform::MacOs
+		} else {
+			Platform::Linux
+		}
+	}
+}
+
+// (format, windows, macos, linux) names of the formats with a dedicated variant
+const PLATFORM_NAMES: &[(ContentFormat, &str, &str, &str)] = &[
+	(
+		ContentFormat::Text,
+		"CF_UNICODETEXT",
+		"public.utf8-plain-text",
+		"UTF8_STRING",
+	),
+	(
+		ContentFormat::Rtf,
+		"Rich Text Format",
+		"public.rtf",
+		"text/rtf",
+	),
+	(
+		ContentFormat::Html,
+		"HTML Format",
+		"public.html",
+		"text/html",
+	),
+	(
+		ContentFormat::Svg,
+		"image/svg+xml",
+		"public.svg-image",
+		"image/svg+xml",
+	),
+	(
+		ContentFormat::Markdown,
+		"text/markdown",
+		"net.daringfireball.markdown",
+		"text/markdown",
+	),
+	(ContentFormat::Image, "PNG", "public.png", "image/png"),
+	(
+		ContentFormat::Files,
+		"CF_HDROP",
+		"public.file-url",
+		"text/uri-list",
+	),
+	(
+		ContentFormat::Url,
+		"UniformResourceLocatorW",
+		"public.url",
+		"text/x-moz-url",
+	),
+	(
+		ContentFormat::Color,
+		"application/x-color",
+		"com.apple.cocoa.pasteboard.color",
+		"application/x-color",
+	),
+	(
+		ContentFormat::Pdf,
+		"Portable Document Format",
+		"com.adobe.pdf",
+		"application/pdf",
+	),
+];
+
+impl ContentFormat {
+	/// en: The name of this format on the current platform, e.g. `HTML Format` on Windows for `Html`,
+	/// `None` if the format has no name there
+	/// zh: 该格式在当前平台上的名称，例如 `Html` 在 Windows 上为 `HTML Format`，没有名称时为 `None`
+	pub fn to_platform_name(&self) -> Option<String> {
+		self.to_platform_name_for(Platform::current())
+	}
+
+	/// en: The name of this format on the given platform, `Other` and `Custom` formats keep their name,
+	/// `None` if the format has no name there
+	/// zh: 该格式在指定平台上的名称，`Other` 与 `Custom` 格式保持原名，没有名称时为 `None`
+	pub fn to_platform_name_for(&self, platform: Platform) -> Option<String> {
+		match self {
+			ContentFormat::Other(name) => return Some(name.clone()),
+			ContentFormat::Custom(handle) => return Some(handle.name().to_owned()),
+			_ => {}
+		}
+		let (_, windows, macos, linux) =
+			PLATFORM_NAMES.iter().find(|(format, ..)| format == self)?;
+		let name = match platform {
+			Platform::Windows => windows,
+			Platform::MacOs => macos,
+			Platform::Linux => linux,
+		};
+		Some(name.to_string())
+	}
+
+	/// en: The format a name of the current platform stands for, unknown names become `Other`
+	/// zh: 当前平台上的名称对应的格式，未知名称为 `Other`
+	pub fn from_platform_name(name: &str) -> Self {
+		ContentFormat::from_platform_name_for(name, Platform::current())
+	}
+
+	/// en: The format a name of the given platform stands for, unknown names become `Other`
+	/// zh: 指定平台上的名称对应的格式，未知名称为 `Other`
+	pub fn from_platform_name_for(name: &str, platform: Platform) -> Self {
+		PLATFORM_NAMES
+			.iter()
+			.find(|(_, windows, macos, linux)| {
+				let platform_name = match platform {
+					Platform::Windows => windows,
+					Platform::MacOs => macos,
+					Platform::Linux => linux,
+				};
+				*platform_name == name
+			})
+			.map(|(format, ..)| format.clone())
+			.unwrap_or_else(|| ContentFormat::Other(name.to_owned()))
+	}
+}
+
+/// en: Formats are written as MIME types, `Other` and `Custom` formats as their name
+/// zh: 格式以 MIME 类型表示，`Other` 与 `Custom` 格式为其名称
+impl fmt::Display for ContentFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			ContentFormat::Text => "text/plain",
+			ContentFormat::Rtf => "text/rtf",
+			ContentFormat::Html => "text/html",
+			ContentFormat::Svg => "image/svg+xml",
+			ContentFormat::Markdown => "text/markdown",
+			ContentFormat::Image => "image/png",
+			ContentFormat::Files => "text/uri-list",
+			ContentFormat::Url => "text/x-moz-url",
+			ContentFormat::Color => "application/x-color",
+			ContentFormat::Pdf => "application/pdf",
+			ContentFormat::Other(name) => name,
+			ContentFormat::Custom(handle) => handle.name(),
+		};
+		f.write_str(name)
+	}
+}
+
+/// en: Parses what `Display` writes, plus the variant names like `html`. Anything else becomes `Other`
+/// zh: 解析 `Display` 的输出以及 `html` 等变体名称，其他名称均为 `Other`
+impl FromStr for ContentFormat {
+	type Err = Box<dyn Error + Send + Sync>;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let format = match s.to_ascii_lowercase().as_str() {
+			"" => return Err("empty format name".into()),
+			"text" | "text/plain" => ContentFormat::Text,
+			"rtf" | "text/rtf" => ContentFormat::Rtf,
+			"html" | "text/html" => ContentFormat::Html,
+			"svg" | "image/svg+xml" => ContentFormat::Svg,
+			"markdown" | "text/markdown" => ContentFormat::Markdown,
+			"image" | "image/png" => ContentFormat::Image,
+			"files" | "text/uri-list" => ContentFormat::Files,
+			"url" | "text/x-moz-url" => ContentFormat::Url,
+			"color" | "application/x-color" => ContentFormat::Color,
+			"pdf" | "application/pdf" => ContentFormat::Pdf,
+			_ => ContentFormat::Other(s.to_owned()),
+		};
+		Ok(format)
+	}
+}
+
+/// en: A link with an optional title, e.g. copied from the address bar of a browser
+/// zh: 带有可选标题的链接，例如从浏览器地址栏复制的链接
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Url {
+	pub url: String,
+	pub title: Option<String>,
+}
+
+impl Url {
+	pub fn new(url: &str) -> Self {
+		Url {
+			url: url.to_owned(),
+			title: None,
+		}
+	}
+
+	pub fn with_title(url: &str, title: &str) -> Self {
+		Url {
+			url: url.to_owned(),
+			title: Some(title.to_owned()),
+		}
+	}
+}
+
+/// en: An sRGB color with straight alpha, every component from 0.0 to 1.0
+/// zh: sRGB 颜色，alpha 未预乘，各分量取值为 0.0 到 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rgba {
+	pub r: f32,
+	pub g: f32,
+	pub b: f32,
+	pub a: f32,
 }
 
+impl Rgba {
+	pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+		Rgba { r, g, b, a }
+	}
+
+	pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+		Rgba::new(
+			f32::from(r) / 255.0,
+			f32::from(g) / 255.0,
+			f32::from(b) / 255.0,
+			f32::from(a) / 255.0,
+		)
+	}
+
+	/// en: The components rounded to 8 bits, out of range values are clamped
+	/// zh: 四舍五入到 8 位的各分量，超出范围的值会被截断
+	pub fn to_rgba8(&self) -> [u8; 4] {
+		[self.r, self.g, self.b, self.a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+	}
+
+	/// The `application/x-color` layout of GTK, four native endian 16-bit components
+	pub(crate) fn to_x_color(self) -> Vec<u8> {
+		[self.r, self.g, self.b, self.a]
+			.iter()
+			.flat_map(|c| ((c.clamp(0.0, 1.0) * 65535.0).round() as u16).to_ne_bytes())
+			.collect()
+	}
+
+	/// Read `application/x-color` as written by GTK (GIMP) or Qt (Krita), which streams a
+	/// QColor: a spec byte and five big endian 16-bit values, alpha, red, green, blue and padding
+	pub(crate) fn from_x_color(data: &[u8]) -> Result<Rgba> {
+		let component = |bytes: [u8; 2], big_endian: bool| {
+			let value = if big_endian {
+				u16::from_be_bytes(bytes)
+			} else {
+				u16::from_ne_bytes(bytes)
+			};
+			f32::from(value) / 65535.0
+		};
+		match data {
+			[r0, r1, g0, g1, b0, b1, a0, a1] => Ok(Rgba::new(
+				component([*r0, *r1], false),
+				component([*g0, *g1], false),
+				component([*b0, *b1], false),
+				component([*a0, *a1], false),
+			)),
+			// spec 1 is QColor::Rgb, the other specs aren't sent by the paint programs
+			[1, a0, a1, r0, r1, g0, g1, b0, b1, _, _] => Ok(Rgba::new(
+				component([*r0, *r1], true),
+				component([*g0, *g1], true),
+				component([*b0, *b1], true),
+				component([*a0, *a1], true),
+			)),
+			_ => Err("unknown application/x-color layout".into()),
+		}
+	}
+}
+
+/// en: Where the clipboard contents came from and how they should be treated, as far as the platform tells.
+/// Fields the platform doesn't provide keep their default
+/// zh: 剪贴板内容的来源及处理方式，以平台提供的信息为准。平台未提供的字段保持默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentMetadata {
+	/// en: The app that wrote the contents: the bundle id from `org.nspasteboard.source` on macOS, the executable of the clipboard owner on Windows
+	/// zh: 写入内容的应用：macOS 上为 `org.nspasteboard.source` 中的 bundle id，Windows 上为剪贴板所有者的可执行文件
+	pub source: Option<String>,
+	/// en: Written by an app on its own rather than copied by the user, `org.nspasteboard.AutoGeneratedType` on macOS
+	/// zh: 由应用自动写入而非用户复制，macOS 上对应 `org.nspasteboard.AutoGeneratedType`
+	pub auto_generated: bool,
+	/// en: Sensitive, e.g. a password, and not to be recorded: `org.nspasteboard.ConcealedType` on macOS,
+	/// `ExcludeClipboardContentFromMonitorProcessing` on Windows
+	/// zh: 敏感内容（例如密码），不应被记录：macOS 上对应 `org.nspasteboard.ConcealedType`，
+	/// Windows 上对应 `ExcludeClipboardContentFromMonitorProcessing`
+	pub concealed: bool,
+	/// en: About to be replaced, e.g. an intermediate state of the writing app, `org.nspasteboard.TransientType` on macOS
+	/// zh: 即将被替换的内容（例如写入应用的中间状态），macOS 上对应 `org.nspasteboard.TransientType`
+	pub transient: bool,
+}
+
+/// en: A format currently in the clipboard, as reported by the platform
+/// zh: 剪贴板中当前存在的格式，由平台提供
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatInfo {
+	/// en: The name `available_formats` reports: the registered or `CF_*` name on Windows, the UTType on macOS, the atom name on X11
+	/// zh: 与 `available_formats` 相同的名称：Windows 上为注册名或 `CF_*` 名称，macOS 上为 UTType，X11 上为 atom 名称
+	pub name: String,
+	/// en: The format id on Windows or the atom on X11, `None` on macOS where the UTType is the identifier
+	/// zh: Windows 上的格式 id 或 X11 上的 atom，macOS 上为 `None`，因为 UTType 即为标识
+	pub platform_id: Option<u32>,
+	/// en: The matching MIME type, when known
+	/// zh: 对应的 MIME 类型（若已知）
+	pub mime: Option<String>,
+	/// en: Size of the data in bytes, when the platform can tell without fetching it
+	/// zh: 数据的字节大小（若平台无需读取数据即可得知）
+	pub size_hint: Option<usize>,
+}
+
+/// en: A custom format registered once with `register_format`, reading and writing through it skips the name lookup
+/// zh: 通过 `register_format` 注册一次的自定义格式，使用它读写数据可省去名称查找
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatHandle {
+	name: String,
+	platform_id: Option<u32>,
+}
+
+impl FormatHandle {
+	pub(crate) fn new(name: &str, platform_id: Option<u32>) -> Self {
+		FormatHandle {
+			name: name.to_owned(),
+			platform_id,
+		}
+	}
+
+	/// en: The name the format was registered with
+	/// zh: 注册时使用的格式名称
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// en: The format id on Windows or the atom on X11, `None` on macOS where the UTType is the identifier
+	/// zh: Windows 上的格式 id 或 X11 上的 atom，macOS 上为 `None`，因为 UTType 即为标识
+	pub fn platform_id(&self) -> Option<u32> {
+		self.platform_id
+	}
+}
+
+/// en: Every format in the clipboard with the start of its data, see `Clipboard::dump`. `Display` prints it
+/// as a hex dump for bug reports
+/// zh: 剪贴板中的所有格式及其数据开头部分，见 `Clipboard::dump`。`Display` 以十六进制形式输出，便于提交问题
+#[derive(Debug, Clone)]
+pub struct ClipboardDump {
+	pub formats: Vec<FormatDump>,
+}
+
+/// en: One format of a `ClipboardDump`
+/// zh: `ClipboardDump` 中的一个格式
+#[derive(Debug, Clone)]
+pub struct FormatDump {
+	pub info: FormatInfo,
+	/// en: The size of the data, `None` when it couldn't be read
+	/// zh: 数据的大小，无法读取时为 `None`
+	pub size: Option<usize>,
+	/// en: The first bytes of the data
+	/// zh: 数据开头的字节
+	pub preview: Vec<u8>,
+	/// en: Why the data couldn't be read
+	/// zh: 数据无法读取的原因
+	pub error: Option<String>,
+}
+
+impl FormatDump {
+	/// en: The preview decoded as UTF-8, invalid sequences replaced
+	/// zh: 按 UTF-8 解码的预览，无效序列会被替换
+	pub fn preview_text(&self) -> String {
+		String::from_utf8_lossy(&self.preview).into_owned()
+	}
+}
+
+impl fmt::Display for ClipboardDump {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.formats.is_empty() {
+			return writeln!(f, "clipboard is empty");
+		}
+		for (index, format) in self.formats.iter().enumerate() {
+			write!(f, "[{}] {}", index, format.info.name)?;
+			if let Some(id) = format.info.platform_id {
+				write!(f, " #{}", id)?;
+			}
+			if let Some(mime) = &format.info.mime {
+				write!(f, " ({})", mime)?;
+			}
+			match (&format.error, format.size) {
+				(Some(error), _) => writeln!(f, ", error: {}", error)?,
+				(None, Some(size)) => writeln!(f, ", {} bytes", size)?,
+				(None, None) => writeln!(f)?,
+			}
+			for (row, chunk) in format.preview.chunks(16).enumerate() {
+				write!(f, "    {:04x}  ", row * 16)?;
+				for i in 0..16 {
+					match chunk.get(i) {
+						Some(byte) => write!(f, "{:02x} ", byte)?,
+						None => write!(f, "   ")?,
+					}
+				}
+				let ascii: String = chunk
+					.iter()
+					.map(|b| {
+						if b.is_ascii_graphic() || *b == b' ' {
+							*b as char
+						} else {
+							'.'
+						}
+					})
+					.collect();
+				writeln!(f, " |{}|", ascii)?;
+			}
+			if !format.preview.is_empty() {
+				writeln!(f, "    utf-8: {:?}", format.preview_text())?;
+			}
+		}
+		Ok(())
+	}
+}
+
+// well known platform format names and their MIME types
+const FORMAT_MIMES: &[(&str, &str)] = &[
+	("CF_TEXT", "text/plain"),
+	("CF_OEMTEXT", "text/plain"),
+	("CF_UNICODETEXT", "text/plain;charset=utf-16le"),
+	("CF_DIB", "image/bmp"),
+	("CF_DIBV5", "image/bmp"),
+	("CF_HDROP", "text/uri-list"),
+	("HTML Format", "text/html"),
+	("Rich Text Format", "text/rtf"),
+	("PNG", "image/png"),
+	("GIF", "image/gif"),
+	("JFIF", "image/jpeg"),
+	("UniformResourceLocatorW", "text/x-uri"),
+	("public.utf8-plain-text", "text/plain;charset=utf-8"),
+	("public.utf16-plain-text", "text/plain;charset=utf-16"),
+	("public.rtf", "text/rtf"),
+	("public.html", "text/html"),
+	("public.png", "image/png"),
+	("public.tiff", "image/tiff"),
+	("public.jpeg", "image/jpeg"),
+	("com.compuserve.gif", "image/gif"),
+	("public.svg-image", "image/svg+xml"),
+	("public.file-url", "text/uri-list"),
+	("public.url", "text/x-uri"),
+	("net.daringfireball.markdown", "text/markdown"),
+	("UTF8_STRING", "text/plain;charset=utf-8"),
+	("STRING", "text/plain;charset=iso-8859-1"),
+	("TEXT", "text/plain"),
+];
+
+/// The MIME type of a platform format name, names that already are MIME types map to themselves
+pub(crate) fn format_mime(name: &str) -> Option<String> {
+	if let Some((_, mime)) = FORMAT_MIMES.iter().find(|(format, _)| *format == name) {
+		return Some(mime.to_string());
+	}
+	let (kind, subtype) = name.split_once('/')?;
+	let is_token = |s: &str| !s.is_empty() && !s.contains(char::is_whitespace);
+	if is_token(kind) && is_token(subtype) {
+		Some(name.to_owned())
+	} else {
+		None
+	}
+}
+
+// signatures at the start of the data, checked in order
+const MAGIC_MIMES: &[(&[u8], &str)] = &[
+	(b"\x89PNG\r\n\x1a\n", "image/png"),
+	(b"\xff\xd8\xff", "image/jpeg"),
+	(b"GIF87a", "image/gif"),
+	(b"GIF89a", "image/gif"),
+	(b"II*\0", "image/tiff"),
+	(b"MM\0*", "image/tiff"),
+	(b"\0\0\x01\0", "image/x-icon"),
+	(b"%PDF-", "application/pdf"),
+	(b"{\\rtf", "text/rtf"),
+	(b"PK\x03\x04", "application/zip"),
+	(b"PK\x05\x06", "application/zip"),
+	(b"\x1f\x8b", "application/gzip"),
+	(b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+	(
+		b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+		"application/x-ole-storage",
+	),
+	(b"bplist00", "application/x-bplist"),
+	(b"OggS", "audio/ogg"),
+	(b"fLaC", "audio/flac"),
+	(b"ID3", "audio/mpeg"),
+];
+
+/// en: A best guess of the MIME type of `bytes` from their content, for custom formats whose name tells nothing.
+/// Binary formats are recognized by their signature, markup by its first tag
+/// zh: 根据数据内容推测 `bytes` 的 MIME 类型，适用于名称无法说明类型的自定义格式。
+/// 二进制格式按签名识别，标记语言按第一个标签识别
+pub fn detect_mime(bytes: &[u8]) -> Option<&'static str> {
+	if let Some((_, mime)) = MAGIC_MIMES
+		.iter()
+		.find(|(magic, _)| bytes.starts_with(magic))
+	{
+		return Some(*mime);
+	}
+	// "BM" alone would match plenty of text, the reserved header fields must be zero too
+	if bytes.len() >= 14 && bytes.starts_with(b"BM") && bytes[6..10] == [0; 4] {
+		return Some("image/bmp");
+	}
+	if bytes.len() >= 12 && bytes.starts_with(b"RIFF") {
+		match &bytes[8..12] {
+			b"WEBP" => return Some("image/webp"),
+			b"WAVE" => return Some("audio/wav"),
+			_ => {}
+		}
+	}
+	if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+		return Some(match &bytes[8..12] {
+			b"heic" | b"heix" | b"mif1" => "image/heic",
+			b"avif" => "image/avif",
+			_ => "video/mp4",
+		});
+	}
+	// markup, after a BOM and leading whitespace
+	let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+	let start = text.iter().position(|b| !b.is_ascii_whitespace())?;
+	let head =
+		String::from_utf8_lossy(&text[start..text.len().min(start + 512)]).to_ascii_lowercase();
+	if head.starts_with("<!doctype html") || head.starts_with("<html") {
+		Some("text/html")
+	} else if head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg")) {
+		Some("image/svg+xml")
+	} else if head.starts_with("<?xml") {
+		Some("application/xml")
+	} else if head.starts_with("version:") && head.contains("starthtml:") {
+		// CF_HTML, html behind a header of offsets
+		Some("text/html")
+	} else {
+		None
+	}
+}
+
+/// en: Whether the files in the clipboard are meant to be copied or moved when pasted
+/// zh: 剪贴板中的文件在粘贴时应被复制还是移动
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileOperation {
+	Copy,
+	Cut,
+}
+
+/// en: A file that only exists in the clipboard, e.g. an Outlook attachment or a file inside a zip folder. Its bytes are read on demand
+/// zh: 仅存在于剪贴板中的文件，例如 Outlook 附件或压缩文件夹中的文件，内容按需读取
+pub struct VirtualFile {
+	pub name: String,
+	/// en: `None` when the source didn't tell the size up front
+	/// zh: 来源未提供大小时为 `None`
+	pub size: Option<u64>,
+	pub reader: Box<dyn Read>,
+}
+
+/// en: Produces the bytes of a virtual file, called each time a paste target asks for them
+/// zh: 生成虚拟文件的内容，每次粘贴目标请求时调用
+pub type VirtualFileProvider = dyn Fn() -> Result<Box<dyn Read + Send>> + Send + Sync;
+
+/// en: A file offered to paste targets without a path on disk, its bytes come from a provider when pasted
+/// zh: 提供给粘贴目标的无磁盘路径文件，粘贴时由提供者生成内容
+#[derive(Clone)]
+pub struct VirtualFileSource {
+	pub name: String,
+	pub size: Option<u64>,
+	pub provider: Arc<VirtualFileProvider>,
+}
+
+impl VirtualFileSource {
+	pub fn new<F>(name: &str, provider: F) -> Self
+	where
+		F: Fn() -> Result<Box<dyn Read + Send>> + Send + Sync + 'static,
+	{
+		VirtualFileSource {
+			name: name.to_owned(),
+			size: None,
+			provider: Arc::new(provider),
+		}
+	}
+
+	/// en: Tell paste targets the size up front, so they can show progress
+	/// zh: 预先告知粘贴目标文件大小，以便显示进度
+	pub fn with_size(mut self, size: u64) -> Self {
+		self.size = Some(size);
+		self
+	}
+
+	/// en: A virtual file backed by bytes already in memory
+	/// zh: 由内存中的字节构成的虚拟文件
+	pub fn from_bytes(name: &str, bytes: Vec<u8>) -> Self {
+		let size = bytes.len() as u64;
+		let bytes = Arc::new(bytes);
+		VirtualFileSource::new(name, move || {
+			Ok(Box::new(Cursor::new(bytes.to_vec())) as Box<dyn Read + Send>)
+		})
+		.with_size(size)
+	}
+}
+
+/// en: How to handle text that isn't valid in its declared encoding
+/// zh: 如何处理不符合其声明编码的文本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDecoding {
+	/// en: Fail on invalid sequences
+	/// zh: 遇到无效序列时返回错误
+	Strict,
+	/// en: Replace invalid sequences with U+FFFD
+	/// zh: 将无效序列替换为 U+FFFD
+	Lossy,
+}
+
+/// Decode UTF-8 bytes, stopping at the first NUL character
+#[cfg_attr(any(windows, target_os = "macos"), allow(dead_code))]
+pub(crate) fn decode_utf8(bytes: &[u8], decoding: TextDecoding) -> Result<String> {
+	let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+	match decoding {
+		TextDecoding::Strict => std::str::from_utf8(&bytes[..end])
+			.map(str::to_owned)
+			.map_err(|e| format!("Invalid UTF-8 text: {}", e).into()),
+		TextDecoding::Lossy => Ok(String::from_utf8_lossy(&bytes[..end]).into_owned()),
+	}
+}
+
+/// The UTF-16 code units of little endian bytes, up to the first NUL character
+fn utf16le_units(bytes: &[u8]) -> Vec<u16> {
+	bytes
+		.chunks_exact(2)
+		.map(|c| u16::from_le_bytes([c[0], c[1]]))
+		.take_while(|c| *c != 0)
+		.collect()
+}
+
+/// Decode UTF-16LE bytes with the given handling of invalid text, stopping at the first NUL character
+pub(crate) fn decode_utf16le(bytes: &[u8], decoding: TextDecoding) -> Result<String> {
+	let wide = utf16le_units(bytes);
+	match decoding {
+		TextDecoding::Strict => {
+			String::from_utf16(&wide).map_err(|e| format!("Invalid UTF-16 text: {}", e).into())
+		}
+		TextDecoding::Lossy => Ok(String::from_utf16_lossy(&wide)),
+	}
+}
+
+/// Decode ISO Latin-1 bytes, every byte maps to the code point of the same value
+#[cfg_attr(any(windows, target_os = "macos"), allow(dead_code))]
+pub(crate) fn latin1_to_string(bytes: &[u8]) -> String {
+	bytes
+		.iter()
+		.take_while(|b| **b != 0)
+		.map(|b| *b as char)
+		.collect()
+}
+
+/// Decode UTF-16LE bytes, stopping at the first NUL character
+pub(crate) fn utf16le_to_string(bytes: &[u8]) -> String {
+	String::from_utf16_lossy(&utf16le_units(bytes))
+}
+
+/// Encode a string as UTF-16LE bytes, optionally NUL terminated
+pub(crate) fn string_to_utf16le(text: &str, nul_terminated: bool) -> Vec<u8> {
+	let mut bytes: Vec<u8> = text.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+	if nul_terminated {
+		bytes.extend_from_slice(&[0, 0]);
+	}
+	bytes
+}
+
+const FILE_URI_PREFIX: &str = "file://";
+
+/// A `text/uri-list` of the files, paths become `file://` URIs and lines end with CRLF as RFC 2483 asks
+fn files_to_uri_list(files: &[String]) -> String {
+	files
+		.iter()
+		.map(|file| {
+			if file.starts_with(FILE_URI_PREFIX) {
+				file.to_owned()
+			} else {
+				path_to_file_uri(Path::new(file))
+			}
+		})
+		.map(|uri| uri + "\r\n")
+		.collect()
+}
+
+/// The entries of a `text/uri-list`, local `file://` URIs become paths and comments are skipped
+fn uri_list_to_files(list: &str) -> Vec<String> {
+	list.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|line| match file_uri_to_path(line) {
+			Some(path) => path.to_string_lossy().into_owned(),
+			None => line.to_owned(),
+		})
+		.collect()
+}
+
+/// A `text/x-moz-url`, each url followed by its title on the next line
+pub(crate) fn urls_to_moz_url(urls: &[Url]) -> String {
+	urls.iter()
+		.map(|url| format!("{}\n{}", url.url, url.title.as_deref().unwrap_or_default()))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+pub(crate) fn moz_url_to_urls(text: &str) -> Vec<Url> {
+	let lines: Vec<&str> = text.lines().collect();
+	lines
+		.chunks(2)
+		.filter(|pair| !pair[0].trim().is_empty())
+		.map(|pair| Url {
+			url: pair[0].trim().to_owned(),
+			title: pair
+				.get(1)
+				.filter(|title| !title.is_empty())
+				.map(|title| title.to_string()),
+		})
+		.collect()
+}
+
+/// en: Convert a `file://` URI to a local path, decoding percent escapes. Returns `None` for other schemes or remote hosts
+/// zh: 将 `file://` URI 转换为本地路径，并解码百分号转义。其他协议或远程主机返回 `None`
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+	let rest = uri.trim().strip_prefix(FILE_URI_PREFIX)?;
+	// local files have an empty authority or `localhost`
+	let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+	if !rest.starts_with('/') {
+		return None;
+	}
+	Some(bytes_to_path(percent_decode(rest)))
+}
+
+/// en: Convert a local path to a `file://` URI, escaping spaces, `#`, `%` and non ASCII bytes
+/// zh: 将本地路径转换为 `file://` URI，转义空格、`#`、`%` 以及非 ASCII 字节
+pub fn path_to_file_uri(path: &Path) -> String {
+	let mut uri = String::from(FILE_URI_PREFIX);
+	let bytes = path_to_bytes(path);
+	if bytes.first() != Some(&b'/') {
+		uri.push('/');
+	}
+	for b in bytes {
+		// same set of characters as glib's g_filename_to_uri keeps unescaped
+		if b.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&b) {
+			uri.push(b as char);
+		} else {
+			uri.push_str(&format!("%{:02X}", b));
+		}
+	}
+	uri
+}
+
+fn percent_decode(s: &str) -> Vec<u8> {
+	let bytes = s.as_bytes();
+	let mut res = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i] == b'%' && i + 2 < bytes.len() {
+			let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+			if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+				res.push(b);
+				i += 3;
+				continue;
+			}
+		}
+		res.push(bytes[i]);
+		i += 1;
+	}
+	res
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+	use std::os::unix::ffi::OsStringExt;
+	PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+	let path = String::from_utf8_lossy(&bytes).into_owned();
+	// `/C:/Users` -> `C:/Users`
+	let is_drive = path.len() >= 3 && path.as_bytes()[2] == b':';
+	if is_drive {
+		PathBuf::from(&path[1..])
+	} else {
+		PathBuf::from(path)
+	}
+}
+
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+	use std::os::unix::ffi::OsStrExt;
+	path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+	path.to_string_lossy().replace('\\', "/").into_bytes()
+}
+
+#[cfg(feature = "image")]
+#[derive(Clone, PartialEq)]
 pub struct RustImageData {
 	width: u32,
 	height: u32,
 	data: Option<DynamicImage>,
+	/// the original bytes of a gif image, kept so animations survive a round trip
+	gif: Option<Vec<u8>>,
+	dpi: Option<(f32, f32)>,
+}
+
+/// en: Compression level of the PNG encoder
+/// zh: PNG 编码器的压缩级别
+#[derive(Clone, Copy)]
+pub enum PngCompression {
+	Default,
+	Fast,
+	Best,
+}
+
+/// en: Options used when encoding an image, trading size against fidelity and speed
+/// zh: 图片编码选项，用于在体积、质量和速度之间取舍
+#[derive(Clone, Copy)]
+pub struct EncodeOptions {
+	/// JPEG quality, from 1 (smallest) to 100 (best)
+	pub jpeg_quality: u8,
+	pub png_compression: PngCompression,
+}
+
+impl Default for EncodeOptions {
+	fn default() -> Self {
+		EncodeOptions {
+			jpeg_quality: 75,
+			png_compression: PngCompression::Default,
+		}
+	}
 }
 
 /// 此处的 RustImageBuffer 已经是带有图片格式的字节流，例如 png,jpeg;
+#[derive(Clone, PartialEq, Eq)]
 pub struct RustImageBuffer(Vec<u8>);
 
+// the pixels and encoded bytes would flood the output, only describe them
+#[cfg(feature = "image")]
+impl fmt::Debug for RustImageData {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RustImageData")
+			.field("width", &self.width)
+			.field("height", &self.height)
+			.field("color", &self.data.as_ref().map(|image| image.color()))
+			.field("gif_bytes", &self.gif.as_ref().map(|gif| gif.len()))
+			.field("dpi", &self.dpi)
+			.finish()
+	}
+}
+
+// images are stored encoded, a gif keeps its original bytes so the animation survives
+#[cfg(feature = "serde")]
+impl serde::Serialize for RustImageData {
+	fn serialize<S: serde::Serializer>(
+		&self,
+		serializer: S,
+	) -> std::result::Result<S::Ok, S::Error> {
+		if self.is_empty() {
+			return serializer.serialize_bytes(&[]);
+		}
+		// without the `image` crate the bytes the image was read as are kept whatever their format
+		#[cfg(not(feature = "image"))]
+		if let Some(bytes) = self.encoded_bytes() {
+			return serializer.serialize_bytes(bytes);
+		}
+		match self.gif_bytes() {
+			Some(gif) => serializer.serialize_bytes(gif),
+			None => {
+				let png = self.to_png().map_err(serde::ser::Error::custom)?;
+				serializer.serialize_bytes(png.get_bytes())
+			}
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RustImageData {
+	fn deserialize<D: serde::Deserializer<'de>>(
+		deserializer: D,
+	) -> std::result::Result<Self, D::Error> {
+		let bytes: Vec<u8> = serde::Deserialize::deserialize(deserializer)?;
+		if bytes.is_empty() {
+			return Ok(RustImageData::empty());
+		}
+		RustImageData::from_bytes(&bytes).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for RustImageBuffer {
+	fn serialize<S: serde::Serializer>(
+		&self,
+		serializer: S,
+	) -> std::result::Result<S::Ok, S::Error> {
+		serializer.serialize_bytes(&self.0)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RustImageBuffer {
+	fn deserialize<D: serde::Deserializer<'de>>(
+		deserializer: D,
+	) -> std::result::Result<Self, D::Error> {
+		serde::Deserialize::deserialize(deserializer).map(RustImageBuffer)
+	}
+}
+
+impl fmt::Debug for RustImageBuffer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "RustImageBuffer({} bytes)", self.0.len())
+	}
+}
+
 pub trait RustImage: Sized {
 	/// create an empty image
 	fn empty() -> Self;
 
 	fn is_empty(&self) -> bool;
 
-	/// Read image from file path
+	/// Read image from file path.
+	/// The EXIF orientation is applied, so the pixels are stored upright
 	fn from_path(path: &str) -> Result<Self>;
 
-	/// Create a new image from a byte slice
+	/// Create a new image from a byte slice, the format is detected from the content.
+	/// The EXIF orientation is applied, so the pixels are stored upright.
+	/// WebP and AVIF are decoded when the `webp` and `avif-decode` features are enabled
 	fn from_bytes(bytes: &[u8]) -> Result<Self>;
 
+	#[cfg(feature = "image")]
 	fn from_dynamic_image(image: DynamicImage) -> Self;
 
+	/// en: Create an image from raw RGBA8 pixels, `pixels.len()` must be `width * height * 4`
+	/// zh: 从 RGBA8 像素数据创建图片，`pixels.len()` 必须为 `width * height * 4`
+	fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self>;
+
 	/// width and height
 	fn get_size(&self) -> (u32, u32);
 
@@ -123,23 +1565,117 @@ pub trait RustImage: Sized {
 
 	/// en: Adjust the size of the image without retaining the aspect ratio
 	/// zh: 调整图片大小，不保留长宽比
+	#[cfg(feature = "image")]
 	fn resize(&self, width: u32, height: u32, filter: FilterType) -> Result<Self>;
 
+	/// en: Crop a rectangle out of the image, the rectangle must start inside the image and is clamped to its bounds
+	/// zh: 裁剪图片中的矩形区域，矩形起点必须在图片内，超出部分会被截断
+	#[cfg(feature = "image")]
+	fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Result<Self>;
+
+	/// en: Rotate the image 90 degrees clockwise
+	/// zh: 顺时针旋转 90 度
+	#[cfg(feature = "image")]
+	fn rotate90(&self) -> Result<Self>;
+
+	/// en: Rotate the image 180 degrees
+	/// zh: 旋转 180 度
+	#[cfg(feature = "image")]
+	fn rotate180(&self) -> Result<Self>;
+
+	/// en: Rotate the image 270 degrees clockwise
+	/// zh: 顺时针旋转 270 度
+	#[cfg(feature = "image")]
+	fn rotate270(&self) -> Result<Self>;
+
+	/// en: Flip the image horizontally
+	/// zh: 水平翻转
+	#[cfg(feature = "image")]
+	fn flip_horizontal(&self) -> Result<Self>;
+
+	/// en: Flip the image vertically
+	/// zh: 垂直翻转
+	#[cfg(feature = "image")]
+	fn flip_vertical(&self) -> Result<Self>;
+
+	/// en: Convert the image to grayscale, the alpha channel is kept
+	/// zh: 转为灰度图，保留透明通道
+	#[cfg(feature = "image")]
+	fn grayscale(&self) -> Result<Self>;
+
 	fn to_jpeg(&self) -> Result<RustImageBuffer>;
 
+	/// en: Convert to jpeg format with the given quality (1-100)
+	/// zh: 以指定质量(1-100)转为 jpeg 格式
+	fn to_jpeg_with_quality(&self, quality: u8) -> Result<RustImageBuffer>;
+
+	/// en: Convert to jpeg format, using `options.jpeg_quality`
+	/// zh: 转为 jpeg 格式，使用 `options.jpeg_quality`
+	fn to_jpeg_with_options(&self, options: &EncodeOptions) -> Result<RustImageBuffer>;
+
 	/// en: Convert to png format, the returned image is a new image, and the data itself will not be modified
 	/// zh: 转为 png 格式,返回的为新的图片，本身数据不会修改
 	fn to_png(&self) -> Result<RustImageBuffer>;
 
+	/// en: Convert to png format, using `options.png_compression`
+	/// zh: 转为 png 格式，使用 `options.png_compression`
+	fn to_png_with_options(&self, options: &EncodeOptions) -> Result<RustImageBuffer>;
+
 	fn to_bitmap(&self) -> Result<RustImageBuffer>;
 
+	/// en: Convert to lossless webp format, requires the `webp` feature
+	/// zh: 转为无损 webp 格式，需要开启 `webp` feature
+	#[cfg(feature = "webp")]
+	fn to_webp(&self) -> Result<RustImageBuffer>;
+
+	/// en: Convert to avif format, requires the `avif` feature
+	/// zh: 转为 avif 格式，需要开启 `avif` feature
+	#[cfg(feature = "avif")]
+	fn to_avif(&self) -> Result<RustImageBuffer>;
+
 	fn save_to_path(&self, path: &str) -> Result<()>;
 
+	#[cfg(feature = "image")]
 	fn get_dynamic_image(&self) -> Result<DynamicImage>;
 
+	#[cfg(feature = "image")]
 	fn to_rgba8(&self) -> Result<RgbaImage>;
+
+	/// en: Borrow the pixels without copying, returns `None` if the image is empty or not stored as RGBA8
+	/// zh: 不拷贝地借用像素数据，图片为空或不是 RGBA8 格式时返回 `None`
+	#[cfg(feature = "image")]
+	fn as_rgba8(&self) -> Option<&RgbaImage>;
+
+	/// en: Take the underlying image without cloning it
+	/// zh: 取出内部的图片，不进行拷贝
+	#[cfg(feature = "image")]
+	fn into_dynamic_image(self) -> Result<DynamicImage>;
+
+	/// en: The horizontal and vertical resolution in dots per inch, read from the PNG, JPEG, BMP or TIFF
+	/// header, a DIB on Windows, or the point size of an `NSImage` on macOS, e.g. `(144.0, 144.0)`
+	/// for a Retina screenshot. `None` when the source doesn't say. Kept by crop, flips and rotations,
+	/// dropped by scaling, and written to the pHYs chunk by `to_png`
+	/// zh: 以每英寸点数表示的水平与垂直分辨率，读取自 PNG、JPEG、BMP 或 TIFF 的文件头、Windows 上的 DIB，
+	/// 或 macOS 上 `NSImage` 的点尺寸，例如 Retina 截图为 `(144.0, 144.0)`。来源未注明时为 `None`。
+	/// 裁剪、翻转与旋转会保留，缩放会丢弃，`to_png` 会将其写入 pHYs 块
+	fn dpi(&self) -> Option<(f32, f32)>;
+
+	/// en: The same image declaring another resolution, e.g. to mark a screenshot as Retina before
+	/// writing it
+	/// zh: 声明另一分辨率的同一图片，例如在写入前将截图标记为 Retina
+	fn with_dpi(self, dpi: Option<(f32, f32)>) -> Self;
+
+	/// en: The original bytes of a GIF image, kept so animations are not flattened to the first frame
+	/// zh: GIF 图片的原始字节，保留下来以免动画被压平为第一帧
+	fn gif_bytes(&self) -> Option<&[u8]>;
+
+	/// en: Decode all frames of an animated GIF, other images return a single frame
+	/// zh: 解码动图 GIF 的所有帧，其他图片返回单帧
+	#[cfg(feature = "image")]
+	fn frames(&self) -> Result<Vec<Frame>>;
 }
 
+#[cfg(feature = "image")]
 macro_rules! image_to_format {
 	($name:ident, $format:expr) => {
 		fn $name(&self) -> Result<RustImageBuffer> {
@@ -155,12 +1691,74 @@ macro_rules! image_to_format {
 	};
 }
 
+#[cfg(feature = "image")]
+macro_rules! image_transform {
+	($name:ident, $method:ident) => {
+		fn $name(&self) -> Result<Self> {
+			match &self.data {
+				Some(image) => Ok(Self::from_dynamic_image(image.$method()).with_dpi(self.dpi)),
+				None => Err("image is empty".into()),
+			}
+		}
+	};
+	// a quarter turn swaps the horizontal and vertical resolution
+	($name:ident, $method:ident, swap_dpi) => {
+		fn $name(&self) -> Result<Self> {
+			match &self.data {
+				Some(image) => Ok(Self::from_dynamic_image(image.$method())
+					.with_dpi(self.dpi.map(|(x, y)| (y, x)))),
+				None => Err("image is empty".into()),
+			}
+		}
+	};
+}
+
+#[cfg(feature = "image")]
+impl RustImageData {
+	/// en: Decode an image and scale it down to fit in `max_width` x `max_height` in one step, keeping the aspect ratio.
+	/// The full size image is dropped as soon as it's scaled and never converted to RGBA, which bounds the memory enormous images take
+	/// zh: 一步完成图片解码并按比例缩小到 `max_width` x `max_height` 之内。完整尺寸的图片在缩放后立即释放，
+	/// 且不会转换为 RGBA，从而限制超大图片占用的内存
+	pub fn from_bytes_scaled(bytes: &[u8], max_width: u32, max_height: u32) -> Result<Self> {
+		let (image, _) =
+			decode_oriented(ImageReader::new(Cursor::new(bytes)).with_guessed_format()?)?;
+		Ok(Self::from_dynamic_image(fit_within(
+			image, max_width, max_height,
+		)))
+	}
+
+	/// en: The pixels as RGBA8, for code that has to work with or without the `image` feature
+	/// zh: RGBA8 格式的像素，供无论是否开启 `image` feature 都需运行的代码使用
+	pub fn to_rgba_pixels(&self) -> Result<codec::RgbaPixels> {
+		let rgba = self.to_rgba8()?;
+		Ok(codec::RgbaPixels {
+			width: rgba.width(),
+			height: rgba.height(),
+			data: rgba.into_raw(),
+		})
+	}
+}
+
+/// Scale an image down to fit in the bounds, smaller images are left alone
+#[cfg(feature = "image")]
+pub(crate) fn fit_within(image: DynamicImage, max_width: u32, max_height: u32) -> DynamicImage {
+	let (width, height) = image.dimensions();
+	if width <= max_width && height <= max_height {
+		image
+	} else {
+		image.thumbnail(max_width, max_height)
+	}
+}
+
+#[cfg(feature = "image")]
 impl RustImage for RustImageData {
 	fn empty() -> Self {
 		RustImageData {
 			width: 0,
 			height: 0,
 			data: None,
+			gif: None,
+			dpi: None,
 		}
 	}
 
@@ -169,22 +1767,26 @@ impl RustImage for RustImageData {
 	}
 
 	fn from_path(path: &str) -> Result<Self> {
-		let image = image::open(path)?;
-		let (width, height) = image.dimensions();
-		Ok(RustImageData {
-			width,
-			height,
-			data: Some(image),
-		})
+		let bytes = std::fs::read(path)?;
+		Self::from_bytes(&bytes)
 	}
 
 	fn from_bytes(bytes: &[u8]) -> Result<Self> {
-		let image = image::load_from_memory(bytes)?;
+		let (image, transposed) =
+			decode_oriented(ImageReader::new(Cursor::new(bytes)).with_guessed_format()?)?;
 		let (width, height) = image.dimensions();
+		// the header describes the stored pixels, before the orientation was applied
+		let dpi = dpi::read_dpi(bytes).map(|(x, y)| if transposed { (y, x) } else { (x, y) });
+		let gif = match image::guess_format(bytes) {
+			Ok(ImageFormat::Gif) => Some(bytes.to_vec()),
+			_ => None,
+		};
 		Ok(RustImageData {
 			width,
 			height,
 			data: Some(image),
+			gif,
+			dpi,
 		})
 	}
 
@@ -194,9 +1796,17 @@ impl RustImage for RustImageData {
 			width,
 			height,
 			data: Some(image),
+			gif: None,
+			dpi: None,
 		}
 	}
 
+	fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+		let image = RgbaImage::from_raw(width, height, pixels)
+			.ok_or("pixel buffer size does not match width and height")?;
+		Ok(Self::from_dynamic_image(DynamicImage::ImageRgba8(image)))
+	}
+
 	fn get_size(&self) -> (u32, u32) {
 		(self.width, self.height)
 	}
@@ -209,6 +1819,8 @@ impl RustImage for RustImageData {
 					width: resized.width(),
 					height: resized.height(),
 					data: Some(resized),
+					gif: None,
+					dpi: None,
 				})
 			}
 			None => Err("image is empty".into()),
@@ -223,18 +1835,97 @@ impl RustImage for RustImageData {
 					width: resized.width(),
 					height: resized.height(),
 					data: Some(resized),
+					gif: None,
+					dpi: None,
 				})
 			}
 			None => Err("image is empty".into()),
 		}
 	}
 
+	fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Result<Self> {
+		match &self.data {
+			Some(image) => {
+				if x >= image.width() || y >= image.height() || width == 0 || height == 0 {
+					return Err("crop area is out of the image".into());
+				}
+				Ok(
+					Self::from_dynamic_image(image.crop_imm(x, y, width, height))
+						.with_dpi(self.dpi),
+				)
+			}
+			None => Err("image is empty".into()),
+		}
+	}
+
+	image_transform!(rotate90, rotate90, swap_dpi);
+
+	image_transform!(rotate180, rotate180);
+
+	image_transform!(rotate270, rotate270, swap_dpi);
+
+	image_transform!(flip_horizontal, fliph);
+
+	image_transform!(flip_vertical, flipv);
+
+	image_transform!(grayscale, grayscale);
+
 	image_to_format!(to_jpeg, ImageFormat::Jpeg);
 
-	image_to_format!(to_png, ImageFormat::Png);
+	fn to_png(&self) -> Result<RustImageBuffer> {
+		self.to_png_with_options(&EncodeOptions::default())
+	}
 
 	image_to_format!(to_bitmap, ImageFormat::Bmp);
 
+	#[cfg(feature = "webp")]
+	image_to_format!(to_webp, ImageFormat::WebP);
+
+	#[cfg(feature = "avif")]
+	image_to_format!(to_avif, ImageFormat::Avif);
+
+	fn to_jpeg_with_quality(&self, quality: u8) -> Result<RustImageBuffer> {
+		self.to_jpeg_with_options(&EncodeOptions {
+			jpeg_quality: quality,
+			..EncodeOptions::default()
+		})
+	}
+
+	fn to_jpeg_with_options(&self, options: &EncodeOptions) -> Result<RustImageBuffer> {
+		match &self.data {
+			Some(image) => {
+				let mut bytes: Vec<u8> = Vec::new();
+				let encoder =
+					JpegEncoder::new_with_quality(&mut bytes, options.jpeg_quality.clamp(1, 100));
+				// jpeg has no alpha channel
+				DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+				Ok(RustImageBuffer(bytes))
+			}
+			None => Err("image is empty".into()),
+		}
+	}
+
+	fn to_png_with_options(&self, options: &EncodeOptions) -> Result<RustImageBuffer> {
+		match &self.data {
+			Some(image) => {
+				let compression = match options.png_compression {
+					PngCompression::Default => CompressionType::Default,
+					PngCompression::Fast => CompressionType::Fast,
+					PngCompression::Best => CompressionType::Best,
+				};
+				let mut bytes: Vec<u8> = Vec::new();
+				let encoder =
+					PngEncoder::new_with_quality(&mut bytes, compression, PngFilterType::Adaptive);
+				image.write_with_encoder(encoder)?;
+				Ok(RustImageBuffer(match self.dpi {
+					Some(dpi) => dpi::png_with_dpi(&bytes, dpi),
+					None => bytes,
+				}))
+			}
+			None => Err("image is empty".into()),
+		}
+	}
+
 	fn save_to_path(&self, path: &str) -> Result<()> {
 		match &self.data {
 			Some(image) => {
@@ -258,6 +1949,53 @@ impl RustImage for RustImageData {
 			None => Err("image is empty".into()),
 		}
 	}
+
+	fn as_rgba8(&self) -> Option<&RgbaImage> {
+		self.data.as_ref().and_then(|image| image.as_rgba8())
+	}
+
+	fn into_dynamic_image(self) -> Result<DynamicImage> {
+		self.data.ok_or_else(|| "image is empty".into())
+	}
+
+	fn dpi(&self) -> Option<(f32, f32)> {
+		self.dpi
+	}
+
+	fn with_dpi(self, dpi: Option<(f32, f32)>) -> Self {
+		RustImageData { dpi, ..self }
+	}
+
+	fn gif_bytes(&self) -> Option<&[u8]> {
+		self.gif.as_deref()
+	}
+
+	fn frames(&self) -> Result<Vec<Frame>> {
+		if let Some(gif) = &self.gif {
+			let decoder = GifDecoder::new(Cursor::new(gif.as_slice()))?;
+			return Ok(decoder.into_frames().collect_frames()?);
+		}
+		Ok(vec![Frame::new(self.to_rgba8()?)])
+	}
+}
+
+/// Decode an image and rotate or flip it according to its EXIF orientation,
+/// so that images from cameras don't end up on the clipboard sideways.
+/// Also tells whether the orientation swapped the axes, the resolution in the header is then the other way around.
+#[cfg(feature = "image")]
+fn decode_oriented<R: BufRead + Seek>(reader: ImageReader<R>) -> Result<(DynamicImage, bool)> {
+	let mut decoder = reader.into_decoder()?;
+	let orientation = decoder.orientation()?;
+	let mut image = DynamicImage::from_decoder(decoder)?;
+	image.apply_orientation(orientation);
+	let transposed = matches!(
+		orientation,
+		Orientation::Rotate90
+			| Orientation::Rotate270
+			| Orientation::Rotate90FlipH
+			| Orientation::Rotate270FlipH
+	);
+	Ok((image, transposed))
 }
 
 impl RustImageBuffer {
@@ -0,0 +1,151 @@
+//! en: A simplified model of rich text, runs of text sharing the same attributes, written out as
+//! RTF and html so editors don't need an RTF parser of their own
+//!
+//! zh: 简化的富文本模型，由属性相同的文本片段组成，可写出为 RTF 与 html，编辑器无需自带 RTF 解析器
+
+use crate::common::convert::{escape_html, escape_rtf, rtf_to_attributed};
+use crate::common::Rgba;
+
+/// en: A piece of text with the same attributes throughout
+/// zh: 属性一致的一段文本
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextRun {
+	pub text: String,
+	pub bold: bool,
+	pub italic: bool,
+	pub underline: bool,
+	/// en: The url the text links to
+	/// zh: 文本链接到的 url
+	pub link: Option<String>,
+	/// en: The text color, `None` for the default color
+	/// zh: 文本颜色，`None` 表示默认颜色
+	pub color: Option<Rgba>,
+}
+
+impl TextRun {
+	pub fn new(text: &str) -> Self {
+		TextRun {
+			text: text.to_owned(),
+			..Default::default()
+		}
+	}
+}
+
+/// en: Rich text as a list of runs, line breaks are `\n` inside the runs
+/// zh: 由片段列表组成的富文本，换行为片段中的 `\n`
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributedText {
+	pub runs: Vec<TextRun>,
+}
+
+impl AttributedText {
+	/// en: Text without any attributes
+	/// zh: 不带任何属性的文本
+	pub fn plain(text: &str) -> Self {
+		AttributedText {
+			runs: vec![TextRun::new(text)],
+		}
+	}
+
+	/// en: Read an RTF document, keeping bold, italic and underline. Links and colors need the
+	/// platform's reader, see `Clipboard::get_attributed_text`
+	/// zh: 读取 RTF 文档，保留粗体、斜体与下划线。链接与颜色需要平台的解析器，见 `Clipboard::get_attributed_text`
+	pub fn from_rtf(rtf: &str) -> Self {
+		rtf_to_attributed(rtf)
+	}
+
+	/// en: The text of all runs without attributes
+	/// zh: 所有片段去除属性后的文本
+	pub fn text(&self) -> String {
+		self.runs.iter().map(|run| run.text.as_str()).collect()
+	}
+
+	/// en: An RTF document of the runs, links become HYPERLINK fields
+	/// zh: 由片段生成的 RTF 文档，链接写为 HYPERLINK 字段
+	pub fn to_rtf(&self) -> String {
+		// the color table in order of first use, \cf0 is the default color
+		let mut colors: Vec<[u8; 4]> = Vec::new();
+		for color in self.runs.iter().filter_map(|run| run.color) {
+			let rgba = color.to_rgba8();
+			if !colors.contains(&rgba) {
+				colors.push(rgba);
+			}
+		}
+		let mut rtf =
+			String::from("{\\rtf1\\ansi\\ansicpg1252\\deff0{\\fonttbl{\\f0\\fswiss Helvetica;}}");
+		if !colors.is_empty() {
+			rtf.push_str("{\\colortbl;");
+			for [r, g, b, _] in &colors {
+				rtf.push_str(&format!("\\red{}\\green{}\\blue{};", r, g, b));
+			}
+			rtf.push('}');
+		}
+		rtf.push_str("\\uc1\n");
+		for run in &self.runs {
+			let mut controls = String::new();
+			for (on, word) in [
+				(run.bold, "\\b"),
+				(run.italic, "\\i"),
+				(run.underline, "\\ul"),
+			] {
+				if on {
+					controls.push_str(word);
+				}
+			}
+			if let Some(color) = run.color {
+				let rgba = color.to_rgba8();
+				if let Some(index) = colors.iter().position(|c| *c == rgba) {
+					controls.push_str(&format!("\\cf{}", index + 1));
+				}
+			}
+			// the space ends the last control word, without any it would be text
+			let separator = if controls.is_empty() { "" } else { " " };
+			let group = format!("{{{}{}{}}}", controls, separator, escape_rtf(&run.text));
+			match &run.link {
+				Some(url) => rtf.push_str(&format!(
+					"{{\\field{{\\*\\fldinst{{HYPERLINK \"{}\"}}}}{{\\fldrslt {}}}}}",
+					escape_rtf(url),
+					group
+				)),
+				None => rtf.push_str(&group),
+			}
+		}
+		rtf.push('}');
+		rtf
+	}
+
+	/// en: An html fragment of the runs, colors become inline styles
+	/// zh: 由片段生成的 html 片段，颜色写为内联样式
+	pub fn to_html(&self) -> String {
+		let mut html = String::new();
+		for run in &self.runs {
+			let mut open = String::new();
+			let mut close = String::new();
+			let mut wrap = |tag: &str, attributes: String| {
+				open.push_str(&format!("<{}{}>", tag, attributes));
+				close.insert_str(0, &format!("</{}>", tag));
+			};
+			if let Some(url) = &run.link {
+				wrap("a", format!(" href=\"{}\"", escape_html(url)));
+			}
+			if let Some(color) = run.color {
+				let [r, g, b, _] = color.to_rgba8();
+				wrap(
+					"span",
+					format!(" style=\"color:#{:02x}{:02x}{:02x}\"", r, g, b),
+				);
+			}
+			for (on, tag) in [(run.bold, "b"), (run.italic, "i"), (run.underline, "u")] {
+				if on {
+					wrap(tag, String::new());
+				}
+			}
+			html.push_str(&open);
+			html.push_str(&escape_html(&run.text).replace('\n', "<br>"));
+			html.push_str(&close);
+		}
+		html
+	}
+}
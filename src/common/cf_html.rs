@@ -0,0 +1,164 @@
+//! en: CF_HTML, the html clipboard format of Windows. The payload starts with description
+//! headers holding byte offsets of the html document and of the selected fragment, see
+//! <https://learn.microsoft.com/en-us/windows/win32/dataxchg/html-clipboard-format>
+//!
+//! zh: CF_HTML，Windows 的 html 剪贴板格式。内容以描述头开始，描述头中记录了 html 文档和所选片段的字节偏移
+
+use crate::common::Result;
+
+const VERSION: &str = "Version";
+const START_HTML: &str = "StartHTML";
+const END_HTML: &str = "EndHTML";
+const START_FRAGMENT: &str = "StartFragment";
+const END_FRAGMENT: &str = "EndFragment";
+const SOURCE_URL: &str = "SourceURL";
+const START_FRAGMENT_MARKER: &str = "<!--StartFragment-->";
+const END_FRAGMENT_MARKER: &str = "<!--EndFragment-->";
+
+/// en: A parsed CF_HTML payload
+/// zh: 解析后的 CF_HTML 内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfHtml {
+	/// en: The whole payload, including the description headers
+	/// zh: 完整内容，包括描述头
+	pub raw: String,
+	/// en: The html document between `StartHTML` and `EndHTML`
+	/// zh: `StartHTML` 与 `EndHTML` 之间的 html 文档
+	pub html: String,
+	/// en: The selected part between `StartFragment` and `EndFragment`
+	/// zh: `StartFragment` 与 `EndFragment` 之间被选中的部分
+	pub fragment: String,
+	/// en: The page the html was copied from
+	/// zh: html 的来源页面
+	pub source_url: Option<String>,
+}
+
+/// en: Build a CF_HTML payload from an html fragment or a whole document
+/// zh: 由 html 片段或完整文档构建 CF_HTML 内容
+pub fn build(html: &str) -> String {
+	build_with_source_url(html, None)
+}
+
+/// en: Build a CF_HTML payload, with the page the html comes from
+/// zh: 构建 CF_HTML 内容，并附带 html 的来源页面
+pub fn build_with_source_url(html: &str, source_url: Option<&str>) -> String {
+	let (document, fragment_start, fragment_end) = wrap_fragment(html);
+	// the offsets are zero padded to a fixed width, so the header length doesn't depend on them
+	let header =
+		|start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+			let mut header = format!(
+				"{}:0.9\r\n{}:{:010}\r\n{}:{:010}\r\n{}:{:010}\r\n{}:{:010}\r\n",
+				VERSION,
+				START_HTML,
+				start_html,
+				END_HTML,
+				end_html,
+				START_FRAGMENT,
+				start_fragment,
+				END_FRAGMENT,
+				end_fragment
+			);
+			if let Some(url) = source_url {
+				header.push_str(&format!("{}:{}\r\n", SOURCE_URL, url));
+			}
+			header
+		};
+	let header_len = header(0, 0, 0, 0).len();
+	let mut payload = header(
+		header_len,
+		header_len + document.len(),
+		header_len + fragment_start,
+		header_len + fragment_end,
+	);
+	payload.push_str(&document);
+	payload
+}
+
+/// Wrap the html into a document with fragment markers,
+/// returns the document and the byte range of the fragment inside it
+fn wrap_fragment(html: &str) -> (String, usize, usize) {
+	// already marked, e.g. a payload read from the clipboard
+	if let (Some(start), Some(end)) = (
+		html.find(START_FRAGMENT_MARKER),
+		html.rfind(END_FRAGMENT_MARKER),
+	) {
+		let start = start + START_FRAGMENT_MARKER.len();
+		if start <= end {
+			return (html.to_owned(), start, end);
+		}
+	}
+	let lower = html.to_ascii_lowercase();
+	let trimmed = lower.trim_start();
+	if trimmed.starts_with("<html") || trimmed.starts_with("<!doctype") {
+		// mark the content of <body>, or the whole document without one
+		let body_start = lower
+			.find("<body")
+			.and_then(|i| lower[i..].find('>').map(|j| i + j + 1));
+		let body_end = lower.rfind("</body>");
+		let (start, end) = match (body_start, body_end) {
+			(Some(start), Some(end)) if start <= end => (start, end),
+			_ => (0, html.len()),
+		};
+		let document = [
+			&html[..start],
+			START_FRAGMENT_MARKER,
+			&html[start..end],
+			END_FRAGMENT_MARKER,
+			&html[end..],
+		]
+		.concat();
+		let fragment_start = start + START_FRAGMENT_MARKER.len();
+		return (document, fragment_start, fragment_start + end - start);
+	}
+	let prefix = ["<html>\r\n<body>\r\n", START_FRAGMENT_MARKER].concat();
+	let suffix = [END_FRAGMENT_MARKER, "\r\n</body>\r\n</html>"].concat();
+	let document = [prefix.as_str(), html, suffix.as_str()].concat();
+	(document, prefix.len(), prefix.len() + html.len())
+}
+
+/// en: Parse a CF_HTML payload, the offsets are checked against the payload
+/// zh: 解析 CF_HTML 内容，会校验偏移是否有效
+pub fn parse(data: &[u8]) -> Result<CfHtml> {
+	// some writers add a trailing NUL
+	let end = data.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+	let data = &data[..end];
+
+	let mut start_html = None;
+	let mut end_html = None;
+	let mut start_fragment = None;
+	let mut end_fragment = None;
+	let mut source_url = None;
+	for line in data.split(|b| *b == b'\n') {
+		let line = String::from_utf8_lossy(line);
+		let line = line.trim_end_matches('\r');
+		// the headers end where the html begins
+		let (key, value) = match line.split_once(':') {
+			Some((key, value)) if !key.contains('<') => (key, value.trim()),
+			_ => break,
+		};
+		// StartHTML and EndHTML are -1 when only the fragment is present
+		let offset = value.parse::<i64>().ok().filter(|v| *v >= 0);
+		match key {
+			START_HTML => start_html = offset,
+			END_HTML => end_html = offset,
+			START_FRAGMENT => start_fragment = offset,
+			END_FRAGMENT => end_fragment = offset,
+			SOURCE_URL => source_url = Some(value.to_owned()),
+			_ => {}
+		}
+	}
+
+	let slice = |start: Option<i64>, end: Option<i64>| -> Option<String> {
+		let (start, end) = (start? as usize, (end? as usize).min(data.len()));
+		data.get(start..end)
+			.map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+	};
+	let fragment = slice(start_fragment, end_fragment).ok_or("invalid CF_HTML fragment offsets")?;
+	let html = slice(start_html, end_html).unwrap_or_else(|| fragment.clone());
+	Ok(CfHtml {
+		raw: String::from_utf8_lossy(data).into_owned(),
+		html,
+		fragment,
+		source_url,
+	})
+}
@@ -0,0 +1,101 @@
+//! en: The image codec `RustImageData` converts through when the `image` feature is off, so builds
+//! that don't want the `image` crate can still bring a decoder of their own, e.g. the platform's
+//!
+//! zh: 关闭 `image` feature 时 `RustImageData` 进行转换所用的图片编解码器，不需要 `image` crate 的构建
+//! 仍可提供自己的解码器，例如平台自带的解码器
+
+use crate::common::Result;
+use std::sync::{Arc, Mutex};
+
+/// en: RGBA8 pixels, row by row from the top left, `data.len()` is `width * height * 4`
+/// zh: RGBA8 像素，从左上角逐行排列，`data.len()` 为 `width * height * 4`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaPixels {
+	pub width: u32,
+	pub height: u32,
+	pub data: Vec<u8>,
+}
+
+/// en: Decodes and encodes images for `RustImageData`
+/// zh: 为 `RustImageData` 解码与编码图片
+pub trait ImageCodec: Send + Sync {
+	/// en: Decode an encoded image, the format is detected from the content
+	/// zh: 解码图片，格式由内容检测
+	fn decode(&self, bytes: &[u8]) -> Result<RgbaPixels>;
+
+	/// en: Encode pixels as `mime`, at least `image/png` should be supported, `image/jpeg` and
+	/// `image/bmp` are asked for by `to_jpeg` and `to_bitmap`
+	/// zh: 将像素编码为 `mime` 格式，至少应支持 `image/png`，`to_jpeg` 与 `to_bitmap` 会请求 `image/jpeg` 与 `image/bmp`
+	fn encode(&self, pixels: &RgbaPixels, mime: &str) -> Result<Vec<u8>>;
+}
+
+static CODEC: Mutex<Option<Arc<dyn ImageCodec>>> = Mutex::new(None);
+
+/// en: Use `codec` for all images from now on, replacing the previous one
+/// zh: 此后所有图片都使用 `codec`，替换之前的编解码器
+pub fn set_image_codec<C: ImageCodec + 'static>(codec: C) {
+	if let Ok(mut current) = CODEC.lock() {
+		*current = Some(Arc::new(codec));
+	}
+}
+
+/// en: The codec set with `set_image_codec`, otherwise the `image` crate when the feature is on
+/// zh: 通过 `set_image_codec` 设置的编解码器，未设置时在开启 feature 的情况下为 `image` crate
+pub fn image_codec() -> Option<Arc<dyn ImageCodec>> {
+	CODEC
+		.lock()
+		.ok()
+		.and_then(|current| current.clone())
+		.or_else(default_codec)
+}
+
+#[cfg(feature = "image")]
+fn default_codec() -> Option<Arc<dyn ImageCodec>> {
+	Some(Arc::new(ImageCrateCodec))
+}
+
+#[cfg(not(feature = "image"))]
+fn default_codec() -> Option<Arc<dyn ImageCodec>> {
+	None
+}
+
+#[cfg_attr(feature = "image", allow(dead_code))]
+pub(crate) fn require_codec() -> Result<Arc<dyn ImageCodec>> {
+	image_codec().ok_or_else(|| {
+		"no image codec, enable the `image` feature or call `set_image_codec`".into()
+	})
+}
+
+/// en: The codec backed by the `image` crate
+/// zh: 基于 `image` crate 的编解码器
+#[cfg(feature = "image")]
+pub struct ImageCrateCodec;
+
+#[cfg(feature = "image")]
+impl ImageCodec for ImageCrateCodec {
+	fn decode(&self, bytes: &[u8]) -> Result<RgbaPixels> {
+		let image = image::load_from_memory(bytes)?.to_rgba8();
+		Ok(RgbaPixels {
+			width: image.width(),
+			height: image.height(),
+			data: image.into_raw(),
+		})
+	}
+
+	fn encode(&self, pixels: &RgbaPixels, mime: &str) -> Result<Vec<u8>> {
+		let format = image::ImageFormat::from_mime_type(mime)
+			.ok_or_else(|| format!("unsupported image type {}", mime))?;
+		let image = image::RgbaImage::from_raw(pixels.width, pixels.height, pixels.data.clone())
+			.ok_or("pixel buffer size does not match width and height")?;
+		let mut bytes = Vec::new();
+		let image = image::DynamicImage::ImageRgba8(image);
+		// jpeg has no alpha channel
+		let image = if format == image::ImageFormat::Jpeg {
+			image::DynamicImage::ImageRgb8(image.to_rgb8())
+		} else {
+			image
+		};
+		image.write_to(&mut std::io::Cursor::new(&mut bytes), format)?;
+		Ok(bytes)
+	}
+}
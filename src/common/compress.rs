@@ -0,0 +1,85 @@
+//! en: LZ4 compression of clipboard data for storage, skipping data that is compressed already
+//!
+//! zh: 用于存储的剪贴板数据 LZ4 压缩，已压缩的数据会被跳过
+
+use crate::common::{detect_mime, ClipboardContent, ContentData, ContentFormat, Result};
+
+/// en: Data shorter than this is stored as is, the frame would eat the savings
+/// zh: 短于该长度的数据原样保存，压缩帧的开销会抵消收益
+pub const MIN_COMPRESS_SIZE: usize = 1024;
+
+const STORED: u8 = 0;
+const LZ4: u8 = 1;
+
+// formats whose data doesn't get smaller with another pass
+const PRECOMPRESSED_MIMES: &[&str] = &[
+	"image/png",
+	"image/jpeg",
+	"image/gif",
+	"image/webp",
+	"image/avif",
+	"image/heic",
+	"application/zip",
+	"application/gzip",
+	"application/x-7z-compressed",
+	"audio/ogg",
+	"audio/flac",
+	"audio/mpeg",
+	"video/mp4",
+];
+
+/// en: Whether `data` of `format` would be worth compressing: images are stored as PNG
+/// and custom formats are sniffed for PNG, JPEG, zip and the like
+/// zh: `format` 格式的 `data` 是否值得压缩：图片以 PNG 保存，自定义格式会检测是否为 PNG、JPEG、zip 等
+pub fn is_compressible(format: &ContentFormat, data: &[u8]) -> bool {
+	if data.len() < MIN_COMPRESS_SIZE {
+		return false;
+	}
+	match format {
+		ContentFormat::Image => false,
+		_ => !detect_mime(data).map_or(false, |mime| PRECOMPRESSED_MIMES.contains(&mime)),
+	}
+}
+
+/// en: Compress `data` of `format` into a frame `decompress` reads back.
+/// Data that is small, already compressed or doesn't shrink is framed uncompressed
+/// zh: 将 `format` 格式的 `data` 压缩为 `decompress` 可读回的帧。
+/// 较小、已压缩或无法变小的数据以不压缩的方式成帧
+pub fn compress(format: &ContentFormat, data: &[u8]) -> Vec<u8> {
+	if is_compressible(format, data) {
+		let compressed = lz4_flex::compress_prepend_size(data);
+		if compressed.len() < data.len() {
+			let mut out = Vec::with_capacity(compressed.len() + 1);
+			out.push(LZ4);
+			out.extend_from_slice(&compressed);
+			return out;
+		}
+	}
+	let mut out = Vec::with_capacity(data.len() + 1);
+	out.push(STORED);
+	out.extend_from_slice(data);
+	out
+}
+
+/// en: The data of a frame written by `compress`
+/// zh: 读取由 `compress` 写出的帧中的数据
+pub fn decompress(frame: &[u8]) -> Result<Vec<u8>> {
+	match frame.split_first() {
+		Some((&STORED, data)) => Ok(data.to_vec()),
+		Some((&LZ4, data)) => Ok(lz4_flex::decompress_size_prepended(data)?),
+		Some((method, _)) => Err(format!("unknown compression method {}", method).into()),
+		None => Err("empty compressed frame".into()),
+	}
+}
+
+/// en: `compress` over the content encoded like `ContentData::to_bytes`
+/// zh: 对按 `ContentData::to_bytes` 编码的内容执行 `compress`
+pub fn compress_content(content: &ClipboardContent) -> Result<Vec<u8>> {
+	Ok(compress(&content.get_format(), &content.to_bytes()?))
+}
+
+/// en: The content of a frame written by `compress_content`
+/// zh: 读取由 `compress_content` 写出的帧中的内容
+pub fn decompress_content(format: ContentFormat, frame: &[u8]) -> Result<ClipboardContent> {
+	ClipboardContent::from_bytes(format, &decompress(frame)?)
+}
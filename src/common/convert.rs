@@ -0,0 +1,901 @@
+//! en: Conversions between the text formats, used to derive a plain text fallback
+//!
+//! zh: 文本格式之间的转换，用于生成纯文本备选内容
+
+use crate::common::attributed::{AttributedText, TextRun};
+use crate::common::{ClipboardContent, ContentData, ContentFormat};
+
+/// en: Strip the tags of an html document or fragment, block elements become line breaks
+/// and entities are decoded
+/// zh: 去除 html 文档或片段中的标签，块级元素转为换行，并解码实体
+pub fn html_to_text(html: &str) -> String {
+	let mut text = String::with_capacity(html.len());
+	let mut rest = html;
+	while let Some(start) = rest.find('<') {
+		push_decoded(&mut text, &rest[..start]);
+		rest = &rest[start..];
+		// comments may contain '>'
+		if rest.starts_with("<!--") {
+			rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+			continue;
+		}
+		let end = match rest.find('>') {
+			Some(end) => end,
+			None => {
+				rest = "";
+				break;
+			}
+		};
+		let tag = rest[1..end].trim().to_ascii_lowercase();
+		rest = &rest[end + 1..];
+		let closing = tag.starts_with('/');
+		let name: String = tag
+			.trim_start_matches('/')
+			.chars()
+			.take_while(|c| c.is_ascii_alphanumeric())
+			.collect();
+		match name.as_str() {
+			// their content is never shown
+			"script" | "style" | "head" | "title" if !closing => {
+				let close = format!("</{}", name);
+				rest = rest
+					.to_ascii_lowercase()
+					.find(&close)
+					.and_then(|i| rest[i..].find('>').map(|j| &rest[i + j + 1..]))
+					.unwrap_or("");
+			}
+			"br" => text.push('\n'),
+			"p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "blockquote"
+			| "pre" | "table" | "ul" | "ol"
+				if !text.is_empty() && !text.ends_with('\n') =>
+			{
+				text.push('\n')
+			}
+			"td" | "th" if closing => text.push('\t'),
+			_ => {}
+		}
+	}
+	push_decoded(&mut text, rest);
+	text.trim().to_owned()
+}
+
+/// Collapse whitespace like a browser and decode entities
+fn push_decoded(text: &mut String, raw: &str) {
+	let mut rest = raw;
+	let mut last_space = text.is_empty() || text.ends_with(char::is_whitespace);
+	while !rest.is_empty() {
+		let c = rest.chars().next().unwrap_or(' ');
+		if c == '&' {
+			if let Some(end) = rest[1..].find(';').filter(|end| *end <= 10) {
+				if let Some(decoded) = decode_entity(&rest[1..end + 1]) {
+					text.push(decoded);
+					last_space = false;
+					rest = &rest[end + 2..];
+					continue;
+				}
+			}
+		}
+		if c.is_whitespace() {
+			if !last_space {
+				text.push(' ');
+				last_space = true;
+			}
+		} else {
+			text.push(c);
+			last_space = false;
+		}
+		rest = &rest[c.len_utf8()..];
+	}
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+	let decoded = match entity {
+		"amp" => '&',
+		"lt" => '<',
+		"gt" => '>',
+		"quot" => '"',
+		"apos" => '\'',
+		"nbsp" => '\u{a0}',
+		"copy" => '©',
+		"reg" => '®',
+		"hellip" => '…',
+		"mdash" => '—',
+		"ndash" => '–',
+		_ => {
+			let code = entity.strip_prefix('#')?;
+			let value = match code.strip_prefix('x').or_else(|| code.strip_prefix('X')) {
+				Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+				None => code.parse().ok()?,
+			};
+			return char::from_u32(value);
+		}
+	};
+	Some(decoded)
+}
+
+/// en: Extract the text of an RTF document, control words are dropped and destinations like
+/// the font table or pictures are skipped
+/// zh: 提取 RTF 文档中的文本，去除控制字并跳过字体表、图片等非正文内容
+pub fn rtf_to_text(rtf: &str) -> String {
+	let mut text: Vec<u16> = Vec::with_capacity(rtf.len());
+	walk_rtf(rtf, |event| match event {
+		RtfEvent::Char(c, _) => text.push(c),
+		RtfEvent::Paragraph | RtfEvent::Line => text.push(u16::from(b'\n')),
+		RtfEvent::Tab => text.push(u16::from(b'\t')),
+	});
+	String::from_utf16_lossy(&text).trim().to_owned()
+}
+
+/// en: Read an RTF document into runs of bold, italic and underlined text, paragraphs end with a line break
+/// zh: 将 RTF 文档读取为粗体、斜体与下划线文本的片段，段落以换行结束
+pub fn rtf_to_attributed(rtf: &str) -> AttributedText {
+	let mut runs: Vec<(RtfStyle, Vec<u16>)> = Vec::new();
+	walk_rtf(rtf, |event| {
+		let (unit, style) = match event {
+			RtfEvent::Char(unit, style) => (unit, style),
+			RtfEvent::Paragraph | RtfEvent::Line => (u16::from(b'\n'), RtfStyle::default()),
+			RtfEvent::Tab => (u16::from(b'\t'), RtfStyle::default()),
+		};
+		// breaks and tabs join the run before them
+		match runs.last_mut() {
+			Some((last, units)) if *last == style || unit < 0x20 => units.push(unit),
+			_ => runs.push((style, vec![unit])),
+		}
+	});
+	let mut runs: Vec<TextRun> = runs
+		.into_iter()
+		.map(|(style, units)| TextRun {
+			text: String::from_utf16_lossy(&units),
+			bold: style.bold,
+			italic: style.italic,
+			underline: style.underline,
+			link: None,
+			color: None,
+		})
+		.collect();
+	// a trailing \par doesn't end with an empty line
+	if let Some(last) = runs.last_mut() {
+		let len = last.text.trim_end_matches('\n').len();
+		last.text.truncate(len);
+	}
+	runs.retain(|run| !run.text.is_empty());
+	AttributedText { runs }
+}
+
+/// The character formatting of RTF text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RtfStyle {
+	bold: bool,
+	italic: bool,
+	underline: bool,
+}
+
+enum RtfEvent {
+	/// A UTF-16 code unit of the text
+	Char(u16, RtfStyle),
+	Paragraph,
+	Line,
+	Tab,
+}
+
+/// Walk the text of an RTF document, skipping the destinations that aren't shown
+fn walk_rtf<F: FnMut(RtfEvent)>(rtf: &str, mut sink: F) {
+	let bytes = rtf.as_bytes();
+	// the state of the enclosing groups
+	let mut stack: Vec<(bool, usize, RtfStyle)> = Vec::new();
+	let mut skipped = false;
+	let mut uc = 1usize;
+	let mut style = RtfStyle::default();
+	// chars to skip after \u, the fallback for readers without unicode support
+	let mut pending_skip = 0usize;
+	let mut i = 0;
+	while i < bytes.len() {
+		let b = bytes[i];
+		match b {
+			b'{' => {
+				stack.push((skipped, uc, style));
+				i += 1;
+			}
+			b'}' => {
+				if let Some((s, u, st)) = stack.pop() {
+					skipped = s;
+					uc = u;
+					style = st;
+				}
+				i += 1;
+			}
+			b'\\' => {
+				i += 1;
+				let next = match bytes.get(i) {
+					Some(next) => *next,
+					None => break,
+				};
+				if next.is_ascii_alphabetic() {
+					let start = i;
+					while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+						i += 1;
+					}
+					let word = &rtf[start..i];
+					let num_start = i;
+					if i < bytes.len() && bytes[i] == b'-' {
+						i += 1;
+					}
+					while i < bytes.len() && bytes[i].is_ascii_digit() {
+						i += 1;
+					}
+					let param: Option<i32> = rtf[num_start..i].parse().ok();
+					// a space delimiting the control word is part of it
+					if i < bytes.len() && bytes[i] == b' ' {
+						i += 1;
+					}
+					if skipped {
+						continue;
+					}
+					let on = param != Some(0);
+					match word {
+						"fonttbl" | "colortbl" | "stylesheet" | "info" | "pict" | "object"
+						| "header" | "footer" | "themedata" | "datastore" | "latentstyles" => skipped = true,
+						"par" | "sect" | "row" => sink(RtfEvent::Paragraph),
+						"line" => sink(RtfEvent::Line),
+						"tab" | "cell" => sink(RtfEvent::Tab),
+						"b" => style.bold = on,
+						"i" => style.italic = on,
+						"ul" => style.underline = on,
+						"ulnone" => style.underline = false,
+						"plain" => style = RtfStyle::default(),
+						"uc" => uc = param.unwrap_or(1).max(0) as usize,
+						"u" => {
+							// negative values are used for code points above 32767
+							let code = param.unwrap_or(0);
+							let unit = if code < 0 {
+								(code + 65536) as u16
+							} else {
+								code as u16
+							};
+							sink(RtfEvent::Char(unit, style));
+							pending_skip = uc;
+						}
+						_ => {}
+					}
+				} else if next == b'\'' {
+					// \'hh, a byte of the document code page, read as latin-1
+					let digits = bytes[i + 1..]
+						.iter()
+						.take(2)
+						.take_while(|b| b.is_ascii_hexdigit())
+						.count();
+					let hex = u8::from_str_radix(&rtf[i + 1..i + 1 + digits], 16).ok();
+					i += 1 + digits;
+					if pending_skip > 0 {
+						pending_skip -= 1;
+					} else if let (Some(byte), false) = (hex, skipped) {
+						sink(RtfEvent::Char(u16::from(byte), style));
+					}
+				} else {
+					// a control symbol, step over the whole char when it isn't ascii
+					i += rtf[i..].chars().next().map_or(1, char::len_utf8);
+					if next == b'*' {
+						// unknown destination, ignorable
+						skipped = true;
+					} else if !skipped && (next == b'\\' || next == b'{' || next == b'}') {
+						sink(RtfEvent::Char(u16::from(next), style));
+					} else if !skipped && next == b'~' {
+						sink(RtfEvent::Char(0xa0, style));
+					}
+				}
+			}
+			b'\r' | b'\n' => i += 1,
+			_ => {
+				let c = rtf[i..].chars().next().unwrap_or(' ');
+				i += c.len_utf8();
+				if pending_skip > 0 {
+					pending_skip -= 1;
+				} else if !skipped {
+					let mut buf = [0u16; 2];
+					for unit in c.encode_utf16(&mut buf) {
+						sink(RtfEvent::Char(*unit, style));
+					}
+				}
+			}
+		}
+	}
+}
+
+/// en: Convert an RTF document to html, keeping paragraphs and bold, italic and underline
+/// zh: 将 RTF 文档转换为 html，保留段落以及粗体、斜体和下划线
+#[cfg(feature = "convert")]
+pub fn rtf_to_html(rtf: &str) -> String {
+	// text of the current paragraph, split in runs of the same style
+	let mut runs: Vec<(RtfStyle, Vec<u16>)> = Vec::new();
+	let mut paragraphs: Vec<String> = Vec::new();
+	let flush = |runs: &mut Vec<(RtfStyle, Vec<u16>)>, paragraphs: &mut Vec<String>| {
+		let mut html = String::new();
+		for (style, units) in runs.drain(..) {
+			let text = escape_html(&String::from_utf16_lossy(&units));
+			let text = text.replace('\n', "<br>").replace('\t', "&emsp;");
+			let (open, close) = style_tags(style);
+			html.push_str(&format!("{}{}{}", open, text, close));
+		}
+		paragraphs.push(html);
+	};
+	walk_rtf(rtf, |event| {
+		let (unit, style) = match event {
+			RtfEvent::Char(unit, style) => (unit, style),
+			RtfEvent::Paragraph => {
+				flush(&mut runs, &mut paragraphs);
+				return;
+			}
+			RtfEvent::Line => (u16::from(b'\n'), RtfStyle::default()),
+			RtfEvent::Tab => (u16::from(b'\t'), RtfStyle::default()),
+		};
+		match runs.last_mut() {
+			Some((last, units)) if *last == style || unit < 0x20 => units.push(unit),
+			_ => runs.push((style, vec![unit])),
+		}
+	});
+	if !runs.is_empty() {
+		flush(&mut runs, &mut paragraphs);
+	}
+	// a trailing \par doesn't start another paragraph
+	while matches!(paragraphs.last(), Some(p) if p.is_empty()) {
+		paragraphs.pop();
+	}
+	paragraphs
+		.iter()
+		.map(|p| format!("<p>{}</p>", p))
+		.collect::<Vec<String>>()
+		.join("\n")
+}
+
+#[cfg(feature = "convert")]
+fn style_tags(style: RtfStyle) -> (String, String) {
+	let mut open = String::new();
+	let mut close = String::new();
+	for (on, tag) in [
+		(style.bold, "b"),
+		(style.italic, "i"),
+		(style.underline, "u"),
+	] {
+		if on {
+			open.push_str(&format!("<{}>", tag));
+			close.insert_str(0, &format!("</{}>", tag));
+		}
+	}
+	(open, close)
+}
+
+/// en: Convert html to an RTF document, keeping paragraphs, headings, lists, links and
+/// bold, italic, underline and monospace text
+/// zh: 将 html 转换为 RTF 文档，保留段落、标题、列表、链接以及粗体、斜体、下划线和等宽文本
+#[cfg(feature = "convert")]
+pub fn html_to_rtf(html: &str) -> String {
+	let mut rtf = String::from(
+		"{\\rtf1\\ansi\\ansicpg1252\\deff0{\\fonttbl{\\f0\\fswiss Helvetica;}{\\f1\\fmodern Courier;}}\\uc1\n",
+	);
+	// all text so far, kept so whitespace is collapsed across tags
+	let mut text = String::new();
+	let mut written = 0;
+	// the hrefs of the open links
+	let mut links: Vec<String> = Vec::new();
+	let mut rest = html;
+	let flush_text = |rtf: &mut String, text: &str, written: &mut usize| {
+		rtf.push_str(&escape_rtf(&text[*written..]));
+		*written = text.len();
+	};
+	while let Some(start) = rest.find('<') {
+		push_decoded(&mut text, &rest[..start]);
+		rest = &rest[start..];
+		if rest.starts_with("<!--") {
+			rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+			continue;
+		}
+		let end = match rest.find('>') {
+			Some(end) => end,
+			None => {
+				rest = "";
+				break;
+			}
+		};
+		let tag = &rest[1..end];
+		rest = &rest[end + 1..];
+		let closing = tag.starts_with('/');
+		let name = tag
+			.trim_start_matches('/')
+			.chars()
+			.take_while(|c| c.is_ascii_alphanumeric())
+			.collect::<String>()
+			.to_ascii_lowercase();
+		flush_text(&mut rtf, &text, &mut written);
+		match (name.as_str(), closing) {
+			("script" | "style" | "head" | "title", false) => {
+				let close = format!("</{}", name);
+				rest = rest
+					.to_ascii_lowercase()
+					.find(&close)
+					.and_then(|i| rest[i..].find('>').map(|j| &rest[i + j + 1..]))
+					.unwrap_or("");
+			}
+			("b" | "strong", false) => rtf.push_str("{\\b "),
+			("i" | "em", false) => rtf.push_str("{\\i "),
+			("u", false) => rtf.push_str("{\\ul "),
+			("code" | "pre", false) => rtf.push_str("{\\f1 "),
+			("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+				// in half points, from 20pt for h1 down to 10pt for h6
+				let level: usize = name[1..].parse().unwrap_or(1);
+				rtf.push_str(&format!("{{\\b\\fs{} ", 44 - level * 4));
+			}
+			("b" | "strong" | "i" | "em" | "u" | "code", true) => rtf.push('}'),
+			("pre", true) => rtf.push_str("}\\par\n"),
+			("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => rtf.push_str("}\\par\n"),
+			("p" | "div" | "blockquote" | "tr" | "ul" | "ol", true) => rtf.push_str("\\par\n"),
+			("li", false) => rtf.push_str("\\bullet\\tab "),
+			("li", true) => rtf.push_str("\\par\n"),
+			("br", _) => rtf.push_str("\\line "),
+			("td" | "th", true) => rtf.push_str("\\tab "),
+			("a", false) => {
+				let href = attribute(tag, "href").unwrap_or_default();
+				rtf.push_str(&format!(
+					"{{\\field{{\\*\\fldinst HYPERLINK \"{}\"}}{{\\fldrslt{{\\ul ",
+					escape_rtf(&href)
+				));
+				links.push(href);
+			}
+			("a", true) if links.pop().is_some() => rtf.push_str("}}}"),
+			_ => {}
+		}
+	}
+	push_decoded(&mut text, rest);
+	flush_text(&mut rtf, &text, &mut written);
+	for _ in links {
+		rtf.push_str("}}}");
+	}
+	rtf.push('}');
+	rtf
+}
+
+/// Escape the RTF special characters, non ASCII text becomes \uN with a `?` fallback
+pub(crate) fn escape_rtf(text: &str) -> String {
+	let mut rtf = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'\\' | '{' | '}' => {
+				rtf.push('\\');
+				rtf.push(c);
+			}
+			'\n' => rtf.push_str("\\line "),
+			'\t' => rtf.push_str("\\tab "),
+			c if c.is_ascii() => rtf.push(c),
+			c => {
+				let mut buf = [0u16; 2];
+				for unit in c.encode_utf16(&mut buf) {
+					// the parameter is a signed 16 bit number
+					rtf.push_str(&format!("\\u{}?", *unit as i16));
+				}
+			}
+		}
+	}
+	rtf
+}
+
+/// en: Render markdown as html, covering the common syntax: headings, paragraphs, lists,
+/// quotes, code, emphasis, links and images
+/// zh: 将 Markdown 渲染为 html，支持常用语法：标题、段落、列表、引用、代码、强调、链接和图片
+pub fn markdown_to_html(markdown: &str) -> String {
+	let mut html = String::with_capacity(markdown.len() * 2);
+	let mut paragraph: Vec<String> = Vec::new();
+	// the list being written, "ul" or "ol"
+	let mut list: Option<&str> = None;
+	let mut lines = markdown.lines();
+
+	let flush_paragraph = |html: &mut String, paragraph: &mut Vec<String>| {
+		if !paragraph.is_empty() {
+			html.push_str(&format!("<p>{}</p>\n", paragraph.join("\n")));
+			paragraph.clear();
+		}
+	};
+	let close_list = |html: &mut String, list: &mut Option<&str>| {
+		if let Some(tag) = list.take() {
+			html.push_str(&format!("</{}>\n", tag));
+		}
+	};
+
+	while let Some(line) = lines.next() {
+		let trimmed = line.trim();
+		if trimmed.starts_with("```") {
+			flush_paragraph(&mut html, &mut paragraph);
+			close_list(&mut html, &mut list);
+			let mut code = Vec::new();
+			for code_line in lines.by_ref() {
+				if code_line.trim().starts_with("```") {
+					break;
+				}
+				code.push(escape_html(code_line));
+			}
+			html.push_str(&format!("<pre><code>{}</code></pre>\n", code.join("\n")));
+			continue;
+		}
+		if trimmed.is_empty() {
+			flush_paragraph(&mut html, &mut paragraph);
+			close_list(&mut html, &mut list);
+			continue;
+		}
+		let level = trimmed.chars().take_while(|c| *c == '#').count();
+		if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+			flush_paragraph(&mut html, &mut paragraph);
+			close_list(&mut html, &mut list);
+			let text = inline_markdown(trimmed[level..].trim().trim_end_matches('#').trim());
+			html.push_str(&format!("<h{0}>{1}</h{0}>\n", level, text));
+			continue;
+		}
+		if trimmed.len() >= 3
+			&& ['-', '*', '_']
+				.iter()
+				.any(|c| trimmed.chars().all(|t| t == *c || t == ' '))
+		{
+			flush_paragraph(&mut html, &mut paragraph);
+			close_list(&mut html, &mut list);
+			html.push_str("<hr>\n");
+			continue;
+		}
+		if let Some(quote) = trimmed.strip_prefix('>') {
+			flush_paragraph(&mut html, &mut paragraph);
+			close_list(&mut html, &mut list);
+			html.push_str(&format!(
+				"<blockquote><p>{}</p></blockquote>\n",
+				inline_markdown(quote.trim())
+			));
+			continue;
+		}
+		let item = list_item(trimmed);
+		if let Some((tag, text)) = item {
+			flush_paragraph(&mut html, &mut paragraph);
+			if list != Some(tag) {
+				close_list(&mut html, &mut list);
+				html.push_str(&format!("<{}>\n", tag));
+				list = Some(tag);
+			}
+			html.push_str(&format!("<li>{}</li>\n", inline_markdown(text)));
+			continue;
+		}
+		close_list(&mut html, &mut list);
+		// two trailing spaces are a hard line break
+		let text = inline_markdown(trimmed);
+		if line.ends_with("  ") {
+			paragraph.push(format!("{}<br>", text));
+		} else {
+			paragraph.push(text);
+		}
+	}
+	flush_paragraph(&mut html, &mut paragraph);
+	close_list(&mut html, &mut list);
+	html.trim_end().to_owned()
+}
+
+/// Returns the list tag and the text of a list item line
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+	for marker in ["- ", "* ", "+ "] {
+		if let Some(text) = line.strip_prefix(marker) {
+			return Some(("ul", text.trim()));
+		}
+	}
+	let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+	if digits > 0 {
+		if let Some(text) = line[digits..].strip_prefix(". ") {
+			return Some(("ol", text.trim()));
+		}
+	}
+	None
+}
+
+/// Render the inline syntax of one line
+fn inline_markdown(text: &str) -> String {
+	let mut html = String::with_capacity(text.len());
+	let mut rest = text;
+	let mut prev: Option<char> = None;
+	while let Some(c) = rest.chars().next() {
+		// `code`
+		if c == '`' {
+			if let Some(end) = rest[1..].find('`') {
+				html.push_str(&format!("<code>{}</code>", escape_html(&rest[1..end + 1])));
+				rest = &rest[end + 2..];
+				continue;
+			}
+		}
+		// ![alt](src) and [text](href)
+		let is_image = rest.starts_with("![");
+		if is_image || c == '[' {
+			let open = if is_image { 2 } else { 1 };
+			if let Some((label, target, len)) = link_parts(&rest[open..]) {
+				if is_image {
+					html.push_str(&format!(
+						"<img src=\"{}\" alt=\"{}\">",
+						escape_html(target),
+						escape_html(label)
+					));
+				} else {
+					html.push_str(&format!(
+						"<a href=\"{}\">{}</a>",
+						escape_html(target),
+						inline_markdown(label)
+					));
+				}
+				rest = &rest[open + len..];
+				continue;
+			}
+		}
+		// **strong**, __strong__, *em*, _em_, but not the underscores of snake_case
+		let intraword = c == '_' && matches!(prev, Some(p) if p.is_alphanumeric());
+		if (c == '*' || c == '_') && !intraword {
+			let double: String = [c, c].iter().collect();
+			if rest.starts_with(&double) {
+				if let Some(end) = rest[2..].find(&double).filter(|end| *end > 0) {
+					html.push_str(&format!(
+						"<strong>{}</strong>",
+						inline_markdown(&rest[2..end + 2])
+					));
+					rest = &rest[end + 4..];
+					continue;
+				}
+			} else if let Some(end) = rest[1..].find(c).filter(|end| *end > 0) {
+				html.push_str(&format!("<em>{}</em>", inline_markdown(&rest[1..end + 1])));
+				rest = &rest[end + 2..];
+				continue;
+			}
+		}
+		html.push_str(&escape_html(&rest[..c.len_utf8()]));
+		rest = &rest[c.len_utf8()..];
+		prev = Some(c);
+	}
+	html
+}
+
+/// Split `label](target)`, returns the label, the target and the consumed length
+fn link_parts(text: &str) -> Option<(&str, &str, usize)> {
+	let label_end = text.find("](")?;
+	let target_end = text[label_end + 2..].find(')')? + label_end + 2;
+	Some((
+		&text[..label_end],
+		&text[label_end + 2..target_end],
+		target_end + 1,
+	))
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+/// en: Convert html to markdown, tags without a markdown equivalent are dropped and keep their text
+/// zh: 将 html 转换为 Markdown，没有对应语法的标签会被去除，只保留文本
+pub fn html_to_markdown(html: &str) -> String {
+	let mut markdown = String::with_capacity(html.len());
+	// the open lists, with the next number for ordered ones
+	let mut lists: Vec<Option<usize>> = Vec::new();
+	// the hrefs of the open links
+	let mut links: Vec<String> = Vec::new();
+	let mut in_pre = false;
+	let mut rest = html;
+	while let Some(start) = rest.find('<') {
+		push_markdown_text(&mut markdown, &rest[..start], in_pre);
+		rest = &rest[start..];
+		if rest.starts_with("<!--") {
+			rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+			continue;
+		}
+		let end = match rest.find('>') {
+			Some(end) => end,
+			None => {
+				rest = "";
+				break;
+			}
+		};
+		let tag = &rest[1..end];
+		rest = &rest[end + 1..];
+		let closing = tag.starts_with('/');
+		let name: String = tag
+			.trim_start_matches('/')
+			.chars()
+			.take_while(|c| c.is_ascii_alphanumeric())
+			.collect::<String>()
+			.to_ascii_lowercase();
+		match (name.as_str(), closing) {
+			("script" | "style" | "head" | "title", false) => {
+				let close = format!("</{}", name);
+				rest = rest
+					.to_ascii_lowercase()
+					.find(&close)
+					.and_then(|i| rest[i..].find('>').map(|j| &rest[i + j + 1..]))
+					.unwrap_or("");
+			}
+			("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+				start_block(&mut markdown);
+				let level = name[1..].parse().unwrap_or(1);
+				markdown.push_str(&"#".repeat(level));
+				markdown.push(' ');
+			}
+			("p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "table", _) => {
+				start_block(&mut markdown)
+			}
+			("blockquote", false) => {
+				start_block(&mut markdown);
+				markdown.push_str("> ");
+			}
+			("blockquote", true) => start_block(&mut markdown),
+			("br", _) => markdown.push_str("  \n"),
+			("hr", _) => {
+				start_block(&mut markdown);
+				markdown.push_str("---");
+				start_block(&mut markdown);
+			}
+			("tr", true) => markdown.push('\n'),
+			("td" | "th", true) => markdown.push(' '),
+			("strong" | "b", _) => markdown.push_str("**"),
+			("em" | "i", _) => markdown.push('*'),
+			("code", _) if !in_pre => markdown.push('`'),
+			("pre", false) => {
+				start_block(&mut markdown);
+				markdown.push_str("```\n");
+				in_pre = true;
+			}
+			("pre", true) => {
+				if !markdown.ends_with('\n') {
+					markdown.push('\n');
+				}
+				markdown.push_str("```");
+				start_block(&mut markdown);
+				in_pre = false;
+			}
+			("ul", false) => lists.push(None),
+			("ol", false) => lists.push(Some(1)),
+			("ul" | "ol", true) => {
+				lists.pop();
+				start_block(&mut markdown);
+			}
+			("li", false) => {
+				if !markdown.is_empty() && !markdown.ends_with('\n') {
+					markdown.push('\n');
+				}
+				markdown.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+				match lists.last_mut() {
+					Some(Some(number)) => {
+						markdown.push_str(&format!("{}. ", number));
+						*number += 1;
+					}
+					_ => markdown.push_str("- "),
+				}
+			}
+			("a", false) => {
+				links.push(attribute(tag, "href").unwrap_or_default());
+				markdown.push('[');
+			}
+			("a", true) => {
+				if let Some(href) = links.pop() {
+					markdown.push_str(&format!("]({})", href));
+				}
+			}
+			("img", _) => {
+				let alt = attribute(tag, "alt").unwrap_or_default();
+				let src = attribute(tag, "src").unwrap_or_default();
+				markdown.push_str(&format!("![{}]({})", alt, src));
+			}
+			_ => {}
+		}
+	}
+	push_markdown_text(&mut markdown, rest, in_pre);
+	markdown.trim().to_owned()
+}
+
+/// Start a new block separated by a blank line
+fn start_block(markdown: &mut String) {
+	if markdown.is_empty() {
+		return;
+	}
+	while !markdown.ends_with("\n\n") {
+		markdown.push('\n');
+	}
+}
+
+fn push_markdown_text(markdown: &mut String, raw: &str, in_pre: bool) {
+	if in_pre {
+		// keep the whitespace of preformatted text, only decode entities
+		let mut rest = raw;
+		while let Some(i) = rest.find('&') {
+			markdown.push_str(&rest[..i]);
+			rest = &rest[i..];
+			let decoded = rest[1..]
+				.find(';')
+				.filter(|end| *end <= 10)
+				.and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+			match decoded {
+				Some((c, len)) => {
+					markdown.push(c);
+					rest = &rest[len..];
+				}
+				None => {
+					markdown.push('&');
+					rest = &rest[1..];
+				}
+			}
+		}
+		markdown.push_str(rest);
+		return;
+	}
+	push_decoded(markdown, raw);
+}
+
+/// Read an attribute value of a tag, quoted or not
+fn attribute(tag: &str, name: &str) -> Option<String> {
+	let lower = tag.to_ascii_lowercase();
+	let mut search = 0;
+	while let Some(i) = lower[search..].find(name) {
+		let pos = search + i;
+		search = pos + name.len();
+		let before = lower[..pos].chars().last();
+		if !matches!(before, Some(c) if c.is_whitespace()) {
+			continue;
+		}
+		let value = tag[search..].trim_start().strip_prefix('=')?.trim_start();
+		let value = match value.chars().next()? {
+			quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+			_ => value
+				.split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+				.next()?,
+		};
+		let mut decoded = String::new();
+		push_decoded(&mut decoded, value);
+		return Some(decoded);
+	}
+	None
+}
+
+/// Add the other rich text format to a request, so `bridge_rich_text` can convert it when only
+/// one of them is on the clipboard. Without the `convert` feature the request is kept as is.
+pub(crate) fn rich_text_request(formats: &[ContentFormat]) -> Vec<ContentFormat> {
+	let mut request = formats.to_vec();
+	if cfg!(feature = "convert") {
+		let wants_html = formats.iter().any(|f| matches!(f, ContentFormat::Html));
+		let wants_rtf = formats.iter().any(|f| matches!(f, ContentFormat::Rtf));
+		if wants_html && !wants_rtf {
+			request.push(ContentFormat::Rtf);
+		} else if wants_rtf && !wants_html {
+			request.push(ContentFormat::Html);
+		}
+	}
+	request
+}
+
+/// Keep the requested contents in the requested order, converting between html and RTF
+/// when only one of them was found
+pub(crate) fn bridge_rich_text(
+	requested: &[ContentFormat],
+	mut contents: Vec<ClipboardContent>,
+) -> Vec<ClipboardContent> {
+	#[cfg(feature = "convert")]
+	{
+		let find_html = |c: &ClipboardContent| matches!(c, ClipboardContent::Html(_));
+		let find_rtf = |c: &ClipboardContent| matches!(c, ClipboardContent::Rtf(_));
+		let bridged = match (
+			contents.iter().find(|c| find_html(c)),
+			contents.iter().find(|c| find_rtf(c)),
+		) {
+			(None, Some(ClipboardContent::Rtf(rtf))) => {
+				Some(ClipboardContent::Html(rtf_to_html(rtf)))
+			}
+			(Some(ClipboardContent::Html(html)), None) => {
+				Some(ClipboardContent::Rtf(html_to_rtf(html)))
+			}
+			_ => None,
+		};
+		contents.extend(bridged);
+	}
+	let mut result = Vec::with_capacity(requested.len());
+	for format in requested {
+		let found = contents.iter().position(|c| c.get_format() == *format);
+		if let Some(index) = found {
+			result.push(contents.remove(index));
+		}
+	}
+	result
+}
@@ -0,0 +1,180 @@
+// The resolution stored in image headers: pHYs in PNG, the JFIF density in JPEG, pixels per meter in
+// BMP and DIB, and the resolution tags of TIFF
+
+const INCH_PER_METER: f32 = 0.0254;
+
+pub(crate) fn ppm_to_dpi(ppm: u32) -> f32 {
+	ppm as f32 * INCH_PER_METER
+}
+
+pub(crate) fn dpi_to_ppm(dpi: f32) -> u32 {
+	(dpi / INCH_PER_METER).round() as u32
+}
+
+fn u16_be(data: &[u8], offset: usize) -> Option<u16> {
+	let b = data.get(offset..offset + 2)?;
+	Some(u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn u32_be(data: &[u8], offset: usize) -> Option<u32> {
+	let b = data.get(offset..offset + 4)?;
+	Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u32_le(data: &[u8], offset: usize) -> Option<u32> {
+	let b = data.get(offset..offset + 4)?;
+	Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// both axes must be known, a zero means the image doesn't say
+fn pair(x: f32, y: f32) -> Option<(f32, f32)> {
+	if x > 0.0 && y > 0.0 {
+		Some((x, y))
+	} else {
+		None
+	}
+}
+
+/// The horizontal and vertical dots per inch an encoded image declares
+pub(crate) fn read_dpi(bytes: &[u8]) -> Option<(f32, f32)> {
+	if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+		png_dpi(bytes)
+	} else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+		jfif_dpi(bytes)
+	} else if bytes.starts_with(b"BM") {
+		pair(
+			ppm_to_dpi(u32_le(bytes, 38)?),
+			ppm_to_dpi(u32_le(bytes, 42)?),
+		)
+	} else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+		tiff_dpi(bytes)
+	} else {
+		None
+	}
+}
+
+// the chunks of a PNG as (type, data), up to the image data
+fn png_chunks(png: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+	let mut offset = 8;
+	std::iter::from_fn(move || {
+		let len = u32_be(png, offset)? as usize;
+		let kind = png.get(offset + 4..offset + 8)?;
+		let data = png.get(offset + 8..offset + 8 + len)?;
+		offset += 12 + len;
+		Some((kind, data))
+	})
+}
+
+fn png_dpi(png: &[u8]) -> Option<(f32, f32)> {
+	let (_, data) = png_chunks(png)
+		.take_while(|(kind, _)| *kind != b"IDAT")
+		.find(|(kind, _)| *kind == b"pHYs")?;
+	// unit 0 only gives the aspect ratio
+	if data.get(8) != Some(&1) {
+		return None;
+	}
+	pair(ppm_to_dpi(u32_be(data, 0)?), ppm_to_dpi(u32_be(data, 4)?))
+}
+
+fn jfif_dpi(jpeg: &[u8]) -> Option<(f32, f32)> {
+	if jpeg.get(2..4)? != [0xff, 0xe0] || jpeg.get(6..11)? != b"JFIF\0" {
+		return None;
+	}
+	let (x, y) = (u16_be(jpeg, 14)? as f32, u16_be(jpeg, 16)? as f32);
+	match jpeg.get(13)? {
+		1 => pair(x, y),
+		2 => pair(x * 2.54, y * 2.54),
+		_ => None,
+	}
+}
+
+fn tiff_dpi(tiff: &[u8]) -> Option<(f32, f32)> {
+	let big_endian = tiff.starts_with(b"MM");
+	let read_u16 = |offset: usize| -> Option<u32> {
+		let b = tiff.get(offset..offset + 2)?;
+		Some(if big_endian {
+			u16::from_be_bytes([b[0], b[1]])
+		} else {
+			u16::from_le_bytes([b[0], b[1]])
+		} as u32)
+	};
+	let read_u32 = |offset: usize| -> Option<u32> {
+		if big_endian {
+			u32_be(tiff, offset)
+		} else {
+			u32_le(tiff, offset)
+		}
+	};
+	let rational = |offset: usize| -> Option<f32> {
+		let denominator = read_u32(offset + 4)?;
+		if denominator == 0 {
+			return None;
+		}
+		Some(read_u32(offset)? as f32 / denominator as f32)
+	};
+
+	let ifd = read_u32(4)? as usize;
+	let (mut x, mut y, mut unit) = (None, None, 2);
+	for index in 0..read_u16(ifd)? as usize {
+		let entry = ifd + 2 + index * 12;
+		match read_u16(entry)? {
+			282 => x = rational(read_u32(entry + 8)? as usize),
+			283 => y = rational(read_u32(entry + 8)? as usize),
+			296 => unit = read_u16(entry + 8)?,
+			_ => {}
+		}
+	}
+	match unit {
+		2 => pair(x?, y?),
+		3 => pair(x? * 2.54, y? * 2.54),
+		_ => None,
+	}
+}
+
+pub(crate) fn crc32(chunks: &[&[u8]]) -> u32 {
+	let mut crc = 0xffff_ffffu32;
+	for byte in chunks.iter().flat_map(|chunk| chunk.iter()) {
+		crc ^= *byte as u32;
+		for _ in 0..8 {
+			crc = if crc & 1 == 1 {
+				(crc >> 1) ^ 0xedb8_8320
+			} else {
+				crc >> 1
+			};
+		}
+	}
+	!crc
+}
+
+pub(crate) fn push_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+	png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+	png.extend_from_slice(kind);
+	png.extend_from_slice(data);
+	png.extend_from_slice(&crc32(&[&kind[..], data]).to_be_bytes());
+}
+
+/// The PNG with its pHYs chunk replaced by one declaring `dpi`
+pub(crate) fn png_with_dpi(png: &[u8], (x, y): (f32, f32)) -> Vec<u8> {
+	let mut out = Vec::with_capacity(png.len() + 21);
+	out.extend_from_slice(&png[..8.min(png.len())]);
+	let mut consumed = 8;
+	for (kind, data) in png_chunks(png) {
+		consumed += 12 + data.len();
+		if kind == b"pHYs" {
+			continue;
+		}
+		let mut kind_bytes = [0u8; 4];
+		kind_bytes.copy_from_slice(kind);
+		push_png_chunk(&mut out, &kind_bytes, data);
+		if kind == b"IHDR" {
+			let mut phys = Vec::with_capacity(9);
+			phys.extend_from_slice(&dpi_to_ppm(x).to_be_bytes());
+			phys.extend_from_slice(&dpi_to_ppm(y).to_be_bytes());
+			phys.push(1);
+			push_png_chunk(&mut out, b"pHYs", &phys);
+		}
+	}
+	// anything after the last whole chunk is kept as it was
+	out.extend_from_slice(png.get(consumed..).unwrap_or_default());
+	out
+}
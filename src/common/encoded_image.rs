@@ -0,0 +1,363 @@
+// RustImageData without the `image` crate: images keep the bytes they were read as, PNG and BMP of
+// raw pixels are written here and anything that needs decoding goes through the codec set with
+// `codec::set_image_codec`
+
+use super::codec::{image_codec, require_codec, RgbaPixels};
+use super::dpi::{png_with_dpi, push_png_chunk, read_dpi};
+use super::{detect_mime, EncodeOptions, Result, RustImage, RustImageBuffer};
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
+enum ImageData {
+	Encoded { bytes: Vec<u8>, mime: &'static str },
+	Pixels(RgbaPixels),
+}
+
+#[derive(Clone, PartialEq)]
+pub struct RustImageData {
+	width: u32,
+	height: u32,
+	data: Option<ImageData>,
+	dpi: Option<(f32, f32)>,
+}
+
+// the pixels and encoded bytes would flood the output, only describe them
+impl fmt::Debug for RustImageData {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let encoded = match &self.data {
+			Some(ImageData::Encoded { bytes, mime }) => Some((*mime, bytes.len())),
+			_ => None,
+		};
+		f.debug_struct("RustImageData")
+			.field("width", &self.width)
+			.field("height", &self.height)
+			.field("encoded", &encoded)
+			.field("dpi", &self.dpi)
+			.finish()
+	}
+}
+
+impl RustImageData {
+	/// en: The bytes the image was read as, `None` for images made from pixels
+	/// zh: 读取图片时的原始字节，由像素创建的图片为 `None`
+	pub fn encoded_bytes(&self) -> Option<&[u8]> {
+		match &self.data {
+			Some(ImageData::Encoded { bytes, .. }) => Some(bytes),
+			_ => None,
+		}
+	}
+
+	/// en: The pixels as RGBA8, images read from bytes need an image codec
+	/// zh: RGBA8 格式的像素，从字节读取的图片需要图片编解码器
+	pub fn to_rgba_pixels(&self) -> Result<RgbaPixels> {
+		match &self.data {
+			Some(ImageData::Pixels(pixels)) => Ok(pixels.clone()),
+			Some(ImageData::Encoded { bytes, .. }) => require_codec()?.decode(bytes),
+			None => Err("image is empty".into()),
+		}
+	}
+
+	fn from_pixels(pixels: RgbaPixels) -> Self {
+		RustImageData {
+			width: pixels.width,
+			height: pixels.height,
+			data: Some(ImageData::Pixels(pixels)),
+			dpi: None,
+		}
+	}
+
+	// the bytes as they are when already `mime`, converted through the pixels otherwise
+	fn encode_as(&self, mime: &str) -> Result<RustImageBuffer> {
+		if let Some(ImageData::Encoded {
+			bytes,
+			mime: current,
+		}) = &self.data
+		{
+			if *current == mime {
+				return Ok(RustImageBuffer(bytes.clone()));
+			}
+		}
+		let pixels = self.to_rgba_pixels()?;
+		let bytes = match (image_codec(), mime) {
+			(Some(codec), _) => codec.encode(&pixels, mime)?,
+			(None, "image/png") => stored_png(&pixels),
+			(None, "image/bmp") => bmp(&pixels),
+			(None, _) => require_codec()?.encode(&pixels, mime)?,
+		};
+		Ok(RustImageBuffer(bytes))
+	}
+}
+
+impl RustImage for RustImageData {
+	fn empty() -> Self {
+		RustImageData {
+			width: 0,
+			height: 0,
+			data: None,
+			dpi: None,
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		self.data.is_none()
+	}
+
+	fn from_path(path: &str) -> Result<Self> {
+		let bytes = std::fs::read(path)?;
+		Self::from_bytes(&bytes)
+	}
+
+	/// The bytes are kept as they are, only the size is read from the header
+	fn from_bytes(bytes: &[u8]) -> Result<Self> {
+		let mime = detect_mime(bytes)
+			.filter(|mime| mime.starts_with("image/") && *mime != "image/svg+xml")
+			.ok_or("unrecognized image data")?;
+		let (width, height) = match dimensions(bytes) {
+			Some(size) => size,
+			None => {
+				let pixels = require_codec()?.decode(bytes)?;
+				(pixels.width, pixels.height)
+			}
+		};
+		Ok(RustImageData {
+			width,
+			height,
+			data: Some(ImageData::Encoded {
+				bytes: bytes.to_vec(),
+				mime,
+			}),
+			dpi: read_dpi(bytes),
+		})
+	}
+
+	fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+		if pixels.len() as u64 != width as u64 * height as u64 * 4 {
+			return Err("pixel buffer size does not match width and height".into());
+		}
+		Ok(Self::from_pixels(RgbaPixels {
+			width,
+			height,
+			data: pixels,
+		}))
+	}
+
+	fn get_size(&self) -> (u32, u32) {
+		(self.width, self.height)
+	}
+
+	/// Nearest neighbour scaling, the aspect ratio is kept
+	fn thumbnail(&self, width: u32, height: u32) -> Result<Self> {
+		let pixels = self.to_rgba_pixels()?;
+		if pixels.width == 0 || pixels.height == 0 {
+			return Err("image is empty".into());
+		}
+		let scale = f64::min(
+			width as f64 / pixels.width as f64,
+			height as f64 / pixels.height as f64,
+		);
+		let new_width = ((pixels.width as f64 * scale).round() as u32).max(1);
+		let new_height = ((pixels.height as f64 * scale).round() as u32).max(1);
+		let mut data = Vec::with_capacity(new_width as usize * new_height as usize * 4);
+		for y in 0..new_height {
+			let src_y = (y as u64 * pixels.height as u64 / new_height as u64) as usize;
+			for x in 0..new_width {
+				let src_x = (x as u64 * pixels.width as u64 / new_width as u64) as usize;
+				let offset = (src_y * pixels.width as usize + src_x) * 4;
+				data.extend_from_slice(&pixels.data[offset..offset + 4]);
+			}
+		}
+		Ok(Self::from_pixels(RgbaPixels {
+			width: new_width,
+			height: new_height,
+			data,
+		}))
+	}
+
+	fn to_jpeg(&self) -> Result<RustImageBuffer> {
+		self.encode_as("image/jpeg")
+	}
+
+	/// The codec picks the quality
+	fn to_jpeg_with_quality(&self, _quality: u8) -> Result<RustImageBuffer> {
+		self.to_jpeg()
+	}
+
+	fn to_jpeg_with_options(&self, _options: &EncodeOptions) -> Result<RustImageBuffer> {
+		self.to_jpeg()
+	}
+
+	fn to_png(&self) -> Result<RustImageBuffer> {
+		let png = self.encode_as("image/png")?;
+		Ok(match self.dpi {
+			Some(dpi) => RustImageBuffer(png_with_dpi(&png.0, dpi)),
+			None => png,
+		})
+	}
+
+	fn to_png_with_options(&self, _options: &EncodeOptions) -> Result<RustImageBuffer> {
+		self.to_png()
+	}
+
+	fn to_bitmap(&self) -> Result<RustImageBuffer> {
+		self.encode_as("image/bmp")
+	}
+
+	/// The format follows the extension, PNG when it is none of jpg, jpeg and bmp
+	fn save_to_path(&self, path: &str) -> Result<()> {
+		let extension = std::path::Path::new(path)
+			.extension()
+			.map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+		let buffer = match extension.as_deref() {
+			Some("jpg") | Some("jpeg") => self.to_jpeg()?,
+			Some("bmp") => self.to_bitmap()?,
+			_ => self.to_png()?,
+		};
+		buffer.save_to_path(path)
+	}
+
+	fn dpi(&self) -> Option<(f32, f32)> {
+		self.dpi
+	}
+
+	fn with_dpi(self, dpi: Option<(f32, f32)>) -> Self {
+		RustImageData { dpi, ..self }
+	}
+
+	fn gif_bytes(&self) -> Option<&[u8]> {
+		match &self.data {
+			Some(ImageData::Encoded {
+				bytes,
+				mime: "image/gif",
+			}) => Some(bytes),
+			_ => None,
+		}
+	}
+}
+
+fn read_u16_be(data: &[u8], offset: usize) -> Option<u32> {
+	let b = data.get(offset..offset + 2)?;
+	Some(u16::from_be_bytes([b[0], b[1]]) as u32)
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+	let b = data.get(offset..offset + 4)?;
+	Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// the size from the header of PNG, GIF, BMP and JPEG images
+fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+	match detect_mime(bytes)? {
+		"image/png" => {
+			let b = bytes.get(16..24)?;
+			Some((
+				u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+				u32::from_be_bytes([b[4], b[5], b[6], b[7]]),
+			))
+		}
+		"image/gif" => {
+			let b = bytes.get(6..10)?;
+			Some((
+				u16::from_le_bytes([b[0], b[1]]) as u32,
+				u16::from_le_bytes([b[2], b[3]]) as u32,
+			))
+		}
+		"image/bmp" => {
+			let width = read_u32_le(bytes, 18)? as i32;
+			let height = read_u32_le(bytes, 22)? as i32;
+			Some((width.unsigned_abs(), height.unsigned_abs()))
+		}
+		"image/jpeg" => {
+			// walk the segments up to the start of frame
+			let mut offset = 2;
+			loop {
+				if *bytes.get(offset)? != 0xff {
+					return None;
+				}
+				let marker = *bytes.get(offset + 1)?;
+				let length = read_u16_be(bytes, offset + 2)? as usize;
+				let is_sof = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+				if is_sof {
+					return Some((
+						read_u16_be(bytes, offset + 7)?,
+						read_u16_be(bytes, offset + 5)?,
+					));
+				}
+				offset += 2 + length;
+			}
+		}
+		_ => None,
+	}
+}
+
+// a valid but uncompressed PNG: the zlib stream holds stored deflate blocks
+fn stored_png(pixels: &RgbaPixels) -> Vec<u8> {
+	let stride = pixels.width as usize * 4;
+	let mut raw = Vec::with_capacity((stride + 1) * pixels.height as usize);
+	for row in pixels.data.chunks_exact(stride.max(1)) {
+		// filter type none
+		raw.push(0);
+		raw.extend_from_slice(row);
+	}
+
+	let mut zlib = vec![0x78, 0x01];
+	let mut blocks = raw.chunks(0xffff).peekable();
+	if blocks.peek().is_none() {
+		zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+	}
+	while let Some(block) = blocks.next() {
+		let last = blocks.peek().is_none();
+		zlib.push(last as u8);
+		let len = block.len() as u16;
+		zlib.extend_from_slice(&len.to_le_bytes());
+		zlib.extend_from_slice(&(!len).to_le_bytes());
+		zlib.extend_from_slice(block);
+	}
+	let (mut a, mut b) = (1u32, 0u32);
+	for byte in &raw {
+		a = (a + *byte as u32) % 65521;
+		b = (b + a) % 65521;
+	}
+	zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+
+	let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+	let mut header = Vec::with_capacity(13);
+	header.extend_from_slice(&pixels.width.to_be_bytes());
+	header.extend_from_slice(&pixels.height.to_be_bytes());
+	// 8 bit RGBA, deflate, adaptive filtering, no interlace
+	header.extend_from_slice(&[8, 6, 0, 0, 0]);
+	push_png_chunk(&mut png, b"IHDR", &header);
+	push_png_chunk(&mut png, b"IDAT", &zlib);
+	push_png_chunk(&mut png, b"IEND", &[]);
+	png
+}
+
+// a bottom-up 32 bit BMP with an alpha mask
+fn bmp(pixels: &RgbaPixels) -> Vec<u8> {
+	const HEADER_SIZE: u32 = 14 + 56;
+	let stride = pixels.width as usize * 4;
+	let size_image = (stride * pixels.height as usize) as u32;
+	let mut bmp = Vec::with_capacity((HEADER_SIZE + size_image) as usize);
+	bmp.extend_from_slice(b"BM");
+	bmp.extend_from_slice(&(HEADER_SIZE + size_image).to_le_bytes());
+	bmp.extend_from_slice(&[0; 4]);
+	bmp.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+	// BITMAPV3INFOHEADER: a BITMAPINFOHEADER followed by the four masks
+	bmp.extend_from_slice(&56u32.to_le_bytes());
+	bmp.extend_from_slice(&(pixels.width as i32).to_le_bytes());
+	bmp.extend_from_slice(&(pixels.height as i32).to_le_bytes());
+	bmp.extend_from_slice(&1u16.to_le_bytes());
+	bmp.extend_from_slice(&32u16.to_le_bytes());
+	// BI_BITFIELDS
+	bmp.extend_from_slice(&3u32.to_le_bytes());
+	bmp.extend_from_slice(&size_image.to_le_bytes());
+	bmp.extend_from_slice(&[0; 16]);
+	for mask in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+		bmp.extend_from_slice(&mask.to_le_bytes());
+	}
+	for row in pixels.data.chunks_exact(stride.max(1)).rev() {
+		for px in row.chunks_exact(4) {
+			bmp.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+		}
+	}
+	bmp
+}
@@ -0,0 +1,74 @@
+//! en: The raw data of every format at one point in time, and what changed between two of them
+//!
+//! zh: 某一时刻每个格式的原始数据，以及两个快照之间的变化
+
+/// en: The bytes of every readable format, see `Clipboard::snapshot`. Formats keep the order the
+/// clipboard listed them in
+/// zh: 每个可读格式的字节，见 `Clipboard::snapshot`。格式保持剪贴板列出时的顺序
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipboardSnapshot {
+	pub formats: Vec<(String, Vec<u8>)>,
+}
+
+/// en: The formats that differ between two snapshots
+/// zh: 两个快照之间不同的格式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotDiff {
+	/// en: Formats only in the newer snapshot
+	/// zh: 仅存在于较新快照中的格式
+	pub added: Vec<String>,
+	/// en: Formats only in the older snapshot
+	/// zh: 仅存在于较旧快照中的格式
+	pub removed: Vec<String>,
+	/// en: Formats in both whose bytes differ
+	/// zh: 两者都有但字节不同的格式
+	pub changed: Vec<String>,
+}
+
+impl SnapshotDiff {
+	/// en: Whether the snapshots hold the same formats with the same bytes
+	/// zh: 两个快照的格式与字节是否完全相同
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+	}
+}
+
+impl ClipboardSnapshot {
+	/// en: The bytes of a format
+	/// zh: 某个格式的字节
+	pub fn get(&self, format: &str) -> Option<&[u8]> {
+		self.formats
+			.iter()
+			.find(|(name, _)| name == format)
+			.map(|(_, data)| data.as_slice())
+	}
+
+	/// en: The total size of the data of all formats
+	/// zh: 所有格式数据的总大小
+	pub fn size(&self) -> usize {
+		self.formats.iter().map(|(_, data)| data.len()).sum()
+	}
+
+	/// en: What changed from `self` to the newer `other`, e.g. so only the changed formats are
+	/// stored or sent
+	/// zh: 从 `self` 到较新的 `other` 之间的变化，例如只存储或传输变化的格式
+	pub fn diff(&self, other: &ClipboardSnapshot) -> SnapshotDiff {
+		let mut diff = SnapshotDiff::default();
+		for (name, data) in &other.formats {
+			match self.get(name) {
+				None => diff.added.push(name.clone()),
+				Some(old) if old != data.as_slice() => diff.changed.push(name.clone()),
+				Some(_) => {}
+			}
+		}
+		diff.removed = self
+			.formats
+			.iter()
+			.filter(|(name, _)| other.get(name).is_none())
+			.map(|(name, _)| name.clone())
+			.collect();
+		diff
+	}
+}
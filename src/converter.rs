@@ -0,0 +1,441 @@
+//! en: Converters between formats, consulted when a requested format is missing but another one
+//! in the clipboard can be turned into it, e.g. RTF into plain text or TIFF into an image
+//!
+//! zh: 格式之间的转换器，当请求的格式不存在但剪贴板中有可转换为它的格式时使用，
+//! 例如将 RTF 转为纯文本或将 TIFF 转为图片
+
+use crate::common::{
+	convert, ContentData, ContentMetadata, FileOperation, FormatHandle, FormatInfo, Result, Url,
+};
+use crate::{Clipboard, ClipboardContent, ContentFormat, RustImageData};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// en: Turns the bytes of one format into the bytes of another, both encoded as their MIME types like `ContentData::to_bytes`
+/// zh: 将一种格式的数据转换为另一种格式的数据，两者均按各自的 MIME 类型编码，与 `ContentData::to_bytes` 相同
+pub type ConvertFn = dyn Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync;
+
+#[derive(Clone)]
+struct Converter {
+	from: ContentFormat,
+	to: ContentFormat,
+	convert: Arc<ConvertFn>,
+}
+
+/// en: The installed converters, tried in the order they were added
+/// zh: 已安装的转换器，按添加顺序尝试
+#[derive(Clone, Default)]
+pub struct ConverterRegistry {
+	converters: Vec<Converter>,
+}
+
+impl ConverterRegistry {
+	pub fn new() -> Self {
+		ConverterRegistry::default()
+	}
+
+	/// en: The conversions between the text formats of `common::convert`: RTF, html and markdown to plain text,
+	/// markdown to html and html to markdown, plus RTF to html and back with the `convert` feature
+	/// zh: `common::convert` 提供的文本格式转换：RTF、html 与 markdown 转为纯文本，markdown 与 html 互相转换，
+	/// 启用 `convert` 特性时还有 RTF 与 html 的互相转换
+	#[cfg_attr(not(feature = "convert"), allow(clippy::let_and_return))]
+	pub fn with_defaults() -> Self {
+		let registry = ConverterRegistry::new()
+			.with_text(
+				ContentFormat::Rtf,
+				ContentFormat::Text,
+				convert::rtf_to_text,
+			)
+			.with_text(
+				ContentFormat::Html,
+				ContentFormat::Text,
+				convert::html_to_text,
+			)
+			.with_text(ContentFormat::Markdown, ContentFormat::Text, |markdown| {
+				markdown.to_owned()
+			})
+			.with_text(
+				ContentFormat::Markdown,
+				ContentFormat::Html,
+				convert::markdown_to_html,
+			)
+			.with_text(
+				ContentFormat::Html,
+				ContentFormat::Markdown,
+				convert::html_to_markdown,
+			);
+		#[cfg(feature = "convert")]
+		let registry = registry
+			.with_text(
+				ContentFormat::Rtf,
+				ContentFormat::Html,
+				convert::rtf_to_html,
+			)
+			.with_text(
+				ContentFormat::Html,
+				ContentFormat::Rtf,
+				convert::html_to_rtf,
+			);
+		registry
+	}
+
+	/// en: Install a converter from `from` to `to`
+	/// zh: 安装从 `from` 到 `to` 的转换器
+	pub fn with<F>(mut self, from: ContentFormat, to: ContentFormat, convert: F) -> Self
+	where
+		F: Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+	{
+		self.converters.push(Converter {
+			from,
+			to,
+			convert: Arc::new(convert),
+		});
+		self
+	}
+
+	fn with_text(
+		self,
+		from: ContentFormat,
+		to: ContentFormat,
+		convert: fn(&str) -> String,
+	) -> Self {
+		self.with(from, to, move |bytes| {
+			Ok(convert(std::str::from_utf8(bytes)?).into_bytes())
+		})
+	}
+
+	/// en: The formats a converter turns into `to`, in the order they are tried
+	/// zh: 可被转换为 `to` 的格式，按尝试顺序排列
+	pub fn sources(&self, to: &ContentFormat) -> Vec<ContentFormat> {
+		let mut sources: Vec<ContentFormat> = Vec::new();
+		for converter in self.converters.iter().filter(|c| c.to == *to) {
+			if !sources.contains(&converter.from) {
+				sources.push(converter.from.clone());
+			}
+		}
+		sources
+	}
+
+	/// en: Convert `content` to `to` with the first converter that succeeds, `None` when none applies
+	/// zh: 使用第一个成功的转换器将 `content` 转换为 `to`，没有可用的转换器时返回 `None`
+	pub fn convert(
+		&self,
+		content: &ClipboardContent,
+		to: &ContentFormat,
+	) -> Option<ClipboardContent> {
+		let from = content.get_format();
+		let bytes = content.to_bytes().ok()?;
+		self.converters
+			.iter()
+			.filter(|c| c.from == from && c.to == *to)
+			.find_map(|converter| {
+				let converted = (converter.convert)(&bytes)
+					.and_then(|bytes| ClipboardContent::from_bytes(to.clone(), &bytes));
+				converted
+					.map_err(|_e| {
+						trace_debug!(from = %from, to = %to, error = %_e, "conversion failed");
+					})
+					.ok()
+			})
+	}
+
+	/// Add a converted content for every format of `formats` that `contents` lacks, the sources come from `read`
+	fn fill_missing(
+		&self,
+		read: impl FnOnce(&[ContentFormat]) -> Result<Vec<ClipboardContent>>,
+		formats: &[ContentFormat],
+		contents: &mut Vec<ClipboardContent>,
+	) -> Result<()> {
+		let missing: Vec<&ContentFormat> = formats
+			.iter()
+			.filter(|format| !contents.iter().any(|c| c.get_format() == **format))
+			.collect();
+		let mut sources: Vec<ContentFormat> = Vec::new();
+		for format in missing.iter() {
+			for source in self.sources(format) {
+				if !sources.contains(&source) && !contents.iter().any(|c| c.get_format() == source)
+				{
+					sources.push(source);
+				}
+			}
+		}
+		if sources.is_empty() {
+			return Ok(());
+		}
+		// the sources are read in one go, so conversions see the same clipboard
+		let mut available = contents.clone();
+		available.extend(read(&sources)?);
+		for format in missing {
+			let converted = self.sources(format).iter().find_map(|source| {
+				available
+					.iter()
+					.find(|c| c.get_format() == *source)
+					.and_then(|content| self.convert(content, format))
+			});
+			if let Some(content) = converted {
+				contents.push(content);
+			}
+		}
+		Ok(())
+	}
+}
+
+/// en: A clipboard that converts from the available formats when a requested format is missing.
+/// Native contents always win over converted ones, writes and the byte level methods go to the inner clipboard unchanged
+///
+/// zh: 在请求的格式不存在时从可用格式转换的剪贴板。原生内容总是优先于转换得到的内容，
+/// 写入以及字节级方法直接交给内部剪贴板
+#[derive(Clone)]
+pub struct ConvertingClipboard {
+	inner: Arc<dyn Clipboard + Send + Sync>,
+	registry: ConverterRegistry,
+}
+
+impl ConvertingClipboard {
+	pub fn new<C: Clipboard + Sync + 'static>(clipboard: C, registry: ConverterRegistry) -> Self {
+		ConvertingClipboard {
+			inner: Arc::new(clipboard),
+			registry,
+		}
+	}
+
+	pub fn registry(&self) -> &ConverterRegistry {
+		&self.registry
+	}
+
+	fn converted(&self, format: ContentFormat) -> Result<ClipboardContent> {
+		let mut contents = Vec::new();
+		self.registry
+			.fill_missing(|formats| self.inner.get(formats), &[format], &mut contents)?;
+		contents
+			.pop()
+			.ok_or_else(|| "no convertible format in clipboard".into())
+	}
+
+	fn converted_string(&self, format: ContentFormat) -> Result<String> {
+		match self.converted(format)? {
+			ClipboardContent::Text(text)
+			| ClipboardContent::Rtf(text)
+			| ClipboardContent::Html(text)
+			| ClipboardContent::Svg(text)
+			| ClipboardContent::Markdown(text) => Ok(text),
+			_ => Err("the conversion returned another format".into()),
+		}
+	}
+}
+
+impl Clipboard for ConvertingClipboard {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		self.inner.available_formats()
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		self.inner.available_formats_detailed()
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		let sources = self.registry.sources(&format);
+		self.inner.has(format) || self.inner.has_any(&sources)
+	}
+
+	fn has_any(&self, formats: &[ContentFormat]) -> bool {
+		let mut all = formats.to_vec();
+		for format in formats {
+			all.extend(self.registry.sources(format));
+		}
+		self.inner.has_any(&all)
+	}
+
+	fn clear(&self) -> Result<()> {
+		self.inner.clear()
+	}
+
+	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
+		self.inner.get_buffer(format)
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		self.inner.get_size(format)
+	}
+
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		self.inner.get_reader(format)
+	}
+
+	fn get_text(&self) -> Result<String> {
+		self.inner
+			.get_text()
+			.or_else(|e| self.converted_string(ContentFormat::Text).map_err(|_| e))
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		self.inner
+			.get_rich_text()
+			.or_else(|e| self.converted_string(ContentFormat::Rtf).map_err(|_| e))
+	}
+
+	fn get_html(&self) -> Result<String> {
+		self.inner
+			.get_html()
+			.or_else(|e| self.converted_string(ContentFormat::Html).map_err(|_| e))
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		self.inner
+			.get_svg()
+			.or_else(|e| self.converted_string(ContentFormat::Svg).map_err(|_| e))
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		self.inner.get_markdown().or_else(|e| {
+			self.converted_string(ContentFormat::Markdown)
+				.map_err(|_| e)
+		})
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		self.inner
+			.get_image()
+			.or_else(|e| match self.converted(ContentFormat::Image) {
+				Ok(ClipboardContent::Image(image)) => Ok(image),
+				_ => Err(e),
+			})
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		self.inner
+			.get_files()
+			.or_else(|e| match self.converted(ContentFormat::Files) {
+				Ok(ClipboardContent::Files(files)) => Ok(files),
+				_ => Err(e),
+			})
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		self.inner.get_file_paths()
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		self.inner.get_file_operation()
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		self.inner
+			.get_urls()
+			.or_else(|e| match self.converted(ContentFormat::Url) {
+				Ok(ClipboardContent::Urls(urls)) => Ok(urls),
+				_ => Err(e),
+			})
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		self.inner.get_image_raw()
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		let mut contents = self.inner.get(formats)?;
+		self.registry
+			.fill_missing(|formats| self.inner.get(formats), formats, &mut contents)?;
+		Ok(contents)
+	}
+
+	fn try_get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		let mut contents = self.inner.try_get(formats)?;
+		self.registry.fill_missing(
+			|formats| self.inner.try_get(formats),
+			formats,
+			&mut contents,
+		)?;
+		Ok(contents)
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		let mut contents = self.get(formats)?;
+		formats
+			.iter()
+			.find_map(|format| {
+				let index = contents.iter().position(|c| c.get_format() == *format)?;
+				Some(contents.swap_remove(index))
+			})
+			.ok_or_else(|| "None of the formats is available".into())
+	}
+
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		let (mut contents, metadata) = self.inner.get_with_metadata(formats)?;
+		self.registry
+			.fill_missing(|formats| self.inner.get(formats), formats, &mut contents)?;
+		Ok((contents, metadata))
+	}
+
+	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
+		self.inner.set_buffer(format, buffer)
+	}
+
+	fn register_format(&self, name: &str) -> Result<FormatHandle> {
+		self.inner.register_format(name)
+	}
+
+	fn get_buffer_by_handle(&self, format: &FormatHandle) -> Result<Vec<u8>> {
+		self.inner.get_buffer_by_handle(format)
+	}
+
+	fn set_buffer_by_handle(&self, format: &FormatHandle, buffer: Vec<u8>) -> Result<()> {
+		self.inner.set_buffer_by_handle(format, buffer)
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		self.inner.set_text(text)
+	}
+
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		self.inner.append_text(separator, text)
+	}
+
+	fn set_rich_text(&self, text: String) -> Result<()> {
+		self.inner.set_rich_text(text)
+	}
+
+	fn set_html(&self, html: String) -> Result<()> {
+		self.inner.set_html(html)
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		self.inner.set_svg(svg)
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		self.inner.set_markdown(markdown)
+	}
+
+	fn set_image(&self, image: RustImageData) -> Result<()> {
+		self.inner.set_image(image)
+	}
+
+	fn set_files(&self, files: Vec<String>) -> Result<()> {
+		self.inner.set_files(files)
+	}
+
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()> {
+		self.inner.set_files_with_operation(files, operation)
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		self.inner.set_urls(urls)
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		self.inner.set_image_png(png)
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		self.inner.set_image_raw(format, bytes)
+	}
+
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		self.inner.set(contents)
+	}
+}
@@ -0,0 +1,222 @@
+//! en: A stable JSON shape for clipboard changes, so processes not written in Rust can follow the
+//! clipboard through a pipe or socket instead of decoding platform formats themselves
+//!
+//! zh: 剪贴板变化的稳定 JSON 结构，非 Rust 进程可通过管道或套接字跟踪剪贴板，无需自行解析各平台格式
+
+use crate::common::{base64_encode, combine_hashes, Fnv64, Result};
+use crate::{Clipboard, ClipboardHandler};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// en: The schema version written in every event, bumped only when a field changes meaning or is removed
+/// zh: 每个事件中写入的结构版本，仅在字段含义变化或被移除时递增
+pub const CLIPBOARD_EVENT_VERSION: u32 = 1;
+
+/// en: What goes into an event
+/// zh: 事件中包含哪些内容
+#[derive(Debug, Clone)]
+pub struct EventOptions {
+	/// en: The number of leading bytes of each format kept as `preview`
+	/// zh: 每种格式保留为 `preview` 的前导字节数
+	pub preview_len: usize,
+	/// en: Also put the whole data of each format into `content`
+	/// zh: 同时将每种格式的完整数据放入 `content`
+	pub include_content: bool,
+}
+
+impl Default for EventOptions {
+	fn default() -> Self {
+		EventOptions {
+			preview_len: 64,
+			include_content: false,
+		}
+	}
+}
+
+/// en: One clipboard change. Hashes are 16 hex digits so JavaScript doesn't round them,
+/// byte data is standard base64
+/// zh: 一次剪贴板变化。哈希为 16 位十六进制字符串，避免 JavaScript 损失精度，字节数据为标准 base64
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipboardEvent {
+	/// en: Always `CLIPBOARD_EVENT_VERSION`
+	/// zh: 始终为 `CLIPBOARD_EVENT_VERSION`
+	pub version: u32,
+	/// en: Milliseconds since the Unix epoch when the change was read
+	/// zh: 读取变化时距 Unix 纪元的毫秒数
+	pub timestamp: u64,
+	/// en: A hash over all formats, independent of their order
+	/// zh: 所有格式的哈希，与格式顺序无关
+	pub hash: String,
+	/// en: The formats in the order the platform lists them
+	/// zh: 按平台列出顺序排列的格式
+	pub formats: Vec<EventFormat>,
+}
+
+/// en: One format of a `ClipboardEvent`
+/// zh: `ClipboardEvent` 中的一种格式
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventFormat {
+	/// en: The platform format name, as in `available_formats`
+	/// zh: 平台格式名称，与 `available_formats` 相同
+	pub name: String,
+	/// en: The matching MIME type, when known
+	/// zh: 对应的 MIME 类型（若已知）
+	pub mime: Option<String>,
+	/// en: Size of the data in bytes, `None` when it couldn't be read
+	/// zh: 数据的字节大小，无法读取时为 `None`
+	pub size: Option<usize>,
+	/// en: FNV-1a hash of the data
+	/// zh: 数据的 FNV-1a 哈希
+	pub hash: Option<String>,
+	/// en: The first `preview_len` bytes, base64 encoded
+	/// zh: 前 `preview_len` 个字节，base64 编码
+	pub preview: Option<String>,
+	/// en: All of the data base64 encoded, only with `include_content`
+	/// zh: base64 编码的完整数据，仅在 `include_content` 时存在
+	pub content: Option<String>,
+	/// en: Why the data couldn't be read
+	/// zh: 数据无法读取的原因
+	pub error: Option<String>,
+}
+
+impl ClipboardEvent {
+	/// en: Read every format of the clipboard into an event
+	/// zh: 将剪贴板的所有格式读入一个事件
+	pub fn capture<C: Clipboard + ?Sized>(clipboard: &C, options: &EventOptions) -> Result<Self> {
+		let mut hashes = Vec::new();
+		let formats = clipboard
+			.available_formats_detailed()?
+			.into_iter()
+			.map(|info| match clipboard.get_buffer(&info.name) {
+				Ok(data) => {
+					let mut hasher = Fnv64::new();
+					hasher.write(&data);
+					hashes.push(hasher.finish());
+					let preview = &data[..data.len().min(options.preview_len)];
+					EventFormat {
+						name: info.name,
+						mime: info.mime,
+						size: Some(data.len()),
+						hash: Some(format!("{:016x}", hasher.finish())),
+						preview: Some(base64_encode(preview)),
+						content: if options.include_content {
+							Some(base64_encode(&data))
+						} else {
+							None
+						},
+						error: None,
+					}
+				}
+				Err(e) => EventFormat {
+					name: info.name,
+					mime: info.mime,
+					size: None,
+					hash: None,
+					preview: None,
+					content: None,
+					error: Some(e.to_string()),
+				},
+			})
+			.collect();
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |d| d.as_millis() as u64);
+		Ok(ClipboardEvent {
+			version: CLIPBOARD_EVENT_VERSION,
+			timestamp,
+			hash: format!("{:016x}", combine_hashes(hashes)),
+			formats,
+		})
+	}
+
+	/// en: The event as one line of JSON, the same document `serde_json` writes with the `serde` feature
+	/// zh: 以单行 JSON 表示的事件，与启用 `serde` 特性时 `serde_json` 写出的文档相同
+	pub fn to_json(&self) -> String {
+		let formats = self
+			.formats
+			.iter()
+			.map(|f| {
+				format!(
+					"{{\"name\":{},\"mime\":{},\"size\":{},\"hash\":{},\"preview\":{},\"content\":{},\"error\":{}}}",
+					json_string(&f.name),
+					json_option(f.mime.as_deref()),
+					f.size.map_or("null".to_string(), |s| s.to_string()),
+					json_option(f.hash.as_deref()),
+					json_option(f.preview.as_deref()),
+					json_option(f.content.as_deref()),
+					json_option(f.error.as_deref()),
+				)
+			})
+			.collect::<Vec<_>>()
+			.join(",");
+		format!(
+			"{{\"version\":{},\"timestamp\":{},\"hash\":{},\"formats\":[{}]}}",
+			self.version,
+			self.timestamp,
+			json_string(&self.hash),
+			formats
+		)
+	}
+}
+
+fn json_option(s: Option<&str>) -> String {
+	s.map_or("null".to_string(), json_string)
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+	let mut out = String::with_capacity(s.len() + 2);
+	out.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+/// en: A watcher handler writing each change as a line of JSON, e.g. to stdout or a socket
+/// zh: 将每次变化写为一行 JSON 的监视器处理器，例如写到标准输出或套接字
+pub struct EventWriter<C, W> {
+	clipboard: C,
+	writer: W,
+	options: EventOptions,
+}
+
+impl<C: Clipboard, W: Write> EventWriter<C, W> {
+	pub fn new(clipboard: C, writer: W, options: EventOptions) -> Self {
+		EventWriter {
+			clipboard,
+			writer,
+			options,
+		}
+	}
+
+	/// en: Write the current clipboard as one event line
+	/// zh: 将当前剪贴板写为一行事件
+	pub fn write_event(&mut self) -> Result<()> {
+		let event = ClipboardEvent::capture(&self.clipboard, &self.options)?;
+		writeln!(self.writer, "{}", event.to_json())?;
+		self.writer.flush()?;
+		Ok(())
+	}
+
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+impl<C: Clipboard, W: Write> ClipboardHandler for EventWriter<C, W> {
+	fn on_clipboard_change(&mut self) {
+		// a closed pipe or unreadable clipboard shouldn't stop the watcher
+		let _ = self.write_event();
+	}
+}
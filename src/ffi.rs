@@ -0,0 +1,312 @@
+//! en: A C ABI over the system clipboard, see `include/clipboard_rs.h`. Functions returning `int32_t`
+//! give 0 on success and -1 on failure, `clipboard_last_error` tells why. Returned strings and
+//! byte buffers belong to the caller and are released with `clipboard_string_free` and `clipboard_bytes_free`.
+//! Build the shared library with `cargo rustc --release --features ffi --crate-type cdylib`
+//!
+//! zh: 系统剪贴板的 C ABI，参见 `include/clipboard_rs.h`。返回 `int32_t` 的函数成功时返回 0，失败时返回 -1，
+//! 可通过 `clipboard_last_error` 获得原因。返回的字符串与字节缓冲区归调用方所有，需使用
+//! `clipboard_string_free` 与 `clipboard_bytes_free` 释放。
+//! 使用 `cargo rustc --release --features ffi --crate-type cdylib` 构建动态库
+
+use crate::common::{Result, RustImage};
+use crate::{
+	Clipboard, ClipboardContext, ClipboardHandler, ClipboardWatcher, ClipboardWatcherContext,
+	ContentFormat, WatcherShutdown,
+};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+thread_local! {
+	static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The clipboard handle of the C API
+pub struct ClipboardRs {
+	ctx: ClipboardContext,
+}
+
+/// A running watcher, stopped and freed by `clipboard_watch_stop`
+pub struct ClipboardWatchHandle {
+	shutdown: WatcherShutdown,
+	thread: JoinHandle<()>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardFormat {
+	Text = 0,
+	Rtf = 1,
+	Html = 2,
+	Image = 3,
+	Files = 4,
+	Svg = 5,
+	Markdown = 6,
+	Url = 7,
+}
+
+impl From<ClipboardFormat> for ContentFormat {
+	fn from(format: ClipboardFormat) -> Self {
+		match format {
+			ClipboardFormat::Text => ContentFormat::Text,
+			ClipboardFormat::Rtf => ContentFormat::Rtf,
+			ClipboardFormat::Html => ContentFormat::Html,
+			ClipboardFormat::Image => ContentFormat::Image,
+			ClipboardFormat::Files => ContentFormat::Files,
+			ClipboardFormat::Svg => ContentFormat::Svg,
+			ClipboardFormat::Markdown => ContentFormat::Markdown,
+			ClipboardFormat::Url => ContentFormat::Url,
+		}
+	}
+}
+
+/// Called on the watcher thread after every clipboard change, with the `user_data` given to `clipboard_watch`
+pub type ClipboardChangeCallback = extern "C" fn(user_data: *mut c_void);
+
+struct CallbackHandler {
+	callback: ClipboardChangeCallback,
+	user_data: *mut c_void,
+}
+
+// the caller promises the user data can be used from the watcher thread
+unsafe impl Send for CallbackHandler {}
+
+impl ClipboardHandler for CallbackHandler {
+	fn on_clipboard_change(&mut self) {
+		(self.callback)(self.user_data);
+	}
+}
+
+fn set_last_error(message: String) {
+	let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+	LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Report the outcome of an operation as 0 or -1
+fn status(res: Result<()>) -> i32 {
+	match res {
+		Ok(()) => 0,
+		Err(e) => {
+			set_last_error(e.to_string());
+			-1
+		}
+	}
+}
+
+fn string_or_null(res: Result<String>) -> *mut c_char {
+	match res.and_then(|s| Ok(CString::new(s)?)) {
+		Ok(s) => s.into_raw(),
+		Err(e) => {
+			set_last_error(e.to_string());
+			ptr::null_mut()
+		}
+	}
+}
+
+unsafe fn read_str<'a>(text: *const c_char) -> Result<&'a str> {
+	if text.is_null() {
+		return Err("null string".into());
+	}
+	Ok(CStr::from_ptr(text).to_str()?)
+}
+
+unsafe fn context<'a>(clipboard: *const ClipboardRs) -> Result<&'a ClipboardContext> {
+	clipboard
+		.as_ref()
+		.map(|clipboard| &clipboard.ctx)
+		.ok_or_else(|| "null clipboard".into())
+}
+
+/// Create a clipboard handle, null on failure. Free it with `clipboard_free`
+#[no_mangle]
+pub extern "C" fn clipboard_new() -> *mut ClipboardRs {
+	match ClipboardContext::new() {
+		Ok(ctx) => Box::into_raw(Box::new(ClipboardRs { ctx })),
+		Err(e) => {
+			set_last_error(e.to_string());
+			ptr::null_mut()
+		}
+	}
+}
+
+/// # Safety
+/// `clipboard` must come from `clipboard_new` and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_free(clipboard: *mut ClipboardRs) {
+	if !clipboard.is_null() {
+		drop(Box::from_raw(clipboard));
+	}
+}
+
+/// The message of the last failure on this thread, null when nothing failed.
+/// The string stays valid until the next failing call on this thread, don't free it
+#[no_mangle]
+pub extern "C" fn clipboard_last_error() -> *const c_char {
+	LAST_ERROR.with(|last| {
+		last.borrow()
+			.as_ref()
+			.map_or(ptr::null(), |message| message.as_ptr())
+	})
+}
+
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_has(
+	clipboard: *const ClipboardRs,
+	format: ClipboardFormat,
+) -> bool {
+	match context(clipboard) {
+		Ok(ctx) => ctx.has(format.into()),
+		Err(_) => false,
+	}
+}
+
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_clear(clipboard: *const ClipboardRs) -> i32 {
+	status(context(clipboard).and_then(|ctx| ctx.clear()))
+}
+
+/// The text in the clipboard, null when there is none. Free it with `clipboard_string_free`
+///
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_get_text(clipboard: *const ClipboardRs) -> *mut c_char {
+	string_or_null(context(clipboard).and_then(|ctx| ctx.get_text()))
+}
+
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`, `text` a NUL terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_set_text(
+	clipboard: *const ClipboardRs,
+	text: *const c_char,
+) -> i32 {
+	status(context(clipboard).and_then(|ctx| ctx.set_text(read_str(text)?.to_owned())))
+}
+
+/// The html in the clipboard, null when there is none. Free it with `clipboard_string_free`
+///
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_get_html(clipboard: *const ClipboardRs) -> *mut c_char {
+	string_or_null(context(clipboard).and_then(|ctx| ctx.get_html()))
+}
+
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`, `html` a NUL terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_set_html(
+	clipboard: *const ClipboardRs,
+	html: *const c_char,
+) -> i32 {
+	status(context(clipboard).and_then(|ctx| ctx.set_html(read_str(html)?.to_owned())))
+}
+
+/// The image in the clipboard as PNG, null when there is none. The length goes to `len`,
+/// free the bytes with `clipboard_bytes_free`
+///
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`, `len` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_get_image_png(
+	clipboard: *const ClipboardRs,
+	len: *mut usize,
+) -> *mut u8 {
+	let png =
+		context(clipboard).and_then(|ctx| Ok(ctx.get_image()?.to_png()?.get_bytes().to_vec()));
+	match png {
+		Ok(png) => {
+			let png = png.into_boxed_slice();
+			if !len.is_null() {
+				*len = png.len();
+			}
+			Box::into_raw(png) as *mut u8
+		}
+		Err(e) => {
+			set_last_error(e.to_string());
+			ptr::null_mut()
+		}
+	}
+}
+
+/// # Safety
+/// `clipboard` must be a live handle from `clipboard_new`, `png` must point to `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_set_image_png(
+	clipboard: *const ClipboardRs,
+	png: *const u8,
+	len: usize,
+) -> i32 {
+	status(context(clipboard).and_then(|ctx| {
+		if png.is_null() {
+			return Err("null image".into());
+		}
+		ctx.set_image_png(std::slice::from_raw_parts(png, len).to_vec())
+	}))
+}
+
+/// # Safety
+/// `text` must come from this library and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_string_free(text: *mut c_char) {
+	if !text.is_null() {
+		drop(CString::from_raw(text));
+	}
+}
+
+/// # Safety
+/// `bytes` and `len` must come from this library and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_bytes_free(bytes: *mut u8, len: usize) {
+	if !bytes.is_null() {
+		drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)));
+	}
+}
+
+/// Watch the clipboard on a new thread, `callback` runs there after every change.
+/// Returns null on failure, stop the watcher with `clipboard_watch_stop`
+///
+/// # Safety
+/// `user_data` must be usable from the watcher thread until the watcher is stopped
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_watch(
+	callback: ClipboardChangeCallback,
+	user_data: *mut c_void,
+) -> *mut ClipboardWatchHandle {
+	let mut watcher = match ClipboardWatcherContext::new() {
+		Ok(watcher) => watcher,
+		Err(e) => {
+			set_last_error(e.to_string());
+			return ptr::null_mut();
+		}
+	};
+	watcher.add_handler(CallbackHandler {
+		callback,
+		user_data,
+	});
+	let shutdown = watcher.get_shutdown_channel();
+	let thread = thread::spawn(move || watcher.start_watch(Duration::from_millis(100)));
+	Box::into_raw(Box::new(ClipboardWatchHandle { shutdown, thread }))
+}
+
+/// Stop a watcher and wait for its thread, no callback runs once this returns
+///
+/// # Safety
+/// `handle` must come from `clipboard_watch` and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_watch_stop(handle: *mut ClipboardWatchHandle) {
+	if handle.is_null() {
+		return;
+	}
+	let handle = Box::from_raw(handle);
+	handle.shutdown.stop();
+	let _ = handle.thread.join();
+}
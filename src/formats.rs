@@ -0,0 +1,383 @@
+//! en: Names and parsers for formats other applications put on the clipboard, read them with
+//! `Clipboard::get_buffer` and hand the bytes to the parsers here
+//!
+//! zh: 其他应用放入剪贴板的格式名称与解析器，使用 `Clipboard::get_buffer` 读取后将字节交给这里的解析器
+
+use crate::common::{
+	moz_url_to_urls, string_to_utf16le, urls_to_moz_url, utf16le_to_string, ContentFormat, Result,
+	Url,
+};
+use crate::event::json_string;
+use crate::Clipboard;
+use std::collections::HashMap;
+
+/// en: Firefox's url list, each url followed by its title on the next line, in UTF-16
+/// zh: Firefox 的 url 列表，每个 url 的下一行为其标题，使用 UTF-16 编码
+pub const MOZ_URL: &str = "text/x-moz-url";
+/// en: The page a Firefox selection was copied from, same layout as `MOZ_URL`
+/// zh: Firefox 选区被复制时所在的页面，结构同 `MOZ_URL`
+pub const MOZ_URL_PRIV: &str = "text/x-moz-url-priv";
+
+/// en: The page a Chromium selection was copied from, on macOS
+/// zh: Chromium 选区被复制时所在的页面（macOS）
+pub const CHROMIUM_SOURCE_URL_MACOS: &str = "org.chromium.source-url";
+/// en: The page a Chromium selection was copied from, on Linux
+/// zh: Chromium 选区被复制时所在的页面（Linux）
+pub const CHROMIUM_SOURCE_URL_LINUX: &str = "chromium/x-source-url";
+
+/// en: Data a web page put on the clipboard under its own mime types, on Windows
+/// zh: 网页以自定义 mime 类型放入剪贴板的数据（Windows）
+pub const WEB_CUSTOM_DATA_WINDOWS: &str = "Chromium Web Custom MIME Data";
+/// en: Data a web page put on the clipboard under its own mime types, on macOS
+/// zh: 网页以自定义 mime 类型放入剪贴板的数据（macOS）
+pub const WEB_CUSTOM_DATA_MACOS: &str = "org.chromium.web-custom-data";
+/// en: Data a web page put on the clipboard under its own mime types, on Linux
+/// zh: 网页以自定义 mime 类型放入剪贴板的数据（Linux）
+pub const WEB_CUSTOM_DATA_LINUX: &str = "chromium/x-web-custom-data";
+
+/// en: The web custom data format name of the current platform
+/// zh: 当前平台的网页自定义数据格式名称
+pub const WEB_CUSTOM_DATA: &str = if cfg!(target_os = "windows") {
+	WEB_CUSTOM_DATA_WINDOWS
+} else if cfg!(target_os = "macos") {
+	WEB_CUSTOM_DATA_MACOS
+} else {
+	WEB_CUSTOM_DATA_LINUX
+};
+
+/// en: The mime type VS Code stores its editor metadata under, inside the web custom data
+/// zh: VS Code 存放编辑器元数据所用的 mime 类型，位于网页自定义数据中
+pub const VSCODE_EDITOR_DATA: &str = "vscode-editor-data";
+
+/// en: Read a `MOZ_URL` or `MOZ_URL_PRIV`, Firefox writes UTF-16 but UTF-8 is accepted too
+/// zh: 读取 `MOZ_URL` 或 `MOZ_URL_PRIV`，Firefox 写入 UTF-16，也接受 UTF-8
+pub fn parse_moz_url(data: &[u8]) -> Vec<Url> {
+	moz_url_to_urls(&decode_text(data))
+}
+
+/// en: A `MOZ_URL` in UTF-16 as Firefox writes it
+/// zh: 按 Firefox 的方式以 UTF-16 编码的 `MOZ_URL`
+pub fn encode_moz_url(urls: &[Url]) -> Vec<u8> {
+	string_to_utf16le(&urls_to_moz_url(urls), false)
+}
+
+/// en: Read a Chromium source url, `None` when it is empty
+/// zh: 读取 Chromium 来源 url，为空时返回 `None`
+pub fn parse_source_url(data: &[u8]) -> Option<String> {
+	let url = decode_text(data);
+	let url = url.trim();
+	if url.is_empty() {
+		None
+	} else {
+		Some(url.to_owned())
+	}
+}
+
+// UTF-16 text of ASCII has a NUL in every other byte, UTF-8 text has none
+fn decode_text(data: &[u8]) -> String {
+	if data.len() >= 2 && data.len() % 2 == 0 && data[1] == 0 {
+		utf16le_to_string(data)
+	} else {
+		let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+		String::from_utf8_lossy(&data[..end]).into_owned()
+	}
+}
+
+/// en: Read a Chromium web custom data pickle, the mime types a page set mapped to their data
+/// zh: 读取 Chromium 网页自定义数据 pickle，即网页设置的 mime 类型到数据的映射
+pub fn decode_web_custom_data(data: &[u8]) -> Result<HashMap<String, String>> {
+	let mut pickle = PickleReader::new(data)?;
+	let count = pickle.read_u32()?;
+	let mut entries = HashMap::new();
+	for _ in 0..count {
+		let mime = pickle.read_string16()?;
+		let value = pickle.read_string16()?;
+		entries.insert(mime, value);
+	}
+	Ok(entries)
+}
+
+/// en: A Chromium web custom data pickle of the entries, written in mime type order
+/// zh: 由条目生成的 Chromium 网页自定义数据 pickle，按 mime 类型排序写入
+pub fn encode_web_custom_data(entries: &HashMap<String, String>) -> Vec<u8> {
+	let mut sorted: Vec<_> = entries.iter().collect();
+	sorted.sort();
+	// the header holds the payload size, filled in at the end
+	let mut pickle = vec![0; 4];
+	pickle.extend_from_slice(&(sorted.len() as u32).to_le_bytes());
+	for (mime, value) in sorted {
+		write_string16(&mut pickle, mime);
+		write_string16(&mut pickle, value);
+	}
+	let payload_size = (pickle.len() - 4) as u32;
+	pickle[..4].copy_from_slice(&payload_size.to_le_bytes());
+	pickle
+}
+
+/// en: The web custom data on the clipboard, empty when there is none
+/// zh: 剪贴板上的网页自定义数据，没有时为空
+pub fn read_web_custom_data<C: Clipboard + ?Sized>(
+	clipboard: &C,
+) -> Result<HashMap<String, String>> {
+	if !clipboard.has(ContentFormat::Other(WEB_CUSTOM_DATA.to_string())) {
+		return Ok(HashMap::new());
+	}
+	decode_web_custom_data(&clipboard.get_buffer(WEB_CUSTOM_DATA)?)
+}
+
+// a string16 is its length in UTF-16 units followed by the units, padded to 4 bytes
+fn write_string16(pickle: &mut Vec<u8>, s: &str) {
+	let units = string_to_utf16le(s, false);
+	pickle.extend_from_slice(&((units.len() / 2) as u32).to_le_bytes());
+	pickle.extend_from_slice(&units);
+	pickle.resize(align4(pickle.len()), 0);
+}
+
+fn align4(n: usize) -> usize {
+	(n + 3) & !3
+}
+
+struct PickleReader<'a> {
+	payload: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> PickleReader<'a> {
+	fn new(data: &'a [u8]) -> Result<Self> {
+		if data.len() < 4 {
+			return Err("web custom data is too short".into());
+		}
+		let size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+		let payload = data
+			.get(4..4 + size)
+			.ok_or("web custom data is shorter than its header says")?;
+		Ok(PickleReader { payload, pos: 0 })
+	}
+
+	fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+		let bytes = self
+			.payload
+			.get(self.pos..self.pos + len)
+			.ok_or("web custom data ends early")?;
+		self.pos = align4(self.pos + len);
+		Ok(bytes)
+	}
+
+	fn read_u32(&mut self) -> Result<u32> {
+		let b = self.read_bytes(4)?;
+		Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+	}
+
+	fn read_string16(&mut self) -> Result<String> {
+		let len = self.read_u32()? as usize;
+		let units: Vec<u16> = self
+			.read_bytes(len * 2)?
+			.chunks_exact(2)
+			.map(|c| u16::from_le_bytes([c[0], c[1]]))
+			.collect();
+		Ok(String::from_utf16_lossy(&units))
+	}
+}
+
+/// en: VS Code's metadata for a copy from its editor
+/// zh: 从 VS Code 编辑器复制时附带的元数据
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VsCodeEditorData {
+	pub version: u32,
+	/// en: The whole line was copied because nothing was selected
+	/// zh: 因未选中内容而复制了整行
+	pub is_from_empty_selection: bool,
+	/// en: The text of each cursor when copied with several cursors
+	/// zh: 多光标复制时每个光标的文本
+	pub multicursor_text: Option<Vec<String>>,
+	/// en: The language mode of the editor, e.g. `rust`
+	/// zh: 编辑器的语言模式，例如 `rust`
+	pub mode: Option<String>,
+}
+
+impl VsCodeEditorData {
+	/// en: Read the `VSCODE_EDITOR_DATA` JSON, unknown fields are ignored
+	/// zh: 读取 `VSCODE_EDITOR_DATA` JSON，未知字段会被忽略
+	pub fn parse(json: &str) -> Result<Self> {
+		let mut data = VsCodeEditorData::default();
+		for (key, value) in JsonReader::new(json).object()? {
+			match (key.as_str(), value) {
+				("version", JsonValue::Number(n)) => data.version = n as u32,
+				("isFromEmptySelection", JsonValue::Bool(b)) => data.is_from_empty_selection = b,
+				("multicursorText", JsonValue::Strings(s)) => data.multicursor_text = Some(s),
+				("mode", JsonValue::String(s)) => data.mode = Some(s),
+				_ => {}
+			}
+		}
+		Ok(data)
+	}
+
+	/// en: The JSON VS Code reads back
+	/// zh: VS Code 可读回的 JSON
+	pub fn to_json(&self) -> String {
+		let multicursor_text = match &self.multicursor_text {
+			Some(lines) => format!(
+				"[{}]",
+				lines
+					.iter()
+					.map(|line| json_string(line))
+					.collect::<Vec<_>>()
+					.join(",")
+			),
+			None => "null".to_string(),
+		};
+		format!(
+			"{{\"version\":{},\"isFromEmptySelection\":{},\"multicursorText\":{},\"mode\":{}}}",
+			self.version,
+			self.is_from_empty_selection,
+			multicursor_text,
+			self.mode.as_deref().map_or("null".to_string(), json_string)
+		)
+	}
+}
+
+enum JsonValue {
+	Null,
+	Bool(bool),
+	Number(f64),
+	String(String),
+	Strings(Vec<String>),
+}
+
+// just enough JSON for the flat objects editors write: strings, numbers, booleans, null and string arrays
+struct JsonReader<'a> {
+	chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> JsonReader<'a> {
+	fn new(json: &'a str) -> Self {
+		JsonReader {
+			chars: json.chars().peekable(),
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.chars.peek().map_or(false, |c| c.is_whitespace()) {
+			self.chars.next();
+		}
+	}
+
+	fn expect(&mut self, expected: char) -> Result<()> {
+		self.skip_whitespace();
+		match self.chars.next() {
+			Some(c) if c == expected => Ok(()),
+			other => Err(format!("expected '{}' in JSON, found {:?}", expected, other).into()),
+		}
+	}
+
+	// consume `c` when it is next
+	fn eat(&mut self, c: char) -> bool {
+		self.skip_whitespace();
+		if self.chars.peek() == Some(&c) {
+			self.chars.next();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn object(&mut self) -> Result<Vec<(String, JsonValue)>> {
+		self.expect('{')?;
+		let mut fields = Vec::new();
+		if self.eat('}') {
+			return Ok(fields);
+		}
+		loop {
+			self.expect('"')?;
+			let key = self.string()?;
+			self.expect(':')?;
+			fields.push((key, self.value()?));
+			if !self.eat(',') {
+				self.expect('}')?;
+				return Ok(fields);
+			}
+		}
+	}
+
+	fn value(&mut self) -> Result<JsonValue> {
+		if self.eat('"') {
+			return Ok(JsonValue::String(self.string()?));
+		}
+		if self.eat('[') {
+			let mut items = Vec::new();
+			if self.eat(']') {
+				return Ok(JsonValue::Strings(items));
+			}
+			loop {
+				self.expect('"')?;
+				items.push(self.string()?);
+				if !self.eat(',') {
+					self.expect(']')?;
+					return Ok(JsonValue::Strings(items));
+				}
+			}
+		}
+		let mut word = String::new();
+		while let Some(c) = self.chars.peek() {
+			if c.is_alphanumeric() || matches!(c, '-' | '+' | '.') {
+				word.push(*c);
+				self.chars.next();
+			} else {
+				break;
+			}
+		}
+		match word.as_str() {
+			"null" => Ok(JsonValue::Null),
+			"true" => Ok(JsonValue::Bool(true)),
+			"false" => Ok(JsonValue::Bool(false)),
+			_ => word
+				.parse()
+				.map(JsonValue::Number)
+				.map_err(|_| format!("unsupported JSON value {:?}", word).into()),
+		}
+	}
+
+	// the rest of a string after its opening quote
+	fn string(&mut self) -> Result<String> {
+		let mut out = String::new();
+		loop {
+			match self.chars.next() {
+				Some('"') => return Ok(out),
+				Some('\\') => match self.chars.next() {
+					Some('n') => out.push('\n'),
+					Some('r') => out.push('\r'),
+					Some('t') => out.push('\t'),
+					Some('b') => out.push('\u{8}'),
+					Some('f') => out.push('\u{c}'),
+					Some('u') => {
+						let unit = self.hex4()?;
+						// a surrogate pair is written as two escapes, look ahead before taking the
+						// second one, a lone surrogate becomes U+FFFD
+						let mut ahead = self.chars.clone();
+						if (0xd800..0xdc00).contains(&unit)
+							&& ahead.next() == Some('\\')
+							&& ahead.next() == Some('u')
+						{
+							self.chars.nth(1);
+							let low = self.hex4()?;
+							out.push_str(&String::from_utf16_lossy(&[unit, low]));
+						} else {
+							out.push_str(&String::from_utf16_lossy(&[unit]));
+						}
+					}
+					Some(c) => out.push(c),
+					None => break,
+				},
+				Some(c) => out.push(c),
+				None => break,
+			}
+		}
+		Err("unterminated JSON string".into())
+	}
+
+	fn hex4(&mut self) -> Result<u16> {
+		let digits: String = (0..4).filter_map(|_| self.chars.next()).collect();
+		u16::from_str_radix(&digits, 16)
+			.map_err(|_| format!("invalid JSON escape {:?}", digits).into())
+	}
+}
@@ -0,0 +1,251 @@
+//! en: Clipboard history, records the clipboard every time it changes and can put an old entry back
+//!
+//! zh: 剪贴板历史，在剪贴板每次变化时记录内容，并可将旧条目重新放回剪贴板
+
+use crate::common::{Result, RustImage, RustImageData};
+use crate::{Clipboard, ClipboardContent, ClipboardHandler, ContentFormat};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+pub mod store;
+
+pub use store::{FileHistoryStore, HistoryStore};
+
+/// en: What the history keeps
+/// zh: 历史记录保存哪些内容
+#[derive(Debug, Clone)]
+pub struct HistoryOptions {
+	/// en: The number of entries kept, the oldest ones are dropped first
+	/// zh: 保留的条目数量，最旧的条目最先被丢弃
+	pub capacity: usize,
+	/// en: The formats recorded for each entry
+	/// zh: 每个条目记录的格式
+	pub formats: Vec<ContentFormat>,
+	/// en: Contents larger than this many bytes are not recorded, images count 4 bytes per pixel
+	/// zh: 大于该字节数的内容不会被记录，图片按每像素 4 字节计算
+	pub max_content_size: usize,
+	/// en: Copying an entry already in the history moves it to the front instead of adding it again
+	/// zh: 再次复制已存在于历史中的条目时，将其移到最前而不是重复添加
+	pub dedupe: bool,
+	/// en: When set, entries with an image also get a thumbnail fitting in a `size` x `size` square,
+	/// made while recording on the watcher thread, so list views don't scale full screenshots
+	/// zh: 设置后，含图片的条目会额外生成适合 `size` x `size` 正方形的缩略图，在监视器线程中记录时生成，
+	/// 列表视图无需缩放完整的截图
+	pub thumbnail_size: Option<u32>,
+}
+
+impl Default for HistoryOptions {
+	fn default() -> Self {
+		HistoryOptions {
+			capacity: 50,
+			formats: vec![
+				ContentFormat::Text,
+				ContentFormat::Rtf,
+				ContentFormat::Html,
+				ContentFormat::Image,
+				ContentFormat::Files,
+				ContentFormat::Url,
+			],
+			max_content_size: 16 * 1024 * 1024,
+			dedupe: true,
+			thumbnail_size: None,
+		}
+	}
+}
+
+/// en: One copy, with every recorded format of it
+/// zh: 一次复制的内容，包含其所有被记录的格式
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryEntry {
+	pub contents: Vec<ClipboardContent>,
+	pub time: SystemTime,
+	/// en: The scaled down image of the entry, see `HistoryOptions::thumbnail_size`
+	/// zh: 条目中图片的缩略图，见 `HistoryOptions::thumbnail_size`
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub thumbnail: Option<RustImageData>,
+}
+
+impl HistoryEntry {
+	/// en: The image to show for the entry, the thumbnail when there is one
+	/// zh: 用于展示条目的图片，有缩略图时返回缩略图
+	pub fn preview(&self) -> Option<&RustImageData> {
+		self.thumbnail.as_ref().or_else(|| {
+			self.contents.iter().find_map(|content| match content {
+				ClipboardContent::Image(image) => Some(image),
+				_ => None,
+			})
+		})
+	}
+}
+
+/// en: Records the clipboard on every change when added as a watcher handler, clones share the same entries.
+/// Entries are ordered from the newest (index 0) to the oldest
+///
+/// zh: 作为监视器的处理器添加后，在每次变化时记录剪贴板，克隆之间共享同一份条目。
+/// 条目按从新（索引 0）到旧排列
+#[derive(Clone)]
+pub struct ClipboardHistory {
+	clipboard: Arc<dyn Clipboard + Send + Sync>,
+	options: Arc<HistoryOptions>,
+	entries: Arc<Mutex<VecDeque<HistoryEntry>>>,
+	store: Option<Arc<dyn HistoryStore>>,
+}
+
+impl ClipboardHistory {
+	pub fn new<C: Clipboard + Sync + 'static>(clipboard: C, options: HistoryOptions) -> Self {
+		ClipboardHistory {
+			clipboard: Arc::new(clipboard),
+			options: Arc::new(options),
+			entries: Arc::new(Mutex::new(VecDeque::new())),
+			store: None,
+		}
+	}
+
+	/// en: A history that starts with the entries of `store` and saves every change to it
+	/// zh: 以 `store` 中的条目开始，并将每次变化保存到其中的历史
+	pub fn with_store<C: Clipboard + Sync + 'static, S: HistoryStore + 'static>(
+		clipboard: C,
+		options: HistoryOptions,
+		store: S,
+	) -> Result<Self> {
+		let mut entries: VecDeque<HistoryEntry> = store.load()?.into();
+		entries.truncate(options.capacity);
+		for entry in entries.iter_mut() {
+			entry.thumbnail = thumbnail(&entry.contents, options.thumbnail_size);
+		}
+		Ok(ClipboardHistory {
+			clipboard: Arc::new(clipboard),
+			options: Arc::new(options),
+			entries: Arc::new(Mutex::new(entries)),
+			store: Some(Arc::new(store)),
+		})
+	}
+
+	pub fn options(&self) -> &HistoryOptions {
+		&self.options
+	}
+
+	/// en: Read the clipboard and add it to the history, returns false when nothing was added,
+	/// e.g. the clipboard is empty or holds the newest entry already
+	/// zh: 读取剪贴板并加入历史，未添加时返回 false，例如剪贴板为空或内容与最新条目相同
+	pub fn record(&self) -> Result<bool> {
+		let contents: Vec<ClipboardContent> = self
+			.clipboard
+			.get(&self.options.formats)?
+			.into_iter()
+			.filter(|content| content.size() <= self.options.max_content_size)
+			.collect();
+		self.push(contents)
+	}
+
+	/// en: Add contents to the history without reading the clipboard
+	/// zh: 不读取剪贴板，直接将内容加入历史
+	pub fn push(&self, contents: Vec<ClipboardContent>) -> Result<bool> {
+		if contents.is_empty() || self.options.capacity == 0 {
+			return Ok(false);
+		}
+		// scaled before locking, readers of the history don't wait for it
+		let thumbnail = thumbnail(&contents, self.options.thumbnail_size);
+		let mut entries = self.lock();
+		if let Some(index) = entries.iter().position(|entry| entry.contents == contents) {
+			// the same copy reported twice is not a new entry
+			if index == 0 {
+				return Ok(false);
+			}
+			if self.options.dedupe {
+				entries.remove(index);
+			}
+		}
+		entries.push_front(HistoryEntry {
+			contents,
+			time: SystemTime::now(),
+			thumbnail,
+		});
+		entries.truncate(self.options.capacity);
+		self.save(&mut entries)?;
+		Ok(true)
+	}
+
+	/// en: Put an entry back onto the clipboard, it becomes the newest entry
+	/// zh: 将条目重新放回剪贴板，该条目成为最新条目
+	pub fn restore(&self, index: usize) -> Result<()> {
+		let mut entries = self.lock();
+		let mut entry = entries.remove(index).ok_or("history index out of range")?;
+		if let Err(e) = self.clipboard.set(entry.contents.clone()) {
+			entries.insert(index, entry);
+			return Err(e);
+		}
+		entry.time = SystemTime::now();
+		entries.push_front(entry);
+		self.save(&mut entries)
+	}
+
+	pub fn get(&self, index: usize) -> Option<HistoryEntry> {
+		self.lock().get(index).cloned()
+	}
+
+	/// en: A snapshot of all entries, newest first
+	/// zh: 所有条目的快照，最新的在前
+	pub fn entries(&self) -> Vec<HistoryEntry> {
+		self.lock().iter().cloned().collect()
+	}
+
+	pub fn remove(&self, index: usize) -> Result<Option<HistoryEntry>> {
+		let mut entries = self.lock();
+		let entry = entries.remove(index);
+		if entry.is_some() {
+			self.save(&mut entries)?;
+		}
+		Ok(entry)
+	}
+
+	pub fn len(&self) -> usize {
+		self.lock().len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.lock().is_empty()
+	}
+
+	pub fn clear(&self) -> Result<()> {
+		let mut entries = self.lock();
+		entries.clear();
+		self.save(&mut entries)
+	}
+
+	fn save(&self, entries: &mut VecDeque<HistoryEntry>) -> Result<()> {
+		match &self.store {
+			Some(store) => store.save(entries.make_contiguous()),
+			None => Ok(()),
+		}
+	}
+
+	fn lock(&self) -> MutexGuard<'_, VecDeque<HistoryEntry>> {
+		// an entry list is never left half updated, a panic elsewhere doesn't poison it
+		self.entries.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl ClipboardHandler for ClipboardHistory {
+	fn on_clipboard_change(&mut self) {
+		// an empty or unreadable clipboard is simply not recorded
+		let _ = self.record();
+	}
+}
+
+/// The thumbnail of the first image in `contents`, images already within `size` need none
+fn thumbnail(contents: &[ClipboardContent], size: Option<u32>) -> Option<RustImageData> {
+	let size = size?;
+	contents.iter().find_map(|content| match content {
+		ClipboardContent::Image(image) => {
+			let (width, height) = image.get_size();
+			if width <= size && height <= size {
+				return None;
+			}
+			image.thumbnail(size, size).ok()
+		}
+		_ => None,
+	})
+}
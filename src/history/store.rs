@@ -0,0 +1,389 @@
+//! en: Persistent storage for the clipboard history, so entries survive restarts
+//!
+//! zh: 剪贴板历史的持久化存储，使条目在重启后仍然保留
+
+use crate::common::{ContentData, Result, Rgba, RustImage, RustImageData, Url};
+use crate::history::HistoryEntry;
+use crate::{ClipboardContent, ContentFormat};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// en: Where a `ClipboardHistory` keeps its entries, entries are passed newest first
+/// zh: `ClipboardHistory` 保存条目的位置，条目按从新到旧传递
+pub trait HistoryStore: Send + Sync {
+	fn load(&self) -> Result<Vec<HistoryEntry>>;
+
+	/// en: Replace the stored entries with the given ones
+	/// zh: 用给定的条目替换已保存的条目
+	fn save(&self, entries: &[HistoryEntry]) -> Result<()>;
+}
+
+const MAGIC: &[u8; 4] = b"CRHS";
+const VERSION: u8 = 1;
+// like VERSION, with the content data in `compress` frames
+const VERSION_COMPRESSED: u8 = 2;
+
+/// en: Stores the history in a single file, which is rewritten on every change.
+/// Images are stored as PNG, scaled down to `thumbnail_size` when set
+///
+/// zh: 将历史保存在单个文件中，每次变化时重写该文件。
+/// 图片以 PNG 保存，设置 `thumbnail_size` 时会缩小到该尺寸
+#[derive(Debug, Clone)]
+pub struct FileHistoryStore {
+	path: PathBuf,
+	max_age: Option<Duration>,
+	max_size: Option<usize>,
+	thumbnail_size: Option<u32>,
+	compress: bool,
+}
+
+impl FileHistoryStore {
+	pub fn new<P: AsRef<Path>>(path: P) -> Self {
+		FileHistoryStore {
+			path: path.as_ref().to_path_buf(),
+			max_age: None,
+			max_size: None,
+			thumbnail_size: None,
+			compress: false,
+		}
+	}
+
+	/// en: Drop entries older than `max_age` when loading and saving
+	/// zh: 加载与保存时丢弃早于 `max_age` 的条目
+	pub fn with_max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = Some(max_age);
+		self
+	}
+
+	/// en: Keep the file below `max_size` bytes by dropping the oldest entries
+	/// zh: 通过丢弃最旧的条目使文件小于 `max_size` 字节
+	pub fn with_max_size(mut self, max_size: usize) -> Self {
+		self.max_size = Some(max_size);
+		self
+	}
+
+	/// en: Scale images down to fit in a `size` x `size` square, keeping the aspect ratio
+	/// zh: 将图片按比例缩小到 `size` x `size` 的正方形内
+	pub fn with_thumbnail_size(mut self, size: u32) -> Self {
+		self.thumbnail_size = Some(size);
+		self
+	}
+
+	/// en: Compress the text and custom format data with LZ4, images and data that is compressed already are kept as is.
+	/// Files written this way need the `compress` feature to be loaded
+	/// zh: 使用 LZ4 压缩文本与自定义格式的数据，图片与已压缩的数据保持原样。
+	/// 以此方式写入的文件需要启用 `compress` 特性才能加载
+	#[cfg(feature = "compress")]
+	pub fn with_compression(mut self) -> Self {
+		self.compress = true;
+		self
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	fn expired(&self, entry: &HistoryEntry) -> bool {
+		match (self.max_age, entry.time.elapsed()) {
+			(Some(max_age), Ok(age)) => age > max_age,
+			_ => false,
+		}
+	}
+}
+
+impl HistoryStore for FileHistoryStore {
+	fn load(&self) -> Result<Vec<HistoryEntry>> {
+		let bytes = match fs::read(&self.path) {
+			Ok(bytes) => bytes,
+			// nothing saved yet
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e.into()),
+		};
+		let mut reader = Reader {
+			bytes: &bytes,
+			compressed: false,
+		};
+		if reader.take(MAGIC.len())? != MAGIC {
+			return Err("not a clipboard history file".into());
+		}
+		reader.compressed = match reader.u8()? {
+			VERSION => false,
+			VERSION_COMPRESSED => true,
+			version => {
+				return Err(format!("unsupported clipboard history version {}", version).into())
+			}
+		};
+		let count = reader.u32()?;
+		let mut entries = Vec::new();
+		for _ in 0..count {
+			let entry = read_entry(&mut reader)?;
+			if !self.expired(&entry) {
+				entries.push(entry);
+			}
+		}
+		Ok(entries)
+	}
+
+	fn save(&self, entries: &[HistoryEntry]) -> Result<()> {
+		let mut records = Vec::with_capacity(entries.len());
+		let mut size = MAGIC.len() + 1 + 4;
+		for entry in entries.iter().filter(|entry| !self.expired(entry)) {
+			let mut record = Vec::new();
+			write_entry(&mut record, entry, self.thumbnail_size, self.compress)?;
+			// entries come newest first, everything past the limit is older
+			if self.max_size.map_or(false, |max| size + record.len() > max) {
+				break;
+			}
+			size += record.len();
+			records.push(record);
+		}
+
+		let mut bytes = Vec::with_capacity(size);
+		bytes.extend_from_slice(MAGIC);
+		bytes.push(if self.compress {
+			VERSION_COMPRESSED
+		} else {
+			VERSION
+		});
+		bytes.extend_from_slice(&(records.len() as u32).to_le_bytes());
+		for record in records {
+			bytes.extend_from_slice(&record);
+		}
+		// write aside and rename, a crash never leaves a truncated history behind
+		let mut tmp = self.path.clone().into_os_string();
+		tmp.push(".tmp");
+		fs::write(&tmp, bytes)?;
+		fs::rename(&tmp, &self.path)?;
+		Ok(())
+	}
+}
+
+fn write_entry(
+	out: &mut Vec<u8>,
+	entry: &HistoryEntry,
+	thumbnail_size: Option<u32>,
+	compress: bool,
+) -> Result<()> {
+	let time = entry.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+	out.extend_from_slice(&time.as_secs().to_le_bytes());
+	out.extend_from_slice(&time.subsec_nanos().to_le_bytes());
+	out.extend_from_slice(&(entry.contents.len() as u32).to_le_bytes());
+	for content in &entry.contents {
+		let format = content.get_format();
+		let write_tagged = |out: &mut Vec<u8>, tag: u8, bytes: &[u8]| {
+			out.push(tag);
+			if compress {
+				write_bytes(out, &pack(&format, bytes));
+			} else {
+				write_bytes(out, bytes);
+			}
+		};
+		match content {
+			ClipboardContent::Text(text) => write_tagged(out, 0, text.as_bytes()),
+			ClipboardContent::Rtf(text) => write_tagged(out, 1, text.as_bytes()),
+			ClipboardContent::Html(text) => write_tagged(out, 2, text.as_bytes()),
+			ClipboardContent::Svg(text) => write_tagged(out, 3, text.as_bytes()),
+			ClipboardContent::Markdown(text) => write_tagged(out, 4, text.as_bytes()),
+			ClipboardContent::Image(image) => {
+				let png = if image.is_empty() {
+					Vec::new()
+				} else {
+					match thumbnail_size {
+						Some(size) => image.thumbnail(size, size)?.to_png()?,
+						None => image.to_png()?,
+					}
+					.get_bytes()
+					.to_vec()
+				};
+				write_tagged(out, 5, &png);
+			}
+			ClipboardContent::Files(files) => {
+				out.push(6);
+				out.extend_from_slice(&(files.len() as u32).to_le_bytes());
+				for file in files {
+					write_bytes(out, file.as_bytes());
+				}
+			}
+			ClipboardContent::Urls(urls) => {
+				out.push(7);
+				out.extend_from_slice(&(urls.len() as u32).to_le_bytes());
+				for url in urls {
+					write_bytes(out, url.url.as_bytes());
+					match &url.title {
+						Some(title) => {
+							out.push(1);
+							write_bytes(out, title.as_bytes());
+						}
+						None => out.push(0),
+					}
+				}
+			}
+			ClipboardContent::Color(color) => {
+				out.push(9);
+				for component in [color.r, color.g, color.b, color.a] {
+					out.extend_from_slice(&component.to_le_bytes());
+				}
+			}
+			ClipboardContent::Pdf(data) => write_tagged(out, 10, data),
+			// a registered format is stored by name, its platform id doesn't outlive the process
+			ClipboardContent::Other(_, data) | ClipboardContent::Custom(_, data) => {
+				out.push(8);
+				write_bytes(out, format.to_string().as_bytes());
+				if compress {
+					write_bytes(out, &pack(&format, data));
+				} else {
+					write_bytes(out, data);
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+#[cfg(feature = "compress")]
+fn pack(format: &ContentFormat, bytes: &[u8]) -> Vec<u8> {
+	crate::common::compress::compress(format, bytes)
+}
+
+// without the feature nothing asks for compression
+#[cfg(not(feature = "compress"))]
+fn pack(_format: &ContentFormat, bytes: &[u8]) -> Vec<u8> {
+	bytes.to_vec()
+}
+
+#[cfg(feature = "compress")]
+fn unpack(frame: &[u8]) -> Result<Vec<u8>> {
+	crate::common::compress::decompress(frame)
+}
+
+#[cfg(not(feature = "compress"))]
+fn unpack(_frame: &[u8]) -> Result<Vec<u8>> {
+	Err("the clipboard history file is compressed, enable the `compress` feature to load it".into())
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+	out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+	out.extend_from_slice(bytes);
+}
+
+fn read_entry(reader: &mut Reader) -> Result<HistoryEntry> {
+	let secs = reader.u64()?;
+	let nanos = reader.u32()?;
+	let count = reader.u32()?;
+	let mut contents = Vec::new();
+	for _ in 0..count {
+		let content = match reader.u8()? {
+			0 => ClipboardContent::Text(reader.text()?),
+			1 => ClipboardContent::Rtf(reader.text()?),
+			2 => ClipboardContent::Html(reader.text()?),
+			3 => ClipboardContent::Svg(reader.text()?),
+			4 => ClipboardContent::Markdown(reader.text()?),
+			5 => {
+				let png = reader.data()?;
+				if png.is_empty() {
+					ClipboardContent::Image(RustImageData::empty())
+				} else {
+					ClipboardContent::Image(RustImageData::from_bytes(&png)?)
+				}
+			}
+			6 => {
+				let len = reader.u32()?;
+				let files = (0..len)
+					.map(|_| reader.string())
+					.collect::<Result<Vec<_>>>()?;
+				ClipboardContent::Files(files)
+			}
+			7 => {
+				let len = reader.u32()?;
+				let mut urls = Vec::new();
+				for _ in 0..len {
+					let url = reader.string()?;
+					let title = match reader.u8()? {
+						0 => None,
+						_ => Some(reader.string()?),
+					};
+					urls.push(Url { url, title });
+				}
+				ClipboardContent::Urls(urls)
+			}
+			8 => {
+				let format = reader.string()?;
+				ClipboardContent::Other(format, reader.data()?)
+			}
+			9 => {
+				let mut components = [0f32; 4];
+				for component in components.iter_mut() {
+					*component = f32::from_bits(reader.u32()?);
+				}
+				let [r, g, b, a] = components;
+				ClipboardContent::Color(Rgba::new(r, g, b, a))
+			}
+			10 => ClipboardContent::Pdf(reader.data()?),
+			tag => return Err(format!("unknown clipboard history content {}", tag).into()),
+		};
+		contents.push(content);
+	}
+	Ok(HistoryEntry {
+		contents,
+		time: UNIX_EPOCH + Duration::new(secs, nanos),
+		thumbnail: None,
+	})
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	// content data is in `compress` frames
+	compressed: bool,
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+		if self.bytes.len() < len {
+			return Err("truncated clipboard history file".into());
+		}
+		let (head, rest) = self.bytes.split_at(len);
+		self.bytes = rest;
+		Ok(head)
+	}
+
+	fn u8(&mut self) -> Result<u8> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn u32(&mut self) -> Result<u32> {
+		let mut bytes = [0u8; 4];
+		bytes.copy_from_slice(self.take(4)?);
+		Ok(u32::from_le_bytes(bytes))
+	}
+
+	fn u64(&mut self) -> Result<u64> {
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(self.take(8)?);
+		Ok(u64::from_le_bytes(bytes))
+	}
+
+	fn bytes(&mut self) -> Result<&'a [u8]> {
+		let len = self.u32()? as usize;
+		self.take(len)
+	}
+
+	fn string(&mut self) -> Result<String> {
+		Ok(String::from_utf8(self.bytes()?.to_vec())?)
+	}
+
+	/// Content data, decompressed when the file is compressed
+	fn data(&mut self) -> Result<Vec<u8>> {
+		let bytes = self.bytes()?;
+		if self.compressed {
+			unpack(bytes)
+		} else {
+			Ok(bytes.to_vec())
+		}
+	}
+
+	fn text(&mut self) -> Result<String> {
+		Ok(String::from_utf8(self.data()?)?)
+	}
+}
@@ -1,17 +1,89 @@
+#[macro_use]
+mod trace;
+
 pub mod common;
+pub mod converter;
+pub mod event;
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
+pub mod formats;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod limit;
+pub mod middleware;
+pub mod mock;
+pub mod osc52;
 mod platform;
+pub mod polling;
+pub mod queue;
+pub mod stats;
+use common::RustImage;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-pub use common::{ClipboardContent, ClipboardHandler, ContentFormat, Result, RustImageData};
+pub use common::attributed::{AttributedText, TextRun};
+pub use common::snapshot::{ClipboardSnapshot, SnapshotDiff};
+pub use common::{
+	ClipboardContent, ClipboardDump, ClipboardExpiry, ClipboardHandler, ContentFormat,
+	ContentMetadata, EncodeOptions, FileOperation, FormatDump, FormatHandle, FormatInfo, Platform,
+	PngCompression, Result, Rgba, RustImageData, Selection, TextDecoding, Url, VirtualFile,
+	VirtualFileSource, WatchHandle, WatcherShutdown,
+};
+#[cfg(feature = "image")]
 pub use image::imageops::FilterType;
-pub use platform::{ClipboardContext, ClipboardWatcherContext, WatcherShutdown};
+pub use platform::ClipboardContext;
+#[cfg(not(target_arch = "wasm32"))]
+pub use platform::ClipboardWatcherContext;
+#[cfg(target_os = "windows")]
+pub use platform::CloudClipboardOptions;
+#[cfg(target_os = "ios")]
+pub use platform::PasteboardOptions;
+#[cfg(all(
+	feature = "raw-handle",
+	any(
+		target_os = "windows",
+		all(
+			unix,
+			not(any(
+				target_os = "macos",
+				target_os = "ios",
+				target_os = "android",
+				target_os = "emscripten"
+			))
+		)
+	)
+))]
+pub use platform::RawHandle;
+#[cfg(target_os = "macos")]
+pub use platform::{Attachment, FileBookmark, PasteboardName, Rtfd, ScopedAccess, WebArchive};
+
+/// zh: `Clipboard` 是对象安全的，可以保存为 `Box<dyn Clipboard + Send + Sync>`，以便在系统剪贴板与 `mock::MockClipboard` 之间切换。新增方法需保持这一点：不使用泛型方法，也不返回 `Self`
+/// en: `Clipboard` is object safe, it can be stored as `Box<dyn Clipboard + Send + Sync>` to swap between the system clipboard and `mock::MockClipboard`. New methods keep it that way: no generic methods and no `Self` in return position
 pub trait Clipboard: Send {
 	/// zh: 获得剪切板当前内容的所有格式
 	/// en: Get all formats of the current content in the clipboard
 	fn available_formats(&self) -> Result<Vec<String>>;
 
+	/// zh: 获得剪贴板当前内容的所有格式，附带平台标识、MIME 类型与大小
+	/// en: Get all formats of the current content in the clipboard with their platform ids, MIME types and sizes
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>>;
+
 	fn has(&self, format: ContentFormat) -> bool;
 
+	/// zh: 剪贴板中是否没有任何内容，无法读取剪贴板时也视为空
+	/// en: Whether the clipboard holds nothing, a clipboard that can't be read counts as empty
+	fn is_empty(&self) -> bool {
+		self.available_formats()
+			.map_or(true, |formats| formats.is_empty())
+	}
+
+	/// zh: 剪贴板中是否有给定格式中的任意一种，平台允许时只查询一次剪贴板
+	/// en: Whether the clipboard has any of the given formats, queried once where the platform allows
+	fn has_any(&self, formats: &[ContentFormat]) -> bool {
+		formats.iter().any(|format| self.has(format.clone()))
+	}
+
 	/// zh: 清空剪切板
 	/// en: clear clipboard
 	fn clear(&self) -> Result<()>;
@@ -20,38 +92,486 @@ pub trait Clipboard: Send {
 	/// en: Get the data in the specified format in the clipboard as a byte array
 	fn get_buffer(&self, format: &str) -> Result<Vec<u8>>;
 
+	/// zh: 将指定格式的数据读入 `buffer`，复用其已分配的空间并返回字节数，适合频繁读取大图片的剪贴板管理器。
+	/// Windows 与 macOS 上数据直接从剪贴板内存复制到 `buffer`，不会像 `get_buffer` 那样额外复制一次
+	/// en: Read the data in the specified format into `buffer`, reusing its allocation, and return the number of bytes, for clipboard managers reading large images often.
+	/// On Windows and macOS the data is copied straight from clipboard memory into `buffer`, without the extra copy of `get_buffer`
+	fn get_buffer_into(&self, format: &str, buffer: &mut Vec<u8>) -> Result<usize> {
+		buffer.clear();
+		Ok(self.get_reader(format)?.read_to_end(buffer)?)
+	}
+
+	/// zh: 获得指定格式数据的字节大小而不读取数据，便于跳过或延迟读取超大内容。X11 上的 INCR 传输只能得到大小的下限
+	/// en: Get the size in bytes of the data in the specified format without fetching it, so huge payloads can be skipped or deferred. INCR transfers on X11 only announce a lower bound
+	fn get_size(&self, format: &str) -> Result<usize>;
+
+	/// zh: 以流的方式读取指定格式的数据，避免一次性载入超大内容。Windows 上剪贴板会保持打开直到 reader 被释放
+	/// en: Stream the data in the specified format instead of loading huge payloads at once. On Windows the clipboard stays open until the reader is dropped
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>>;
+
 	/// zh: 仅获得无格式纯文本，以字符串形式返回
 	/// en: Get plain text content in the clipboard as string
 	fn get_text(&self) -> Result<String>;
 
+	/// zh: 获得纯文本，可选择遇到无效编码时报错还是替换。Windows 会回退到按代码页解码的 `CF_TEXT`，X11 会回退到 Latin-1 的 `STRING`
+	/// en: Get plain text, choosing whether invalid encodings fail or get replaced. Windows falls back to `CF_TEXT` decoded in its codepage, X11 to Latin-1 `STRING`
+	fn get_text_with_decoding(&self, decoding: TextDecoding) -> Result<String> {
+		// platforms that only ever hold unicode text have nothing to decode
+		let _ = decoding;
+		self.get_text()
+	}
+
 	/// zh: 获得剪贴板中的富文本内容，以字符串形式返回
 	/// en: Get the rich text content in the clipboard as string
 	fn get_rich_text(&self) -> Result<String>;
 
+	/// zh: 将富文本读取为简化的片段模型（粗体、斜体、下划线、链接与颜色）。macOS 通过 NSAttributedString 读取 RTFD 与 RTF，
+	/// 其他平台使用内置的 RTF 解析器，不含链接与颜色。没有富文本时返回纯文本
+	/// en: Read the rich text as a simplified model of runs (bold, italic, underline, link and color). macOS reads RTFD and RTF through NSAttributedString,
+	/// elsewhere the built-in RTF reader is used, without links and colors. Plain text is returned when there is no rich text
+	fn get_attributed_text(&self) -> Result<AttributedText> {
+		match self.get_rich_text() {
+			Ok(rtf) if !rtf.is_empty() => Ok(AttributedText::from_rtf(&rtf)),
+			_ => Ok(AttributedText::plain(&self.get_text()?)),
+		}
+	}
+
 	/// zh: 获得剪贴板中的html内容，以字符串形式返回
 	/// en: Get the html format content in the clipboard as string
 	fn get_html(&self) -> Result<String>;
 
+	/// zh: 获得 html 及其来源页面，可用于解析复制的网页内容中的相对链接。来源来自 Windows 的 CF_HTML `SourceURL`、
+	/// macOS 上 Chromium 的 source-url 类型或 Safari 的网页归档、Linux 上 Firefox 与 Chromium 的来源格式
+	/// en: Get the html with the page it was copied from, to resolve relative links in copied web content. The source comes from the CF_HTML `SourceURL` on Windows,
+	/// the Chromium source-url type or the Safari web archive on macOS, the source formats of Firefox and Chromium on Linux
+	fn get_html_with_source(&self) -> Result<(String, Option<Url>)> {
+		Ok((self.get_html()?, None))
+	}
+
+	/// zh: 写入 html 及其来源页面，没有来源格式的平台只写入 html
+	/// en: Set html together with the page it came from, platforms without a source format only set the html
+	fn set_html_with_source(&self, html: String, source: Url) -> Result<()> {
+		let _ = source;
+		self.set_html(html)
+	}
+
+	/// zh: 获得剪贴板中的 SVG 内容，以字符串形式返回
+	/// en: Get the svg content in the clipboard as string
+	fn get_svg(&self) -> Result<String>;
+
+	/// zh: 获得剪贴板中的 Markdown 内容，以字符串形式返回
+	/// en: Get the markdown content in the clipboard as string
+	fn get_markdown(&self) -> Result<String>;
+
 	fn get_image(&self) -> Result<RustImageData>;
 
 	fn get_files(&self) -> Result<Vec<String>>;
 
+	/// zh: 获得剪贴板中的文件路径，会正确解码 `file://` URI 中的空格、`#` 以及非 UTF-8 字节
+	/// en: Get the files in the clipboard as paths, `file://` URIs are decoded so spaces, `#` and non UTF-8 bytes survive
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>>;
+
+	/// zh: 获得剪贴板中文件的操作类型（复制或剪切），没有标记时视为复制
+	/// en: Get whether the files in the clipboard were copied or cut, defaults to copy when no marker is present
+	fn get_file_operation(&self) -> Result<FileOperation>;
+
+	/// zh: 获得剪贴板中的链接及其标题（平台支持时）
+	/// en: Get the links in the clipboard, with their titles when the platform provides them
+	fn get_urls(&self) -> Result<Vec<Url>>;
+
+	/// zh: 获得剪贴板中的颜色，例如设计工具复制的颜色。macOS 上为 `NSColor`，Linux 与 Windows 上为 GIMP 与 Krita 使用的 `application/x-color`
+	/// en: Get the color in the clipboard, e.g. copied from a design tool. An `NSColor` on macOS, the `application/x-color` of GIMP and Krita on Linux and Windows
+	fn get_color(&self) -> Result<Rgba> {
+		match self.get_best(&[ContentFormat::Color])? {
+			ClipboardContent::Color(color) => Ok(color),
+			_ => Err("no color in clipboard".into()),
+		}
+	}
+
+	/// zh: 获得剪贴板中的 PDF 文档，例如 Preview 或 Sketch 复制的矢量内容
+	/// en: Get the PDF document in the clipboard, e.g. vector content copied from Preview or Sketch
+	fn get_pdf(&self) -> Result<Vec<u8>> {
+		match self.get_best(&[ContentFormat::Pdf])? {
+			ClipboardContent::Pdf(pdf) => Ok(pdf),
+			_ => Err("no pdf in clipboard".into()),
+		}
+	}
+
+	/// zh: 获得按比例缩小到 `max_width` x `max_height` 之内的图片，解码与缩放一步完成，复制超大图片时可限制内存峰值。
+	/// 平台数据无法直接解码时（例如 Windows 的 DIB）会先读取完整图片再缩放
+	/// en: Get the image scaled down to fit in `max_width` x `max_height`, decoded and scaled in one step to bound peak memory when huge images are copied.
+	/// Platform data that can't be decoded directly, e.g. a DIB on Windows, is read as a full image first
+	#[cfg(feature = "image")]
+	fn get_image_scaled(&self, max_width: u32, max_height: u32) -> Result<RustImageData> {
+		let scaled = self
+			.get_image_raw()
+			.and_then(|(_, bytes)| RustImageData::from_bytes_scaled(&bytes, max_width, max_height));
+		match scaled {
+			Ok(image) => Ok(image),
+			Err(_) => {
+				let image = self.get_image()?;
+				match image.get_dynamic_image() {
+					Ok(dynamic) => Ok(RustImageData::from_dynamic_image(common::fit_within(
+						dynamic, max_width, max_height,
+					))),
+					Err(_) => Ok(image),
+				}
+			}
+		}
+	}
+
+	/// zh: 列出剪贴板中的每个格式及其名称、大小与前 `preview_len` 个字节，`Display` 以十六进制输出，适用于调试面板与互操作问题报告。
+	/// 无法读取的格式会记录错误而不是使整个调用失败
+	/// en: List every format in the clipboard with its name, size and first `preview_len` bytes, `Display` prints it as a hex dump, for debug panels and interop bug reports.
+	/// A format that can't be read records its error instead of failing the whole call
+	fn dump(&self, preview_len: usize) -> Result<ClipboardDump> {
+		let formats = self
+			.available_formats_detailed()?
+			.into_iter()
+			.map(|info| {
+				let mut preview = Vec::new();
+				let read = self.get_reader(&info.name).and_then(|reader| {
+					Ok(reader.take(preview_len as u64).read_to_end(&mut preview)?)
+				});
+				let size = self.get_size(&info.name).ok().or(info.size_hint);
+				FormatDump {
+					size,
+					preview,
+					error: read.err().map(|e| e.to_string()),
+					info,
+				}
+			})
+			.collect();
+		Ok(ClipboardDump { formats })
+	}
+
+	/// zh: 读取每个格式的原始字节，用 `ClipboardSnapshot::diff` 比较两次观察之间哪些格式被添加、移除或修改。
+	/// 无法读取的格式不会包含在内
+	/// en: Read the raw bytes of every format, compare two observations with `ClipboardSnapshot::diff` to see which formats were added, removed or changed.
+	/// Formats that can't be read are left out
+	fn snapshot(&self) -> Result<ClipboardSnapshot> {
+		let mut formats: Vec<(String, Vec<u8>)> = Vec::new();
+		for name in self.available_formats()? {
+			// platforms may list a format more than once, e.g. under several atoms
+			if formats.iter().any(|(known, _)| *known == name) {
+				continue;
+			}
+			if let Ok(data) = self.get_buffer(&name) {
+				formats.push((name, data));
+			}
+		}
+		Ok(ClipboardSnapshot { formats })
+	}
+
+	/// zh: 获得剪贴板中未经解码的图片数据，返回 (平台格式名, 字节)，例如 PNG、TIFF、DIB
+	/// en: Get the image bytes as stored in the clipboard without decoding, returns (platform format name, bytes), e.g. PNG, TIFF or DIB
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)>;
+
 	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>>;
 
+	/// zh: 按给定的优先级返回第一个可用格式的内容，整个过程只读取一次剪贴板，避免 `has` 与 `get_*` 之间的竞争
+	/// en: Get the content of the first available format in the given priority order, read in one go so it can't race like `has` followed by `get_*`
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent>;
+
+	/// zh: 逐个格式返回 `get` 的结果，按请求顺序排列，可区分格式不存在（见 `is_missing_format`）与格式存在但读取或解码失败。
+	/// 先一次读取所有格式，只有未读到的格式会再单独读取以得到其错误
+	/// en: The outcome of `get` for each requested format in order, telling a missing format (see `is_missing_format`) apart from one that is there but failed to read or decode.
+	/// All formats are read in one go first, only the ones that didn't come back are read again on their own for their error
+	fn get_detailed(
+		&self,
+		formats: &[ContentFormat],
+	) -> Vec<(ContentFormat, Result<ClipboardContent>)> {
+		let mut contents = self.get(formats).unwrap_or_default();
+		formats
+			.iter()
+			.map(|format| {
+				let result = match contents
+					.iter()
+					.position(|c| common::ContentData::get_format(c) == *format)
+				{
+					Some(index) => Ok(contents.swap_remove(index)),
+					None if !self.has(format.clone()) => Err(common::missing_format(format)),
+					None => self.get(std::slice::from_ref(format)).and_then(|mut read| {
+						read.pop().ok_or_else(|| {
+							format!("{} is in the clipboard but couldn't be read", format).into()
+						})
+					}),
+				};
+				(format.clone(), result)
+			})
+			.collect()
+	}
+
+	/// zh: `get` 的非阻塞版本，剪贴板正被其他进程占用时立即返回 `WouldBlock` 错误（见 `is_would_block`），而不是重试等待，
+	/// 适用于宁可跳过一帧的 UI 线程。只有 Windows 的剪贴板会被占用，其他平台与 `get` 相同
+	/// en: The non-blocking `get`, failing at once with a `WouldBlock` error (see `is_would_block`) while another process holds the clipboard instead of retrying,
+	/// for UI threads that would rather skip a frame. Only the Windows clipboard can be held, elsewhere this is `get`
+	fn try_get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		self.get(formats)
+	}
+
+	/// zh: `get_text` 的非阻塞版本，见 `try_get`
+	/// en: The non-blocking `get_text`, see `try_get`
+	fn try_get_text(&self) -> Result<String> {
+		match self.try_get(&[ContentFormat::Text])?.pop() {
+			Some(ClipboardContent::Text(text)) => Ok(text),
+			_ => Err("no text in clipboard".into()),
+		}
+	}
+
+	/// zh: `get_html` 的非阻塞版本，见 `try_get`
+	/// en: The non-blocking `get_html`, see `try_get`
+	fn try_get_html(&self) -> Result<String> {
+		match self.try_get(&[ContentFormat::Html])?.pop() {
+			Some(ClipboardContent::Html(html)) => Ok(html),
+			_ => Err("no html in clipboard".into()),
+		}
+	}
+
+	/// zh: `get_image` 的非阻塞版本，见 `try_get`
+	/// en: The non-blocking `get_image`, see `try_get`
+	fn try_get_image(&self) -> Result<RustImageData> {
+		match self.try_get(&[ContentFormat::Image])?.pop() {
+			Some(ClipboardContent::Image(image)) => Ok(image),
+			_ => Err("no image in clipboard".into()),
+		}
+	}
+
+	/// zh: `get_files` 的非阻塞版本，见 `try_get`
+	/// en: The non-blocking `get_files`, see `try_get`
+	fn try_get_files(&self) -> Result<Vec<String>> {
+		match self.try_get(&[ContentFormat::Files])?.pop() {
+			Some(ClipboardContent::Files(files)) => Ok(files),
+			_ => Err("no files in clipboard".into()),
+		}
+	}
+
+	/// zh: 获得给定格式的内容以及其来源等元数据，供剪贴板历史等区分用户复制与自动写入的内容
+	/// en: Get the contents in the given formats with their provenance metadata, e.g. for history apps telling user copies from automatic ones
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		Ok((self.get(formats)?, ContentMetadata::default()))
+	}
+
+	/// zh: 剪贴板中给定格式内容的稳定哈希，与格式的顺序无关，可用于判断内容是否变化
+	/// en: A stable hash of the clipboard contents in the given formats, independent of their order, to tell whether the contents changed
+	fn current_hash(&self, formats: &[ContentFormat]) -> Result<u64> {
+		let hashes = self
+			.get(formats)?
+			.iter()
+			.map(ClipboardContent::content_hash)
+			.collect();
+		Ok(common::combine_hashes(hashes))
+	}
+
 	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()>;
 
+	/// zh: 将 `text` 追加到剪贴板当前的文本之后，两者之间插入 `separator`，剪贴板为空时只写入 `text`。
+	/// 默认实现尽力而为：读取与写入之间其他程序写入的内容会被覆盖。Windows、macOS 与 X11 会检测或阻止这种修改
+	/// en: Append `text` to the text in the clipboard with `separator` in between, an empty clipboard gets `text` alone.
+	/// The default is best effort: content another program writes between the read and the write is overwritten.
+	/// Windows, macOS and X11 override it to detect or prevent such changes
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		let current = match self.get(&[ContentFormat::Text])?.into_iter().next() {
+			Some(ClipboardContent::Text(current)) => current,
+			_ => String::new(),
+		};
+		self.set_text(common::appended_text(&current, separator, text))
+	}
+
+	/// zh: 注册自定义格式（Windows 上为 RegisterClipboardFormat，X11 上为 atom），返回的句柄可重复用于 `get_buffer_by_handle` 与 `set_buffer_by_handle`
+	/// en: Register a custom format once (RegisterClipboardFormat on Windows, an atom on X11), the handle can be reused with `get_buffer_by_handle` and `set_buffer_by_handle`
+	fn register_format(&self, name: &str) -> Result<FormatHandle> {
+		Ok(FormatHandle::new(name, None))
+	}
+
+	/// zh: 通过已注册的格式句柄获得数据
+	/// en: Get the data in a registered format
+	fn get_buffer_by_handle(&self, format: &FormatHandle) -> Result<Vec<u8>> {
+		self.get_buffer(format.name())
+	}
+
+	/// zh: 通过已注册的格式句柄写入数据
+	/// en: Set the data in a registered format
+	fn set_buffer_by_handle(&self, format: &FormatHandle, buffer: Vec<u8>) -> Result<()> {
+		self.set_buffer(format.name(), buffer)
+	}
+
+	/// zh: 从 reader 写入指定格式的数据，`len_hint` 用于预先分配空间。Windows 上数据直接分块复制到剪贴板内存中，不会额外缓冲
+	/// en: Set the data in the specified format from a reader, `len_hint` is used to allocate up front. On Windows the data is copied in chunks straight into clipboard memory without an extra buffer
+	fn set_from_reader(
+		&self,
+		format: &str,
+		reader: &mut dyn Read,
+		len_hint: Option<usize>,
+	) -> Result<()> {
+		let mut buffer = Vec::with_capacity(len_hint.unwrap_or(0));
+		reader.read_to_end(&mut buffer)?;
+		self.set_buffer(format, buffer)
+	}
+
+	/// zh: 获得指定格式的数据并按 UTF-16LE 解码，适用于 Windows 上保存宽字符串的自定义格式
+	/// en: Get the data in the specified format decoded as UTF-16LE, for custom formats holding wide strings on Windows
+	fn get_buffer_utf16(&self, format: &str) -> Result<String> {
+		ClipboardContent::Other(format.to_owned(), self.get_buffer(format)?).as_utf16_str()
+	}
+
+	/// zh: 以 NUL 结尾的 UTF-16LE 写入指定格式的数据
+	/// en: Set the data in the specified format as NUL terminated UTF-16LE
+	fn set_buffer_utf16(&self, format: &str, text: &str) -> Result<()> {
+		self.set_buffer(format, common::string_to_utf16le(text, true))
+	}
+
 	fn set_text(&self, text: String) -> Result<()>;
 
 	fn set_rich_text(&self, text: String) -> Result<()>;
 
+	/// zh: 写入富文本并同时写入纯文本，未提供纯文本时从 RTF 中提取
+	/// en: Set rich text together with a plain text fallback, the text is extracted from the RTF when not given
+	fn set_rich_text_with_text_fallback(&self, rtf: String, text: Option<String>) -> Result<()> {
+		let text = text.unwrap_or_else(|| common::convert::rtf_to_text(&rtf));
+		self.set(vec![
+			ClipboardContent::Rtf(rtf),
+			ClipboardContent::Text(text),
+		])
+	}
+
+	/// zh: 写入由片段模型生成的 RTF、html 与纯文本
+	/// en: Set the RTF, html and plain text made from the runs
+	fn set_attributed_text(&self, text: &AttributedText) -> Result<()> {
+		self.set(vec![
+			ClipboardContent::Rtf(text.to_rtf()),
+			ClipboardContent::Html(text.to_html()),
+			ClipboardContent::Text(text.text()),
+		])
+	}
+
 	fn set_html(&self, html: String) -> Result<()>;
 
+	/// zh: 写入 html 并同时写入纯文本，未提供纯文本时从 html 中去除标签得到，粘贴到终端等纯文本编辑器时使用
+	/// en: Set html together with a plain text fallback for terminals and plain editors, the text is derived by stripping the tags when not given
+	fn set_html_with_text_fallback(&self, html: String, text: Option<String>) -> Result<()> {
+		let text = text.unwrap_or_else(|| common::convert::html_to_text(&html));
+		self.set(vec![
+			ClipboardContent::Html(html),
+			ClipboardContent::Text(text),
+		])
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()>;
+
+	fn set_markdown(&self, markdown: String) -> Result<()>;
+
+	/// zh: 写入 Markdown，并同时写入由其转换得到的 html 和原文纯文本
+	/// en: Set markdown together with the html rendered from it and the markdown source as plain text
+	fn set_markdown_with_fallback(&self, markdown: String) -> Result<()> {
+		let html = common::convert::markdown_to_html(&markdown);
+		self.set(vec![
+			ClipboardContent::Markdown(markdown.clone()),
+			ClipboardContent::Html(html),
+			ClipboardContent::Text(markdown),
+		])
+	}
+
 	fn set_image(&self, image: RustImageData) -> Result<()>;
 
 	fn set_files(&self, files: Vec<String>) -> Result<()>;
 
-	/// set image will clear clipboard
+	/// zh: 读取图片文件并写入剪贴板，同时写入指向该文件的文件列表，粘贴到图片编辑器得到图片，粘贴到文件管理器得到文件
+	/// en: Load an image file and set it together with a file list pointing at it, so it pastes as an image into editors and as a file into file managers
+	fn set_image_from_path(&self, path: &Path) -> Result<()> {
+		let image = RustImageData::from_bytes(&std::fs::read(path)?)?;
+		// file managers resolve relative paths against their own directory
+		let path = if path.is_absolute() {
+			path.to_path_buf()
+		} else {
+			std::env::current_dir()?.join(path)
+		};
+		let file = path.to_str().ok_or("path is not valid UTF-8")?.to_owned();
+		self.set(vec![
+			ClipboardContent::Image(image),
+			ClipboardContent::Files(vec![file]),
+		])
+	}
+
+	/// zh: 在一次事务中写入文件列表与图片，文件列表为首选格式，适用于已有图片数据的文件
+	/// en: Set a file list and an image in one transaction with the files preferred, for files whose image is already at hand
+	fn set_files_then_image(&self, files: Vec<String>, image: RustImageData) -> Result<()> {
+		self.set(vec![
+			ClipboardContent::Files(files),
+			ClipboardContent::Image(image),
+		])
+	}
+
+	/// zh: 写入文件列表并标记为复制或剪切，文件管理器据此决定粘贴时复制还是移动
+	/// en: Set files and mark them as copied or cut, so file managers know whether to copy or move them on paste
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()>;
+
+	/// zh: 写入链接。Windows 的链接格式只能保存一个不带标题的链接，因此只写入第一个，所有链接另以每行一个的文本写入
+	/// en: Set links. The Windows link format holds a single link without title, so only the first one is written there,
+	/// all links are also written as text, one per line
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()>;
+
+	/// zh: 设置颜色，格式见 `get_color`
+	/// en: Set a color, see `get_color` for the formats
+	fn set_color(&self, color: Rgba) -> Result<()> {
+		self.set(vec![ClipboardContent::Color(color)])
+	}
+
+	/// zh: 设置 PDF 文档，例如矢量图形，接收方无需将其栅格化
+	/// en: Set a PDF document, e.g. vector graphics that the receiver doesn't have to rasterize
+	fn set_pdf(&self, pdf: Vec<u8>) -> Result<()> {
+		self.set(vec![ClipboardContent::Pdf(pdf)])
+	}
+
+	/// zh: 直接写入 PNG 编码的图片数据，不进行解码和重新编码
+	/// en: Set PNG encoded bytes as the clipboard image, without decoding and re-encoding
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()>;
+
+	/// zh: 以指定的平台图片格式原样写入图片数据
+	/// en: Set image bytes in the given platform format (e.g. `public.tiff`, `image/png`, `CF_DIB`) as is
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()>;
+
+	/// zh: 在一个剪贴板会话中执行多次读写。Windows 上剪贴板在整个闭包期间保持打开，其他程序无法在其间读取或修改，
+	/// 也省去了每次调用时的打开与关闭；其他平台没有剪贴板锁，闭包按原样执行。会话中取得的 `get_reader` 读取器需在会话结束前释放。
+	/// 需要 `Self: Sized`，因此不能通过 `dyn Clipboard` 调用
+	/// en: Run several reads and writes in one clipboard session. On Windows the clipboard stays open for the whole closure, so other programs can't read or change it in between
+	/// and the calls skip opening and closing it each time, other platforms have no clipboard lock and just run the closure. Readers from `get_reader` must be dropped before the session ends.
+	/// Needs `Self: Sized`, so it can't be called through `dyn Clipboard`
+	fn session<R, F: FnOnce(&Self) -> Result<R>>(&self, f: F) -> Result<R>
+	where
+		Self: Sized,
+	{
+		f(self)
+	}
+
+	/// zh: 在一次剪贴板事务中写入所有内容，格式按给定顺序注册，第一个为首选格式
+	/// en: Write all contents in one clipboard transaction, formats are published in the given order and the first one is preferred
 	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()>;
+
+	/// zh: 写入内容，并在 `ttl` 后清空剪贴板，除非内容已被新的复制替换，适用于密码与一次性验证码。
+	/// 清空在后台线程中进行，返回的句柄可取消清空。需要 `Self: Clone`，因此不能通过 `dyn Clipboard` 调用
+	/// en: Set the contents and clear the clipboard after `ttl`, unless a newer copy replaced them by then, e.g. for passwords and one-time codes.
+	/// The clear runs on a background thread, the returned handle cancels it. Needs `Self: Clone`, so it can't be called through `dyn Clipboard`
+	fn set_with_expiry(
+		&self,
+		contents: Vec<ClipboardContent>,
+		ttl: Duration,
+	) -> Result<ClipboardExpiry>
+	where
+		Self: Clone + 'static,
+	{
+		let formats: Vec<ContentFormat> = contents
+			.iter()
+			.map(common::ContentData::get_format)
+			.collect();
+		self.set(contents)?;
+		// hash what the clipboard reports, the platform may normalize the written contents
+		let hash = self.current_hash(&formats)?;
+		Ok(ClipboardExpiry::spawn(self.clone(), formats, hash, ttl))
+	}
 }
 
 pub trait ClipboardWatcher<T: ClipboardHandler>: Send {
@@ -66,6 +586,35 @@ pub trait ClipboardWatcher<T: ClipboardHandler>: Send {
 	/// zh: 获得停止监视的通道，可以通过这个通道停止监视
 	/// en: Get the channel to stop monitoring, you can stop monitoring through this channel
 	fn get_shutdown_channel(&self) -> WatcherShutdown;
+
+	/// zh: 在新线程上开始监视，适用于没有自己消息循环的服务与控制台程序。Windows 上隐藏的仅消息窗口及其消息循环都在该线程中创建与运行，
+	/// 处理器也在该线程中调用
+	/// en: Start watching on a new thread, for services and console apps without a message loop of their own. On Windows the hidden message-only window
+	/// and its message loop are created and run on that thread, which is also where the handlers are called
+	fn start_watch_thread(mut self, update_frequency: Duration) -> WatchHandle
+	where
+		Self: Sized + 'static,
+		T: Send,
+	{
+		let shutdown = self.get_shutdown_channel();
+		let thread = std::thread::spawn(move || self.start_watch(update_frequency));
+		WatchHandle::new(shutdown, thread)
+	}
+}
+
+impl ClipboardContext {
+	/// zh: 创建装箱的剪贴板上下文，可与其他 `Clipboard` 实现互换
+	/// en: Create a boxed clipboard context, interchangeable with other `Clipboard` implementations
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn new_boxed() -> Result<Box<dyn Clipboard + Send + Sync>> {
+		Ok(Box::new(ClipboardContext::new()?))
+	}
+
+	/// zh: 创建通过 OSC 52 转义序列写入终端的剪贴板，适用于 SSH 等没有显示服务的环境，仅支持纯文本
+	/// en: Create a clipboard that goes through the terminal with OSC 52 escape sequences, for SSH and other places without a display server, plain text only
+	pub fn new_osc52<W: Write + Send + 'static>(writer: W) -> osc52::Osc52Clipboard {
+		osc52::Osc52Clipboard::new(writer)
+	}
 }
 
 impl WatcherShutdown {
@@ -0,0 +1,444 @@
+//! en: Limits on how much data is read from the clipboard, so a huge payload put there by a buggy
+//! or malicious application isn't buffered whole
+//!
+//! zh: 限制从剪贴板读取的数据量，避免完整缓冲由有缺陷或恶意的应用放入的超大数据
+
+use crate::common::{
+	ContentData, ContentMetadata, FileOperation, FormatHandle, FormatInfo, Result, Url,
+};
+use crate::{Clipboard, ClipboardContent, ContentFormat, RustImageData};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// en: What happens to data larger than the read limit
+/// zh: 大于读取上限的数据如何处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizePolicy {
+	/// en: The read fails
+	/// zh: 读取失败
+	#[default]
+	Error,
+	/// en: Text and byte data is cut at the limit. Images, file lists and urls can't be cut and are skipped
+	/// zh: 文本与字节数据在上限处截断。图片、文件列表与 url 无法截断，会被跳过
+	Truncate,
+	/// en: The format is treated as missing: `get` leaves it out and the typed getters fail
+	/// zh: 该格式视为不存在：`get` 会忽略它，对应类型的读取方法返回错误
+	Skip,
+}
+
+/// en: Options of a `LimitedClipboard`
+/// zh: `LimitedClipboard` 的选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClipboardOptions {
+	max_read_size: Option<usize>,
+	policy: OversizePolicy,
+}
+
+impl ClipboardOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// en: Apply `policy` to formats holding more than `bytes` bytes
+	/// zh: 对超过 `bytes` 字节的格式应用 `policy`
+	pub fn max_read_size(mut self, bytes: usize, policy: OversizePolicy) -> Self {
+		self.max_read_size = Some(bytes);
+		self.policy = policy;
+		self
+	}
+}
+
+// what to do with one format
+enum Limit {
+	Fits,
+	Truncate(usize),
+	Skip,
+}
+
+/// en: A clipboard enforcing `ClipboardOptions` on the reads of another clipboard. The size of a format
+/// is looked up before it is read and byte reads stop one byte past the limit. Watcher handlers reading
+/// through it, like `ClipboardHistory` or `event::EventWriter`, get the same limits
+///
+/// zh: 对另一个剪贴板的读取应用 `ClipboardOptions` 的剪贴板。读取格式前会先查询其大小，字节读取在超过上限一个字节时停止。
+/// 通过它读取的监视器处理器（如 `ClipboardHistory` 或 `event::EventWriter`）也受相同限制
+#[derive(Clone)]
+pub struct LimitedClipboard {
+	inner: Arc<dyn Clipboard + Send + Sync>,
+	options: ClipboardOptions,
+}
+
+impl LimitedClipboard {
+	pub fn new<C: Clipboard + Sync + 'static>(clipboard: C, options: ClipboardOptions) -> Self {
+		LimitedClipboard {
+			inner: Arc::new(clipboard),
+			options,
+		}
+	}
+
+	pub fn options(&self) -> &ClipboardOptions {
+		&self.options
+	}
+
+	fn size_of(&self, format: &ContentFormat) -> Option<usize> {
+		let formats = self.inner.available_formats_detailed().ok()?;
+		let mime = format.to_string();
+		let info = formats.iter().find(|info| {
+			ContentFormat::from_platform_name(&info.name) == *format
+				|| info.mime.as_deref() == Some(mime.as_str())
+		})?;
+		info.size_hint
+			.or_else(|| self.inner.get_size(&info.name).ok())
+	}
+
+	fn limit(&self, format: &ContentFormat) -> Result<Limit> {
+		let max = match self.options.max_read_size {
+			Some(max) => max,
+			None => return Ok(Limit::Fits),
+		};
+		match self.size_of(format) {
+			Some(size) if size > max => self.oversized(size, max, can_truncate(format)),
+			_ => Ok(Limit::Fits),
+		}
+	}
+
+	fn oversized(&self, size: usize, max: usize, can_truncate: bool) -> Result<Limit> {
+		match self.options.policy {
+			OversizePolicy::Error => Err(too_large(size, max).into()),
+			OversizePolicy::Truncate if can_truncate => Ok(Limit::Truncate(max)),
+			_ => Ok(Limit::Skip),
+		}
+	}
+
+	fn read_string(
+		&self,
+		format: ContentFormat,
+		read: impl FnOnce(&dyn Clipboard) -> Result<String>,
+	) -> Result<String> {
+		match self.limit(&format)? {
+			Limit::Fits => read(self.inner.as_ref()),
+			Limit::Truncate(max) => read(self.inner.as_ref()).map(|text| truncate_text(text, max)),
+			Limit::Skip => Err(SKIPPED.into()),
+		}
+	}
+
+	fn check_whole(&self, format: ContentFormat) -> Result<()> {
+		match self.limit(&format)? {
+			Limit::Fits => Ok(()),
+			_ => Err(SKIPPED.into()),
+		}
+	}
+
+	fn filter(&self, formats: &[ContentFormat]) -> Result<(Vec<ContentFormat>, Option<usize>)> {
+		let mut kept = Vec::with_capacity(formats.len());
+		let mut truncate = None;
+		for format in formats {
+			match self.limit(format)? {
+				Limit::Fits => kept.push(format.clone()),
+				Limit::Truncate(max) => {
+					kept.push(format.clone());
+					truncate = Some(max);
+				}
+				Limit::Skip => {}
+			}
+		}
+		Ok((kept, truncate))
+	}
+
+	fn read_contents(
+		&self,
+		formats: &[ContentFormat],
+		read: impl FnOnce(&[ContentFormat]) -> Result<Vec<ClipboardContent>>,
+	) -> Result<Vec<ClipboardContent>> {
+		let (kept, truncate) = self.filter(formats)?;
+		let contents = read(&kept)?;
+		Ok(match truncate {
+			Some(max) => contents
+				.into_iter()
+				.map(|content| truncate_content(content, max))
+				.collect(),
+			None => contents,
+		})
+	}
+}
+
+const SKIPPED: &str = "clipboard data is larger than the read limit";
+
+fn too_large(size: usize, max: usize) -> String {
+	format!(
+		"{} bytes of clipboard data exceed the read limit of {} bytes",
+		size, max
+	)
+}
+
+fn can_truncate(format: &ContentFormat) -> bool {
+	matches!(
+		format,
+		ContentFormat::Text
+			| ContentFormat::Rtf
+			| ContentFormat::Html
+			| ContentFormat::Svg
+			| ContentFormat::Markdown
+			| ContentFormat::Other(_)
+			| ContentFormat::Custom(_)
+	)
+}
+
+/// `text` cut to at most `max` bytes on a character boundary
+fn truncate_text(mut text: String, max: usize) -> String {
+	if text.len() > max {
+		let mut end = max;
+		while !text.is_char_boundary(end) {
+			end -= 1;
+		}
+		text.truncate(end);
+	}
+	text
+}
+
+fn truncate_content(content: ClipboardContent, max: usize) -> ClipboardContent {
+	match content {
+		ClipboardContent::Text(text) => ClipboardContent::Text(truncate_text(text, max)),
+		ClipboardContent::Rtf(text) => ClipboardContent::Rtf(truncate_text(text, max)),
+		ClipboardContent::Html(text) => ClipboardContent::Html(truncate_text(text, max)),
+		ClipboardContent::Svg(text) => ClipboardContent::Svg(truncate_text(text, max)),
+		ClipboardContent::Markdown(text) => ClipboardContent::Markdown(truncate_text(text, max)),
+		ClipboardContent::Other(format, mut data) => {
+			data.truncate(max);
+			ClipboardContent::Other(format, data)
+		}
+		ClipboardContent::Custom(format, mut data) => {
+			data.truncate(max);
+			ClipboardContent::Custom(format, data)
+		}
+		content => content,
+	}
+}
+
+/// Fails once more than `max` bytes came through
+struct LimitReader {
+	inner: Box<dyn Read>,
+	read: usize,
+	max: usize,
+}
+
+impl Read for LimitReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let len = self.inner.read(buf)?;
+		self.read += len;
+		if self.read > self.max {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				too_large(self.read, self.max),
+			));
+		}
+		Ok(len)
+	}
+}
+
+impl Clipboard for LimitedClipboard {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		self.inner.available_formats()
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		self.inner.available_formats_detailed()
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		// skipped formats look missing, `Error` only fails the read
+		!matches!(self.limit(&format), Ok(Limit::Skip)) && self.inner.has(format)
+	}
+
+	fn has_any(&self, formats: &[ContentFormat]) -> bool {
+		let kept: Vec<ContentFormat> = formats
+			.iter()
+			.filter(|format| !matches!(self.limit(format), Ok(Limit::Skip)))
+			.cloned()
+			.collect();
+		self.inner.has_any(&kept)
+	}
+
+	fn clear(&self) -> Result<()> {
+		self.inner.clear()
+	}
+
+	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
+		let max = match self.options.max_read_size {
+			Some(max) => max,
+			None => return self.inner.get_buffer(format),
+		};
+		let mut buffer = Vec::new();
+		self.inner
+			.get_reader(format)?
+			.take(max as u64 + 1)
+			.read_to_end(&mut buffer)?;
+		if buffer.len() > max {
+			let size = self.inner.get_size(format).unwrap_or(buffer.len());
+			match self.oversized(size, max, true)? {
+				Limit::Skip => return Err(SKIPPED.into()),
+				_ => buffer.truncate(max),
+			}
+		}
+		Ok(buffer)
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		self.inner.get_size(format)
+	}
+
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		let reader = self.inner.get_reader(format)?;
+		Ok(match (self.options.max_read_size, self.options.policy) {
+			(None, _) => reader,
+			(Some(max), OversizePolicy::Truncate) => Box::new(reader.take(max as u64)),
+			// a stream can't be skipped once it started, it fails like `Error`
+			(Some(max), _) => Box::new(LimitReader {
+				inner: reader,
+				read: 0,
+				max,
+			}),
+		})
+	}
+
+	fn get_text(&self) -> Result<String> {
+		self.read_string(ContentFormat::Text, |c| c.get_text())
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		self.read_string(ContentFormat::Rtf, |c| c.get_rich_text())
+	}
+
+	fn get_html(&self) -> Result<String> {
+		self.read_string(ContentFormat::Html, |c| c.get_html())
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		self.read_string(ContentFormat::Svg, |c| c.get_svg())
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		self.read_string(ContentFormat::Markdown, |c| c.get_markdown())
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		self.check_whole(ContentFormat::Image)?;
+		self.inner.get_image()
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		self.check_whole(ContentFormat::Files)?;
+		self.inner.get_files()
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		self.check_whole(ContentFormat::Files)?;
+		self.inner.get_file_paths()
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		self.inner.get_file_operation()
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		self.check_whole(ContentFormat::Url)?;
+		self.inner.get_urls()
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		self.check_whole(ContentFormat::Image)?;
+		self.inner.get_image_raw()
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		self.read_contents(formats, |formats| self.inner.get(formats))
+	}
+
+	fn try_get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		self.read_contents(formats, |formats| self.inner.try_get(formats))
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		let mut contents = self.get(formats)?;
+		formats
+			.iter()
+			.find_map(|format| {
+				let index = contents.iter().position(|c| c.get_format() == *format)?;
+				Some(contents.swap_remove(index))
+			})
+			.ok_or_else(|| "None of the formats is available".into())
+	}
+
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		let mut metadata = None;
+		let contents = self.read_contents(formats, |formats| {
+			let (contents, meta) = self.inner.get_with_metadata(formats)?;
+			metadata = Some(meta);
+			Ok(contents)
+		})?;
+		Ok((contents, metadata.unwrap_or_default()))
+	}
+
+	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
+		self.inner.set_buffer(format, buffer)
+	}
+
+	fn register_format(&self, name: &str) -> Result<FormatHandle> {
+		self.inner.register_format(name)
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		self.inner.set_text(text)
+	}
+
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		self.inner.append_text(separator, text)
+	}
+
+	fn set_rich_text(&self, text: String) -> Result<()> {
+		self.inner.set_rich_text(text)
+	}
+
+	fn set_html(&self, html: String) -> Result<()> {
+		self.inner.set_html(html)
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		self.inner.set_svg(svg)
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		self.inner.set_markdown(markdown)
+	}
+
+	fn set_image(&self, image: RustImageData) -> Result<()> {
+		self.inner.set_image(image)
+	}
+
+	fn set_files(&self, files: Vec<String>) -> Result<()> {
+		self.inner.set_files(files)
+	}
+
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()> {
+		self.inner.set_files_with_operation(files, operation)
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		self.inner.set_urls(urls)
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		self.inner.set_image_png(png)
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		self.inner.set_image_raw(format, bytes)
+	}
+
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		self.inner.set(contents)
+	}
+}
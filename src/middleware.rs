@@ -0,0 +1,293 @@
+//! en: Transforms applied to the contents on their way in and out of a clipboard, e.g. stripping
+//! tracking parameters from links, normalizing line endings or redacting card numbers
+//!
+//! zh: 在内容读出与写入剪贴板时应用的转换，例如去除链接中的跟踪参数、统一换行符或隐藏银行卡号
+
+use crate::common::{
+	ContentMetadata, FileOperation, FormatHandle, FormatInfo, Result, TextDecoding, Url,
+};
+use crate::{Clipboard, ClipboardContent, ContentFormat, RustImageData};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// en: A transform of the contents, both directions leave the content unchanged by default.
+/// A middleware may turn a content into another format, the typed getters and setters then fail
+///
+/// zh: 内容的转换，默认两个方向都不修改内容。中间件可以把内容转换为其他格式，此时对应类型的读写方法会返回错误
+pub trait ClipboardMiddleware: Send + Sync {
+	/// en: Applied to every content read from the clipboard
+	/// zh: 应用于从剪贴板读出的每个内容
+	fn on_read(&self, content: ClipboardContent) -> ClipboardContent {
+		content
+	}
+
+	/// en: Applied to every content before it is written to the clipboard
+	/// zh: 应用于写入剪贴板之前的每个内容
+	fn on_write(&self, content: ClipboardContent) -> ClipboardContent {
+		content
+	}
+}
+
+/// en: A clipboard that runs its middlewares on the contents of another clipboard. Writes go through
+/// the middlewares in the order they were added and reads in the reverse order, so each middleware
+/// sees the contents as it wrote them. The byte level methods (`get_buffer`, `get_reader`, `set_buffer`,
+/// the raw and PNG image methods) as well as file paths and the file operation bypass the middlewares
+///
+/// zh: 对另一个剪贴板的内容运行中间件的剪贴板。写入时按添加顺序经过中间件，读取时按相反顺序，
+/// 使每个中间件看到的内容与其写入时一致。字节级方法（`get_buffer`、`get_reader`、`set_buffer`、
+/// 原始图片与 PNG 方法）以及文件路径与文件操作不经过中间件
+#[derive(Clone)]
+pub struct MiddlewareClipboard {
+	inner: Arc<dyn Clipboard + Send + Sync>,
+	middlewares: Vec<Arc<dyn ClipboardMiddleware>>,
+}
+
+impl MiddlewareClipboard {
+	pub fn new<C: Clipboard + Sync + 'static>(clipboard: C) -> Self {
+		MiddlewareClipboard {
+			inner: Arc::new(clipboard),
+			middlewares: Vec::new(),
+		}
+	}
+
+	/// en: Add a middleware, applied after the ones added before it when writing
+	/// zh: 添加中间件，写入时在之前添加的中间件之后应用
+	pub fn with<M: ClipboardMiddleware + 'static>(mut self, middleware: M) -> Self {
+		self.middlewares.push(Arc::new(middleware));
+		self
+	}
+
+	fn read(&self, content: ClipboardContent) -> ClipboardContent {
+		self.middlewares
+			.iter()
+			.rev()
+			.fold(content, |content, middleware| middleware.on_read(content))
+	}
+
+	fn write(&self, content: ClipboardContent) -> ClipboardContent {
+		self.middlewares
+			.iter()
+			.fold(content, |content, middleware| middleware.on_write(content))
+	}
+
+	fn read_string(&self, content: ClipboardContent) -> Result<String> {
+		match self.read(content) {
+			ClipboardContent::Text(text)
+			| ClipboardContent::Rtf(text)
+			| ClipboardContent::Html(text)
+			| ClipboardContent::Svg(text)
+			| ClipboardContent::Markdown(text) => Ok(text),
+			_ => Err(CHANGED_FORMAT.into()),
+		}
+	}
+}
+
+const CHANGED_FORMAT: &str = "a middleware changed the format of the content";
+
+impl Clipboard for MiddlewareClipboard {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		self.inner.available_formats()
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		self.inner.available_formats_detailed()
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		self.inner.has(format)
+	}
+
+	fn has_any(&self, formats: &[ContentFormat]) -> bool {
+		self.inner.has_any(formats)
+	}
+
+	fn clear(&self) -> Result<()> {
+		self.inner.clear()
+	}
+
+	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
+		self.inner.get_buffer(format)
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		self.inner.get_size(format)
+	}
+
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		self.inner.get_reader(format)
+	}
+
+	fn get_text(&self) -> Result<String> {
+		self.read_string(ClipboardContent::Text(self.inner.get_text()?))
+	}
+
+	fn get_text_with_decoding(&self, decoding: TextDecoding) -> Result<String> {
+		self.read_string(ClipboardContent::Text(
+			self.inner.get_text_with_decoding(decoding)?,
+		))
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		self.read_string(ClipboardContent::Rtf(self.inner.get_rich_text()?))
+	}
+
+	fn get_html(&self) -> Result<String> {
+		self.read_string(ClipboardContent::Html(self.inner.get_html()?))
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		self.read_string(ClipboardContent::Svg(self.inner.get_svg()?))
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		self.read_string(ClipboardContent::Markdown(self.inner.get_markdown()?))
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		match self.read(ClipboardContent::Image(self.inner.get_image()?)) {
+			ClipboardContent::Image(image) => Ok(image),
+			_ => Err(CHANGED_FORMAT.into()),
+		}
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		match self.read(ClipboardContent::Files(self.inner.get_files()?)) {
+			ClipboardContent::Files(files) => Ok(files),
+			_ => Err(CHANGED_FORMAT.into()),
+		}
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		self.inner.get_file_paths()
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		self.inner.get_file_operation()
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		match self.read(ClipboardContent::Urls(self.inner.get_urls()?)) {
+			ClipboardContent::Urls(urls) => Ok(urls),
+			_ => Err(CHANGED_FORMAT.into()),
+		}
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		self.inner.get_image_raw()
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		Ok(self
+			.inner
+			.get(formats)?
+			.into_iter()
+			.map(|content| self.read(content))
+			.collect())
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		Ok(self.read(self.inner.get_best(formats)?))
+	}
+
+	fn try_get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		Ok(self
+			.inner
+			.try_get(formats)?
+			.into_iter()
+			.map(|content| self.read(content))
+			.collect())
+	}
+
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		let (contents, metadata) = self.inner.get_with_metadata(formats)?;
+		let contents = contents
+			.into_iter()
+			.map(|content| self.read(content))
+			.collect();
+		Ok((contents, metadata))
+	}
+
+	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
+		self.inner.set_buffer(format, buffer)
+	}
+
+	fn register_format(&self, name: &str) -> Result<FormatHandle> {
+		self.inner.register_format(name)
+	}
+
+	fn get_buffer_by_handle(&self, format: &FormatHandle) -> Result<Vec<u8>> {
+		self.inner.get_buffer_by_handle(format)
+	}
+
+	fn set_buffer_by_handle(&self, format: &FormatHandle, buffer: Vec<u8>) -> Result<()> {
+		self.inner.set_buffer_by_handle(format, buffer)
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Text(text)])
+	}
+
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		// only the appended part goes through the middlewares, the clipboard text was written before
+		match self.write(ClipboardContent::Text(text.to_owned())) {
+			ClipboardContent::Text(text) => self.inner.append_text(separator, &text),
+			_ => Err(CHANGED_FORMAT.into()),
+		}
+	}
+
+	fn set_rich_text(&self, text: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Rtf(text)])
+	}
+
+	fn set_html(&self, html: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Html(html)])
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Svg(svg)])
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Markdown(markdown)])
+	}
+
+	fn set_image(&self, image: RustImageData) -> Result<()> {
+		self.set(vec![ClipboardContent::Image(image)])
+	}
+
+	fn set_files(&self, files: Vec<String>) -> Result<()> {
+		self.set(vec![ClipboardContent::Files(files)])
+	}
+
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()> {
+		match self.write(ClipboardContent::Files(files)) {
+			ClipboardContent::Files(files) => self.inner.set_files_with_operation(files, operation),
+			_ => Err(CHANGED_FORMAT.into()),
+		}
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		self.set(vec![ClipboardContent::Urls(urls)])
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		self.inner.set_image_png(png)
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		self.inner.set_image_raw(format, bytes)
+	}
+
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		self.inner.set(
+			contents
+				.into_iter()
+				.map(|content| self.write(content))
+				.collect(),
+		)
+	}
+}
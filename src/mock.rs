@@ -0,0 +1,433 @@
+//! en: An in-memory clipboard and watcher for tests, no display server or system clipboard needed
+//!
+//! zh: 用于测试的内存剪贴板与监视器，无需显示服务或系统剪贴板
+
+use crate::common::{
+	file_uri_to_path, format_mime, notify_handlers, would_block, ContentData, ContentMetadata,
+	FileOperation, FormatInfo, Result, RustImage, RustImageData, Selection, Url, WatcherShutdown,
+};
+use crate::{Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat};
+use std::io::{Cursor, Read};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// en: An in-memory clipboard, clones share the same contents
+/// zh: 内存中的剪贴板，克隆之间共享同一份内容
+#[derive(Clone, Default)]
+pub struct MockClipboard {
+	state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+	contents: Vec<ClipboardContent>,
+	operation: Option<FileOperation>,
+	metadata: ContentMetadata,
+	change_count: u64,
+	held: bool,
+	listeners: Vec<Sender<()>>,
+}
+
+impl MockClipboard {
+	pub fn new() -> Self {
+		MockClipboard::default()
+	}
+
+	/// en: Replace the contents as if another application copied them, watchers are notified
+	/// zh: 模拟其他应用复制内容并替换当前内容，会通知监视器
+	pub fn inject(&self, contents: Vec<ClipboardContent>) {
+		self.replace(contents, None);
+	}
+
+	/// en: Like `inject`, with the metadata `get_with_metadata` reports, e.g. an auto-generated entry
+	/// zh: 与 `inject` 相同，并附带 `get_with_metadata` 返回的元数据，例如自动生成的内容
+	pub fn inject_with_metadata(&self, contents: Vec<ClipboardContent>, metadata: ContentMetadata) {
+		self.replace_with_metadata(contents, None, metadata);
+	}
+
+	/// en: Notify watchers of a change without touching the contents
+	/// zh: 不修改内容，仅通知监视器发生了变化
+	pub fn trigger_change(&self) {
+		let mut state = self.state();
+		state.change_count += 1;
+		state.listeners.retain(|listener| listener.send(()).is_ok());
+	}
+
+	/// en: Act as if another process held the clipboard open, `try_get*` fails with `WouldBlock` until released
+	/// zh: 模拟剪贴板被其他进程占用，在释放之前 `try_get*` 会返回 `WouldBlock` 错误
+	pub fn set_held(&self, held: bool) {
+		self.state().held = held;
+	}
+
+	/// en: How many times the contents changed, like `changeCount` on macOS
+	/// zh: 内容变化的次数，类似 macOS 上的 `changeCount`
+	pub fn change_count(&self) -> u64 {
+		self.state().change_count
+	}
+
+	/// en: A watcher notified by every change of this clipboard and its clones
+	/// zh: 监视此剪贴板及其克隆的每次变化的监视器
+	pub fn watcher<T: ClipboardHandler>(&self) -> MockWatcher<T> {
+		let (change_signal, change_receiver) = mpsc::channel();
+		self.state().listeners.push(change_signal);
+		let (stop_signal, stop_receiver) = mpsc::channel();
+		MockWatcher {
+			handlers: Vec::new(),
+			change_receiver,
+			stop_signal,
+			stop_receiver,
+		}
+	}
+
+	fn state(&self) -> MutexGuard<'_, MockState> {
+		// a test that panicked while holding the lock leaves usable contents behind
+		self.state
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn replace(&self, contents: Vec<ClipboardContent>, operation: Option<FileOperation>) {
+		self.replace_with_metadata(contents, operation, ContentMetadata::default());
+	}
+
+	fn replace_with_metadata(
+		&self,
+		contents: Vec<ClipboardContent>,
+		operation: Option<FileOperation>,
+		metadata: ContentMetadata,
+	) {
+		let mut state = self.state();
+		state.contents = contents;
+		state.operation = operation;
+		state.metadata = metadata;
+		state.change_count += 1;
+		state.listeners.retain(|listener| listener.send(()).is_ok());
+	}
+
+	fn find<F, R>(&self, f: F) -> Result<R>
+	where
+		F: Fn(&ClipboardContent) -> Option<R>,
+	{
+		self.state()
+			.contents
+			.iter()
+			.find_map(f)
+			.ok_or_else(|| "no data".into())
+	}
+}
+
+/// The content when it is in `format`. Custom formats are identified by name, so data written with a
+/// handle reads back by name and the other way round
+fn read_as(content: &ClipboardContent, format: &ContentFormat) -> Option<ClipboardContent> {
+	match (content, format) {
+		(ClipboardContent::Other(name, data), ContentFormat::Custom(handle))
+			if name == handle.name() =>
+		{
+			Some(ClipboardContent::Custom(handle.clone(), data.clone()))
+		}
+		(ClipboardContent::Custom(written, data), ContentFormat::Custom(handle))
+			if written.name() == handle.name() =>
+		{
+			Some(ClipboardContent::Custom(handle.clone(), data.clone()))
+		}
+		(ClipboardContent::Custom(handle, data), ContentFormat::Other(name))
+			if handle.name() == name =>
+		{
+			Some(ClipboardContent::Other(name.clone(), data.clone()))
+		}
+		_ if content.get_format() == *format => Some(content.clone()),
+		_ => None,
+	}
+}
+
+impl Clipboard for MockClipboard {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		Ok(self
+			.state()
+			.contents
+			.iter()
+			.map(|content| content.get_format().to_string())
+			.collect())
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		let state = self.state();
+		let mut res = Vec::with_capacity(state.contents.len());
+		for content in &state.contents {
+			let name = content.get_format().to_string();
+			res.push(FormatInfo {
+				mime: format_mime(&name),
+				platform_id: None,
+				size_hint: Some(content.to_bytes()?.len()),
+				name,
+			});
+		}
+		Ok(res)
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		self.state()
+			.contents
+			.iter()
+			.any(|content| content.get_format().to_string() == format.to_string())
+	}
+
+	fn clear(&self) -> Result<()> {
+		self.replace(Vec::new(), None);
+		Ok(())
+	}
+
+	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
+		let state = self.state();
+		match state
+			.contents
+			.iter()
+			.find(|content| content.get_format().to_string() == format)
+		{
+			Some(content) => content.to_bytes(),
+			None => Err("no data".into()),
+		}
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		self.get_buffer(format).map(|buffer| buffer.len())
+	}
+
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		Ok(Box::new(Cursor::new(self.get_buffer(format)?)))
+	}
+
+	fn get_text(&self) -> Result<String> {
+		self.find(|content| match content {
+			ClipboardContent::Text(text) => Some(text.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		self.find(|content| match content {
+			ClipboardContent::Rtf(rtf) => Some(rtf.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_html(&self) -> Result<String> {
+		self.find(|content| match content {
+			ClipboardContent::Html(html) => Some(html.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		self.find(|content| match content {
+			ClipboardContent::Svg(svg) => Some(svg.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		self.find(|content| match content {
+			ClipboardContent::Markdown(markdown) => Some(markdown.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		self.find(|content| match content {
+			ClipboardContent::Image(image) => Some(image.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		self.find(|content| match content {
+			ClipboardContent::Files(files) => Some(files.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		let files = self.get_files()?;
+		Ok(files
+			.iter()
+			.map(|file| file_uri_to_path(file).unwrap_or_else(|| PathBuf::from(file)))
+			.collect())
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		self.get_files()?;
+		Ok(self.state().operation.unwrap_or(FileOperation::Copy))
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		self.find(|content| match content {
+			ClipboardContent::Urls(urls) => Some(urls.clone()),
+			_ => None,
+		})
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		let image = self.get_image()?;
+		Ok((
+			ContentFormat::Image.to_string(),
+			image.to_png()?.get_bytes().to_vec(),
+		))
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		Ok(self
+			.state()
+			.contents
+			.iter()
+			.filter_map(|content| formats.iter().find_map(|format| read_as(content, format)))
+			.collect())
+	}
+
+	fn try_get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		if self.state().held {
+			return Err(would_block());
+		}
+		self.get(formats)
+	}
+
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		let state = self.state();
+		let contents = state
+			.contents
+			.iter()
+			.filter_map(|content| formats.iter().find_map(|format| read_as(content, format)))
+			.collect();
+		Ok((contents, state.metadata.clone()))
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		let state = self.state();
+		formats
+			.iter()
+			.find_map(|format| {
+				state
+					.contents
+					.iter()
+					.find_map(|content| read_as(content, format))
+			})
+			.ok_or_else(|| "None of the formats is available".into())
+	}
+
+	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
+		self.set(vec![ClipboardContent::Other(format.to_owned(), buffer)])
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Text(text)])
+	}
+
+	fn set_rich_text(&self, text: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Rtf(text)])
+	}
+
+	fn set_html(&self, html: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Html(html)])
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Svg(svg)])
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Markdown(markdown)])
+	}
+
+	fn set_image(&self, image: RustImageData) -> Result<()> {
+		self.set(vec![ClipboardContent::Image(image)])
+	}
+
+	fn set_files(&self, files: Vec<String>) -> Result<()> {
+		self.set(vec![ClipboardContent::Files(files)])
+	}
+
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()> {
+		self.replace(vec![ClipboardContent::Files(files)], Some(operation));
+		Ok(())
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		self.set(vec![ClipboardContent::Urls(urls)])
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		self.set_image(RustImageData::from_bytes(&png)?)
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		self.set_buffer(format, bytes)
+	}
+
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		self.replace(contents, None);
+		Ok(())
+	}
+}
+
+/// en: A watcher driven by a `MockClipboard`, handlers run for every change of the clipboard
+/// zh: 由 `MockClipboard` 驱动的监视器，剪贴板每次变化都会调用处理器
+pub struct MockWatcher<T: ClipboardHandler> {
+	handlers: Vec<T>,
+	change_receiver: Receiver<()>,
+	stop_signal: Sender<()>,
+	stop_receiver: Receiver<()>,
+}
+
+impl<T: ClipboardHandler> MockWatcher<T> {
+	/// en: Run the handlers for the changes made so far without blocking, returns how many were handled
+	/// zh: 不阻塞地为目前为止的变化调用处理器，返回处理的变化数量
+	pub fn process_pending(&mut self) -> usize {
+		let mut count = 0;
+		while self.change_receiver.try_recv().is_ok() {
+			count += 1;
+			if self.notify().is_break() {
+				break;
+			}
+		}
+		count
+	}
+
+	// `Break` when a handler asked the watcher to stop
+	fn notify(&mut self) -> ControlFlow<()> {
+		notify_handlers(&mut self.handlers, Selection::Clipboard)
+	}
+}
+
+impl<T: ClipboardHandler + Send> ClipboardWatcher<T> for MockWatcher<T> {
+	fn add_handler(&mut self, f: T) -> &mut Self {
+		self.handlers.push(f);
+		self
+	}
+
+	fn start_watch(&mut self, update_frequency: Duration) {
+		loop {
+			if self.stop_receiver.try_recv().is_ok() {
+				break;
+			}
+			match self.change_receiver.recv_timeout(update_frequency) {
+				Ok(()) => {
+					if self.notify().is_break() {
+						break;
+					}
+				}
+				Err(mpsc::RecvTimeoutError::Timeout) => continue,
+				// the clipboard and all its clones are gone, nothing can change anymore
+				Err(mpsc::RecvTimeoutError::Disconnected) => break,
+			}
+		}
+	}
+
+	fn get_shutdown_channel(&self) -> WatcherShutdown {
+		WatcherShutdown::new(self.stop_signal.clone())
+	}
+}
@@ -0,0 +1,282 @@
+//! en: A clipboard backed by the terminal through OSC 52 escape sequences, for SSH sessions,
+//! tmux and other places without a display server. Only plain text is carried
+//!
+//! zh: 通过 OSC 52 转义序列借助终端访问的剪贴板，适用于 SSH 会话、tmux 等没有显示服务的环境。仅支持纯文本
+
+use crate::common::{
+	base64_decode, base64_encode, FileOperation, FormatInfo, Result, RustImageData, Url,
+};
+use crate::{Clipboard, ClipboardContent, ContentFormat};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const UNSUPPORTED: &str = "OSC 52 only carries plain text";
+
+/// en: The selection an OSC 52 sequence targets
+/// zh: OSC 52 序列操作的选区
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Osc52Selection {
+	Clipboard,
+	/// en: The X11 primary selection, for terminals that expose it
+	/// zh: X11 的 primary 选区，需要终端支持
+	Primary,
+}
+
+impl Osc52Selection {
+	fn code(self) -> &'static str {
+		match self {
+			Osc52Selection::Clipboard => "c",
+			Osc52Selection::Primary => "p",
+		}
+	}
+}
+
+/// en: Writes the clipboard as OSC 52 sequences to the terminal. Reading needs the terminal input
+/// through `with_reader`, with the terminal in raw mode, and a terminal that answers OSC 52 queries,
+/// many only allow setting. Terminals limit the size of a sequence, often to about 100 KB
+///
+/// zh: 以 OSC 52 序列向终端写入剪贴板。读取需要通过 `with_reader` 提供终端输入（终端需处于 raw 模式），
+/// 且终端需要响应 OSC 52 查询，许多终端只允许写入。终端会限制序列的大小，通常约为 100 KB
+#[derive(Clone)]
+pub struct Osc52Clipboard {
+	writer: Arc<Mutex<Box<dyn Write + Send>>>,
+	reader: Option<Arc<Mutex<Box<dyn Read + Send>>>>,
+	selection: Osc52Selection,
+	tmux: bool,
+}
+
+impl Osc52Clipboard {
+	/// en: Sequences are wrapped for tmux when `TMUX` is set
+	/// zh: 设置了 `TMUX` 环境变量时，序列会按 tmux 的要求包装
+	pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+		Osc52Clipboard {
+			writer: Arc::new(Mutex::new(Box::new(writer))),
+			reader: None,
+			selection: Osc52Selection::Clipboard,
+			tmux: std::env::var_os("TMUX").is_some(),
+		}
+	}
+
+	/// en: Read the answers of the terminal from `reader`, usually stdin in raw mode
+	/// zh: 从 `reader` 读取终端的应答，通常为处于 raw 模式的 stdin
+	pub fn with_reader<R: Read + Send + 'static>(mut self, reader: R) -> Self {
+		self.reader = Some(Arc::new(Mutex::new(Box::new(reader))));
+		self
+	}
+
+	pub fn with_selection(mut self, selection: Osc52Selection) -> Self {
+		self.selection = selection;
+		self
+	}
+
+	/// en: Wrap the sequences in a tmux passthrough, needs `allow-passthrough` in tmux
+	/// zh: 使用 tmux passthrough 包装序列，需要开启 tmux 的 `allow-passthrough`
+	pub fn with_tmux(mut self, tmux: bool) -> Self {
+		self.tmux = tmux;
+		self
+	}
+
+	fn send(&self, payload: &str) -> Result<()> {
+		let sequence = format!("\x1b]52;{};{}\x07", self.selection.code(), payload);
+		let sequence = if self.tmux {
+			// every ESC inside a passthrough is doubled
+			format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+		} else {
+			sequence
+		};
+		let mut writer = self.writer.lock().map_err(|_| "OSC 52 writer poisoned")?;
+		writer.write_all(sequence.as_bytes())?;
+		writer.flush()?;
+		Ok(())
+	}
+
+	fn query(&self) -> Result<String> {
+		let reader = self
+			.reader
+			.as_ref()
+			.ok_or("reading needs the terminal input, see with_reader")?;
+		self.send("?")?;
+		let mut reader = reader.lock().map_err(|_| "OSC 52 reader poisoned")?;
+		let mut answer = Vec::new();
+		let mut byte = [0u8; 1];
+		loop {
+			if reader.read(&mut byte)? == 0 {
+				return Err("terminal closed before answering".into());
+			}
+			// the answer ends with BEL or ST (ESC \)
+			if byte[0] == 0x07 {
+				break;
+			}
+			if byte[0] == b'\\' && answer.last() == Some(&0x1b) {
+				answer.pop();
+				break;
+			}
+			answer.push(byte[0]);
+		}
+		let answer = String::from_utf8(answer)?;
+		let start = answer.find("]52;").ok_or("unexpected terminal answer")?;
+		let data = answer[start + 4..]
+			.split_once(';')
+			.map(|(_, data)| data)
+			.ok_or("unexpected terminal answer")?;
+		Ok(String::from_utf8(base64_decode(data)?)?)
+	}
+}
+
+impl Clipboard for Osc52Clipboard {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		Ok(vec![ContentFormat::Text.to_string()])
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		Ok(vec![FormatInfo {
+			name: ContentFormat::Text.to_string(),
+			platform_id: None,
+			mime: Some(ContentFormat::Text.to_string()),
+			size_hint: None,
+		}])
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		format == ContentFormat::Text && self.reader.is_some()
+	}
+
+	fn clear(&self) -> Result<()> {
+		// anything that isn't base64 clears the selection
+		self.send("!")
+	}
+
+	fn get_buffer(&self, _format: &str) -> Result<Vec<u8>> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_size(&self, _format: &str) -> Result<usize> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_reader(&self, _format: &str) -> Result<Box<dyn Read>> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_text(&self) -> Result<String> {
+		self.query()
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_html(&self) -> Result<String> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		if formats.contains(&ContentFormat::Text) && self.reader.is_some() {
+			return Ok(vec![ClipboardContent::Text(self.query()?)]);
+		}
+		Ok(Vec::new())
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		self.get(formats)?
+			.pop()
+			.ok_or_else(|| "None of the formats is available".into())
+	}
+
+	fn set_buffer(&self, _format: &str, _buffer: Vec<u8>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		self.send(&base64_encode(text.as_bytes()))
+	}
+
+	fn set_rich_text(&self, _text: String) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_html(&self, _html: String) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_svg(&self, _svg: String) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_markdown(&self, _markdown: String) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_image(&self, _image: RustImageData) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_files(&self, _files: Vec<String>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_files_with_operation(
+		&self,
+		_files: Vec<String>,
+		_operation: FileOperation,
+	) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_urls(&self, _urls: Vec<Url>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_image_png(&self, _png: Vec<u8>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_image_raw(&self, _format: &str, _bytes: Vec<u8>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	/// Only the text of the contents reaches the terminal, e.g. the fallback of `set_html_with_text_fallback`
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		let text = contents.into_iter().find_map(|content| match content {
+			ClipboardContent::Text(text) => Some(text),
+			_ => None,
+		});
+		match text {
+			Some(text) => self.set_text(text),
+			None => Err(UNSUPPORTED.into()),
+		}
+	}
+}
@@ -0,0 +1,572 @@
+use crate::common::{
+	convert, format_mime, notify_handlers, FileOperation, FormatInfo, Result, RustImageData,
+	Selection, Url, WatcherShutdown,
+};
+use crate::{Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat};
+use jni::errors::Result as JniResult;
+use jni::objects::{GlobalRef, JObject, JString, JValue};
+use jni::{JNIEnv, JavaVM};
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+const UNSUPPORTED: &str = "the Android clipboard only holds text, html and URIs";
+
+const MIME_TEXT: &str = "text/plain";
+const MIME_HTML: &str = "text/html";
+const MIME_URI: &str = "text/uri-list";
+
+/// The system `android.content.ClipboardManager`, reached through the JVM of the app
+/// via `ndk-context`. Android keeps a single clip, with text, html or URIs per item.
+#[derive(Clone)]
+pub struct ClipboardContext {
+	vm: Arc<JavaVM>,
+	context: GlobalRef,
+	manager: GlobalRef,
+}
+
+pub struct ClipboardWatcherContext<T: ClipboardHandler> {
+	clipboard: ClipboardContext,
+	handlers: Vec<T>,
+	stop_signal: Sender<()>,
+	stop_receiver: Receiver<()>,
+	running: bool,
+}
+
+impl<T: ClipboardHandler> ClipboardWatcherContext<T> {
+	pub fn new() -> Result<Self> {
+		let (tx, rx) = mpsc::channel();
+		Ok(ClipboardWatcherContext {
+			clipboard: ClipboardContext::new()?,
+			handlers: Vec::new(),
+			stop_signal: tx,
+			stop_receiver: rx,
+			running: false,
+		})
+	}
+}
+
+impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
+	fn add_handler(&mut self, handler: T) -> &mut Self {
+		self.handlers.push(handler);
+		self
+	}
+
+	fn start_watch(&mut self, update_frequency: Duration) {
+		if self.running {
+			trace_debug!("already watching");
+			return;
+		}
+		if self.handlers.is_empty() {
+			trace_debug!("no handlers, not watching");
+			return;
+		}
+		self.running = true;
+		// OnPrimaryClipChangedListener needs a Java class implementing it,
+		// the timestamp of the clip tells the same without one
+		let mut last_timestamp = self.clipboard.clip_timestamp();
+		loop {
+			// if receive stop signal, break loop
+			if self.stop_receiver.recv_timeout(update_frequency).is_ok() {
+				break;
+			}
+			let timestamp = self.clipboard.clip_timestamp();
+			if timestamp != last_timestamp {
+				last_timestamp = timestamp;
+				if notify_handlers(&mut self.handlers, Selection::Clipboard).is_break() {
+					break;
+				}
+			}
+		}
+		self.running = false;
+	}
+
+	fn get_shutdown_channel(&self) -> WatcherShutdown {
+		WatcherShutdown::new(self.stop_signal.clone())
+	}
+}
+
+impl ClipboardContext {
+	pub fn new() -> Result<ClipboardContext> {
+		let android = ndk_context::android_context();
+		let vm = unsafe { JavaVM::from_raw(android.vm().cast()) }?;
+		let (context, manager) = {
+			let mut env = vm.attach_current_thread()?;
+			// owned by ndk-context, only borrowed here
+			let context = unsafe { JObject::from_raw(android.context().cast()) };
+			let service = env.new_string("clipboard")?;
+			let manager = env
+				.call_method(
+					&context,
+					"getSystemService",
+					"(Ljava/lang/String;)Ljava/lang/Object;",
+					&[JValue::Object(&service)],
+				)?
+				.l()?;
+			if manager.is_null() {
+				return Err("no clipboard service".into());
+			}
+			(env.new_global_ref(&context)?, env.new_global_ref(manager)?)
+		};
+		Ok(ClipboardContext {
+			vm: Arc::new(vm),
+			context,
+			manager,
+		})
+	}
+
+	/// Run `f` attached to the JVM in its own local frame, a pending Java exception becomes the error
+	fn with_env<T>(&self, f: impl FnOnce(&mut JNIEnv) -> JniResult<T>) -> Result<T> {
+		let mut env = self.vm.attach_current_thread()?;
+		let res = env.with_local_frame(16, |env| f(env));
+		res.map_err(|e| {
+			if env.exception_check().unwrap_or(false) {
+				let _ = env.exception_clear();
+			}
+			format!("clipboard error: {}", e).into()
+		})
+	}
+
+	/// When the clip was set, 0 when the clipboard is empty
+	fn clip_timestamp(&self) -> i64 {
+		self.with_env(|env| {
+			let description = self.description(env)?;
+			if description.is_null() {
+				return Ok(0);
+			}
+			env.call_method(&description, "getTimestamp", "()J", &[])?
+				.j()
+		})
+		.unwrap_or(0)
+	}
+
+	fn description<'a>(&self, env: &mut JNIEnv<'a>) -> JniResult<JObject<'a>> {
+		env.call_method(
+			&self.manager,
+			"getPrimaryClipDescription",
+			"()Landroid/content/ClipDescription;",
+			&[],
+		)?
+		.l()
+	}
+
+	fn mime_types(&self) -> Result<Vec<String>> {
+		self.with_env(|env| {
+			let description = self.description(env)?;
+			if description.is_null() {
+				return Ok(Vec::new());
+			}
+			let count = env
+				.call_method(&description, "getMimeTypeCount", "()I", &[])?
+				.i()?;
+			let mut mimes = Vec::with_capacity(count as usize);
+			for i in 0..count {
+				let mime = env
+					.call_method(
+						&description,
+						"getMimeType",
+						"(I)Ljava/lang/String;",
+						&[JValue::Int(i)],
+					)?
+					.l()?;
+				if let Some(mime) = java_string(env, mime)? {
+					mimes.push(mime);
+				}
+			}
+			Ok(mimes)
+		})
+	}
+
+	/// Collect `read` of every item of the clip, items where it gives `None` are skipped
+	fn read_items<T>(
+		&self,
+		read: impl Fn(&mut JNIEnv, &JObject, &JObject) -> JniResult<Option<T>>,
+	) -> Result<Vec<T>> {
+		self.with_env(|env| {
+			let clip = env
+				.call_method(
+					&self.manager,
+					"getPrimaryClip",
+					"()Landroid/content/ClipData;",
+					&[],
+				)?
+				.l()?;
+			if clip.is_null() {
+				return Ok(Vec::new());
+			}
+			let count = env.call_method(&clip, "getItemCount", "()I", &[])?.i()?;
+			let mut values = Vec::new();
+			for i in 0..count {
+				let item = env
+					.call_method(
+						&clip,
+						"getItemAt",
+						"(I)Landroid/content/ClipData$Item;",
+						&[JValue::Int(i)],
+					)?
+					.l()?;
+				if let Some(value) = read(env, &item, self.context.as_obj())? {
+					values.push(value);
+				}
+				env.delete_local_ref(item)?;
+			}
+			Ok(values)
+		})
+	}
+
+	fn read_text(&self) -> Result<String> {
+		let texts = self.read_items(|env, item, context| {
+			// also turns html and URIs into text, like a paste into a text field
+			let text = env
+				.call_method(
+					item,
+					"coerceToText",
+					"(Landroid/content/Context;)Ljava/lang/CharSequence;",
+					&[JValue::Object(context)],
+				)?
+				.l()?;
+			char_sequence(env, text)
+		})?;
+		if texts.is_empty() {
+			return Err("no text in clipboard".into());
+		}
+		Ok(texts.join("\n"))
+	}
+
+	fn read_html(&self) -> Result<String> {
+		let html = self.read_items(|env, item, _| {
+			let html = env
+				.call_method(item, "getHtmlText", "()Ljava/lang/String;", &[])?
+				.l()?;
+			java_string(env, html)
+		})?;
+		html.into_iter()
+			.next()
+			.ok_or_else(|| "no html in clipboard".into())
+	}
+
+	fn read_uris(&self) -> Result<Vec<String>> {
+		let uris = self.read_items(|env, item, _| {
+			let uri = env
+				.call_method(item, "getUri", "()Landroid/net/Uri;", &[])?
+				.l()?;
+			if uri.is_null() {
+				return Ok(None);
+			}
+			let uri = env
+				.call_method(&uri, "toString", "()Ljava/lang/String;", &[])?
+				.l()?;
+			java_string(env, uri)
+		})?;
+		if uris.is_empty() {
+			return Err("no uri in clipboard".into());
+		}
+		Ok(uris)
+	}
+
+	/// Replace the clip, html needs a text alternative and the URIs become extra items
+	fn write(&self, text: Option<&str>, html: Option<&str>, uris: &[String]) -> Result<()> {
+		if text.is_none() && html.is_none() && uris.is_empty() {
+			return Err(UNSUPPORTED.into());
+		}
+		let derived_text = match (text, html) {
+			(None, Some(html)) => Some(convert::html_to_text(html)),
+			_ => None,
+		};
+		let text = text.or(derived_text.as_deref());
+		self.with_env(|env| {
+			let label = env.new_string("")?;
+			let mut uris = uris.iter();
+			let clip = match (text, html) {
+				(Some(text), Some(html)) => {
+					let text = env.new_string(text)?;
+					let html = env.new_string(html)?;
+					env.call_static_method(
+						"android/content/ClipData",
+						"newHtmlText",
+						"(Ljava/lang/CharSequence;Ljava/lang/CharSequence;Ljava/lang/String;)Landroid/content/ClipData;",
+						&[JValue::Object(&label), JValue::Object(&text), JValue::Object(&html)],
+					)?
+					.l()?
+				}
+				(Some(text), None) => {
+					let text = env.new_string(text)?;
+					env.call_static_method(
+						"android/content/ClipData",
+						"newPlainText",
+						"(Ljava/lang/CharSequence;Ljava/lang/CharSequence;)Landroid/content/ClipData;",
+						&[JValue::Object(&label), JValue::Object(&text)],
+					)?
+					.l()?
+				}
+				_ => {
+					// checked above, there is at least one uri
+					let uri = parse_uri(env, uris.next().map_or("", String::as_str))?;
+					env.call_static_method(
+						"android/content/ClipData",
+						"newRawUri",
+						"(Ljava/lang/CharSequence;Landroid/net/Uri;)Landroid/content/ClipData;",
+						&[JValue::Object(&label), JValue::Object(&uri)],
+					)?
+					.l()?
+				}
+			};
+			for uri in uris {
+				let uri = parse_uri(env, uri)?;
+				let item = env.new_object(
+					"android/content/ClipData$Item",
+					"(Landroid/net/Uri;)V",
+					&[JValue::Object(&uri)],
+				)?;
+				env.call_method(
+					&clip,
+					"addItem",
+					"(Landroid/content/ClipData$Item;)V",
+					&[JValue::Object(&item)],
+				)?;
+			}
+			env.call_method(
+				&self.manager,
+				"setPrimaryClip",
+				"(Landroid/content/ClipData;)V",
+				&[JValue::Object(&clip)],
+			)?;
+			Ok(())
+		})
+	}
+}
+
+fn java_string(env: &mut JNIEnv, object: JObject) -> JniResult<Option<String>> {
+	if object.is_null() {
+		return Ok(None);
+	}
+	let string = JString::from(object);
+	let value: String = env.get_string(&string)?.into();
+	Ok(Some(value))
+}
+
+fn char_sequence(env: &mut JNIEnv, object: JObject) -> JniResult<Option<String>> {
+	if object.is_null() {
+		return Ok(None);
+	}
+	let string = env
+		.call_method(&object, "toString", "()Ljava/lang/String;", &[])?
+		.l()?;
+	java_string(env, string)
+}
+
+fn parse_uri<'a>(env: &mut JNIEnv<'a>, uri: &str) -> JniResult<JObject<'a>> {
+	let uri = env.new_string(uri)?;
+	env.call_static_method(
+		"android/net/Uri",
+		"parse",
+		"(Ljava/lang/String;)Landroid/net/Uri;",
+		&[JValue::Object(&uri)],
+	)?
+	.l()
+}
+
+impl Clipboard for ClipboardContext {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		self.mime_types()
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		Ok(self
+			.mime_types()?
+			.into_iter()
+			.map(|name| FormatInfo {
+				mime: format_mime(&name),
+				platform_id: None,
+				size_hint: None,
+				name,
+			})
+			.collect())
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		let mimes = self.mime_types().unwrap_or_default();
+		let has = |mime: &str| mimes.iter().any(|m| m == mime);
+		match format {
+			// every clip can be coerced to text, but only text and html items hold some
+			ContentFormat::Text => has(MIME_TEXT) || has(MIME_HTML),
+			ContentFormat::Html => has(MIME_HTML),
+			ContentFormat::Url => has(MIME_URI),
+			ContentFormat::Other(mime) => has(&mime),
+			ContentFormat::Custom(handle) => has(handle.name()),
+			_ => false,
+		}
+	}
+
+	fn clear(&self) -> Result<()> {
+		// API 28
+		self.with_env(|env| {
+			env.call_method(&self.manager, "clearPrimaryClip", "()V", &[])?;
+			Ok(())
+		})
+	}
+
+	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
+		match format {
+			MIME_TEXT => Ok(self.read_text()?.into_bytes()),
+			MIME_HTML => Ok(self.read_html()?.into_bytes()),
+			MIME_URI => Ok(self.read_uris()?.join("\n").into_bytes()),
+			_ => Err(UNSUPPORTED.into()),
+		}
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		Ok(self.get_buffer(format)?.len())
+	}
+
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		Ok(Box::new(Cursor::new(self.get_buffer(format)?)))
+	}
+
+	fn get_text(&self) -> Result<String> {
+		self.read_text()
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_html(&self) -> Result<String> {
+		self.read_html()
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		Ok(self.read_uris()?.iter().map(|uri| Url::new(uri)).collect())
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		let mut contents = Vec::new();
+		for format in formats {
+			let content = match format {
+				ContentFormat::Text => self.read_text().map(ClipboardContent::Text),
+				ContentFormat::Html => self.read_html().map(ClipboardContent::Html),
+				ContentFormat::Url => self.get_urls().map(ClipboardContent::Urls),
+				_ => continue,
+			};
+			if let Ok(content) = content {
+				contents.push(content);
+			}
+		}
+		Ok(contents)
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		formats
+			.iter()
+			.find_map(|format| self.get(std::slice::from_ref(format)).ok()?.pop())
+			.ok_or_else(|| "None of the formats is available".into())
+	}
+
+	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
+		let text = String::from_utf8(buffer)?;
+		match format {
+			MIME_TEXT => self.write(Some(&text), None, &[]),
+			MIME_HTML => self.write(None, Some(&text), &[]),
+			MIME_URI => {
+				let uris: Vec<String> = text.lines().map(str::to_owned).collect();
+				self.write(None, None, &uris)
+			}
+			_ => Err(UNSUPPORTED.into()),
+		}
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		self.write(Some(&text), None, &[])
+	}
+
+	fn set_rich_text(&self, _text: String) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_html(&self, html: String) -> Result<()> {
+		self.write(None, Some(&html), &[])
+	}
+
+	fn set_svg(&self, _svg: String) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_markdown(&self, _markdown: String) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_image(&self, _image: RustImageData) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_files(&self, _files: Vec<String>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_files_with_operation(
+		&self,
+		_files: Vec<String>,
+		_operation: FileOperation,
+	) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		let uris: Vec<String> = urls.into_iter().map(|url| url.url).collect();
+		self.write(None, None, &uris)
+	}
+
+	fn set_image_png(&self, _png: Vec<u8>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	fn set_image_raw(&self, _format: &str, _bytes: Vec<u8>) -> Result<()> {
+		Err(UNSUPPORTED.into())
+	}
+
+	/// Text, html and URLs go into one clip, other contents are skipped
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		let mut text = None;
+		let mut html = None;
+		let mut uris = Vec::new();
+		for content in contents {
+			match content {
+				ClipboardContent::Text(t) => text = Some(t),
+				ClipboardContent::Html(h) => html = Some(h),
+				ClipboardContent::Urls(urls) => uris.extend(urls.into_iter().map(|url| url.url)),
+				_ => {}
+			}
+		}
+		self.write(text.as_deref(), html.as_deref(), &uris)
+	}
+}
@@ -0,0 +1,492 @@
+use crate::common::{
+	format_mime, notify_handlers, FileOperation, FormatInfo, Result, RustImage, RustImageData,
+	Selection, Url, WatcherShutdown,
+};
+use crate::{Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat};
+use objc2::rc::{autoreleasepool, Retained};
+use objc2::runtime::AnyObject;
+use objc2_foundation::{NSArray, NSData, NSDate, NSDictionary, NSNumber, NSString, NSURL};
+use objc2_ui_kit::{UIPasteboard, UIPasteboardOptionExpirationDate, UIPasteboardOptionLocalOnly};
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const TEXT_TYPE: &str = "public.utf8-plain-text";
+const RTF_TYPE: &str = "public.rtf";
+const HTML_TYPE: &str = "public.html";
+const SVG_TYPE: &str = "public.svg-image";
+const MARKDOWN_TYPE: &str = "net.daringfireball.markdown";
+const PNG_TYPE: &str = "public.png";
+const JPEG_TYPE: &str = "public.jpeg";
+const URL_TYPE: &str = "public.url";
+const FILE_URL_TYPE: &str = "public.file-url";
+const PDF_TYPE: &str = "com.adobe.pdf";
+const COLOR_UNSUPPORTED: &str = "colors are not supported on iOS yet";
+
+/// Options UIPasteboard applies to the contents it is given, see `ClipboardContext::set_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct PasteboardOptions {
+	/// Keep the contents on this device, Universal Clipboard won't offer them to others
+	pub local_only: bool,
+	/// Remove the contents from the pasteboard at this time
+	pub expiration: Option<SystemTime>,
+}
+
+#[derive(Clone)]
+pub struct ClipboardContext {
+	pasteboard: Retained<UIPasteboard>,
+}
+
+unsafe impl Send for ClipboardContext {}
+
+unsafe impl Sync for ClipboardContext {}
+
+pub struct ClipboardWatcherContext<T: ClipboardHandler> {
+	pasteboard: Retained<UIPasteboard>,
+	handlers: Vec<T>,
+	stop_signal: Sender<()>,
+	stop_receiver: Receiver<()>,
+	running: bool,
+}
+
+unsafe impl<T: ClipboardHandler> Send for ClipboardWatcherContext<T> {}
+
+impl<T: ClipboardHandler> ClipboardWatcherContext<T> {
+	pub fn new() -> Result<Self> {
+		let (tx, rx) = mpsc::channel();
+		Ok(ClipboardWatcherContext {
+			pasteboard: unsafe { UIPasteboard::generalPasteboard() },
+			handlers: Vec::new(),
+			stop_signal: tx,
+			stop_receiver: rx,
+			running: false,
+		})
+	}
+}
+
+impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
+	fn add_handler(&mut self, handler: T) -> &mut Self {
+		self.handlers.push(handler);
+		self
+	}
+
+	fn start_watch(&mut self, update_frequency: Duration) {
+		if self.running {
+			trace_debug!("already watching");
+			return;
+		}
+		if self.handlers.is_empty() {
+			trace_debug!("no handlers, not watching");
+			return;
+		}
+		self.running = true;
+		let mut last_change_count = unsafe { self.pasteboard.changeCount() };
+		loop {
+			// if receive stop signal, break loop
+			if self.stop_receiver.recv_timeout(update_frequency).is_ok() {
+				break;
+			}
+			let change_count = unsafe { self.pasteboard.changeCount() };
+			if change_count != last_change_count {
+				last_change_count = change_count;
+				if notify_handlers(&mut self.handlers, Selection::Clipboard).is_break() {
+					break;
+				}
+			}
+		}
+		self.running = false;
+	}
+
+	fn get_shutdown_channel(&self) -> WatcherShutdown {
+		WatcherShutdown::new(self.stop_signal.clone())
+	}
+}
+
+impl ClipboardContext {
+	pub fn new() -> Result<ClipboardContext> {
+		Ok(ClipboardContext {
+			pasteboard: unsafe { UIPasteboard::generalPasteboard() },
+		})
+	}
+
+	/// Set the contents with the options only UIPasteboard has, e.g. keep them off Universal Clipboard
+	pub fn set_with_options(
+		&self,
+		contents: Vec<ClipboardContent>,
+		options: &PasteboardOptions,
+	) -> Result<()> {
+		let mut keys = Vec::new();
+		let mut values: Vec<Retained<AnyObject>> = Vec::new();
+		if options.local_only {
+			keys.push(unsafe { UIPasteboardOptionLocalOnly });
+			values.push(unsafe { Retained::cast(NSNumber::new_bool(true)) });
+		}
+		if let Some(expiration) = options.expiration {
+			let secs = expiration
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs_f64();
+			keys.push(unsafe { UIPasteboardOptionExpirationDate });
+			values.push(unsafe { Retained::cast(NSDate::dateWithTimeIntervalSince1970(secs)) });
+		}
+		let options = NSDictionary::from_vec(&keys, values);
+		self.write(&contents, &options)
+	}
+
+	fn write(
+		&self,
+		contents: &[ClipboardContent],
+		options: &NSDictionary<NSString, AnyObject>,
+	) -> Result<()> {
+		autoreleasepool(|_| {
+			let mut items = vec![Item::default()];
+			for content in contents {
+				match content {
+					ClipboardContent::Text(text) => items[0].push_string(TEXT_TYPE, text),
+					ClipboardContent::Html(html) => items[0].push_string(HTML_TYPE, html),
+					ClipboardContent::Markdown(md) => items[0].push_string(MARKDOWN_TYPE, md),
+					ClipboardContent::Rtf(rtf) => items[0].push_data(RTF_TYPE, rtf.as_bytes()),
+					ClipboardContent::Svg(svg) => items[0].push_data(SVG_TYPE, svg.as_bytes()),
+					ClipboardContent::Image(image) => {
+						items[0].push_data(PNG_TYPE, image.to_png()?.get_bytes())
+					}
+					ClipboardContent::Other(format, data) => items[0].push_data(format, data),
+					ClipboardContent::Custom(handle, data) => {
+						items[0].push_data(handle.name(), data)
+					}
+					ClipboardContent::Color(_) => return Err(COLOR_UNSUPPORTED.into()),
+					ClipboardContent::Pdf(pdf) => items[0].push_data(PDF_TYPE, pdf),
+					// one item per url, the first joins the other representations
+					ClipboardContent::Urls(urls) => {
+						for (i, url) in urls.iter().enumerate() {
+							let ns_url =
+								unsafe { NSURL::URLWithString(&NSString::from_str(&url.url)) }
+									.ok_or_else(|| format!("invalid url {}", url.url))?;
+							item_at(&mut items, i).push(URL_TYPE, ns_url);
+						}
+					}
+					ClipboardContent::Files(files) => {
+						for (i, file) in files.iter().enumerate() {
+							let ns_url =
+								unsafe { NSURL::fileURLWithPath(&NSString::from_str(file)) };
+							item_at(&mut items, i).push(FILE_URL_TYPE, ns_url);
+						}
+					}
+				}
+			}
+			let items: Vec<_> = items
+				.into_iter()
+				.filter(|item| !item.keys.is_empty())
+				.map(|item| {
+					let keys: Vec<&NSString> = item.keys.iter().map(|key| &**key).collect();
+					NSDictionary::from_vec(&keys, item.values)
+				})
+				.collect();
+			unsafe {
+				self.pasteboard
+					.setItems_options(&NSArray::from_vec(items), options)
+			};
+			Ok(())
+		})
+	}
+
+	fn data(&self, r#type: &str) -> Option<Vec<u8>> {
+		autoreleasepool(|_| {
+			unsafe {
+				self.pasteboard
+					.dataForPasteboardType(&NSString::from_str(r#type))
+			}
+			.map(|data| data.bytes().to_vec())
+		})
+	}
+
+	fn string(&self, r#type: &str) -> Result<String> {
+		let data = self
+			.data(r#type)
+			.ok_or_else(|| format!("no {} in clipboard", r#type))?;
+		Ok(String::from_utf8(data)?)
+	}
+
+	fn contains(&self, r#type: &str) -> bool {
+		let types = NSArray::from_vec(vec![NSString::from_str(r#type)]);
+		unsafe { self.pasteboard.containsPasteboardTypes(&types) }
+	}
+
+	fn urls(&self) -> Vec<Retained<NSURL>> {
+		unsafe { self.pasteboard.URLs() }
+			.map(|urls| urls.to_vec_retained())
+			.unwrap_or_default()
+	}
+}
+
+/// The representations of one pasteboard item
+#[derive(Default)]
+struct Item {
+	keys: Vec<Retained<NSString>>,
+	values: Vec<Retained<AnyObject>>,
+}
+
+impl Item {
+	fn push<T: objc2::Message>(&mut self, r#type: &str, value: Retained<T>) {
+		self.keys.push(NSString::from_str(r#type));
+		self.values.push(unsafe { Retained::cast(value) });
+	}
+
+	fn push_string(&mut self, r#type: &str, value: &str) {
+		self.push(r#type, NSString::from_str(value));
+	}
+
+	fn push_data(&mut self, r#type: &str, bytes: &[u8]) {
+		self.push(r#type, NSData::with_bytes(bytes));
+	}
+}
+
+fn item_at(items: &mut Vec<Item>, index: usize) -> &mut Item {
+	while items.len() <= index {
+		items.push(Item::default());
+	}
+	&mut items[index]
+}
+
+impl Clipboard for ClipboardContext {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		autoreleasepool(|_| {
+			let types = unsafe { self.pasteboard.pasteboardTypes() };
+			Ok(types.iter().map(|t| t.to_string()).collect())
+		})
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		Ok(self
+			.available_formats()?
+			.into_iter()
+			.map(|name| FormatInfo {
+				mime: format_mime(&name),
+				platform_id: None,
+				size_hint: None,
+				name,
+			})
+			.collect())
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		match format {
+			ContentFormat::Text => unsafe { self.pasteboard.hasStrings() },
+			ContentFormat::Image => unsafe { self.pasteboard.hasImages() },
+			ContentFormat::Url => unsafe { self.pasteboard.hasURLs() },
+			ContentFormat::Rtf => self.contains(RTF_TYPE),
+			ContentFormat::Html => self.contains(HTML_TYPE),
+			ContentFormat::Svg => self.contains(SVG_TYPE),
+			ContentFormat::Markdown => self.contains(MARKDOWN_TYPE),
+			ContentFormat::Files => self.contains(FILE_URL_TYPE),
+			// UIColor would need UIKit's color archive, not mapped yet
+			ContentFormat::Color => false,
+			ContentFormat::Pdf => self.contains(PDF_TYPE),
+			ContentFormat::Other(format) => self.contains(&format),
+			ContentFormat::Custom(handle) => self.contains(handle.name()),
+		}
+	}
+
+	fn clear(&self) -> Result<()> {
+		unsafe { self.pasteboard.setItems(&NSArray::new()) };
+		Ok(())
+	}
+
+	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
+		self.data(format).ok_or_else(|| "no data".into())
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		Ok(self.get_buffer(format)?.len())
+	}
+
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		Ok(Box::new(Cursor::new(self.get_buffer(format)?)))
+	}
+
+	fn get_text(&self) -> Result<String> {
+		autoreleasepool(|_| {
+			unsafe { self.pasteboard.string() }
+				.map(|text| text.to_string())
+				.ok_or_else(|| "no text in clipboard".into())
+		})
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		self.string(RTF_TYPE)
+	}
+
+	fn get_html(&self) -> Result<String> {
+		self.string(HTML_TYPE)
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		self.string(SVG_TYPE)
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		self.string(MARKDOWN_TYPE)
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		let (_, bytes) = self.get_image_raw()?;
+		RustImageData::from_bytes(&bytes)
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		let files: Vec<String> = self
+			.get_file_paths()?
+			.iter()
+			.map(|path| path.to_string_lossy().into_owned())
+			.collect();
+		Ok(files)
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		autoreleasepool(|_| {
+			let paths: Vec<PathBuf> = self
+				.urls()
+				.iter()
+				.filter(|url| unsafe { url.isFileURL() })
+				.filter_map(|url| unsafe { url.path() })
+				.map(|path| PathBuf::from(path.to_string()))
+				.collect();
+			if paths.is_empty() {
+				return Err("no files in clipboard".into());
+			}
+			Ok(paths)
+		})
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		// UIPasteboard has no notion of moving files
+		self.get_file_paths()?;
+		Ok(FileOperation::Copy)
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		autoreleasepool(|_| {
+			let urls: Vec<Url> = self
+				.urls()
+				.iter()
+				.filter(|url| !unsafe { url.isFileURL() })
+				.filter_map(|url| unsafe { url.absoluteString() })
+				.map(|url| Url::new(&url.to_string()))
+				.collect();
+			if urls.is_empty() {
+				return Err("no urls".into());
+			}
+			Ok(urls)
+		})
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		for r#type in [PNG_TYPE, JPEG_TYPE] {
+			if let Some(data) = self.data(r#type) {
+				return Ok((r#type.to_owned(), data));
+			}
+		}
+		Err("no image in clipboard".into())
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		let mut contents = Vec::new();
+		for format in formats {
+			let content = match format {
+				ContentFormat::Text => self.get_text().map(ClipboardContent::Text),
+				ContentFormat::Rtf => self.get_rich_text().map(ClipboardContent::Rtf),
+				ContentFormat::Html => self.get_html().map(ClipboardContent::Html),
+				ContentFormat::Svg => self.get_svg().map(ClipboardContent::Svg),
+				ContentFormat::Markdown => self.get_markdown().map(ClipboardContent::Markdown),
+				ContentFormat::Image => self.get_image().map(ClipboardContent::Image),
+				ContentFormat::Files => self.get_files().map(ClipboardContent::Files),
+				ContentFormat::Url => self.get_urls().map(ClipboardContent::Urls),
+				ContentFormat::Color => Err(COLOR_UNSUPPORTED.into()),
+				ContentFormat::Pdf => self.get_buffer(PDF_TYPE).map(ClipboardContent::Pdf),
+				ContentFormat::Other(name) => self
+					.get_buffer(name)
+					.map(|data| ClipboardContent::Other(name.clone(), data)),
+				ContentFormat::Custom(handle) => self
+					.get_buffer(handle.name())
+					.map(|data| ClipboardContent::Custom(handle.clone(), data)),
+			};
+			if let Ok(content) = content {
+				contents.push(content);
+			}
+		}
+		Ok(contents)
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		formats
+			.iter()
+			.find_map(|format| self.get(std::slice::from_ref(format)).ok()?.pop())
+			.ok_or_else(|| "None of the formats is available".into())
+	}
+
+	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
+		self.set(vec![ClipboardContent::Other(format.to_owned(), buffer)])
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Text(text)])
+	}
+
+	fn set_rich_text(&self, text: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Rtf(text)])
+	}
+
+	fn set_html(&self, html: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Html(html)])
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Svg(svg)])
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		self.set(vec![ClipboardContent::Markdown(markdown)])
+	}
+
+	fn set_image(&self, image: RustImageData) -> Result<()> {
+		self.set(vec![ClipboardContent::Image(image)])
+	}
+
+	fn set_files(&self, files: Vec<String>) -> Result<()> {
+		self.set(vec![ClipboardContent::Files(files)])
+	}
+
+	fn set_files_with_operation(
+		&self,
+		files: Vec<String>,
+		_operation: FileOperation,
+	) -> Result<()> {
+		// UIPasteboard has no notion of moving files
+		self.set_files(files)
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		if urls.is_empty() {
+			return Err("url list is empty".into());
+		}
+		self.set(vec![ClipboardContent::Urls(urls)])
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		self.set_buffer(PNG_TYPE, png)
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		self.set_buffer(format, bytes)
+	}
+
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		if contents.is_empty() {
+			return Err(
+				"contents is empty, if you want to clear clipboard, please use clear method".into(),
+			);
+		}
+		self.set_with_options(contents, &PasteboardOptions::default())
+	}
+}
@@ -1,24 +1,212 @@
-use crate::common::{Result, RustImage, RustImageData};
-use crate::{Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat};
+use super::macos_promise::{
+	file_promises, has_promised_files, receive_promised_files, PromiseDelegate,
+};
+use crate::common::{
+	appended_text, convert, dpi, format_mime, notify_handlers, ContentMetadata, FileOperation,
+	FormatInfo, Result, Rgba, RustImage, RustImageData, Selection, Url, VirtualFileSource,
+};
+use crate::{
+	stats, AttributedText, Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher,
+	ContentFormat, TextRun, WatcherShutdown,
+};
 use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, Bool, NSObject, NSObjectProtocol, ProtocolObject};
 use objc2::{
-	rc::{autoreleasepool, Id},
-	runtime::ProtocolObject,
+	class, msg_send, msg_send_id,
+	rc::{autoreleasepool, Allocated, Id},
 	ClassType,
 };
 use objc2_app_kit::{
-	NSFilenamesPboardType, NSImage, NSPasteboard, NSPasteboardItem, NSPasteboardType,
-	NSPasteboardTypeHTML, NSPasteboardTypePNG, NSPasteboardTypeRTF, NSPasteboardTypeString,
-	NSPasteboardTypeTIFF, NSPasteboardWriting,
+	NSBitmapImageFileType, NSBitmapImageRep, NSColor, NSColorSpace, NSFilenamesPboardType, NSImage,
+	NSPasteboard, NSPasteboardItem, NSPasteboardNameDrag, NSPasteboardNameFind,
+	NSPasteboardNameFont, NSPasteboardType, NSPasteboardTypeFileURL, NSPasteboardTypeHTML,
+	NSPasteboardTypePDF, NSPasteboardTypePNG, NSPasteboardTypeRTF, NSPasteboardTypeString,
+	NSPasteboardTypeTIFF,
 };
-use objc2_foundation::{NSArray, NSData, NSString};
+use objc2_foundation::{NSArray, NSData, NSDictionary, NSRange, NSString, NSURL};
+use std::borrow::Cow;
 use std::ffi::c_void;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::ptr;
 use std::sync::mpsc::{self, Receiver, Sender};
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::vec;
 
+const READ_ATTEMPTS: usize = 3;
+const WEBP_TYPE: &str = "org.webmproject.webp";
+const SVG_TYPE: &str = "public.svg-image";
+const MARKDOWN_TYPE: &str = "net.daringfireball.markdown";
+const GIF_TYPE: &str = "com.compuserve.gif";
+const URL_TYPE: &str = "public.url";
+const URL_NAME_TYPE: &str = "public.url-name";
+// NSPasteboardTypeColor, a keyed archive of an NSColor
+const COLOR_TYPE: &str = "com.apple.cocoa.pasteboard.color";
+// Safari and other WebKit apps, the selection with the resources it references
+const WEB_ARCHIVE_TYPE: &str = "com.apple.webarchive";
+// TextEdit and Notes, a serialized NSFileWrapper of the RTF and its attachments
+const FLAT_RTFD_TYPE: &str = "com.apple.flat-rtfd";
+// the RTF inside an RTFD, the other files are its attachments
+const RTFD_TEXT_FILE: &str = "TXT.rtf";
+// the page Chromium browsers copied html from
+const SOURCE_URL_TYPE: &str = "org.chromium.source-url";
+// the nspasteboard.org markers, see `ContentMetadata`
+const SOURCE_TYPE: &str = "org.nspasteboard.source";
+const AUTO_GENERATED_TYPE: &str = "org.nspasteboard.AutoGeneratedType";
+const CONCEALED_TYPE: &str = "org.nspasteboard.ConcealedType";
+const TRANSIENT_TYPE: &str = "org.nspasteboard.TransientType";
+// NSURLBookmarkCreationWithSecurityScope and NSURLBookmarkResolutionWithSecurityScope
+const BOOKMARK_WITH_SECURITY_SCOPE: usize = 1 << 11;
+const RESOLVE_WITH_SECURITY_SCOPE: usize = 1 << 10;
+// how often the watcher asks whether the Mac runs on battery
+const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+	fn IOPSCopyPowerSourcesInfo() -> *const c_void;
+	fn IOPSGetProvidingPowerSourceType(snapshot: *const c_void) -> *const NSString;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+	fn CFRelease(cf: *const c_void);
+}
+
+/// Whether the Mac currently draws from its battery, false when IOKit can't tell
+fn on_battery_power() -> bool {
+	unsafe {
+		let snapshot = IOPSCopyPowerSourcesInfo();
+		if snapshot.is_null() {
+			return false;
+		}
+		// the type belongs to the snapshot, CFString is toll-free bridged to NSString
+		let on_battery = IOPSGetProvidingPowerSourceType(snapshot)
+			.as_ref()
+			.map_or(false, |source| source.to_string() == "Battery Power");
+		CFRelease(snapshot);
+		on_battery
+	}
+}
+
+/// The pasteboard a `ClipboardContext` works on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasteboardName {
+	/// The pasteboard used for copy and paste
+	General,
+	/// The search text shared by the find panels of all apps
+	Find,
+	/// The pasteboard used for drag and drop
+	Drag,
+	/// The pasteboard used to copy and paste fonts
+	Font,
+	/// A private pasteboard, e.g. `com.my.app` for transfers inside one app
+	Custom(String),
+}
+
+/// A file embedded in a web archive or an RTFD, e.g. an image of the copied selection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+	/// The URL of a web archive resource, the file name of an RTFD attachment
+	pub name: String,
+	/// Web archives record the MIME type of their resources, RTFD doesn't
+	pub mime: Option<String>,
+	pub data: Vec<u8>,
+}
+
+/// A `com.apple.webarchive`, the html `get_html` returns plus the images and stylesheets it references
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebArchive {
+	pub html: String,
+	/// The page the html was copied from
+	pub url: Option<String>,
+	pub resources: Vec<Attachment>,
+}
+
+impl WebArchive {
+	/// Decode the bytes of a `com.apple.webarchive`, e.g. from `get_buffer`
+	pub fn from_bytes(bytes: &[u8]) -> Result<WebArchive> {
+		autoreleasepool(|_| decode_web_archive(&ns_data(bytes)))
+			.ok_or_else(|| "invalid web archive".into())
+	}
+}
+
+/// A `com.apple.flat-rtfd`, the RTF `get_rich_text` returns plus the attachments it refers to by file name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rtfd {
+	pub rtf: String,
+	pub attachments: Vec<Attachment>,
+}
+
+impl Rtfd {
+	/// Decode the bytes of a `com.apple.flat-rtfd`, e.g. from `get_buffer`
+	pub fn from_bytes(bytes: &[u8]) -> Result<Rtfd> {
+		autoreleasepool(|_| decode_rtfd(&ns_data(bytes))).ok_or_else(|| "invalid rtfd".into())
+	}
+}
+
+/// A pasted file with a security-scoped bookmark, which a sandboxed app keeps to open the file
+/// later, see `get_file_bookmarks`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileBookmark {
+	pub path: PathBuf,
+	/// `None` when the app may not create security-scoped bookmarks, that needs the
+	/// `com.apple.security.files.bookmarks.app-scope` entitlement
+	pub bookmark: Option<Vec<u8>>,
+}
+
+impl FileBookmark {
+	/// Resolve the bookmark and start accessing the file until the returned guard is dropped.
+	/// Without a bookmark the path is used as it is
+	pub fn access(&self) -> Result<ScopedAccess> {
+		let url = match &self.bookmark {
+			Some(bookmark) => resolve_bookmark(bookmark)?,
+			None => unsafe {
+				NSURL::fileURLWithPath(&NSString::from_str(&self.path.to_string_lossy()))
+			},
+		};
+		ScopedAccess::start(url)
+	}
+}
+
+/// Access to a security-scoped file, given up when dropped
+pub struct ScopedAccess {
+	url: Retained<NSURL>,
+	path: PathBuf,
+	// URLs outside the sandbox rules, e.g. in the app container, don't need to be started
+	started: bool,
+}
+
+impl ScopedAccess {
+	fn start(url: Retained<NSURL>) -> Result<ScopedAccess> {
+		let path = unsafe { url.path() }
+			.map(|path| PathBuf::from(path.to_string()))
+			.ok_or("bookmark is not a file")?;
+		let started: bool = unsafe { msg_send![&*url, startAccessingSecurityScopedResource] };
+		Ok(ScopedAccess { url, path, started })
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for ScopedAccess {
+	fn drop(&mut self) {
+		if self.started {
+			unsafe {
+				let _: () = msg_send![&*self.url, stopAccessingSecurityScopedResource];
+			}
+		}
+	}
+}
+
+// clones share the pasteboard and the promise delegates
+#[derive(Clone)]
 pub struct ClipboardContext {
 	pasteboard: Id<NSPasteboard>,
+	// delegates of the file promises on the pasteboard, the promises only hold weak references
+	promise_delegates: Arc<Mutex<Vec<Retained<PromiseDelegate>>>>,
 }
 
 pub struct ClipboardWatcherContext<T: ClipboardHandler> {
@@ -27,6 +215,8 @@ pub struct ClipboardWatcherContext<T: ClipboardHandler> {
 	stop_signal: Sender<()>,
 	stop_receiver: Receiver<()>,
 	running: bool,
+	low_power_interval: Option<Duration>,
+	on_battery: Box<dyn Fn() -> bool + Send>,
 }
 
 unsafe impl<T: ClipboardHandler> Send for ClipboardWatcherContext<T> {}
@@ -41,8 +231,25 @@ impl<T: ClipboardHandler> ClipboardWatcherContext<T> {
 			stop_signal: tx,
 			stop_receiver: rx,
 			running: false,
+			low_power_interval: None,
+			on_battery: Box::new(on_battery_power),
 		})
 	}
+
+	/// Poll the change count at `interval` instead of the `start_watch` frequency while the Mac
+	/// runs on battery. The pasteboard has no change notification, so a long interval is what
+	/// keeps an idle menu bar app from waking the CPU
+	pub fn with_low_power_interval(mut self, interval: Duration) -> Self {
+		self.low_power_interval = Some(interval);
+		self
+	}
+
+	/// Decide when the low power interval applies instead of asking IOKit for the power source,
+	/// e.g. to follow the app's own energy setting. Called about every 10 seconds
+	pub fn with_low_power_check<F: Fn() -> bool + Send + 'static>(mut self, check: F) -> Self {
+		self.on_battery = Box::new(check);
+		self
+	}
 }
 
 impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
@@ -62,28 +269,39 @@ impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
 		}
 		self.running = true;
 		let mut last_change_count = unsafe { self.pasteboard.changeCount() };
+		let mut interval = update_frequency;
+		let mut last_power_check: Option<Instant> = None;
 		loop {
+			if let Some(low_power_interval) = self.low_power_interval {
+				if last_power_check.map_or(true, |at| at.elapsed() >= POWER_CHECK_INTERVAL) {
+					interval = if (self.on_battery)() {
+						low_power_interval.max(update_frequency)
+					} else {
+						update_frequency
+					};
+					last_power_check = Some(Instant::now());
+				}
+			}
 			// if receive stop signal, break loop
-			if self.stop_receiver.recv_timeout(update_frequency).is_ok() {
+			if self.stop_receiver.recv_timeout(interval).is_ok() {
 				break;
 			}
 			let change_count = unsafe { self.pasteboard.changeCount() };
 			if last_change_count == 0 {
 				last_change_count = change_count;
 			} else if change_count != last_change_count {
-				self.handlers
-					.iter_mut()
-					.for_each(|handler| handler.on_clipboard_change());
+				trace_debug!(change_count, "pasteboard changed");
 				last_change_count = change_count;
+				if notify_handlers(&mut self.handlers, Selection::Clipboard).is_break() {
+					break;
+				}
 			}
 		}
 		self.running = false;
 	}
 
 	fn get_shutdown_channel(&self) -> WatcherShutdown {
-		WatcherShutdown {
-			stop_signal: self.stop_signal.clone(),
-		}
+		WatcherShutdown::new(self.stop_signal.clone())
 	}
 }
 
@@ -92,10 +310,251 @@ impl ClipboardContext {
 		let ns_pasteboard = unsafe { NSPasteboard::generalPasteboard() };
 		let clipboard_ctx = ClipboardContext {
 			pasteboard: ns_pasteboard,
+			promise_delegates: Arc::new(Mutex::new(Vec::new())),
 		};
 		Ok(clipboard_ctx)
 	}
 
+	/// Work on another pasteboard than the general one, a custom pasteboard is created if it
+	/// doesn't exist yet
+	pub fn with_pasteboard(name: PasteboardName) -> Result<ClipboardContext> {
+		let ns_pasteboard = unsafe {
+			match name {
+				PasteboardName::General => NSPasteboard::generalPasteboard(),
+				PasteboardName::Find => NSPasteboard::pasteboardWithName(NSPasteboardNameFind),
+				PasteboardName::Drag => NSPasteboard::pasteboardWithName(NSPasteboardNameDrag),
+				PasteboardName::Font => NSPasteboard::pasteboardWithName(NSPasteboardNameFont),
+				PasteboardName::Custom(name) => {
+					if name.is_empty() {
+						return Err("pasteboard name is empty".into());
+					}
+					NSPasteboard::pasteboardWithName(&NSString::from_str(&name))
+				}
+			}
+		};
+		Ok(ClipboardContext {
+			pasteboard: ns_pasteboard,
+			promise_delegates: Arc::new(Mutex::new(Vec::new())),
+		})
+	}
+
+	/// The `NSPasteboard *` this context works on, to call AppKit APIs the crate doesn't wrap.
+	/// The pointer stays valid as long as the context, retain it to keep it longer
+	///
+	/// # Safety
+	/// Writing to the pasteboard behind the context's back can break the file promises it keeps
+	#[cfg(feature = "raw-handle")]
+	pub unsafe fn raw_handle(&self) -> *mut c_void {
+		Id::as_ptr(&self.pasteboard) as *mut c_void
+	}
+
+	/// Get every pasteboard item with all of its representations, e.g. one item per image
+	/// when several photos are copied. `get` flattens the items into a single list.
+	pub fn get_items(&self) -> Result<Vec<Vec<ClipboardContent>>> {
+		autoreleasepool(|_| {
+			let items = unsafe { self.pasteboard.pasteboardItems() }
+				.ok_or("NSPasteboard#pasteboardItems errored")?;
+			Ok(items.iter().map(read_item).collect())
+		})
+	}
+
+	/// Write one pasteboard item per entry, each entry holds the representations of that item
+	/// in order of preference.
+	pub fn set_items(&self, items: Vec<Vec<ClipboardContent>>) -> Result<()> {
+		if items.is_empty() {
+			return Err("item list is empty".into());
+		}
+		autoreleasepool(|_| {
+			let mut ns_items = Vec::with_capacity(items.len());
+			for contents in &items {
+				let item = unsafe { NSPasteboardItem::new() };
+				for (r#type, payload) in pasteboard_entries(contents)? {
+					let written = unsafe {
+						match payload {
+							PasteboardPayload::String(string) => {
+								item.setString_forType(&NSString::from_str(string), &r#type)
+							}
+							PasteboardPayload::Data(bytes) => {
+								item.setData_forType(&ns_data(&bytes), &r#type)
+							}
+							PasteboardPayload::Files(files) => {
+								let ns_string_arr = NSArray::from_vec(
+									files.iter().map(|f| NSString::from_str(f)).collect(),
+								);
+								item.setPropertyList_forType(&ns_string_arr, &r#type)
+							}
+						}
+					};
+					if !written {
+						return Err(format!("write {} to pasteboard item failed", r#type).into());
+					}
+				}
+				ns_items.push(ProtocolObject::from_retained(item));
+			}
+			let written = unsafe {
+				self.pasteboard.clearContents();
+				self.pasteboard.writeObjects(&NSArray::from_vec(ns_items))
+			};
+			if !written {
+				return Err("NSPasteboard#writeObjects errored".into());
+			}
+			Ok(())
+		})
+	}
+
+	/// Whether another app put file promises on the pasteboard, e.g. images copied from Photos
+	/// or attachments from Mail. They have no path until redeemed with `receive_promised_files`.
+	pub fn has_promised_files(&self) -> bool {
+		has_promised_files(&self.pasteboard)
+	}
+
+	/// Redeem the file promises on the pasteboard into `dir`, blocking until the promising apps
+	/// wrote every file, and return the paths of the written files.
+	pub fn receive_promised_files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+		if !dir.is_dir() {
+			return Err(format!("{} is not a directory", dir.display()).into());
+		}
+		receive_promised_files(&self.pasteboard, dir)
+	}
+
+	/// Offer files whose bytes come from a provider instead of a path, as file promises.
+	/// The provider runs on the main thread when a paste target redeems a promise,
+	/// so the main run loop has to be running.
+	pub fn set_virtual_files(&self, files: Vec<VirtualFileSource>) -> Result<()> {
+		if files.is_empty() {
+			return Err("file list is empty".into());
+		}
+		let (providers, delegates) = file_promises(files);
+		let written = unsafe {
+			self.pasteboard.clearContents();
+			self.pasteboard.writeObjects(&NSArray::from_vec(providers))
+		};
+		if !written {
+			return Err("NSPasteboard#writeObjects errored".into());
+		}
+		*self.promise_delegates.lock().unwrap() = delegates;
+		Ok(())
+	}
+
+	/// Get the pasted files with security-scoped bookmarks. A sandboxed app can only open pasted
+	/// files through the URLs the pasteboard hands out, the bookmarks keep that access across launches
+	pub fn get_file_bookmarks(&self) -> Result<Vec<FileBookmark>> {
+		autoreleasepool(|_| {
+			// reading NSURL objects, not their strings, is what grants the sandbox access
+			let classes = unsafe {
+				let class: *const AnyClass = NSURL::class();
+				NSArray::from_vec(vec![
+					Retained::retain(class as *mut NSObject).ok_or("NSURL class is missing")?
+				])
+			};
+			let urls: Option<Retained<NSArray<NSURL>>> = unsafe {
+				msg_send_id![
+					&*self.pasteboard,
+					readObjectsForClasses: &*classes,
+					options: None::<&NSObject>
+				]
+			};
+			let files: Vec<FileBookmark> = urls
+				.ok_or("NSPasteboard#readObjectsForClasses errored")?
+				.iter()
+				.filter(|url| unsafe { url.isFileURL() })
+				.filter_map(|url| {
+					let path = unsafe { url.path() }?;
+					Some(FileBookmark {
+						path: PathBuf::from(path.to_string()),
+						bookmark: bookmark_data(&url),
+					})
+				})
+				.collect();
+			if files.is_empty() {
+				return Err("no files".into());
+			}
+			Ok(files)
+		})
+	}
+
+	/// Set files from bookmarks, e.g. kept from `get_file_bookmarks`, so that sandboxed apps
+	/// pasting them are granted access as well
+	pub fn set_file_bookmarks(&self, files: &[FileBookmark]) -> Result<()> {
+		if files.is_empty() {
+			return Err("file list is empty".into());
+		}
+		autoreleasepool(|_| {
+			// the access is only needed while the urls are written
+			let accesses = files
+				.iter()
+				.map(FileBookmark::access)
+				.collect::<Result<Vec<ScopedAccess>>>()?;
+			let urls = accesses
+				.iter()
+				.map(|access| ProtocolObject::from_retained(access.url.clone()))
+				.collect();
+			let written = unsafe {
+				self.pasteboard.clearContents();
+				self.pasteboard.writeObjects(&NSArray::from_vec(urls))
+			};
+			if !written {
+				return Err("NSPasteboard#writeObjects errored".into());
+			}
+			Ok(())
+		})
+	}
+
+	/// Get the web archive Safari and other WebKit apps copy, with the images `get_html` loses
+	pub fn get_web_archive(&self) -> Result<WebArchive> {
+		WebArchive::from_bytes(&self.get_buffer(WEB_ARCHIVE_TYPE)?)
+	}
+
+	/// Get the RTFD TextEdit and Notes copy, with the attachments `get_rich_text` loses
+	pub fn get_rtfd(&self) -> Result<Rtfd> {
+		Rtfd::from_bytes(&self.get_buffer(FLAT_RTFD_TYPE)?)
+	}
+
+	/// Get the image as TIFF, as Preview and Screenshot copy it. Images only offered in another
+	/// format are converted
+	pub fn get_image_tiff(&self) -> Result<Vec<u8>> {
+		let tiff_type = unsafe { NSPasteboardTypeTIFF }.to_string();
+		if let Ok(tiff) = self.get_buffer(&tiff_type) {
+			return Ok(tiff);
+		}
+		let (_, bytes) = self.get_image_raw()?;
+		image_to_tiff(&bytes).ok_or_else(|| "unable to convert the image to tiff".into())
+	}
+
+	/// Set a TIFF image as it is, with a PNG of it for the apps that only read PNG
+	pub fn set_image_tiff(&self, tiff: Vec<u8>) -> Result<()> {
+		let png = tiff_to_png(&tiff).ok_or("invalid tiff data")?;
+		let tiff_type = unsafe { NSPasteboardTypeTIFF }.to_string();
+		let png_type = unsafe { NSPasteboardTypePNG }.to_string();
+		self.write_to_clipboard(
+			&[
+				ClipboardContent::Other(tiff_type, tiff),
+				ClipboardContent::Other(png_type, png),
+			],
+			true,
+		)
+	}
+
+	/// The nspasteboard.org markers the writing app put next to the contents
+	fn metadata(&self) -> ContentMetadata {
+		autoreleasepool(|_| {
+			let types: Vec<String> = unsafe { self.pasteboard.types() }
+				.map(|types| types.iter().map(|t| t.to_string()).collect())
+				.unwrap_or_default();
+			let has = |r#type: &str| types.iter().any(|t| t == r#type);
+			ContentMetadata {
+				source: unsafe {
+					self.pasteboard
+						.stringForType(&NSString::from_str(SOURCE_TYPE))
+				}
+				.map(|source| source.to_string()),
+				auto_generated: has(AUTO_GENERATED_TYPE),
+				concealed: has(CONCEALED_TYPE),
+				transient: has(TRANSIENT_TYPE),
+			}
+		})
+	}
+
 	fn plain(&self, r#type: &NSPasteboardType) -> Result<String> {
 		autoreleasepool(|_| {
 			let contents = unsafe { self.pasteboard.pasteboardItems() }
@@ -109,92 +568,639 @@ impl ClipboardContext {
 		})
 	}
 
-	fn set_files(&self, files: &[String]) -> Result<()> {
-		let ns_string_arr =
-			NSArray::from_vec(files.iter().map(|f| NSString::from_str(f)).collect());
-		unsafe {
-			self.pasteboard
-				.setPropertyList_forType(&ns_string_arr, NSFilenamesPboardType)
-		};
-		Ok(())
+	fn read_contents(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		autoreleasepool(|_| {
+			let contents = unsafe { self.pasteboard.pasteboardItems() }
+				.ok_or("NSPasteboard#pasteboardItems errored")?;
+			let mut results = Vec::new();
+			for format in formats {
+				// urls are spread over all items
+				if let ContentFormat::Url = format {
+					let urls = read_urls(&contents);
+					if !urls.is_empty() {
+						results.push(ClipboardContent::Urls(urls));
+					}
+					continue;
+				}
+				for item in contents.iter() {
+					match format {
+						ContentFormat::Text => {
+							if let Some(string) =
+								unsafe { item.stringForType(NSPasteboardTypeString) }
+							{
+								results.push(ClipboardContent::Text(string.to_string()));
+								break;
+							}
+						}
+						ContentFormat::Rtf => {
+							if let Some(string) = unsafe { item.stringForType(NSPasteboardTypeRTF) }
+							{
+								results.push(ClipboardContent::Rtf(string.to_string()));
+								break;
+							}
+						}
+						ContentFormat::Html => {
+							if let Some(string) =
+								unsafe { item.stringForType(NSPasteboardTypeHTML) }
+							{
+								results.push(ClipboardContent::Html(string.to_string()));
+								break;
+							}
+						}
+						ContentFormat::Markdown => {
+							if let Some(string) =
+								unsafe { item.stringForType(&NSString::from_str(MARKDOWN_TYPE)) }
+							{
+								results.push(ClipboardContent::Markdown(string.to_string()));
+								break;
+							}
+						}
+						ContentFormat::Svg => {
+							if let Some(data) =
+								unsafe { item.dataForType(&NSString::from_str(SVG_TYPE)) }
+							{
+								results.push(ClipboardContent::Svg(
+									String::from_utf8_lossy(data.bytes()).to_string(),
+								));
+								break;
+							}
+						}
+						ContentFormat::Url => {}
+						ContentFormat::Pdf => {
+							if let Some(data) = unsafe { item.dataForType(NSPasteboardTypePDF) } {
+								results.push(ClipboardContent::Pdf(data.bytes().to_vec()));
+								break;
+							}
+						}
+						ContentFormat::Color => {
+							if let Some(color) =
+								unsafe { item.dataForType(&NSString::from_str(COLOR_TYPE)) }
+									.and_then(|data| color_from_data(&data))
+							{
+								results.push(ClipboardContent::Color(color));
+								break;
+							}
+						}
+						ContentFormat::Image => {
+							if let Ok(image) = self.get_image() {
+								results.push(ClipboardContent::Image(image));
+								break;
+							}
+						}
+						ContentFormat::Files => {
+							if let Ok(files) = self.get_files() {
+								results.push(ClipboardContent::Files(files));
+								break;
+							}
+						}
+						ContentFormat::Other(format_name) => {
+							if let Some(data) =
+								unsafe { item.dataForType(&NSString::from_str(format_name)) }
+							{
+								results.push(ClipboardContent::Other(
+									format_name.to_string(),
+									data.bytes().to_vec(),
+								));
+								break;
+							}
+						}
+						ContentFormat::Custom(handle) => {
+							if let Some(data) =
+								unsafe { item.dataForType(&NSString::from_str(handle.name())) }
+							{
+								results.push(ClipboardContent::Custom(
+									handle.clone(),
+									data.bytes().to_vec(),
+								));
+								break;
+							}
+						}
+					}
+				}
+			}
+			Ok(results)
+		})
 	}
 
-	// learn from https://github.com/zed-industries/zed/blob/79c1003b344ee513cf97ee8313c38c7c3f02c916/crates/gpui/src/platform/mac/platform.rs#L793
+	/// Publish all representations in a single pasteboard item.
+	/// The types are declared in the given order, so the first content is the preferred representation.
 	fn write_to_clipboard(&self, data: &[ClipboardContent], with_clear: bool) -> Result<()> {
-		if with_clear {
-			unsafe {
-				self.pasteboard.clearContents();
-			}
-		}
+		let entries = pasteboard_entries(data)?;
 		autoreleasepool(|_| unsafe {
-			let mut write_objects: Vec<Id<ProtocolObject<(dyn NSPasteboardWriting + 'static)>>> =
-				vec![];
-			for d in data {
-				match d {
-					ClipboardContent::Text(text) => {
-						let item = NSPasteboardItem::new();
-						item.setString_forType(&NSString::from_str(text), NSPasteboardTypeString);
-						write_objects.push(ProtocolObject::from_id(item));
-					}
-					ClipboardContent::Rtf(rtf) => {
-						let item = NSPasteboardItem::new();
-						item.setString_forType(&NSString::from_str(rtf), NSPasteboardTypeRTF);
-						write_objects.push(ProtocolObject::from_id(item));
-					}
-					ClipboardContent::Html(html) => {
-						let item = NSPasteboardItem::new();
-						item.setString_forType(&NSString::from_str(html), NSPasteboardTypeHTML);
-						write_objects.push(ProtocolObject::from_id(item));
-					}
-					ClipboardContent::Image(image) => {
-						let png_img = image.to_png();
-						if let Ok(png_buffer) = png_img {
-							let bytes = png_buffer.get_bytes();
-							let ns_data = {
-								NSData::initWithBytes_length(
-									NSData::alloc(),
-									bytes.as_ptr() as *mut c_void,
-									bytes.len(),
-								)
-							};
-							let item = NSPasteboardItem::new();
-							item.setData_forType(&ns_data, NSPasteboardTypePNG);
-							write_objects.push(ProtocolObject::from_id(item));
-						};
-					}
-					ClipboardContent::Files(files) => {
-						let _ = self.set_files(files);
-					}
-					ClipboardContent::Other(format, buffer) => {
-						let ns_data = {
-							NSData::initWithBytes_length(
-								NSData::alloc(),
-								buffer.as_ptr() as *mut c_void,
-								buffer.len(),
-							)
-						};
-						self.pasteboard.declareTypes_owner(
-							&NSArray::from_vec(vec![NSString::from_str(format)]),
-							None,
+			let types = NSArray::from_vec(entries.iter().map(|(t, _)| t.clone()).collect());
+			if with_clear {
+				// declareTypes clears the pasteboard and increments the change count once
+				self.pasteboard.declareTypes_owner(&types, None);
+			} else {
+				self.pasteboard.addTypes_owner(&types, None);
+			}
+			for (r#type, payload) in &entries {
+				let written = match payload {
+					PasteboardPayload::String(string) => self
+						.pasteboard
+						.setString_forType(&NSString::from_str(string), r#type),
+					PasteboardPayload::Data(bytes) => self
+						.pasteboard
+						.setData_forType(Some(&ns_data(bytes)), r#type),
+					PasteboardPayload::Files(files) => {
+						let ns_string_arr = NSArray::from_vec(
+							files.iter().map(|f| NSString::from_str(f)).collect(),
 						);
-						let item = NSPasteboardItem::new();
-						item.setData_forType(&ns_data, &NSString::from_str(format));
-						write_objects.push(ProtocolObject::from_id(item));
+						self.pasteboard
+							.setPropertyList_forType(&ns_string_arr, r#type)
 					}
+				};
+				if !written {
+					return Err(format!("write {} to pasteboard failed", r#type));
 				}
 			}
-			if !self
-				.pasteboard
-				.writeObjects(&NSArray::from_vec(write_objects))
-			{
-				return Err("writeObjects failed");
-			}
 			Ok(())
 		})?;
 		Ok(())
 	}
 }
 
+/// Map contents to their pasteboard types in order, a type can only appear once
+fn pasteboard_entries(
+	data: &[ClipboardContent],
+) -> Result<Vec<(Id<NSString>, PasteboardPayload<'_>)>> {
+	let mut entries: Vec<(Id<NSString>, PasteboardPayload)> = Vec::with_capacity(data.len());
+	for d in data {
+		let representations = match d {
+			ClipboardContent::Text(text) => vec![(
+				unsafe { NSPasteboardTypeString }.to_owned(),
+				PasteboardPayload::String(text),
+			)],
+			ClipboardContent::Rtf(rtf) => vec![(
+				unsafe { NSPasteboardTypeRTF }.to_owned(),
+				PasteboardPayload::String(rtf),
+			)],
+			ClipboardContent::Html(html) => vec![(
+				unsafe { NSPasteboardTypeHTML }.to_owned(),
+				PasteboardPayload::String(html),
+			)],
+			ClipboardContent::Markdown(markdown) => vec![(
+				NSString::from_str(MARKDOWN_TYPE),
+				PasteboardPayload::String(markdown),
+			)],
+			ClipboardContent::Svg(svg) => vec![(
+				NSString::from_str(SVG_TYPE),
+				PasteboardPayload::Data(Cow::Borrowed(svg.as_bytes())),
+			)],
+			ClipboardContent::Image(image) => {
+				let mut representations = Vec::with_capacity(2);
+				// gif first, so animations are kept
+				if let Some(gif) = image.gif_bytes() {
+					representations.push((
+						NSString::from_str(GIF_TYPE),
+						PasteboardPayload::Data(Cow::Borrowed(gif)),
+					));
+				}
+				let png = image.to_png()?.get_bytes().to_vec();
+				// older Cocoa apps only read TIFF
+				let tiff = image_to_tiff(&png);
+				representations.push((
+					unsafe { NSPasteboardTypePNG }.to_owned(),
+					PasteboardPayload::Data(Cow::Owned(png)),
+				));
+				if let Some(tiff) = tiff {
+					representations.push((
+						unsafe { NSPasteboardTypeTIFF }.to_owned(),
+						PasteboardPayload::Data(Cow::Owned(tiff)),
+					));
+				}
+				representations
+			}
+			ClipboardContent::Files(files) => vec![(
+				unsafe { NSFilenamesPboardType }.to_owned(),
+				PasteboardPayload::Files(files),
+			)],
+			ClipboardContent::Urls(urls) => {
+				// one pasteboard item holds a single url
+				let mut representations = Vec::with_capacity(2);
+				if let Some(url) = urls.first() {
+					representations.push((
+						NSString::from_str(URL_TYPE),
+						PasteboardPayload::String(&url.url),
+					));
+					if let Some(title) = &url.title {
+						representations.push((
+							NSString::from_str(URL_NAME_TYPE),
+							PasteboardPayload::String(title),
+						));
+					}
+				}
+				representations
+			}
+			ClipboardContent::Pdf(pdf) => vec![(
+				unsafe { NSPasteboardTypePDF }.to_owned(),
+				PasteboardPayload::Data(Cow::Borrowed(pdf)),
+			)],
+			ClipboardContent::Color(color) => vec![(
+				NSString::from_str(COLOR_TYPE),
+				PasteboardPayload::Data(Cow::Owned(color_to_data(color))),
+			)],
+			ClipboardContent::Other(format, buffer) => vec![(
+				NSString::from_str(format),
+				PasteboardPayload::Data(Cow::Borrowed(buffer)),
+			)],
+			ClipboardContent::Custom(handle, buffer) => vec![(
+				NSString::from_str(handle.name()),
+				PasteboardPayload::Data(Cow::Borrowed(buffer)),
+			)],
+		};
+		for (r#type, payload) in representations {
+			// a type can only be declared once, the first occurrence wins
+			if !entries.iter().any(|(t, _)| *t == r#type) {
+				entries.push((r#type, payload));
+			}
+		}
+	}
+	Ok(entries)
+}
+
+/// The data of one pasteboard type waiting to be written
+enum PasteboardPayload<'a> {
+	String(&'a str),
+	Data(Cow<'a, [u8]>),
+	Files(&'a [String]),
+}
+
+/// Read all representations of one item, image types are merged into a single image
+fn read_item(item: &NSPasteboardItem) -> Vec<ClipboardContent> {
+	let mut contents = Vec::new();
+	let types = unsafe { item.types() };
+	let image_types = [
+		GIF_TYPE.to_owned(),
+		unsafe { NSPasteboardTypePNG }.to_string(),
+		unsafe { NSPasteboardTypeTIFF }.to_string(),
+		WEBP_TYPE.to_owned(),
+	];
+	let mut has_image = false;
+	for r#type in types.iter() {
+		let type_name = r#type.to_string();
+		let content = unsafe {
+			if r#type == NSPasteboardTypeString {
+				item.stringForType(r#type)
+					.map(|s| ClipboardContent::Text(s.to_string()))
+			} else if r#type == NSPasteboardTypeRTF {
+				item.stringForType(r#type)
+					.map(|s| ClipboardContent::Rtf(s.to_string()))
+			} else if r#type == NSPasteboardTypeHTML {
+				item.stringForType(r#type)
+					.map(|s| ClipboardContent::Html(s.to_string()))
+			} else if type_name == MARKDOWN_TYPE {
+				item.stringForType(r#type)
+					.map(|s| ClipboardContent::Markdown(s.to_string()))
+			} else if type_name == SVG_TYPE {
+				item.dataForType(r#type).map(|data| {
+					ClipboardContent::Svg(String::from_utf8_lossy(data.bytes()).to_string())
+				})
+			} else if image_types.contains(&type_name) {
+				if has_image {
+					continue;
+				}
+				let is_tiff = r#type == NSPasteboardTypeTIFF;
+				item.dataForType(r#type)
+					.and_then(|data| {
+						if is_tiff {
+							image_from_tiff(data.bytes()).ok()
+						} else {
+							RustImageData::from_bytes(data.bytes()).ok()
+						}
+					})
+					.map(|image| {
+						has_image = true;
+						ClipboardContent::Image(image)
+					})
+			} else if r#type == NSPasteboardTypeFileURL {
+				item.stringForType(r#type)
+					.and_then(|uri| NSURL::URLWithString(&uri)?.filePathURL()?.path())
+					.map(|path| ClipboardContent::Files(vec![path.to_string()]))
+			} else if type_name == URL_TYPE {
+				item.stringForType(r#type).map(|url| {
+					let title = item.stringForType(&NSString::from_str(URL_NAME_TYPE));
+					ClipboardContent::Urls(vec![Url {
+						url: url.to_string(),
+						title: title.map(|t| t.to_string()),
+					}])
+				})
+			} else if r#type == NSPasteboardTypePDF {
+				item.dataForType(r#type)
+					.map(|data| ClipboardContent::Pdf(data.bytes().to_vec()))
+			} else if type_name == COLOR_TYPE {
+				item.dataForType(r#type)
+					.and_then(|data| color_from_data(&data))
+					.map(ClipboardContent::Color)
+			} else if type_name == URL_NAME_TYPE {
+				// read together with its url
+				continue;
+			} else {
+				item.dataForType(r#type)
+					.map(|data| ClipboardContent::Other(type_name, data.bytes().to_vec()))
+			}
+		};
+		if let Some(content) = content {
+			contents.push(content);
+		}
+	}
+	contents
+}
+
+/// Unarchive the NSColor of the color type and convert it to sRGB, catalog and pattern
+/// colors have no sRGB components
+fn color_from_data(data: &NSData) -> Option<Rgba> {
+	unsafe {
+		let color: Option<Id<NSColor>> =
+			msg_send_id![class!(NSKeyedUnarchiver), unarchiveObjectWithData: data];
+		let color = color?.colorUsingColorSpace(&NSColorSpace::sRGBColorSpace())?;
+		Some(Rgba::new(
+			color.redComponent() as f32,
+			color.greenComponent() as f32,
+			color.blueComponent() as f32,
+			color.alphaComponent() as f32,
+		))
+	}
+}
+
+/// Archive the color as the NSColor other apps expect under the color type
+fn color_to_data(color: &Rgba) -> Vec<u8> {
+	unsafe {
+		let color = NSColor::colorWithSRGBRed_green_blue_alpha(
+			color.r.into(),
+			color.g.into(),
+			color.b.into(),
+			color.a.into(),
+		);
+		let data: Id<NSData> =
+			msg_send_id![class!(NSKeyedArchiver), archivedDataWithRootObject: &*color];
+		data.bytes().to_vec()
+	}
+}
+
+/// A security-scoped bookmark of the url, `None` when the app isn't entitled to create one
+fn bookmark_data(url: &NSURL) -> Option<Vec<u8>> {
+	let data: Option<Retained<NSData>> = unsafe {
+		msg_send_id![
+			url,
+			bookmarkDataWithOptions: BOOKMARK_WITH_SECURITY_SCOPE,
+			includingResourceValuesForKeys: None::<&NSObject>,
+			relativeToURL: None::<&NSURL>,
+			error: ptr::null_mut::<*mut NSObject>()
+		]
+	};
+	data.map(|data| data.bytes().to_vec())
+}
+
+fn resolve_bookmark(bookmark: &[u8]) -> Result<Retained<NSURL>> {
+	let url: Option<Retained<NSURL>> = unsafe {
+		msg_send_id![
+			class!(NSURL),
+			URLByResolvingBookmarkData: &*ns_data(bookmark),
+			options: RESOLVE_WITH_SECURITY_SCOPE,
+			relativeToURL: None::<&NSURL>,
+			bookmarkDataIsStale: ptr::null_mut::<Bool>(),
+			error: ptr::null_mut::<*mut NSObject>()
+		]
+	};
+	url.ok_or_else(|| "invalid or stale bookmark".into())
+}
+
+/// The value of `key` in a property list dictionary, if it is a `T`
+fn plist_value<T: ClassType>(dict: &NSObject, key: &str) -> Option<Retained<T>> {
+	unsafe {
+		let value: Option<Retained<NSObject>> =
+			msg_send_id![dict, objectForKey: &*NSString::from_str(key)];
+		let value = value?;
+		if value.isKindOfClass(T::class()) {
+			Some(Retained::cast(value))
+		} else {
+			None
+		}
+	}
+}
+
+fn plist_string(dict: &NSObject, key: &str) -> Option<String> {
+	plist_value::<NSString>(dict, key).map(|s| s.to_string())
+}
+
+fn plist_bytes(dict: &NSObject, key: &str) -> Option<Vec<u8>> {
+	plist_value::<NSData>(dict, key).map(|data| data.bytes().to_vec())
+}
+
+/// A web archive is a property list, the page in `WebMainResource` and the images and
+/// stylesheets in `WebSubresources`
+fn decode_web_archive(data: &NSData) -> Option<WebArchive> {
+	let archive: Option<Retained<NSObject>> = unsafe {
+		msg_send_id![
+			class!(NSPropertyListSerialization),
+			propertyListWithData: data,
+			options: 0usize,
+			format: ptr::null_mut::<usize>(),
+			error: ptr::null_mut::<*mut NSObject>()
+		]
+	};
+	let archive = archive?;
+	let main = plist_value::<NSDictionary<NSString, NSObject>>(&archive, "WebMainResource")?;
+	let html = plist_bytes(&main, "WebResourceData")?;
+	let resources = plist_value::<NSArray<NSObject>>(&archive, "WebSubresources")
+		.map(|resources| {
+			resources
+				.iter()
+				.filter_map(|resource| {
+					let resource = &*resource;
+					Some(Attachment {
+						name: plist_string(resource, "WebResourceURL")?,
+						mime: plist_string(resource, "WebResourceMIMEType"),
+						data: plist_bytes(resource, "WebResourceData")?,
+					})
+				})
+				.collect()
+		})
+		.unwrap_or_default();
+	Some(WebArchive {
+		html: String::from_utf8_lossy(&html).into_owned(),
+		url: plist_string(&main, "WebResourceURL"),
+		resources,
+	})
+}
+
+/// A flat RTFD is a serialized directory, `TXT.rtf` next to the attached files
+fn decode_rtfd(data: &NSData) -> Option<Rtfd> {
+	let wrapper: Option<Retained<NSObject>> = unsafe {
+		let wrapper: Allocated<NSObject> = msg_send_id![class!(NSFileWrapper), alloc];
+		msg_send_id![wrapper, initWithSerializedRepresentation: data]
+	};
+	let files: Option<Retained<NSDictionary<NSString, NSObject>>> =
+		unsafe { msg_send_id![&*wrapper?, fileWrappers] };
+	let files = files?;
+	let names: Retained<NSArray<NSString>> = unsafe { msg_send_id![&*files, allKeys] };
+	let mut rtf = None;
+	let mut attachments = Vec::new();
+	for name in names.iter() {
+		let name = name.to_string();
+		let file = plist_value::<NSObject>(&files, &name)?;
+		// nested directories have no contents
+		let contents: Option<Retained<NSData>> =
+			unsafe { msg_send_id![&*file, regularFileContents] };
+		let contents = match contents {
+			Some(contents) => contents,
+			None => continue,
+		};
+		if name == RTFD_TEXT_FILE {
+			rtf = Some(String::from_utf8_lossy(contents.bytes()).into_owned());
+		} else {
+			attachments.push(Attachment {
+				name,
+				mime: None,
+				data: contents.bytes().to_vec(),
+			});
+		}
+	}
+	Some(Rtfd {
+		rtf: rtf?,
+		attachments,
+	})
+}
+
+// NSFontDescriptorSymbolicTraits
+const FONT_TRAIT_ITALIC: u32 = 1 << 0;
+const FONT_TRAIT_BOLD: u32 = 1 << 1;
+
+/// Parse RTF or a flat RTFD with NSAttributedString and split it into runs, `None` when AppKit
+/// can't read the data
+fn attributed_text(data: &NSData, rtfd: bool) -> Option<AttributedText> {
+	unsafe {
+		let string: Allocated<NSObject> = msg_send_id![class!(NSAttributedString), alloc];
+		let string: Option<Retained<NSObject>> = if rtfd {
+			msg_send_id![
+				string,
+				initWithRTFD: data,
+				documentAttributes: ptr::null_mut::<*mut NSObject>()
+			]
+		} else {
+			msg_send_id![
+				string,
+				initWithRTF: data,
+				documentAttributes: ptr::null_mut::<*mut NSObject>()
+			]
+		};
+		let string = string?;
+		let text: Retained<NSString> = msg_send_id![&*string, string];
+		let len: usize = msg_send![&*string, length];
+		let mut runs = Vec::new();
+		let mut index = 0;
+		while index < len {
+			let mut range = NSRange::new(index, 0);
+			let attributes: Retained<NSDictionary<NSString, NSObject>> = msg_send_id![
+				&*string,
+				attributesAtIndex: index,
+				effectiveRange: &mut range
+			];
+			let part: Retained<NSString> = msg_send_id![&*text, substringWithRange: range];
+			let traits = plist_value::<NSObject>(&attributes, "NSFont").map_or(0, |font| {
+				let descriptor: Retained<NSObject> = msg_send_id![&*font, fontDescriptor];
+				let traits: u32 = msg_send![&*descriptor, symbolicTraits];
+				traits
+			});
+			let underline =
+				plist_value::<NSObject>(&attributes, "NSUnderline").map_or(0, |style| {
+					let style: isize = msg_send![&*style, integerValue];
+					style
+				});
+			let link = plist_value::<NSURL>(&attributes, "NSLink")
+				.and_then(|url| url.absoluteString())
+				.map(|url| url.to_string())
+				.or_else(|| plist_string(&attributes, "NSLink"));
+			let color = plist_value::<NSColor>(&attributes, "NSColor")
+				.and_then(|color| color.colorUsingColorSpace(&NSColorSpace::sRGBColorSpace()))
+				.map(|color| {
+					Rgba::new(
+						color.redComponent() as f32,
+						color.greenComponent() as f32,
+						color.blueComponent() as f32,
+						color.alphaComponent() as f32,
+					)
+				});
+			runs.push(TextRun {
+				text: part.to_string(),
+				bold: traits & FONT_TRAIT_BOLD != 0,
+				italic: traits & FONT_TRAIT_ITALIC != 0,
+				underline: underline != 0,
+				link,
+				color,
+			});
+			// an empty range would never move on
+			index = (range.location + range.length).max(index + 1);
+		}
+		Some(AttributedText { runs })
+	}
+}
+
+/// Read `public.url` and its `public.url-name` title from every pasteboard item
+fn read_urls(items: &NSArray<NSPasteboardItem>) -> Vec<Url> {
+	let url_type = NSString::from_str(URL_TYPE);
+	let url_name_type = NSString::from_str(URL_NAME_TYPE);
+	items
+		.iter()
+		.filter_map(|item| {
+			let url = unsafe { item.stringForType(&url_type) }?;
+			let title = unsafe { item.stringForType(&url_name_type) };
+			Some(Url {
+				url: url.to_string(),
+				title: title.map(|t| t.to_string()),
+			})
+		})
+		.collect()
+}
+
+/// Reads pasteboard data in place instead of copying it into a `Vec`
+struct DataReader {
+	data: Id<NSData>,
+	pos: usize,
+}
+
+impl Read for DataReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let rest = &self.data.bytes()[self.pos..];
+		let n = rest.len().min(buf.len());
+		buf[..n].copy_from_slice(&rest[..n]);
+		self.pos += n;
+		Ok(n)
+	}
+}
+
+fn ns_data(bytes: &[u8]) -> Id<NSData> {
+	unsafe {
+		NSData::initWithBytes_length(NSData::alloc(), bytes.as_ptr() as *mut c_void, bytes.len())
+	}
+}
+
+/// Convert an image AppKit can read to TIFF
+fn image_to_tiff(bytes: &[u8]) -> Option<Vec<u8>> {
+	autoreleasepool(|_| unsafe {
+		let rep = NSBitmapImageRep::imageRepWithData(&ns_data(bytes))?;
+		rep.TIFFRepresentation().map(|tiff| tiff.bytes().to_vec())
+	})
+}
+
+/// Convert a TIFF to PNG with AppKit, so no image codec is needed to read it
+fn tiff_to_png(tiff: &[u8]) -> Option<Vec<u8>> {
+	autoreleasepool(|_| unsafe {
+		let rep = NSBitmapImageRep::imageRepWithData(&ns_data(tiff))?;
+		rep.representationUsingType_properties(NSBitmapImageFileType::PNG, &NSDictionary::new())
+			.map(|png| png.bytes().to_vec())
+	})
+}
+
+/// Read a TIFF through PNG, keeping the resolution of the TIFF when the PNG doesn't declare one
+fn image_from_tiff(tiff: &[u8]) -> Result<RustImageData> {
+	let png = tiff_to_png(tiff).ok_or("invalid tiff data")?;
+	let image = RustImageData::from_bytes(&png)?;
+	let dpi = image.dpi().or_else(|| dpi::read_dpi(tiff));
+	Ok(image.with_dpi(dpi))
+}
+
 unsafe impl Send for ClipboardContext {}
 
 unsafe impl Sync for ClipboardContext {}
@@ -206,6 +1212,22 @@ impl Clipboard for ClipboardContext {
 		Ok(res)
 	}
 
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		let res: Vec<FormatInfo> = self
+			.available_formats()?
+			.into_iter()
+			.map(|name| FormatInfo {
+				mime: format_mime(&name),
+				platform_id: None,
+				// the pasteboard only tells the size by loading the data
+				size_hint: None,
+				name,
+			})
+			.collect();
+		trace_debug!(count = res.len(), "formats enumerated");
+		Ok(res)
+	}
+
 	fn has(&self, format: ContentFormat) -> bool {
 		match format {
 			ContentFormat::Text => unsafe {
@@ -224,6 +1246,14 @@ impl Clipboard for ClipboardContext {
 				let types = NSArray::arrayWithObject(NSPasteboardTypeHTML);
 				self.pasteboard.availableTypeFromArray(&types).is_some()
 			},
+			ContentFormat::Markdown => unsafe {
+				let types = NSArray::from_vec(vec![NSString::from_str(MARKDOWN_TYPE)]);
+				self.pasteboard.availableTypeFromArray(&types).is_some()
+			},
+			ContentFormat::Svg => unsafe {
+				let types = NSArray::from_vec(vec![NSString::from_str(SVG_TYPE)]);
+				self.pasteboard.availableTypeFromArray(&types).is_some()
+			},
 			ContentFormat::Image => unsafe {
 				// Currently only judge whether there is a png format
 				let types = NSArray::from_vec(vec![
@@ -236,10 +1266,26 @@ impl Clipboard for ClipboardContext {
 				let types = NSArray::arrayWithObject(NSFilenamesPboardType);
 				self.pasteboard.availableTypeFromArray(&types).is_some()
 			},
+			ContentFormat::Url => unsafe {
+				let types = NSArray::from_vec(vec![NSString::from_str(URL_TYPE)]);
+				self.pasteboard.availableTypeFromArray(&types).is_some()
+			},
+			ContentFormat::Pdf => unsafe {
+				let types = NSArray::arrayWithObject(NSPasteboardTypePDF);
+				self.pasteboard.availableTypeFromArray(&types).is_some()
+			},
+			ContentFormat::Color => unsafe {
+				let types = NSArray::from_vec(vec![NSString::from_str(COLOR_TYPE)]);
+				self.pasteboard.availableTypeFromArray(&types).is_some()
+			},
 			ContentFormat::Other(format) => unsafe {
 				let types = NSArray::from_vec(vec![NSString::from_str(&format)]);
 				self.pasteboard.availableTypeFromArray(&types).is_some()
 			},
+			ContentFormat::Custom(handle) => unsafe {
+				let types = NSArray::from_vec(vec![NSString::from_str(handle.name())]);
+				self.pasteboard.availableTypeFromArray(&types).is_some()
+			},
 		}
 	}
 
@@ -250,11 +1296,26 @@ impl Clipboard for ClipboardContext {
 
 	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
 		if let Some(data) = unsafe { self.pasteboard.dataForType(&NSString::from_str(format)) } {
+			trace_debug!(format, bytes = data.len(), "buffer read");
 			return Ok(data.bytes().to_vec());
 		}
 		Err("no data".into())
 	}
 
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		match unsafe { self.pasteboard.dataForType(&NSString::from_str(format)) } {
+			Some(data) => Ok(Box::new(DataReader { data, pos: 0 })),
+			None => Err("no data".into()),
+		}
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		match unsafe { self.pasteboard.dataForType(&NSString::from_str(format)) } {
+			Some(data) => Ok(data.len()),
+			None => Err("no data".into()),
+		}
+	}
+
 	fn get_text(&self) -> Result<String> {
 		self.plain(unsafe { NSPasteboardTypeString })
 	}
@@ -263,23 +1324,95 @@ impl Clipboard for ClipboardContext {
 		self.plain(unsafe { NSPasteboardTypeRTF })
 	}
 
+	fn get_attributed_text(&self) -> Result<AttributedText> {
+		// RTFD keeps what TextEdit and Notes copy best, RTF is what everything else writes
+		let read = |r#type: &str, rtfd: bool| {
+			let data = self.get_buffer(r#type).ok()?;
+			autoreleasepool(|_| attributed_text(&ns_data(&data), rtfd))
+		};
+		let rtf_type = unsafe { NSPasteboardTypeRTF }.to_string();
+		match read(FLAT_RTFD_TYPE, true).or_else(|| read(&rtf_type, false)) {
+			Some(text) => Ok(text),
+			None => Ok(AttributedText::plain(&self.get_text()?)),
+		}
+	}
+
 	fn get_html(&self) -> Result<String> {
 		self.plain(unsafe { NSPasteboardTypeHTML })
 	}
 
+	fn get_html_with_source(&self) -> Result<(String, Option<Url>)> {
+		let html = self.get_html()?;
+		let source = self
+			.plain(&NSString::from_str(SOURCE_URL_TYPE))
+			.ok()
+			.or_else(|| self.get_web_archive().ok().and_then(|archive| archive.url))
+			.filter(|url| !url.is_empty())
+			.map(|url| Url::new(&url));
+		Ok((html, source))
+	}
+
+	fn set_html_with_source(&self, html: String, source: Url) -> Result<()> {
+		self.set(vec![
+			ClipboardContent::Html(html),
+			ClipboardContent::Other(SOURCE_URL_TYPE.to_owned(), source.url.into_bytes()),
+		])
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		let buffer = self.get_buffer(SVG_TYPE)?;
+		Ok(String::from_utf8_lossy(&buffer).to_string())
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		self.plain(&NSString::from_str(MARKDOWN_TYPE))
+	}
+
 	fn get_image(&self) -> Result<RustImageData> {
 		autoreleasepool(|_| {
+			// gif first, so animations are kept
+			let gif_data = unsafe { self.pasteboard.dataForType(&NSString::from_str(GIF_TYPE)) };
+			if let Some(data) = gif_data {
+				if let Ok(image) = RustImageData::from_bytes(data.bytes()) {
+					return Ok(image);
+				}
+			};
 			let png_data = unsafe { self.pasteboard.dataForType(NSPasteboardTypePNG) };
 			if let Some(data) = png_data {
 				return RustImageData::from_bytes(data.bytes());
 			};
-			// if no png data, read NSImage;
+			// browsers may only offer webp
+			let webp_data = unsafe { self.pasteboard.dataForType(&NSString::from_str(WEBP_TYPE)) };
+			if let Some(data) = webp_data {
+				if let Ok(image) = RustImageData::from_bytes(data.bytes()) {
+					return Ok(image);
+				}
+			};
+			// Preview and Screenshot put TIFF first
+			let tiff_data = unsafe { self.pasteboard.dataForType(NSPasteboardTypeTIFF) };
+			if let Some(data) = tiff_data {
+				if let Ok(image) = image_from_tiff(data.bytes()) {
+					return Ok(image);
+				}
+			};
+			// other types AppKit can read, e.g. pdf and icns
 			let ns_image =
 				unsafe { NSImage::initWithPasteboard(NSImage::alloc(), &self.pasteboard) };
 			if let Some(image) = ns_image {
 				let tiff_data = unsafe { image.TIFFRepresentation() };
 				if let Some(data) = tiff_data {
-					return RustImageData::from_bytes(data.bytes());
+					let decoded = image_from_tiff(data.bytes())?;
+					// the size is in points, 72 per inch, a Retina image has twice the pixels
+					let size = unsafe { image.size() };
+					if size.width <= 0.0 || size.height <= 0.0 {
+						return Ok(decoded);
+					}
+					let (width, height) = decoded.get_size();
+					let dpi = (
+						(72.0 * width as f64 / size.width) as f32,
+						(72.0 * height as f64 / size.height) as f32,
+					);
+					return Ok(decoded.with_dpi(Some(dpi)));
 				}
 			};
 			Err("no image data".into())
@@ -304,67 +1437,99 @@ impl Clipboard for ClipboardContext {
 		Ok(res)
 	}
 
-	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		let paths = autoreleasepool(|_| -> Result<Vec<PathBuf>> {
+			let items = unsafe { self.pasteboard.pasteboardItems() }
+				.ok_or("NSPasteboard#pasteboardItems errored")?;
+			let paths: Vec<PathBuf> = items
+				.iter()
+				.filter_map(|item| unsafe {
+					let uri = item.stringForType(NSPasteboardTypeFileURL)?;
+					// resolve file reference urls like file:///.file/id=...
+					let url = NSURL::URLWithString(&uri)?.filePathURL()?;
+					url.path().map(|p| PathBuf::from(p.to_string()))
+				})
+				.collect();
+			Ok(paths)
+		})?;
+		if !paths.is_empty() {
+			return Ok(paths);
+		}
+		// older apps only publish NSFilenamesPboardType
+		self.get_files()
+			.map(|files| files.into_iter().map(PathBuf::from).collect())
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		// Finder has no cut marker on the pasteboard, moving is chosen by the user when pasting
+		Ok(FileOperation::Copy)
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
 		autoreleasepool(|_| {
 			let contents = unsafe { self.pasteboard.pasteboardItems() }
 				.ok_or("NSPasteboard#pasteboardItems errored")?;
-			let mut results = Vec::new();
-			for format in formats {
-				for item in contents.iter() {
-					match format {
-						ContentFormat::Text => {
-							if let Some(string) =
-								unsafe { item.stringForType(NSPasteboardTypeString) }
-							{
-								results.push(ClipboardContent::Text(string.to_string()));
-								break;
-							}
-						}
-						ContentFormat::Rtf => {
-							if let Some(string) = unsafe { item.stringForType(NSPasteboardTypeRTF) }
-							{
-								results.push(ClipboardContent::Rtf(string.to_string()));
-								break;
-							}
-						}
-						ContentFormat::Html => {
-							if let Some(string) =
-								unsafe { item.stringForType(NSPasteboardTypeHTML) }
-							{
-								results.push(ClipboardContent::Html(string.to_string()));
-								break;
-							}
-						}
-						ContentFormat::Image => {
-							if let Ok(image) = self.get_image() {
-								results.push(ClipboardContent::Image(image));
-								break;
-							}
-						}
-						ContentFormat::Files => {
-							if let Ok(files) = self.get_files() {
-								results.push(ClipboardContent::Files(files));
-								break;
-							}
-						}
-						ContentFormat::Other(format_name) => {
-							if let Some(data) =
-								unsafe { item.dataForType(&NSString::from_str(format_name)) }
-							{
-								results.push(ClipboardContent::Other(
-									format_name.to_string(),
-									data.bytes().to_vec(),
-								));
-								break;
-							}
-						}
-					}
+			let urls = read_urls(&contents);
+			if urls.is_empty() {
+				return Err("no urls".into());
+			}
+			Ok(urls)
+		})
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		autoreleasepool(|_| {
+			let types = unsafe {
+				[
+					NSPasteboardTypePNG.to_owned(),
+					NSPasteboardTypeTIFF.to_owned(),
+					NSString::from_str(WEBP_TYPE),
+				]
+			};
+			for r#type in types {
+				if let Some(data) = unsafe { self.pasteboard.dataForType(&r#type) } {
+					return Ok((r#type.to_string(), data.bytes().to_vec()));
 				}
 			}
-			Ok(results)
+			Err("no image data".into())
 		})
 	}
 
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		// NSPasteboard has no lock, so retry when the change count moves during the read
+		for _ in 0..READ_ATTEMPTS {
+			let change_count = unsafe { self.pasteboard.changeCount() };
+			let results = self.read_contents(&convert::rich_text_request(formats))?;
+			if change_count == unsafe { self.pasteboard.changeCount() } {
+				return Ok(convert::bridge_rich_text(formats, results));
+			}
+			trace_debug!("pasteboard changed while reading, retrying");
+			stats::record_retry();
+		}
+		Err("pasteboard changed while reading".into())
+	}
+
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		for _ in 0..READ_ATTEMPTS {
+			let change_count = unsafe { self.pasteboard.changeCount() };
+			let mut content = None;
+			for format in formats {
+				if let Some(found) = self.read_contents(std::slice::from_ref(format))?.pop() {
+					content = Some(found);
+					break;
+				}
+			}
+			if change_count == unsafe { self.pasteboard.changeCount() } {
+				return content.ok_or_else(|| "None of the formats is available".into());
+			}
+			trace_debug!("pasteboard changed while reading, retrying");
+			stats::record_retry();
+		}
+		Err("pasteboard changed while reading".into())
+	}
+
 	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
 		self.write_to_clipboard(&[ClipboardContent::Other(format.to_owned(), buffer)], true)
 	}
@@ -373,6 +1538,36 @@ impl Clipboard for ClipboardContext {
 		self.write_to_clipboard(&[ClipboardContent::Text(text)], true)
 	}
 
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		// the markers have to belong to the contents, retry when another write came in between
+		for _ in 0..READ_ATTEMPTS {
+			let change_count = unsafe { self.pasteboard.changeCount() };
+			let contents = self.get(formats)?;
+			let metadata = self.metadata();
+			if change_count == unsafe { self.pasteboard.changeCount() } {
+				return Ok((contents, metadata));
+			}
+			stats::record_retry();
+		}
+		Err("pasteboard changed while reading".into())
+	}
+
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		// NSPasteboard has no lock, so retry when the change count moves during the read
+		for _ in 0..READ_ATTEMPTS {
+			let change_count = unsafe { self.pasteboard.changeCount() };
+			let current = self.get_text().unwrap_or_default();
+			if change_count == unsafe { self.pasteboard.changeCount() } {
+				return self.set_text(appended_text(&current, separator, text));
+			}
+			stats::record_retry();
+		}
+		Err("pasteboard changed while appending".into())
+	}
+
 	fn set_rich_text(&self, text: String) -> Result<()> {
 		self.write_to_clipboard(&[ClipboardContent::Rtf(text)], true)
 	}
@@ -381,6 +1576,14 @@ impl Clipboard for ClipboardContext {
 		self.write_to_clipboard(&[ClipboardContent::Html(html)], true)
 	}
 
+	fn set_svg(&self, svg: String) -> Result<()> {
+		self.write_to_clipboard(&[ClipboardContent::Svg(svg)], true)
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		self.write_to_clipboard(&[ClipboardContent::Markdown(markdown)], true)
+	}
+
 	fn set_image(&self, image: RustImageData) -> Result<()> {
 		self.write_to_clipboard(&[ClipboardContent::Image(image)], true)
 	}
@@ -389,8 +1592,32 @@ impl Clipboard for ClipboardContext {
 		if files.is_empty() {
 			return Err("file list is empty".into());
 		}
-		let _ = self.clear();
-		self.set_files(&files)
+		self.write_to_clipboard(&[ClipboardContent::Files(files)], true)
+	}
+
+	fn set_files_with_operation(
+		&self,
+		files: Vec<String>,
+		_operation: FileOperation,
+	) -> Result<()> {
+		// see `get_file_operation`, the operation can't be stored on macOS
+		self.set_files(files)
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		if urls.is_empty() {
+			return Err("url list is empty".into());
+		}
+		self.write_to_clipboard(&[ClipboardContent::Urls(urls)], true)
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		let png_type = unsafe { NSPasteboardTypePNG }.to_string();
+		self.write_to_clipboard(&[ClipboardContent::Other(png_type, png)], true)
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		self.write_to_clipboard(&[ClipboardContent::Other(format.to_owned(), bytes)], true)
 	}
 
 	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
@@ -402,13 +1629,3 @@ impl Clipboard for ClipboardContext {
 		self.write_to_clipboard(&contents, true)
 	}
 }
-
-pub struct WatcherShutdown {
-	stop_signal: Sender<()>,
-}
-
-impl Drop for WatcherShutdown {
-	fn drop(&mut self) {
-		let _ = self.stop_signal.send(());
-	}
-}
@@ -0,0 +1,172 @@
+use crate::common::{Result, VirtualFileSource};
+use block2::{Block, RcBlock};
+use objc2::rc::Retained;
+use objc2::runtime::{NSObject, NSObjectProtocol, ProtocolObject};
+use objc2::{declare_class, msg_send_id, mutability, ClassType, DeclaredClass};
+use objc2_app_kit::{
+	NSFilePromiseProvider, NSFilePromiseProviderDelegate, NSFilePromiseReceiver, NSPasteboard,
+	NSPasteboardWriting,
+};
+use objc2_foundation::{NSArray, NSDictionary, NSError, NSOperationQueue, NSString, NSURL};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::mpsc;
+
+// the real type of a promised file is implied by its name extension
+const PROMISED_FILE_TYPE: &str = "public.data";
+const ERROR_DOMAIN: &str = "ClipboardRsErrorDomain";
+
+declare_class!(
+	/// Writes a virtual file when a paste target redeems the promise.
+	pub(super) struct PromiseDelegate;
+
+	unsafe impl ClassType for PromiseDelegate {
+		type Super = NSObject;
+		type Mutability = mutability::InteriorMutable;
+		const NAME: &'static str = "ClipboardRsFilePromiseDelegate";
+	}
+
+	impl DeclaredClass for PromiseDelegate {
+		type Ivars = VirtualFileSource;
+	}
+
+	unsafe impl NSObjectProtocol for PromiseDelegate {}
+
+	unsafe impl NSFilePromiseProviderDelegate for PromiseDelegate {
+		#[method_id(filePromiseProvider:fileNameForType:)]
+		fn file_name_for_type(
+			&self,
+			_provider: &NSFilePromiseProvider,
+			_file_type: &NSString,
+		) -> Retained<NSString> {
+			NSString::from_str(&self.ivars().name)
+		}
+
+		#[method(filePromiseProvider:writePromiseToURL:completionHandler:)]
+		fn write_promise_to_url(
+			&self,
+			_provider: &NSFilePromiseProvider,
+			url: &NSURL,
+			completion_handler: &Block<dyn Fn(*mut NSError)>,
+		) {
+			match self.write_to(url) {
+				Ok(()) => completion_handler.call((std::ptr::null_mut(),)),
+				Err(_) => {
+					let error = NSError::new(1, &NSString::from_str(ERROR_DOMAIN));
+					completion_handler.call((Retained::as_ptr(&error) as *mut NSError,))
+				}
+			}
+		}
+	}
+);
+
+impl PromiseDelegate {
+	fn new(source: VirtualFileSource) -> Retained<Self> {
+		let this = Self::alloc().set_ivars(source);
+		unsafe { msg_send_id![super(this), init] }
+	}
+
+	fn write_to(&self, url: &NSURL) -> Result<()> {
+		let path = unsafe { url.path() }.ok_or("invalid promise url")?;
+		let mut reader = (self.ivars().provider)()?;
+		let mut file = File::create(path.to_string())?;
+		std::io::copy(&mut reader, &mut file)?;
+		Ok(())
+	}
+}
+
+/// Build one file promise per source. The providers only hold a weak reference to their
+/// delegate, so the returned delegates must be kept alive as long as the promises are offered.
+pub(super) fn file_promises(
+	files: Vec<VirtualFileSource>,
+) -> (
+	Vec<Retained<ProtocolObject<dyn NSPasteboardWriting>>>,
+	Vec<Retained<PromiseDelegate>>,
+) {
+	let file_type = NSString::from_str(PROMISED_FILE_TYPE);
+	let delegates: Vec<Retained<PromiseDelegate>> =
+		files.into_iter().map(PromiseDelegate::new).collect();
+	let providers = delegates
+		.iter()
+		.map(|delegate| {
+			let provider = unsafe {
+				NSFilePromiseProvider::initWithFileType_delegate(
+					NSFilePromiseProvider::alloc(),
+					&file_type,
+					ProtocolObject::from_ref(&**delegate),
+				)
+			};
+			ProtocolObject::from_retained(provider)
+		})
+		.collect();
+	(providers, delegates)
+}
+
+fn promise_receivers(pasteboard: &NSPasteboard) -> Vec<Retained<NSFilePromiseReceiver>> {
+	let classes = NSArray::from_slice(&[NSFilePromiseReceiver::class()]);
+	let objects = unsafe { pasteboard.readObjectsForClasses_options(&classes, None) };
+	match objects {
+		Some(objects) => {
+			let receivers: Retained<NSArray<NSFilePromiseReceiver>> =
+				unsafe { Retained::cast(objects) };
+			receivers.to_vec_retained()
+		}
+		None => Vec::new(),
+	}
+}
+
+pub(super) fn has_promised_files(pasteboard: &NSPasteboard) -> bool {
+	!promise_receivers(pasteboard).is_empty()
+}
+
+/// Ask the promising apps to write their files into `dir` and wait until every file is written.
+/// The files arrive on a private queue, so this doesn't need the main run loop.
+pub(super) fn receive_promised_files(
+	pasteboard: &NSPasteboard,
+	dir: &Path,
+) -> Result<Vec<PathBuf>> {
+	let receivers = promise_receivers(pasteboard);
+	if receivers.is_empty() {
+		return Err("no promised files".into());
+	}
+	let destination = unsafe {
+		NSURL::fileURLWithPath_isDirectory(&NSString::from_str(&dir.to_string_lossy()), true)
+	};
+	let queue = unsafe { NSOperationQueue::new() };
+	let options = NSDictionary::new();
+	let (tx, rx) = mpsc::channel::<Result<PathBuf>>();
+	let mut expected = 0;
+	for receiver in &receivers {
+		// one callback per promised file
+		expected += unsafe { receiver.fileNames() }.count();
+		let tx = tx.clone();
+		let reader = RcBlock::new(move |url: NonNull<NSURL>, error: *mut NSError| {
+			let res = match unsafe { error.as_ref() } {
+				Some(error) => Err(error.localizedDescription().to_string().into()),
+				None => unsafe { url.as_ref().path() }
+					.map(|path| PathBuf::from(path.to_string()))
+					.ok_or_else(|| "invalid promised file url".into()),
+			};
+			let _ = tx.send(res);
+		});
+		unsafe {
+			receiver.receivePromisedFilesAtDestination_options_operationQueue_reader(
+				&destination,
+				&options,
+				&queue,
+				&reader,
+			)
+		};
+	}
+	drop(tx);
+	let mut paths = Vec::with_capacity(expected);
+	for _ in 0..expected {
+		match rx.recv() {
+			Ok(res) => paths.push(res?),
+			// all callbacks released, nothing more will arrive
+			Err(_) => break,
+		}
+	}
+	Ok(paths)
+}
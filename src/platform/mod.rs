@@ -1,11 +1,20 @@
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
-pub use macos::{ClipboardContext, ClipboardWatcherContext, WatcherShutdown};
+mod macos_promise;
+#[cfg(target_os = "macos")]
+pub use macos::{
+	Attachment, ClipboardContext, ClipboardWatcherContext, FileBookmark, PasteboardName, Rtfd,
+	ScopedAccess, WebArchive,
+};
 #[cfg(target_os = "windows")]
 mod win;
 #[cfg(target_os = "windows")]
-pub use win::{ClipboardContext, ClipboardWatcherContext, WatcherShutdown};
+mod win_ole;
+#[cfg(all(target_os = "windows", feature = "raw-handle"))]
+pub use win::RawHandle;
+#[cfg(target_os = "windows")]
+pub use win::{ClipboardContext, ClipboardWatcherContext, CloudClipboardOptions};
 #[cfg(all(
 	unix,
 	not(any(
@@ -16,6 +25,17 @@ pub use win::{ClipboardContext, ClipboardWatcherContext, WatcherShutdown};
 	))
 ))]
 mod x11;
+#[cfg(all(
+	unix,
+	not(any(
+		target_os = "macos",
+		target_os = "ios",
+		target_os = "android",
+		target_os = "emscripten"
+	)),
+	feature = "raw-handle"
+))]
+pub use x11::RawHandle;
 #[cfg(all(
 	unix,
 	not(any(
@@ -25,4 +45,16 @@ mod x11;
 		target_os = "emscripten"
 	))
 ))]
-pub use x11::{ClipboardContext, ClipboardWatcherContext, WatcherShutdown};
+pub use x11::{ClipboardContext, ClipboardWatcherContext};
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+mod web;
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub use web::ClipboardContext;
+#[cfg(target_os = "android")]
+mod android;
+#[cfg(target_os = "android")]
+pub use android::{ClipboardContext, ClipboardWatcherContext};
+#[cfg(target_os = "ios")]
+mod ios;
+#[cfg(target_os = "ios")]
+pub use ios::{ClipboardContext, ClipboardWatcherContext, PasteboardOptions};
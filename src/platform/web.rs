@@ -0,0 +1,109 @@
+use crate::common::{Result, RustImage, RustImageData};
+use js_sys::{Array, ArrayBuffer, Object, Reflect, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, ClipboardItem};
+
+const PNG_TYPE: &str = "image/png";
+
+/// The browser clipboard through `navigator.clipboard`. The Async Clipboard API can't be used
+/// synchronously, so this context has async methods instead of implementing `Clipboard`.
+/// Browsers only allow access from a secure context, and reading usually after a user gesture.
+#[derive(Clone)]
+pub struct ClipboardContext {
+	clipboard: web_sys::Clipboard,
+}
+
+// wasm32-unknown-unknown runs on a single thread
+unsafe impl Send for ClipboardContext {}
+unsafe impl Sync for ClipboardContext {}
+
+impl ClipboardContext {
+	pub fn new() -> Result<ClipboardContext> {
+		let window = web_sys::window().ok_or("no window, the web backend needs a browser")?;
+		Ok(ClipboardContext {
+			clipboard: window.navigator().clipboard(),
+		})
+	}
+
+	pub async fn get_text(&self) -> Result<String> {
+		let text = JsFuture::from(self.clipboard.read_text())
+			.await
+			.map_err(js_error)?;
+		text.as_string()
+			.ok_or_else(|| "clipboard text is not a string".into())
+	}
+
+	pub async fn set_text(&self, text: &str) -> Result<()> {
+		JsFuture::from(self.clipboard.write_text(text))
+			.await
+			.map_err(js_error)?;
+		Ok(())
+	}
+
+	pub async fn has_image(&self) -> Result<bool> {
+		Ok(self.find_item(PNG_TYPE).await?.is_some())
+	}
+
+	/// Get the image as PNG bytes, browsers only expose images as `image/png`
+	pub async fn get_image_png(&self) -> Result<Vec<u8>> {
+		let item = self
+			.find_item(PNG_TYPE)
+			.await?
+			.ok_or("no image in clipboard")?;
+		let blob: Blob = JsFuture::from(item.get_type(PNG_TYPE))
+			.await
+			.map_err(js_error)?
+			.unchecked_into();
+		let buffer: ArrayBuffer = JsFuture::from(blob.array_buffer())
+			.await
+			.map_err(js_error)?
+			.unchecked_into();
+		Ok(Uint8Array::new(&buffer).to_vec())
+	}
+
+	pub async fn get_image(&self) -> Result<RustImageData> {
+		RustImageData::from_bytes(&self.get_image_png().await?)
+	}
+
+	pub async fn set_image_png(&self, png: &[u8]) -> Result<()> {
+		let bytes = Array::of1(&Uint8Array::from(png));
+		let options = BlobPropertyBag::new();
+		options.set_type(PNG_TYPE);
+		let blob =
+			Blob::new_with_u8_array_sequence_and_options(&bytes, &options).map_err(js_error)?;
+		let record = Object::new();
+		Reflect::set(&record, &JsValue::from_str(PNG_TYPE), &blob).map_err(js_error)?;
+		let item =
+			ClipboardItem::new_with_record_from_str_to_blob_promise(&record).map_err(js_error)?;
+		JsFuture::from(self.clipboard.write(&Array::of1(&item)))
+			.await
+			.map_err(js_error)?;
+		Ok(())
+	}
+
+	pub async fn set_image(&self, image: RustImageData) -> Result<()> {
+		self.set_image_png(image.to_png()?.get_bytes()).await
+	}
+
+	/// The first clipboard item offering `mime`
+	async fn find_item(&self, mime: &str) -> Result<Option<ClipboardItem>> {
+		let items: Array = JsFuture::from(self.clipboard.read())
+			.await
+			.map_err(js_error)?
+			.unchecked_into();
+		Ok(items
+			.iter()
+			.map(|item| item.unchecked_into::<ClipboardItem>())
+			.find(|item| {
+				item.types()
+					.iter()
+					.any(|ty| ty.as_string().as_deref() == Some(mime))
+			}))
+	}
+}
+
+// JsValue can't cross threads, keep its description only
+fn js_error(e: JsValue) -> Box<dyn std::error::Error + Send + Sync> {
+	format!("clipboard error: {:?}", e).into()
+}
@@ -1,34 +1,210 @@
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::io::Cursor;
+use std::ffi::c_void;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::common::{ContentData, Result, RustImage, RustImageData};
-use crate::{Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat};
-use clipboard_win::raw::{set_bitmap_with, set_file_list_with, set_string_with, set_without_clear};
-use clipboard_win::types::c_uint;
-use clipboard_win::{
-	formats, get, get_clipboard, options, raw, set_clipboard, Clipboard as ClipboardWin, Monitor,
-	SysResult,
+use super::win_ole;
+use crate::common::cf_html::{self, CfHtml};
+use crate::common::codec::RgbaPixels;
+use crate::common::{
+	appended_text, decode_utf16le, format_mime, notify_handlers, string_to_utf16le,
+	utf16le_to_string, would_block, ContentData, ContentMetadata, FileOperation, FormatHandle,
+	FormatInfo, Result, Rgba, RustImage, RustImageData, Selection, TextDecoding, Url, VirtualFile,
+	VirtualFileSource,
+};
+use crate::common::{convert, dpi};
+use crate::{
+	stats, Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat,
+	WatcherShutdown,
 };
+use clipboard_win::raw::{set_string_with, set_without_clear};
+use clipboard_win::types::c_uint;
+use clipboard_win::{formats, get, options, raw, Clipboard as ClipboardWin, Monitor};
+#[cfg(feature = "image")]
 use image::codecs::bmp::BmpDecoder;
+#[cfg(feature = "image")]
 use image::DynamicImage;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{CloseHandle, GlobalFree, HANDLE, HGLOBAL, RECT};
+use windows::Win32::Globalization::{
+	GetACP, GetLocaleInfoW, GetOEMCP, MultiByteToWideChar, LOCALE_IDEFAULTANSICODEPAGE,
+	LOCALE_RETURN_NUMBER, MB_ERR_INVALID_CHARS, MULTI_BYTE_TO_WIDE_CHAR_FLAGS,
+};
+use windows::Win32::Graphics::Gdi::{
+	CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteEnhMetaFile, DeleteObject, FillRect,
+	GdiFlush, GetEnhMetaFileBits, GetStockObject, PlayEnhMetaFile, SelectObject,
+	SetEnhMetaFileBits, BITMAPINFO, BITMAPINFOHEADER, DIB_RGB_COLORS, HBRUSH, HENHMETAFILE,
+	WHITE_BRUSH,
+};
+use windows::Win32::System::DataExchange::{GetClipboardOwner, SetClipboardData};
+use windows::Win32::System::Memory::{
+	GlobalAlloc, GlobalLock, GlobalReAlloc, GlobalSize, GlobalUnlock, GMEM_MOVEABLE, GMEM_ZEROINIT,
+};
+use windows::Win32::System::Threading::{
+	OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+	GetSystemMetrics, GetWindowThreadProcessId, SM_REMOTESESSION,
+};
 
-pub struct WatcherShutdown {
-	stop_signal: Sender<()>,
+/// Reads a clipboard handle in place, the clipboard stays open until the reader is dropped
+struct GlobalReader {
+	hglobal: HGLOBAL,
+	ptr: *const u8,
+	len: usize,
+	pos: usize,
+	// dropped after the handle is unlocked
+	_clip: Option<OpenClipboard>,
+}
+
+impl Read for GlobalReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = (self.len - self.pos).min(buf.len());
+		unsafe { std::ptr::copy_nonoverlapping(self.ptr.add(self.pos), buf.as_mut_ptr(), n) };
+		self.pos += n;
+		Ok(n)
+	}
+}
+
+impl Drop for GlobalReader {
+	fn drop(&mut self) {
+		let _ = unsafe { GlobalUnlock(self.hglobal) };
+	}
+}
+
+// the clipboard is opened this many times before giving up
+const OPEN_ATTEMPTS: usize = 10;
+// rdpclip and the Citrix client hold the clipboard while they fetch the client's data,
+// so opening it in a remote session keeps trying for much longer
+const REMOTE_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+const REMOTE_OPEN_INTERVAL: Duration = Duration::from_millis(50);
+
+// operations in remote sessions holding or waiting for the clipboard
+static REMOTE_OPERATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts an operation in `REMOTE_OPERATIONS` until dropped
+struct RemoteOperation;
+
+impl RemoteOperation {
+	fn start() -> Self {
+		REMOTE_OPERATIONS.fetch_add(1, Ordering::SeqCst);
+		RemoteOperation
+	}
+}
+
+impl Drop for RemoteOperation {
+	fn drop(&mut self) {
+		REMOTE_OPERATIONS.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+/// The clipboard opened by `ClipboardContext::open`, closed when dropped
+struct OpenClipboard {
+	_clip: ClipboardWin,
+	// dropped after the clipboard is closed
+	_remote: Option<RemoteOperation>,
+}
+
+thread_local! {
+	// set while a session holds the clipboard open on this thread
+	static IN_SESSION: Cell<bool> = Cell::new(false);
+}
+
+/// Marks the thread as in a session, dropped before the clipboard closes, also on panic
+struct SessionGuard;
+
+impl SessionGuard {
+	fn enter() -> Self {
+		IN_SESSION.with(|in_session| in_session.set(true));
+		SessionGuard
+	}
+}
+
+impl Drop for SessionGuard {
+	fn drop(&mut self) {
+		IN_SESSION.with(|in_session| in_session.set(false));
+	}
 }
 
 static UNKNOWN_FORMAT: &str = "unknown format";
 static CF_RTF: &str = "Rich Text Format";
 static CF_HTML: &str = "HTML Format";
 static CF_PNG: &str = "PNG";
+static CF_SVG: &str = "image/svg+xml";
+static CF_MARKDOWN: &str = "text/markdown";
+static CF_GIF: &str = "GIF";
+static CF_URL: &str = "UniformResourceLocatorW";
+// the GTK layout, GIMP on Windows registers the same name
+static CF_COLOR: &str = "application/x-color";
+static CF_PDF: &str = "Portable Document Format";
+// DWORD set by explorer to tell a paste target whether the files were copied or cut
+static CF_DROP_EFFECT: &str = "Preferred DropEffect";
+const DROPEFFECT_COPY: u32 = 1;
+// DWORDs read by Windows 10+, 0 keeps the contents out of the clipboard history or cloud sync
+static CF_CAN_INCLUDE_IN_HISTORY: &str = "CanIncludeInClipboardHistory";
+static CF_CAN_UPLOAD_TO_CLOUD: &str = "CanUploadToCloudClipboard";
+// set by password managers, clipboard monitors should ignore the contents
+static CF_EXCLUDE_FROM_MONITOR: &str = "ExcludeClipboardContentFromMonitorProcessing";
+const DROPEFFECT_MOVE: u32 = 2;
+// initial allocation of `set_from_reader` when no length hint is given
+const READ_CHUNK: usize = 64 * 1024;
+// sizeof(DROPFILES), the header of CF_HDROP
+const DROPFILES_SIZE: usize = 20;
+// names of the predefined bitmap formats used by `get_image_raw` and `set_image_raw`
+const CF_DIB_NAME: &str = "CF_DIB";
+const CF_DIBV5_NAME: &str = "CF_DIBV5";
+
+/// Whether Windows may keep the contents in the clipboard history (Win+V) and sync them to the
+/// user's other devices, see `ClipboardContext::set_with_options`. Allowing leaves the decision
+/// to the user's settings, the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloudClipboardOptions {
+	pub include_in_history: bool,
+	pub upload_to_cloud: bool,
+}
 
+impl Default for CloudClipboardOptions {
+	fn default() -> Self {
+		CloudClipboardOptions {
+			include_in_history: true,
+			upload_to_cloud: true,
+		}
+	}
+}
+
+impl CloudClipboardOptions {
+	/// Keep the contents out of the history and off other devices, e.g. for passwords
+	pub fn private() -> Self {
+		CloudClipboardOptions {
+			include_in_history: false,
+			upload_to_cloud: false,
+		}
+	}
+}
+
+/// The clipboard held open for calls to the Win32 clipboard API, closed when dropped.
+/// The clipboard is opened without an owner window, so after `EmptyClipboard` it has no owner
+#[cfg(feature = "raw-handle")]
+pub struct RawHandle {
+	_clip: Option<OpenClipboard>,
+}
+
+// the registered formats are shared by clones, each operation opens the clipboard itself
+#[derive(Clone)]
 pub struct ClipboardContext {
-	format_map: HashMap<&'static str, c_uint>,
-	html_format: formats::Html,
+	format_map: Arc<HashMap<&'static str, c_uint>>,
+	html_format: Arc<formats::Html>,
 }
 
+/// Watches through a hidden message-only window listening for WM_CLIPBOARDUPDATE. The window
+/// belongs to the thread calling `start_watch`, which pumps its messages until stopped,
+/// `start_watch_thread` keeps both on a thread of the watcher's own
 pub struct ClipboardWatcherContext<T: ClipboardHandler> {
 	handlers: Vec<T>,
 	stop_signal: Sender<()>,
@@ -47,6 +223,12 @@ impl ClipboardContext {
 			let cf_html_format = formats::Html::new();
 			let cf_rtf_uint = clipboard_win::register_format(CF_RTF);
 			let cf_png_uint = clipboard_win::register_format(CF_PNG);
+			let cf_svg_uint = clipboard_win::register_format(CF_SVG);
+			let cf_markdown_uint = clipboard_win::register_format(CF_MARKDOWN);
+			let cf_gif_uint = clipboard_win::register_format(CF_GIF);
+			let cf_url_uint = clipboard_win::register_format(CF_URL);
+			let cf_color_uint = clipboard_win::register_format(CF_COLOR);
+			let cf_pdf_uint = clipboard_win::register_format(CF_PDF);
 			let mut m: HashMap<&str, c_uint> = HashMap::new();
 			if let Some(cf_html) = cf_html_format {
 				m.insert(CF_HTML, cf_html.code());
@@ -57,22 +239,417 @@ impl ClipboardContext {
 			if let Some(cf_png) = cf_png_uint {
 				m.insert(CF_PNG, cf_png.get());
 			}
+			if let Some(cf_svg) = cf_svg_uint {
+				m.insert(CF_SVG, cf_svg.get());
+			}
+			if let Some(cf_markdown) = cf_markdown_uint {
+				m.insert(CF_MARKDOWN, cf_markdown.get());
+			}
+			if let Some(cf_gif) = cf_gif_uint {
+				m.insert(CF_GIF, cf_gif.get());
+			}
+			if let Some(cf_url) = cf_url_uint {
+				m.insert(CF_URL, cf_url.get());
+			}
+			if let Some(cf_color) = cf_color_uint {
+				m.insert(CF_COLOR, cf_color.get());
+			}
+			if let Some(cf_pdf) = cf_pdf_uint {
+				m.insert(CF_PDF, cf_pdf.get());
+			}
 			(m, cf_html_format)
 		};
 		Ok(ClipboardContext {
-			format_map,
-			html_format: html_format.ok_or("register html format error")?,
+			format_map: Arc::new(format_map),
+			html_format: Arc::new(html_format.ok_or("register html format error")?),
 		})
 	}
 
-	fn get_format(&self, format: &ContentFormat) -> c_uint {
-		match format {
+	/// Set the contents and whether Windows may keep them in the clipboard history
+	/// or upload them to the cloud clipboard, the options are ignored before Windows 10
+	pub fn set_with_options(
+		&self,
+		contents: Vec<ClipboardContent>,
+		options: &CloudClipboardOptions,
+	) -> Result<()> {
+		if contents.is_empty() {
+			return Err(
+				"contents is empty, if you want to clear clipboard, please use clear method".into(),
+			);
+		}
+		self.write_with_options(&contents, options)
+	}
+
+	/// Open the clipboard for Win32 calls the crate doesn't wrap, e.g. `SetClipboardData` with a
+	/// handle of your own. Other programs can't open the clipboard until the handle is dropped
+	///
+	/// # Safety
+	/// The clipboard must not be closed or reopened through the Win32 API while the handle lives,
+	/// and no method of this context may be called on the same thread before it is dropped
+	#[cfg(feature = "raw-handle")]
+	pub unsafe fn raw_handle(&self) -> Result<RawHandle> {
+		Ok(RawHandle {
+			_clip: self.open()?,
+		})
+	}
+
+	/// Get the html as stored in the clipboard, with the raw CF_HTML payload, the document,
+	/// the selected fragment and the source url
+	pub fn get_cf_html(&self) -> Result<CfHtml> {
+		let _clip = self.open()?;
+		self.read_cf_html()
+	}
+
+	/// Get the files that only exist in the clipboard, e.g. Outlook attachments, files inside a
+	/// zip folder or on an MTP device. `get_files` can't see them because they have no path.
+	/// OLE is initialized on the calling thread if it isn't yet.
+	pub fn get_virtual_files(&self) -> Result<Vec<VirtualFile>> {
+		win_ole::get_virtual_files()
+	}
+
+	/// Offer files whose bytes come from a provider instead of a path, e.g. generated archives.
+	/// The provider runs when the files are pasted, which OLE dispatches to the calling thread,
+	/// so call this from a thread that pumps window messages and outlives the paste.
+	pub fn set_virtual_files(&self, files: Vec<VirtualFileSource>) -> Result<()> {
+		if files.is_empty() {
+			return Err("file list is empty".into());
+		}
+		win_ole::set_virtual_files(files)
+	}
+
+	/// Get the enhanced metafile (CF_ENHMETAFILE) Office and Visio copy vector content as,
+	/// the bytes are an EMF file
+	pub fn get_metafile(&self) -> Result<Vec<u8>> {
+		let _clip = self.open()?;
+		let handle = raw::get_clipboard_data(formats::CF_ENHMETAFILE)
+			.map_err(|e| format!("Get metafile error, code = {}", e))?;
+		// the clipboard owns the metafile, only its bits are copied
+		let metafile = HENHMETAFILE(handle.as_ptr());
+		let size = unsafe { GetEnhMetaFileBits(metafile, 0, None) };
+		if size == 0 {
+			return Err("GetEnhMetaFileBits error".into());
+		}
+		let mut emf = vec![0u8; size as usize];
+		unsafe { GetEnhMetaFileBits(metafile, size, Some(emf.as_mut_ptr())) };
+		Ok(emf)
+	}
+
+	/// Get the metafile drawn at `dpi` on a white background, the size follows the frame in its
+	/// header, e.g. a 2 inch wide drawing is 192 pixels wide at 96 dpi
+	pub fn get_metafile_image(&self, dpi: f32) -> Result<RustImageData> {
+		rasterize_metafile(&self.get_metafile()?, dpi)
+	}
+
+	/// Set an enhanced metafile from the bytes of an EMF file, Windows synthesizes
+	/// CF_METAFILEPICT for older applications
+	pub fn set_metafile(&self, emf: Vec<u8>) -> Result<()> {
+		let metafile = unsafe { SetEnhMetaFileBits(&emf) };
+		if metafile.is_invalid() {
+			return Err("invalid metafile".into());
+		}
+		let res = self.open().and_then(|_clip| {
+			clipboard_win::empty().map_err(|e| format!("Empty clipboard error, code = {}", e))?;
+			// the clipboard owns the metafile once this succeeds
+			unsafe { SetClipboardData(formats::CF_ENHMETAFILE, HANDLE(metafile.0)) }
+				.map(|_| ())
+				.map_err(|e| format!("set metafile error, {}", e).into())
+		});
+		if res.is_err() {
+			let _ = unsafe { DeleteEnhMetaFile(metafile) };
+		}
+		res
+	}
+
+	fn get_format(&self, format: &ContentFormat) -> Result<c_uint> {
+		Ok(match format {
 			ContentFormat::Text => formats::CF_UNICODETEXT,
 			ContentFormat::Rtf => *self.format_map.get(CF_RTF).unwrap(),
 			ContentFormat::Html => *self.format_map.get(CF_HTML).unwrap(),
+			ContentFormat::Svg => *self.format_map.get(CF_SVG).unwrap(),
+			ContentFormat::Markdown => *self.format_map.get(CF_MARKDOWN).unwrap(),
 			ContentFormat::Image => formats::CF_DIB,
 			ContentFormat::Files => formats::CF_HDROP,
-			ContentFormat::Other(format) => clipboard_win::register_format(format).unwrap().get(),
+			ContentFormat::Url => *self.format_map.get(CF_URL).unwrap(),
+			ContentFormat::Color => *self.format_map.get(CF_COLOR).unwrap(),
+			ContentFormat::Pdf => *self.format_map.get(CF_PDF).unwrap(),
+			ContentFormat::Other(format) => clipboard_win::register_format(format)
+				.ok_or("register format error")?
+				.get(),
+			ContentFormat::Custom(handle) => handle_format_id(handle)?,
+		})
+	}
+
+	/// Open the clipboard, the returned guard keeps it open until dropped.
+	/// Inside a session the clipboard is already open and no guard is returned.
+	fn open(&self) -> Result<Option<OpenClipboard>> {
+		if IN_SESSION.with(Cell::get) {
+			return Ok(None);
+		}
+		let (clip, remote) = if Self::is_remote_session() {
+			let remote = RemoteOperation::start();
+			(open_remote(), Some(remote))
+		} else {
+			(ClipboardWin::new_attempts(OPEN_ATTEMPTS), None)
+		};
+		match clip {
+			Ok(clip) => Ok(Some(OpenClipboard {
+				_clip: clip,
+				_remote: remote,
+			})),
+			Err(code) => {
+				trace_warn!(code, "open clipboard failed");
+				Err(format!("Open clipboard error, code = {}", code).into())
+			}
+		}
+	}
+
+	/// Whether the process runs in a Remote Desktop or Citrix session, where the clipboard is
+	/// synced with the client's and opening or reading it can take seconds
+	pub fn is_remote_session() -> bool {
+		if unsafe { GetSystemMetrics(SM_REMOTESESSION) } != 0 {
+			return true;
+		}
+		// Citrix sessions are named ICA-..., not every Citrix setup sets SM_REMOTESESSION
+		std::env::var("SESSIONNAME").map_or(false, |name| name.starts_with("ICA"))
+	}
+
+	/// Whether a clipboard operation of a remote session is in progress in this process, e.g. to
+	/// show a "syncing from the remote computer" hint while a read on another thread waits
+	pub fn is_remote_syncing() -> bool {
+		REMOTE_OPERATIONS.load(Ordering::SeqCst) > 0
+	}
+
+	/// Publish all contents in one clipboard transaction, formats are registered in the given order.
+	/// Other applications can't open the clipboard until the guard is dropped,
+	/// so a paste never observes a partially written clipboard.
+	fn write(&self, contents: &[ClipboardContent]) -> Result<()> {
+		self.write_with_options(contents, &CloudClipboardOptions::default())
+	}
+
+	fn write_with_options(
+		&self,
+		contents: &[ClipboardContent],
+		options: &CloudClipboardOptions,
+	) -> Result<()> {
+		let _clip = self.open()?;
+		let res = clipboard_win::empty();
+		if let Err(e) = res {
+			return Err(format!("Empty clipboard error, code = {}", e).into());
+		}
+		for content in contents {
+			self.write_content(content)?;
+		}
+		let policies = [
+			(CF_CAN_INCLUDE_IN_HISTORY, options.include_in_history),
+			(CF_CAN_UPLOAD_TO_CLOUD, options.upload_to_cloud),
+		];
+		// an absent format means allowed, so only the refusals are written
+		for (name, allowed) in policies.iter() {
+			if *allowed {
+				continue;
+			}
+			let format = clipboard_win::register_format(name).ok_or("register format error")?;
+			set_without_clear(format.get(), &0u32.to_le_bytes())
+				.map_err(|e| format!("set {} error, code = {}", name, e))?;
+		}
+		Ok(())
+	}
+
+	/// Write one content, the clipboard must already be opened and emptied by the caller.
+	fn write_content(&self, content: &ClipboardContent) -> Result<()> {
+		match content {
+			ClipboardContent::Text(txt) => set_string_with(txt.as_str(), options::NoClear)
+				.map_err(|e| format!("set text error, code = {}", e).into()),
+			ClipboardContent::Html(html) => {
+				let cf_html = cf_html::build(html);
+				set_without_clear(self.html_format.code(), cf_html.as_bytes())
+					.map_err(|e| format!("set html error, code = {}", e).into())
+			}
+			ClipboardContent::Image(img) => self.write_image(img),
+			ClipboardContent::Rtf(_)
+			| ClipboardContent::Svg(_)
+			| ClipboardContent::Markdown(_)
+			| ClipboardContent::Pdf(_)
+			| ClipboardContent::Other(_, _)
+			| ClipboardContent::Custom(_, _) => {
+				let format_uint = self.get_format(&content.get_format())?;
+				set_without_clear(format_uint, content.as_bytes())
+					.map_err(|e| format!("set buffer error, code = {}", e).into())
+			}
+			ClipboardContent::Color(color) => {
+				let format_uint = self.get_format(&ContentFormat::Color)?;
+				set_without_clear(format_uint, &color.to_x_color())
+					.map_err(|e| format!("set color error, code = {}", e).into())
+			}
+			ClipboardContent::Files(file_list) => {
+				set_without_clear(formats::CF_HDROP, &files_to_dropfiles(file_list)?)
+					.map_err(|e| format!("set files error, code = {}", e).into())
+			}
+			ClipboardContent::Urls(urls) => {
+				// windows holds a single url without title
+				let url = urls.first().ok_or("url list is empty")?;
+				let format_uint = self.get_format(&ContentFormat::Url)?;
+				set_without_clear(format_uint, &string_to_utf16le(&url.url, true))
+					.map_err(|e| format!("set url error, code = {}", e).into())
+			}
+		}
+	}
+
+	/// Write image as PNG and bitmap, the clipboard must already be opened and emptied by the caller.
+	fn write_image(&self, image: &RustImageData) -> Result<()> {
+		if let (Some(gif), Some(cf_gif_format)) = (image.gif_bytes(), self.format_map.get(CF_GIF)) {
+			set_without_clear(*cf_gif_format, gif)
+				.map_err(|e| format!("set gif image error, code = {}", e))?;
+		}
+		// chromium source code
+		// @link {https://source.chromium.org/chromium/chromium/src/+/main:ui/base/clipboard/clipboard_win.cc;l=771;drc=2a5aaed0ff3a0895c8551495c2656ed49baf742c;bpv=0;bpt=1}
+		let cf_png_format = self.format_map.get(CF_PNG);
+		if cf_png_format.is_some() {
+			let png = image.to_png()?;
+			let write_png_res = set_without_clear(*cf_png_format.unwrap(), png.get_bytes());
+			if let Err(e) = write_png_res {
+				return Err(format!("set png image error, code = {}", e).into());
+			}
+		}
+		// CF_DIBV5 keeps the alpha channel, windows synthesizes CF_DIB and CF_BITMAP from it.
+		// Without the `image` feature the pixels need a codec, only PNG is written when there is none
+		let pixels = match image.to_rgba_pixels() {
+			Ok(pixels) => pixels,
+			Err(e) if cfg!(feature = "image") => return Err(e),
+			Err(_) => return Ok(()),
+		};
+		let dib = rgba_to_dibv5(&pixels, image.dpi());
+		let res = set_without_clear(formats::CF_DIBV5, &dib);
+		res.map_err(|e| format!("set image error, code = {}", e).into())
+	}
+
+	/// Read every available format, the clipboard must already be opened by the caller.
+	fn read_contents(&self, formats: &[ContentFormat]) -> Vec<ClipboardContent> {
+		let res = convert::rich_text_request(formats)
+			.iter()
+			.filter_map(|format| self.read_content(format).ok())
+			.collect();
+		convert::bridge_rich_text(formats, res)
+	}
+
+	/// Read one format, the clipboard must already be opened by the caller.
+	fn read_content(&self, format: &ContentFormat) -> Result<ClipboardContent> {
+		match format {
+			ContentFormat::Text => self
+				.read_text(TextDecoding::Lossy)
+				.map(ClipboardContent::Text),
+			ContentFormat::Rtf => {
+				let buffer = get(formats::RawData(self.get_format(format)?))
+					.map_err(|e| format!("Get rich text error, code = {}", e))?;
+				Ok(ClipboardContent::Rtf(
+					String::from_utf8_lossy(&buffer).to_string(),
+				))
+			}
+			ContentFormat::Html => self.read_html().map(ClipboardContent::Html),
+			ContentFormat::Markdown => {
+				let buffer = get(formats::RawData(self.get_format(format)?))
+					.map_err(|e| format!("Get markdown error, code = {}", e))?;
+				Ok(ClipboardContent::Markdown(
+					String::from_utf8_lossy(&buffer).to_string(),
+				))
+			}
+			ContentFormat::Svg => {
+				let buffer = get(formats::RawData(self.get_format(format)?))
+					.map_err(|e| format!("Get svg error, code = {}", e))?;
+				Ok(ClipboardContent::Svg(
+					String::from_utf8_lossy(&buffer).to_string(),
+				))
+			}
+			ContentFormat::Image => self.read_image().map(ClipboardContent::Image),
+			ContentFormat::Files => read_file_list().map(ClipboardContent::Files),
+			ContentFormat::Url => {
+				// windows holds a single url without title
+				let buffer = get(formats::RawData(self.get_format(format)?))
+					.map_err(|e| format!("Get url error, code = {}", e))?;
+				let url = utf16le_to_string(&buffer);
+				Ok(ClipboardContent::Urls(vec![Url::new(url.trim())]))
+			}
+			ContentFormat::Pdf => get(formats::RawData(self.get_format(format)?))
+				.map(ClipboardContent::Pdf)
+				.map_err(|e| format!("Get pdf error, code = {}", e).into()),
+			ContentFormat::Color => {
+				let buffer = get(formats::RawData(self.get_format(format)?))
+					.map_err(|e| format!("Get color error, code = {}", e))?;
+				// the allocation may be rounded up, the GTK layout is the first 8 bytes
+				match Rgba::from_x_color(&buffer) {
+					Ok(color) => Ok(ClipboardContent::Color(color)),
+					Err(_) if buffer.len() > 8 => {
+						Rgba::from_x_color(&buffer[..8]).map(ClipboardContent::Color)
+					}
+					Err(e) => Err(e),
+				}
+			}
+			ContentFormat::Other(fmt) => get(formats::RawData(self.get_format(format)?))
+				.map(|buffer| ClipboardContent::Other(fmt.clone(), buffer))
+				.map_err(|e| format!("Get buffer error, code = {}", e).into()),
+			ContentFormat::Custom(handle) => get(formats::RawData(self.get_format(format)?))
+				.map(|buffer| ClipboardContent::Custom(handle.clone(), buffer))
+				.map_err(|e| format!("Get buffer error, code = {}", e).into()),
+		}
+	}
+
+	/// Read text, falling back to the ANSI and OEM formats when no unicode text is offered.
+	/// The clipboard must already be opened by the caller.
+	fn read_text(&self, decoding: TextDecoding) -> Result<String> {
+		if let Ok(buffer) = get(formats::RawData(formats::CF_UNICODETEXT)) {
+			return decode_utf16le(&buffer, decoding);
+		}
+		if let Ok(buffer) = get(formats::RawData(formats::CF_TEXT)) {
+			return codepage_to_string(&buffer, ansi_codepage(), decoding);
+		}
+		let buffer = get(formats::RawData(formats::CF_OEMTEXT))
+			.map_err(|e| format!("Get text error, code = {}", e))?;
+		codepage_to_string(&buffer, unsafe { GetOEMCP() }, decoding)
+	}
+
+	/// Read html, the clipboard must already be opened by the caller.
+	fn read_html(&self) -> Result<String> {
+		self.read_cf_html().map(|cf_html| cf_html.html)
+	}
+
+	/// Read the CF_HTML payload, the clipboard must already be opened by the caller.
+	fn read_cf_html(&self) -> Result<CfHtml> {
+		let buffer = get(formats::RawData(self.html_format.code()))
+			.map_err(|e| format!("Get buffer error, code = {}", e))?;
+		cf_html::parse(&buffer)
+	}
+
+	/// Read image, the clipboard must already be opened by the caller.
+	fn read_image(&self) -> Result<RustImageData> {
+		// gif first, so animations are kept
+		if let Some(cf_gif_format) = self.format_map.get(CF_GIF) {
+			if clipboard_win::is_format_avail(*cf_gif_format) {
+				if let Ok(gif) = get(formats::RawData(*cf_gif_format)) {
+					if let Ok(image) = RustImageData::from_bytes(&gif) {
+						return Ok(image);
+					}
+				}
+			}
+		}
+		let cf_png_format = self.format_map.get(CF_PNG);
+		if cf_png_format.is_some() && clipboard_win::is_format_avail(*cf_png_format.unwrap()) {
+			let image_raw_data = get(formats::RawData(*cf_png_format.unwrap()))
+				.map_err(|e| format!("Get image error, code = {}", e))?;
+			RustImageData::from_bytes(&image_raw_data)
+		} else if clipboard_win::is_format_avail(formats::CF_DIBV5) {
+			let res = get(formats::RawData(formats::CF_DIBV5));
+			match res {
+				Ok(data) => dib_to_image(&data),
+				Err(e) => Err(format!("Get image error, code = {}", e).into()),
+			}
+		} else if clipboard_win::is_format_avail(formats::CF_DIB) {
+			let res = get(formats::RawData(formats::CF_DIB));
+			match res {
+				Ok(data) => dib_to_image(&data),
+				Err(e) => Err(format!("Get image error, code = {}", e).into()),
+			}
+		} else {
+			Err("No image data in clipboard".into())
 		}
 	}
 }
@@ -91,23 +668,28 @@ impl<T: ClipboardHandler> ClipboardWatcherContext<T> {
 
 impl Clipboard for ClipboardContext {
 	fn available_formats(&self) -> Result<Vec<String>> {
-		let _clip = ClipboardWin::new_attempts(10)
-			.map_err(|code| format!("Open clipboard error, code = {}", code));
-		let format_count = clipboard_win::count_formats();
-		if format_count.is_none() {
-			return Ok(Vec::new());
-		}
-		let mut res = Vec::new();
-		let enum_formats = clipboard_win::raw::EnumFormats::new();
-		enum_formats.into_iter().for_each(|format| {
-			let f_name = raw::format_name_big(format);
-			match f_name {
-				Some(name) => res.push(name),
-				None => {
-					res.push(UNKNOWN_FORMAT.to_string());
+		let formats = self.available_formats_detailed()?;
+		Ok(formats.into_iter().map(|format| format.name).collect())
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		let _clip = self.open()?;
+		let res: Vec<FormatInfo> = raw::EnumFormats::new()
+			.map(|format| {
+				let name = predefined_format_name(format)
+					.map(str::to_owned)
+					.or_else(|| raw::format_name_big(format))
+					.unwrap_or_else(|| UNKNOWN_FORMAT.to_string());
+				FormatInfo {
+					mime: format_mime(&name),
+					platform_id: Some(format),
+					// GlobalSize of the handle, which may be rounded up by the allocator
+					size_hint: raw::size(format).map(|size| size.get()),
+					name,
 				}
-			}
-		});
+			})
+			.collect();
+		trace_debug!(count = res.len(), "formats enumerated");
 		Ok(res)
 	}
 
@@ -128,7 +710,27 @@ impl Clipboard for ClipboardContext {
 				clipboard_win::is_format_avail(*cf_png_uint)
 					|| clipboard_win::is_format_avail(formats::CF_DIB)
 			}
+			ContentFormat::Svg => {
+				let cf_svg_uint = self.format_map.get(CF_SVG).unwrap();
+				clipboard_win::is_format_avail(*cf_svg_uint)
+			}
+			ContentFormat::Markdown => {
+				let cf_markdown_uint = self.format_map.get(CF_MARKDOWN).unwrap();
+				clipboard_win::is_format_avail(*cf_markdown_uint)
+			}
 			ContentFormat::Files => clipboard_win::is_format_avail(formats::CF_HDROP),
+			ContentFormat::Url => {
+				let cf_url_uint = self.format_map.get(CF_URL).unwrap();
+				clipboard_win::is_format_avail(*cf_url_uint)
+			}
+			ContentFormat::Color => {
+				let cf_color_uint = self.format_map.get(CF_COLOR).unwrap();
+				clipboard_win::is_format_avail(*cf_color_uint)
+			}
+			ContentFormat::Pdf => {
+				let cf_pdf_uint = self.format_map.get(CF_PDF).unwrap();
+				clipboard_win::is_format_avail(*cf_pdf_uint)
+			}
 			ContentFormat::Other(format) => {
 				let format_uint = clipboard_win::register_format(format.as_str());
 				if let Some(format_uint) = format_uint {
@@ -136,12 +738,19 @@ impl Clipboard for ClipboardContext {
 				}
 				false
 			}
+			ContentFormat::Custom(handle) => {
+				handle_format_id(&handle).map_or(false, clipboard_win::is_format_avail)
+			}
 		}
 	}
 
+	fn is_empty(&self) -> bool {
+		// CountClipboardFormats doesn't need the clipboard open
+		clipboard_win::raw::count_formats().map_or(true, |count| count == 0)
+	}
+
 	fn clear(&self) -> Result<()> {
-		let _clip = ClipboardWin::new_attempts(10)
-			.map_err(|code| format!("Open clipboard error, code = {}", code));
+		let _clip = self.open()?;
 		let res = clipboard_win::empty();
 		if let Err(e) = res {
 			return Err(format!("Empty clipboard error, code = {}", e).into());
@@ -155,19 +764,56 @@ impl Clipboard for ClipboardContext {
 			return Err("register format error".into());
 		}
 		let format_uint = format_uint.unwrap().get();
-		let buffer = get_clipboard(formats::RawData(format_uint));
+		let _clip = self.open()?;
+		let buffer = get(formats::RawData(format_uint));
 		match buffer {
-			Ok(data) => Ok(data),
+			Ok(data) => {
+				trace_debug!(format, bytes = data.len(), "buffer read");
+				Ok(data)
+			}
 			Err(e) => Err(format!("Get buffer error, code = {}", e).into()),
 		}
 	}
 
-	fn get_text(&self) -> Result<String> {
-		let string: SysResult<String> = get_clipboard(formats::Unicode);
-		match string {
-			Ok(s) => Ok(s),
-			Err(e) => Err(format!("Get text error, code = {}", e).into()),
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		let format_uint = format_id(format)?;
+		let clip = self.open()?;
+		let handle = raw::get_clipboard_data(format_uint)
+			.map_err(|e| format!("Get buffer error, code = {}", e))?;
+		let hglobal = HGLOBAL(handle.as_ptr());
+		let ptr = unsafe { GlobalLock(hglobal) } as *const u8;
+		if ptr.is_null() {
+			return Err("GlobalLock error".into());
 		}
+		Ok(Box::new(GlobalReader {
+			hglobal,
+			ptr,
+			len: unsafe { GlobalSize(hglobal) },
+			pos: 0,
+			_clip: clip,
+		}))
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		let format_uint = format_id(format)?;
+		let _clip = self.open()?;
+		if !clipboard_win::is_format_avail(format_uint) {
+			return Err("no data".into());
+		}
+		// GlobalSize of the handle, which may be rounded up by the allocator
+		raw::size(format_uint)
+			.map(|size| size.get())
+			.ok_or_else(|| format!("Get size error, format = {}", format).into())
+	}
+
+	fn get_text(&self) -> Result<String> {
+		let _clip = self.open()?;
+		self.read_text(TextDecoding::Lossy)
+	}
+
+	fn get_text_with_decoding(&self, decoding: TextDecoding) -> Result<String> {
+		let _clip = self.open()?;
+		self.read_text(decoding)
 	}
 
 	fn get_rich_text(&self) -> Result<String> {
@@ -176,136 +822,129 @@ impl Clipboard for ClipboardContext {
 	}
 
 	fn get_html(&self) -> Result<String> {
-		let buffer = get_clipboard(formats::RawData(self.html_format.code()));
-		match buffer {
-			Ok(data) => {
-				let html_res = String::from_utf8(data);
-				if let Ok(html_full_str) = html_res {
-					let html = extract_html_from_clipboard_data(html_full_str.as_str());
-					if let Ok(html) = html {
-						return Ok(html);
-					}
-				}
-				Err("Get html error".into())
-			}
-			Err(e) => Err(format!("Get buffer error, code = {}", e).into()),
+		let _clip = self.open()?;
+		self.read_html()
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		let _clip = self.open()?;
+		match self.read_content(&ContentFormat::Svg)? {
+			ClipboardContent::Svg(svg) => Ok(svg),
+			_ => Err("Get svg error".into()),
+		}
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		let _clip = self.open()?;
+		match self.read_content(&ContentFormat::Markdown)? {
+			ClipboardContent::Markdown(markdown) => Ok(markdown),
+			_ => Err("Get markdown error".into()),
 		}
 	}
 
 	fn get_image(&self) -> Result<RustImageData> {
-		let cf_png_format = self.format_map.get(CF_PNG);
-		if cf_png_format.is_some() && clipboard_win::is_format_avail(*cf_png_format.unwrap()) {
-			let image_raw_data = self.get_buffer(CF_PNG)?;
-			RustImageData::from_bytes(&image_raw_data)
-		} else if clipboard_win::is_format_avail(formats::CF_DIBV5) {
-			let res = get_clipboard(formats::RawData(formats::CF_DIBV5));
-			match res {
-				Ok(data) => {
-					let decoder = {
-						// if data.as_slice().starts_with(b"BM") {
-						// 	BmpDecoder::new(Cursor::new(data.as_slice()))
-						// } else {
-						BmpDecoder::new_without_file_header(Cursor::new(data.as_slice()))
-						// }
-					};
-					let decoder = decoder.map_err(|e| format!("{}", e))?;
-					let dynamic_image =
-						DynamicImage::from_decoder(decoder).map_err(|e| format!("{}", e))?;
-					Ok(RustImageData::from_dynamic_image(dynamic_image))
-				}
-				Err(e) => Err(format!("Get image error, code = {}", e).into()),
-			}
-		} else if clipboard_win::is_format_avail(formats::CF_DIB) {
-			let res = get_clipboard(formats::Bitmap);
-			match res {
-				Ok(data) => RustImageData::from_bytes(&data),
-				Err(e) => Err(format!("Get image error, code = {}", e).into()),
-			}
+		let _clip = self.open()?;
+		self.read_image()
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		let _clip = self.open()?;
+		read_file_list()
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		// CF_HDROP already holds wide char paths, no decoding needed
+		self.get_files()
+			.map(|files| files.into_iter().map(PathBuf::from).collect())
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		let _clip = self.open()?;
+		let format_uint = clipboard_win::register_format(CF_DROP_EFFECT)
+			.ok_or("register format error")?
+			.get();
+		if !clipboard_win::is_format_avail(format_uint) {
+			return Ok(FileOperation::Copy);
+		}
+		let data = get(formats::RawData(format_uint))
+			.map_err(|e| format!("Get drop effect error, code = {}", e))?;
+		let effect = read_u32(&data, 0).unwrap_or(DROPEFFECT_COPY);
+		if effect & DROPEFFECT_MOVE != 0 {
+			Ok(FileOperation::Cut)
 		} else {
-			Err("No image data in clipboard".into())
+			Ok(FileOperation::Copy)
 		}
 	}
 
-	fn get_files(&self) -> Result<Vec<String>> {
-		let files: SysResult<Vec<String>> = get_clipboard(formats::FileList);
-		match files {
-			Ok(f) => Ok(f),
-			Err(e) => Err(format!("Get files error, code = {}", e).into()),
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		let _clip = self.open()?;
+		match self.read_content(&ContentFormat::Url)? {
+			ClipboardContent::Urls(urls) => Ok(urls),
+			_ => Err("Get url error".into()),
 		}
 	}
 
-	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
-		let _clip = ClipboardWin::new_attempts(10)
-			.map_err(|code| format!("Open clipboard error, code = {}", code));
-		let mut res = Vec::new();
-		for format in formats {
-			match format {
-				ContentFormat::Text => {
-					let r = get(formats::Unicode);
-					match r {
-						Ok(txt) => {
-							res.push(ClipboardContent::Text(txt));
-						}
-						Err(_) => continue,
-					}
-				}
-				ContentFormat::Rtf => {
-					let format_uint = self.get_format(format);
-					let buffer = get(formats::RawData(format_uint));
-					match buffer {
-						Ok(buffer) => {
-							let rtf = String::from_utf8_lossy(&buffer);
-							res.push(ClipboardContent::Rtf(rtf.to_string()));
-						}
-						Err(_) => continue,
-					}
-				}
-				ContentFormat::Html => {
-					let html_buffer = get(formats::RawData(self.html_format.code()));
-					match html_buffer {
-						Ok(html) => {
-							let html_res = String::from_utf8(html);
-							if let Ok(html_full_str) = html_res {
-								let html = extract_html_from_clipboard_data(html_full_str.as_str());
-								if let Ok(html) = html {
-									res.push(ClipboardContent::Html(html));
-								}
-							}
-						}
-						Err(_) => continue,
-					}
-				}
-				ContentFormat::Image => {
-					let img = self.get_image();
-					match img {
-						Ok(img) => {
-							res.push(ClipboardContent::Image(img));
-						}
-						Err(_) => continue,
-					}
-				}
-				ContentFormat::Other(fmt) => {
-					let format_uint = self.get_format(format);
-					let buffer = get(formats::RawData(format_uint));
-					match buffer {
-						Ok(buffer) => {
-							res.push(ClipboardContent::Other(fmt.clone(), buffer));
-						}
-						Err(_) => continue,
-					}
-				}
-				ContentFormat::Files => {
-					let files = self.get_files();
-					match files {
-						Ok(files) => {
-							res.push(ClipboardContent::Files(files));
-						}
-						Err(_) => continue,
-					}
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		let _clip = self.open()?;
+		let candidates = [
+			(CF_PNG, self.format_map.get(CF_PNG).copied()),
+			(CF_DIBV5_NAME, Some(formats::CF_DIBV5)),
+			(CF_DIB_NAME, Some(formats::CF_DIB)),
+		];
+		for (name, format_uint) in candidates {
+			if let Some(format_uint) = format_uint {
+				if clipboard_win::is_format_avail(format_uint) {
+					let buffer = get(formats::RawData(format_uint))
+						.map_err(|e| format!("Get image error, code = {}", e))?;
+					return Ok((name.to_string(), buffer));
 				}
 			}
 		}
-		Ok(res)
+		Err("No image data in clipboard".into())
+	}
+
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		// all formats are read under a single open, so the result is a consistent snapshot
+		let _clip = self.open()?;
+		Ok(self.read_contents(formats))
+	}
+
+	fn try_get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		if IN_SESSION.with(Cell::get) {
+			return Ok(self.read_contents(formats));
+		}
+		// a single attempt, failing while another process has the clipboard open
+		let _clip = ClipboardWin::new().map_err(|code| {
+			trace_debug!(code, "clipboard is held");
+			would_block()
+		})?;
+		Ok(self.read_contents(formats))
+	}
+
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		// the owner can't change while the clipboard is open
+		let _clip = self.open()?;
+		let concealed = clipboard_win::register_format(CF_EXCLUDE_FROM_MONITOR)
+			.map_or(false, |format| clipboard_win::is_format_avail(format.get()));
+		let metadata = ContentMetadata {
+			source: clipboard_owner(),
+			concealed,
+			..ContentMetadata::default()
+		};
+		Ok((self.read_contents(formats), metadata))
+	}
+
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		let _clip = self.open()?;
+		formats
+			.iter()
+			.find_map(|format| self.read_content(format).ok())
+			.ok_or_else(|| "None of the formats is available".into())
 	}
 
 	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
@@ -313,109 +952,163 @@ impl Clipboard for ClipboardContext {
 		if format_uint.is_none() {
 			return Err("register format error".into());
 		}
-		let format_uint = format_uint.unwrap().get();
-		let res = set_clipboard(formats::RawData(format_uint), buffer);
+		self.write(&[ClipboardContent::Other(format.to_owned(), buffer)])
+			.map_err(|e| format!("set buffer error, {}", e).into())
+	}
+
+	fn register_format(&self, name: &str) -> Result<FormatHandle> {
+		Ok(FormatHandle::new(name, Some(format_id(name)?)))
+	}
+
+	fn get_buffer_by_handle(&self, format: &FormatHandle) -> Result<Vec<u8>> {
+		let format_uint = handle_format_id(format)?;
+		let _clip = self.open()?;
+		get(formats::RawData(format_uint))
+			.map_err(|e| format!("Get buffer error, code = {}", e).into())
+	}
+
+	fn set_buffer_by_handle(&self, format: &FormatHandle, buffer: Vec<u8>) -> Result<()> {
+		let format_uint = handle_format_id(format)?;
+		let _clip = self.open()?;
+		clipboard_win::empty().map_err(|e| format!("Empty clipboard error, code = {}", e))?;
+		set_without_clear(format_uint, &buffer)
+			.map_err(|e| format!("set buffer error, code = {}", e).into())
+	}
+
+	fn set_from_reader(
+		&self,
+		format: &str,
+		reader: &mut dyn Read,
+		len_hint: Option<usize>,
+	) -> Result<()> {
+		let format_uint = format_id(format)?;
+		// read before opening, a slow reader must not block other applications
+		let hglobal = read_to_hglobal(reader, len_hint)?;
+		let res = self.open().and_then(|_clip| {
+			clipboard_win::empty().map_err(|e| format!("Empty clipboard error, code = {}", e))?;
+			// the clipboard owns the memory once this succeeds
+			unsafe { SetClipboardData(format_uint, HANDLE(hglobal.0)) }
+				.map(|_| ())
+				.map_err(|e| format!("set buffer error, {}", e).into())
+		});
 		if res.is_err() {
-			return Err("set buffer error".into());
+			let _ = unsafe { GlobalFree(hglobal) };
 		}
-		Ok(())
+		res
 	}
 
 	fn set_text(&self, text: String) -> Result<()> {
-		let res = set_clipboard(formats::Unicode, text);
-		res.map_err(|e| format!("set text error, code = {}", e).into())
+		self.write(&[ClipboardContent::Text(text)])
+	}
+
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		// the clipboard stays open from the read to the write, no other program can change it in between
+		let _clip = self.open()?;
+		let current = self.read_text(TextDecoding::Lossy).unwrap_or_default();
+		if let Err(e) = clipboard_win::empty() {
+			return Err(format!("Empty clipboard error, code = {}", e).into());
+		}
+		self.write_content(&ClipboardContent::Text(appended_text(
+			&current, separator, text,
+		)))
 	}
 
 	fn set_rich_text(&self, text: String) -> Result<()> {
-		let res = self.set_buffer(CF_RTF, text.as_bytes().to_vec());
-		res.map_err(|e| format!("set rich text error, code = {}", e).into())
+		self.write(&[ClipboardContent::Rtf(text)])
 	}
 
 	fn set_html(&self, html: String) -> Result<()> {
-		let cf_html = plain_html_to_cf_html(&html);
-		let res = set_clipboard(
-			formats::RawData(self.html_format.code()),
-			cf_html.as_bytes(),
-		);
-		res.map_err(|e| format!("set html error, code = {}", e).into())
+		self.write(&[ClipboardContent::Html(html)])
+	}
+
+	fn get_html_with_source(&self) -> Result<(String, Option<Url>)> {
+		let _clip = self.open()?;
+		let cf_html = self.read_cf_html()?;
+		Ok((cf_html.html, cf_html.source_url.map(|url| Url::new(&url))))
+	}
+
+	fn set_html_with_source(&self, html: String, source: Url) -> Result<()> {
+		let _clip = self.open()?;
+		clipboard_win::empty().map_err(|e| format!("Empty clipboard error, code = {}", e))?;
+		let cf_html = cf_html::build_with_source_url(&html, Some(&source.url));
+		set_without_clear(self.html_format.code(), cf_html.as_bytes())
+			.map_err(|e| format!("set html error, code = {}", e).into())
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		self.write(&[ClipboardContent::Svg(svg)])
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		self.write(&[ClipboardContent::Markdown(markdown)])
 	}
 
 	fn set_image(&self, image: RustImageData) -> Result<()> {
-		let _clip = ClipboardWin::new_attempts(10)
-			.map_err(|code| format!("Open clipboard error, code = {}", code));
-		let res = clipboard_win::empty();
-		if let Err(e) = res {
-			return Err(format!("Empty clipboard error, code = {}", e).into());
-		}
-		// chromium source code
-		// @link {https://source.chromium.org/chromium/chromium/src/+/main:ui/base/clipboard/clipboard_win.cc;l=771;drc=2a5aaed0ff3a0895c8551495c2656ed49baf742c;bpv=0;bpt=1}
-		let cf_png_format = self.format_map.get(CF_PNG);
-		if cf_png_format.is_some() {
-			let png = image.to_png()?;
-			let write_png_res = set_without_clear(*cf_png_format.unwrap(), png.get_bytes());
-			if let Err(e) = write_png_res {
-				return Err(format!("set png image error, code = {}", e).into());
-			}
-		}
-		let bmp = image
-			.to_bitmap()
-			.map_err(|e| format!("to bitmap error, code = {}", e))?;
-		let res = set_bitmap_with(bmp.get_bytes(), options::NoClear);
-		res.map_err(|e| format!("set image error, code = {}", e).into())
+		self.write(&[ClipboardContent::Image(image)])
 	}
 
 	fn set_files(&self, files: Vec<String>) -> Result<()> {
-		let _clip = ClipboardWin::new_attempts(10)
-			.map_err(|code| format!("Open clipboard error, code = {}", code));
-		let res = set_file_list_with(&files, options::DoClear);
-		res.map_err(|e| format!("set files error, code = {}", e).into())
+		self.write(&[ClipboardContent::Files(files)])
 	}
 
-	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
-		let _clip = ClipboardWin::new_attempts(10)
-			.map_err(|code| format!("Open clipboard error, code = {}", code));
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()> {
+		let effect = match operation {
+			FileOperation::Copy => DROPEFFECT_COPY,
+			FileOperation::Cut => DROPEFFECT_MOVE,
+		};
+		self.write(&[
+			ClipboardContent::Files(files),
+			ClipboardContent::Other(CF_DROP_EFFECT.to_owned(), effect.to_le_bytes().to_vec()),
+		])
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		// the url format holds only the first url, the text keeps all of them, one per line
+		let text = urls
+			.iter()
+			.map(|url| url.url.as_str())
+			.collect::<Vec<_>>()
+			.join("\r\n");
+		self.write(&[ClipboardContent::Urls(urls), ClipboardContent::Text(text)])
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		self.set_image_raw(CF_PNG, png)
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		let format_uint = match format {
+			CF_DIB_NAME => formats::CF_DIB,
+			CF_DIBV5_NAME => formats::CF_DIBV5,
+			_ => clipboard_win::register_format(format)
+				.ok_or("register format error")?
+				.get(),
+		};
+		let _clip = self.open()?;
 		let res = clipboard_win::empty();
 		if let Err(e) = res {
 			return Err(format!("Empty clipboard error, code = {}", e).into());
 		}
-		for content in contents {
-			match content {
-				ClipboardContent::Text(txt) => {
-					let res = set_string_with(txt.as_str(), options::NoClear);
-					if res.is_err() {
-						continue;
-					}
-				}
-				ClipboardContent::Html(html) => {
-					let format_uint_html = self.html_format.code();
-					let res = set_without_clear(format_uint_html, html.as_bytes());
-					if res.is_err() {
-						continue;
-					}
-				}
-				ClipboardContent::Image(img) => {
-					// set image will clear clipboard
-					let res = self.set_image(img);
-					if res.is_err() {
-						continue;
-					}
-				}
-				ClipboardContent::Rtf(_) | ClipboardContent::Other(_, _) => {
-					let format_uint = self.get_format(&content.get_format());
-					let res = set_without_clear(format_uint, content.as_bytes());
-					if res.is_err() {
-						continue;
-					}
-				}
-				ClipboardContent::Files(file_list) => {
-					let res = set_file_list_with(&file_list, options::NoClear);
-					if res.is_err() {
-						continue;
-					}
-				}
-			}
+		set_without_clear(format_uint, &bytes)
+			.map_err(|e| format!("set image error, code = {}", e).into())
+	}
+
+	/// The clipboard stays open for the whole closure, other programs can't read or change it until it returns
+	fn session<R, F: FnOnce(&Self) -> Result<R>>(&self, f: F) -> Result<R>
+	where
+		Self: Sized,
+	{
+		let clip = self.open()?;
+		if clip.is_none() {
+			// nested in another session, which closes the clipboard
+			return f(self);
 		}
-		Ok(())
+		let _session = SessionGuard::enter();
+		f(self)
+	}
+
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		self.write(&contents)
 	}
 }
 
@@ -435,27 +1128,84 @@ impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
 			return;
 		}
 		self.running = true;
-		let mut monitor = Monitor::new().expect("create monitor error");
-		let shutdown = monitor.shutdown_channel();
+		// the message-only window, created on this thread and pumped by `try_recv` below
+		let mut monitor = match Monitor::new() {
+			Ok(monitor) => monitor,
+			Err(_e) => {
+				trace_warn!(error = %_e, "create monitor failed");
+				self.running = false;
+				return;
+			}
+		};
+		let mut shutdown = monitor.shutdown_channel();
+		// the sequence number of the last change delivered, one moving on without a notification
+		// means the listener was dropped, e.g. by a session switch or an explorer restart
+		let mut sequence = raw::seq_num();
+		let mut unannounced = None;
+		let mut idle_ticks = 0u32;
 		loop {
 			if self.stop_receiver.try_recv().is_ok() {
 				break;
 			}
-			let msg = monitor.try_recv();
-			match msg {
+			let lost = match monitor.try_recv() {
 				Ok(true) => {
-					self.handlers.iter_mut().for_each(|f| {
-						f.on_clipboard_change();
-					});
+					trace_debug!("clipboard changed");
+					sequence = raw::seq_num();
+					unannounced = None;
+					if notify_handlers(&mut self.handlers, Selection::Clipboard).is_break() {
+						break;
+					}
+					false
 				}
 				Ok(false) => {
-					// no change
-					thread::park_timeout(Duration::from_millis(200));
-					continue;
+					idle_ticks += 1;
+					// checked about once a second, the number has to be stuck on two checks in a row
+					// so a notification that is merely on its way isn't taken for a lost one
+					let lost = if idle_ticks % 5 == 0 {
+						let current = raw::seq_num();
+						if current == sequence {
+							unannounced = None;
+							false
+						} else if unannounced == Some(current) {
+							true
+						} else {
+							unannounced = Some(current);
+							false
+						}
+					} else {
+						false
+					};
+					if !lost {
+						thread::park_timeout(Duration::from_millis(200));
+					}
+					lost
 				}
-				Err(e) => {
-					eprintln!("watch error, code = {}", e);
-					break;
+				Err(_e) => {
+					trace_warn!(error = %_e, "watch failed");
+					true
+				}
+			};
+			if lost {
+				match Monitor::new() {
+					Ok(new_monitor) => {
+						trace_warn!("clipboard listener lost, registered again");
+						drop(shutdown);
+						monitor = new_monitor;
+						shutdown = monitor.shutdown_channel();
+						let missed = raw::seq_num() != sequence;
+						sequence = raw::seq_num();
+						unannounced = None;
+						self.handlers.iter_mut().for_each(|f| f.on_reconnected());
+						if missed
+							&& notify_handlers(&mut self.handlers, Selection::Clipboard).is_break()
+						{
+							break;
+						}
+					}
+					Err(_e) => {
+						trace_warn!(error = %_e, "create monitor failed");
+						break;
+					}
 				}
 			}
 		}
@@ -464,15 +1214,7 @@ impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
 	}
 
 	fn get_shutdown_channel(&self) -> WatcherShutdown {
-		WatcherShutdown {
-			stop_signal: self.stop_signal.clone(),
-		}
-	}
-}
-
-impl Drop for WatcherShutdown {
-	fn drop(&mut self) {
-		let _ = self.stop_signal.send(());
+		WatcherShutdown::new(self.stop_signal.clone())
 	}
 }
 
@@ -502,109 +1244,508 @@ impl Drop for WatcherShutdown {
 // EndFragment:000000375
 // <html><head><meta http-equiv="content-type" content="text/html; charset=UTF-8"></head><body><div style="background-color:#2b2b2b;color:#a9b7c6;font-family:'JetBrains Mono',monospace;font-size:9.8pt;"><pre><span style="color:#9876aa;">sellChannel</span></pre></div></body></html>
 // cp from https://github.com/Devolutions/IronRDP/blob/37aa6426dba3272f38a2bb46a513144a326854ee/crates/ironrdp-cliprdr-format/src/html.rs#L91
-fn plain_html_to_cf_html(fragment: &str) -> String {
-	const POS_PLACEHOLDER: &str = "0000000000";
+const BITMAPINFOHEADER_SIZE: usize = 40;
+const BITMAPV5HEADER_SIZE: usize = 124;
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const LCS_SRGB: u32 = 0x7352_4742;
+const LCS_GM_IMAGES: u32 = 4;
+// " EMF" in the dSignature field of ENHMETAHEADER
+const EMF_SIGNATURE: u32 = 0x464d_4520;
 
-	let mut buffer = String::new();
+/// Open the clipboard, retrying until `REMOTE_OPEN_TIMEOUT` while the remote session's client holds it
+fn open_remote() -> clipboard_win::SysResult<ClipboardWin> {
+	let deadline = Instant::now() + REMOTE_OPEN_TIMEOUT;
+	loop {
+		match ClipboardWin::new() {
+			Ok(clip) => return Ok(clip),
+			Err(code) if Instant::now() >= deadline => return Err(code),
+			Err(_) => {
+				stats::record_retry();
+				thread::sleep(REMOTE_OPEN_INTERVAL);
+			}
+		}
+	}
+}
 
-	let mut write_header = |key: &str, value: &str| {
-		let size = key.len() + value.len() + ":\r\n".len();
-		buffer.reserve(size);
+/// Draw an EMF at `dpi`. GDI leaves the alpha channel undefined, so the background is white
+/// and the image opaque
+fn rasterize_metafile(emf: &[u8], dpi: f32) -> Result<RustImageData> {
+	if read_u32(emf, 40) != Some(EMF_SIGNATURE) {
+		return Err("invalid metafile".into());
+	}
+	if dpi.is_nan() || dpi <= 0.0 {
+		return Err("dpi must be positive".into());
+	}
+	// rclFrame, the picture in hundredths of a millimeter
+	let frame = |offset: usize| read_u32(emf, offset).map(|value| value as i32);
+	let (left, top) = (frame(24).unwrap_or(0), frame(28).unwrap_or(0));
+	let (right, bottom) = (frame(32).unwrap_or(0), frame(36).unwrap_or(0));
+	let to_pixels = |hundredths: i32| ((hundredths as f32 / 2540.0 * dpi).round() as i32).max(1);
+	let (width, height) = (to_pixels(right - left), to_pixels(bottom - top));
 
-		buffer.push_str(key);
-		buffer.push(':');
-		let value_pos = buffer.len();
-		buffer.push_str(value);
-		buffer.push_str("\r\n");
+	let metafile = unsafe { SetEnhMetaFileBits(emf) };
+	if metafile.is_invalid() {
+		return Err("invalid metafile".into());
+	}
+	let pixels = draw_metafile(metafile, width, height);
+	let _ = unsafe { DeleteEnhMetaFile(metafile) };
+	let image = RustImageData::from_rgba(width as u32, height as u32, pixels?)?;
+	Ok(image.with_dpi(Some((dpi, dpi))))
+}
+
+/// Play a metafile into a top-down 32 bit DIB section, returns RGBA pixels
+fn draw_metafile(metafile: HENHMETAFILE, width: i32, height: i32) -> Result<Vec<u8>> {
+	unsafe {
+		let dc = CreateCompatibleDC(None);
+		if dc.is_invalid() {
+			return Err("CreateCompatibleDC error".into());
+		}
+		let info = BITMAPINFO {
+			bmiHeader: BITMAPINFOHEADER {
+				biSize: BITMAPINFOHEADER_SIZE as u32,
+				biWidth: width,
+				// negative for top-down rows
+				biHeight: -height,
+				biPlanes: 1,
+				biBitCount: 32,
+				biCompression: BI_RGB,
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let mut bits: *mut c_void = std::ptr::null_mut();
+		let bitmap = match CreateDIBSection(dc, &info, DIB_RGB_COLORS, &mut bits, None, 0) {
+			Ok(bitmap) => bitmap,
+			Err(e) => {
+				let _ = DeleteDC(dc);
+				return Err(format!("CreateDIBSection error, {}", e).into());
+			}
+		};
+		let previous = SelectObject(dc, bitmap);
+		let rect = RECT {
+			left: 0,
+			top: 0,
+			right: width,
+			bottom: height,
+		};
+		FillRect(dc, &rect, HBRUSH(GetStockObject(WHITE_BRUSH).0));
+		let drawn = PlayEnhMetaFile(dc, metafile, &rect).as_bool();
+		let _ = GdiFlush();
+		let len = width as usize * height as usize * 4;
+		let pixels = std::slice::from_raw_parts(bits as *const u8, len)
+			.chunks_exact(4)
+			.flat_map(|px| [px[2], px[1], px[0], 255])
+			.collect();
+		SelectObject(dc, previous);
+		let _ = DeleteObject(bitmap);
+		let _ = DeleteDC(dc);
+		if drawn {
+			Ok(pixels)
+		} else {
+			Err("PlayEnhMetaFile error".into())
+		}
+	}
+}
 
-		value_pos
+/// Decode a packed DIB (CF_DIB or CF_DIBV5), 32 bit images are decoded by hand to keep the alpha channel
+fn dib_to_image(data: &[u8]) -> Result<RustImageData> {
+	let image = match dib32_to_rgba(data) {
+		Some(rgba) => RustImageData::from_rgba(rgba.width, rgba.height, rgba.data)?,
+		None => decode_dib(data)?,
 	};
+	Ok(image.with_dpi(dib_dpi(data)))
+}
 
-	write_header("Version", "0.9");
+/// The resolution in the biXPelsPerMeter and biYPelsPerMeter fields, most applications leave them 0
+fn dib_dpi(data: &[u8]) -> Option<(f32, f32)> {
+	let (x, y) = (read_u32(data, 24)?, read_u32(data, 28)?);
+	if x == 0 || y == 0 {
+		return None;
+	}
+	Some((dpi::ppm_to_dpi(x), dpi::ppm_to_dpi(y)))
+}
 
-	let start_html_header_value_pos = write_header("StartHTML", POS_PLACEHOLDER);
-	let end_html_header_value_pos = write_header("EndHTML", POS_PLACEHOLDER);
-	let start_fragment_header_value_pos = write_header("StartFragment", POS_PLACEHOLDER);
-	let end_fragment_header_value_pos = write_header("EndFragment", POS_PLACEHOLDER);
+#[cfg(feature = "image")]
+fn decode_dib(data: &[u8]) -> Result<RustImageData> {
+	let decoder =
+		BmpDecoder::new_without_file_header(io::Cursor::new(data)).map_err(|e| format!("{}", e))?;
+	let dynamic_image = DynamicImage::from_decoder(decoder).map_err(|e| format!("{}", e))?;
+	Ok(RustImageData::from_dynamic_image(dynamic_image))
+}
+
+// kept as a BMP file, which is a DIB behind a 14 byte file header
+#[cfg(not(feature = "image"))]
+fn decode_dib(data: &[u8]) -> Result<RustImageData> {
+	let header_size = read_u32(data, 0).ok_or("invalid DIB")?;
+	let bit_count = read_u16(data, 14).ok_or("invalid DIB")?;
+	let compression = read_u32(data, 16).ok_or("invalid DIB")?;
+	let colors_used = read_u32(data, 32).unwrap_or(0);
+	let masks = if compression == BI_BITFIELDS && header_size as usize == BITMAPINFOHEADER_SIZE {
+		12
+	} else {
+		0
+	};
+	let palette = if (1..=8).contains(&bit_count) {
+		4 * if colors_used == 0 {
+			1u32 << bit_count
+		} else {
+			colors_used
+		}
+	} else {
+		4 * colors_used
+	};
+	let pixel_offset = 14 + header_size + masks + palette;
+	let mut bmp = Vec::with_capacity(14 + data.len());
+	bmp.extend_from_slice(b"BM");
+	bmp.extend_from_slice(&((14 + data.len()) as u32).to_le_bytes());
+	bmp.extend_from_slice(&[0; 4]);
+	bmp.extend_from_slice(&pixel_offset.to_le_bytes());
+	bmp.extend_from_slice(data);
+	RustImageData::from_bytes(&bmp)
+}
+
+/// Names of the formats predefined by windows, which have no registered name
+fn predefined_format_name(format: c_uint) -> Option<&'static str> {
+	let name = match format {
+		formats::CF_TEXT => "CF_TEXT",
+		formats::CF_BITMAP => "CF_BITMAP",
+		formats::CF_METAFILEPICT => "CF_METAFILEPICT",
+		formats::CF_SYLK => "CF_SYLK",
+		formats::CF_DIF => "CF_DIF",
+		formats::CF_TIFF => "CF_TIFF",
+		formats::CF_OEMTEXT => "CF_OEMTEXT",
+		formats::CF_DIB => CF_DIB_NAME,
+		formats::CF_PALETTE => "CF_PALETTE",
+		formats::CF_PENDATA => "CF_PENDATA",
+		formats::CF_RIFF => "CF_RIFF",
+		formats::CF_WAVE => "CF_WAVE",
+		formats::CF_UNICODETEXT => "CF_UNICODETEXT",
+		formats::CF_ENHMETAFILE => "CF_ENHMETAFILE",
+		formats::CF_HDROP => "CF_HDROP",
+		formats::CF_LOCALE => "CF_LOCALE",
+		formats::CF_DIBV5 => CF_DIBV5_NAME,
+		_ => return None,
+	};
+	Some(name)
+}
 
-	let start_html_pos = buffer.len();
-	if !fragment.starts_with("<html>") {
-		buffer.push_str("<html>\r\n<body>\r\n<!--StartFragment-->");
+/// Copy a reader into moveable global memory chunk by chunk, growing the allocation as needed
+fn read_to_hglobal(reader: &mut dyn Read, len_hint: Option<usize>) -> Result<HGLOBAL> {
+	let flags = GMEM_MOVEABLE | GMEM_ZEROINIT;
+	let mut capacity = len_hint.unwrap_or(READ_CHUNK).max(1);
+	let mut hglobal = unsafe { GlobalAlloc(flags, capacity) }?;
+	let mut len = 0;
+	let res: Result<()> = loop {
+		if len == capacity {
+			capacity *= 2;
+			match unsafe { GlobalReAlloc(hglobal, capacity, flags.0) } {
+				Ok(grown) => hglobal = grown,
+				Err(e) => break Err(e.into()),
+			}
+		}
+		let ptr = unsafe { GlobalLock(hglobal) } as *mut u8;
+		if ptr.is_null() {
+			break Err("GlobalLock error".into());
+		}
+		let buf = unsafe { std::slice::from_raw_parts_mut(ptr.add(len), capacity - len) };
+		let read = reader.read(buf);
+		let _ = unsafe { GlobalUnlock(hglobal) };
+		match read {
+			Ok(0) => break Ok(()),
+			Ok(n) => len += n,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+			Err(e) => break Err(e.into()),
+		}
+	};
+	if let Err(e) = res {
+		let _ = unsafe { GlobalFree(hglobal) };
+		return Err(e);
+	}
+	if len < capacity {
+		// trim the unused tail, keeping the larger block if the shrink fails
+		if let Ok(trimmed) = unsafe { GlobalReAlloc(hglobal, len.max(1), GMEM_MOVEABLE.0) } {
+			hglobal = trimmed;
+		}
 	}
+	Ok(hglobal)
+}
 
-	let start_fragment_pos = buffer.len();
-	buffer.push_str(fragment);
+/// The executable of the process owning the clipboard, `None` when no window owns it
+/// or the process can't be queried, e.g. an elevated one.
+fn clipboard_owner() -> Option<String> {
+	unsafe {
+		let owner = GetClipboardOwner().ok()?;
+		let mut pid = 0;
+		GetWindowThreadProcessId(owner, Some(&mut pid));
+		if pid == 0 {
+			return None;
+		}
+		let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+		let mut path = [0u16; 1024];
+		let mut len = path.len() as u32;
+		let res = QueryFullProcessImageNameW(
+			process,
+			PROCESS_NAME_WIN32,
+			PWSTR(path.as_mut_ptr()),
+			&mut len,
+		);
+		let _ = CloseHandle(process);
+		res.ok()?;
+		Some(String::from_utf16_lossy(&path[..len as usize]))
+	}
+}
 
-	let end_fragment_pos = buffer.len();
-	if !fragment.ends_with("</html>") {
-		buffer.push_str("<!--EndFragment-->\r\n</body>\r\n</html>");
+/// Resolve a format name as reported by `available_formats`
+fn format_id(name: &str) -> Result<c_uint> {
+	if let Some(format) =
+		(formats::CF_TEXT..=formats::CF_DIBV5).find(|f| predefined_format_name(*f) == Some(name))
+	{
+		return Ok(format);
 	}
+	clipboard_win::register_format(name)
+		.map(|format| format.get())
+		.ok_or_else(|| "register format error".into())
+}
 
-	let end_html_pos = buffer.len();
+/// The id of a format handle, handles made by another implementation are registered by name.
+fn handle_format_id(format: &FormatHandle) -> Result<c_uint> {
+	match format.platform_id() {
+		Some(id) => Ok(id),
+		None => format_id(format.name()),
+	}
+}
 
-	let start_html_pos_value = format!("{:0>10}", start_html_pos);
-	let end_html_pos_value = format!("{:0>10}", end_html_pos);
-	let start_fragment_pos_value = format!("{:0>10}", start_fragment_pos);
-	let end_fragment_pos_value = format!("{:0>10}", end_fragment_pos);
+/// Codepage of the ANSI text on the clipboard, taken from CF_LOCALE when the owner set it.
+/// The clipboard must already be opened by the caller.
+fn ansi_codepage() -> u32 {
+	let lcid = get(formats::RawData(formats::CF_LOCALE))
+		.ok()
+		.and_then(|data| read_u32(&data, 0));
+	if let Some(lcid) = lcid {
+		// LOCALE_RETURN_NUMBER writes the codepage as a DWORD into two wide chars
+		let mut buffer = [0u16; 2];
+		let len = unsafe {
+			GetLocaleInfoW(
+				lcid,
+				LOCALE_IDEFAULTANSICODEPAGE | LOCALE_RETURN_NUMBER,
+				Some(&mut buffer),
+			)
+		};
+		if len == 2 {
+			return u32::from(buffer[0]) | (u32::from(buffer[1]) << 16);
+		}
+	}
+	unsafe { GetACP() }
+}
 
-	let mut replace_placeholder = |value_begin_idx: usize, header_value: &str| {
-		let value_end_idx = value_begin_idx + POS_PLACEHOLDER.len();
-		buffer.replace_range(value_begin_idx..value_end_idx, header_value);
+/// Decode text in a windows codepage, stopping at the first NUL character
+fn codepage_to_string(bytes: &[u8], codepage: u32, decoding: TextDecoding) -> Result<String> {
+	let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+	let bytes = &bytes[..end];
+	if bytes.is_empty() {
+		return Ok(String::new());
+	}
+	let flags = match decoding {
+		TextDecoding::Strict => MB_ERR_INVALID_CHARS,
+		// invalid bytes become the codepage's default character
+		TextDecoding::Lossy => MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0),
 	};
+	let len = unsafe { MultiByteToWideChar(codepage, flags, bytes, None) };
+	if len <= 0 {
+		return Err(format!("Invalid text in codepage {}", codepage).into());
+	}
+	let mut wide = vec![0u16; len as usize];
+	let len = unsafe { MultiByteToWideChar(codepage, flags, bytes, Some(&mut wide)) };
+	if len <= 0 {
+		return Err(format!("Invalid text in codepage {}", codepage).into());
+	}
+	wide.truncate(len as usize);
+	Ok(String::from_utf16_lossy(&wide))
+}
 
-	replace_placeholder(start_html_header_value_pos, &start_html_pos_value);
-	replace_placeholder(end_html_header_value_pos, &end_html_pos_value);
-	replace_placeholder(start_fragment_header_value_pos, &start_fragment_pos_value);
-	replace_placeholder(end_fragment_header_value_pos, &end_fragment_pos_value);
+/// A CF_HDROP block: a DROPFILES header, then every path as a NUL terminated wide string and a
+/// final NUL. Paths are stored whole, so long `\\?\` paths, UNC shares and a total beyond
+/// MAX_PATH survive as they are
+fn files_to_dropfiles(files: &[String]) -> Result<Vec<u8>> {
+	if files.is_empty() {
+		return Err("file list is empty".into());
+	}
+	let mut data = Vec::with_capacity(
+		DROPFILES_SIZE + files.iter().map(|file| file.len() * 2 + 2).sum::<usize>() + 2,
+	);
+	data.extend_from_slice(&(DROPFILES_SIZE as u32).to_le_bytes());
+	// the drop point and fNC stay 0
+	data.extend_from_slice(&[0; 12]);
+	// fWide
+	data.extend_from_slice(&1u32.to_le_bytes());
+	for file in files {
+		if file.is_empty() || file.contains('\0') {
+			return Err(format!("invalid file path {:?}", file).into());
+		}
+		data.extend(string_to_utf16le(file, true));
+	}
+	data.extend_from_slice(&[0, 0]);
+	Ok(data)
+}
 
-	buffer
+/// Parse a CF_HDROP block, the paths are wide or in the ANSI codepage as `fWide` says.
+/// No path length is assumed, unlike DragQueryFile with a MAX_PATH buffer
+fn dropfiles_to_files(data: &[u8]) -> Result<Vec<String>> {
+	let offset = read_u32(data, 0).ok_or("CF_HDROP is too short")? as usize;
+	let wide = read_u32(data, 16).ok_or("CF_HDROP is too short")? != 0;
+	let list = data
+		.get(offset..)
+		.ok_or("CF_HDROP file list is out of bounds")?;
+	if wide {
+		let list: Vec<u16> = list
+			.chunks_exact(2)
+			.map(|c| u16::from_le_bytes([c[0], c[1]]))
+			.collect();
+		Ok(list
+			.split(|c| *c == 0)
+			.take_while(|file| !file.is_empty())
+			.map(String::from_utf16_lossy)
+			.collect())
+	} else {
+		let codepage = ansi_codepage();
+		list.split(|b| *b == 0)
+			.take_while(|file| !file.is_empty())
+			.map(|file| codepage_to_string(file, codepage, TextDecoding::Lossy))
+			.collect()
+	}
 }
 
-const SEP: char = ':';
-const START_HTML: &str = "StartHTML";
-const END_HTML: &str = "EndHTML";
+/// Read the CF_HDROP paths, the clipboard must already be opened by the caller.
+fn read_file_list() -> Result<Vec<String>> {
+	let data = get(formats::RawData(formats::CF_HDROP))
+		.map_err(|e| format!("Get files error, code = {}", e))?;
+	dropfiles_to_files(&data)
+}
 
-fn extract_html_from_clipboard_data(data: &str) -> Result<String> {
-	let mut start_idx = 0usize;
-	let mut end_idx = data.len();
-	for line in data.lines() {
-		let mut split = line.split(SEP);
-		let key = match split.next() {
-			Some(key) => key,
-			None => break,
-		};
-		let value = match split.next() {
-			Some(value) => value,
-			//Reached HTML
-			None => break,
-		};
-		match key {
-			START_HTML => match value.trim_start_matches('0').parse() {
-				Ok(value) => {
-					start_idx = value;
-					continue;
-				}
-				//Should not really happen
-				Err(_) => break,
-			},
-			END_HTML => match value.trim_start_matches('0').parse() {
-				Ok(value) => {
-					end_idx = value;
-					continue;
-				}
-				//Should not really happen
-				Err(_) => break,
-			},
-			_ => continue,
-		}
+pub(super) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+	let bytes = data.get(offset..offset + 4)?;
+	Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+	let bytes = data.get(offset..offset + 2)?;
+	Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Decode an uncompressed 32 bit DIB, returns `None` for other bit depths
+fn dib32_to_rgba(data: &[u8]) -> Option<RgbaPixels> {
+	let header_size = read_u32(data, 0)? as usize;
+	let width = read_u32(data, 4)? as i32;
+	let height = read_u32(data, 8)? as i32;
+	let bit_count = read_u16(data, 14)?;
+	let compression = read_u32(data, 16)?;
+	if header_size < BITMAPINFOHEADER_SIZE || bit_count != 32 || width <= 0 || height == 0 {
+		return None;
 	}
-	//Make sure HTML writer didn't screw up offsets of fragment
-	let size = match end_idx.checked_sub(start_idx) {
-		Some(size) => size,
-		None => return Err("Invalid HTML offsets".into()),
+	let (red_mask, green_mask, blue_mask, alpha_mask, pixel_offset) = match compression {
+		BI_RGB => (
+			0x00ff_0000,
+			0x0000_ff00,
+			0x0000_00ff,
+			0xff00_0000,
+			header_size,
+		),
+		// the masks follow a BITMAPINFOHEADER, and are part of the V4/V5 headers
+		BI_BITFIELDS if header_size == BITMAPINFOHEADER_SIZE => (
+			read_u32(data, 40)?,
+			read_u32(data, 44)?,
+			read_u32(data, 48)?,
+			0,
+			header_size + 12,
+		),
+		BI_BITFIELDS => (
+			read_u32(data, 40)?,
+			read_u32(data, 44)?,
+			read_u32(data, 48)?,
+			read_u32(data, 52)?,
+			header_size,
+		),
+		_ => return None,
 	};
-	if size > data.len() {
-		return Err("Invalid HTML offsets".into());
+	let (width, rows) = (width as u32, height.unsigned_abs());
+	let stride = width as usize * 4;
+	let pixels = data.get(pixel_offset..pixel_offset + stride * rows as usize)?;
+
+	let channel = |pixel: u32, mask: u32| -> u8 {
+		if mask == 0 {
+			return 0;
+		}
+		let value = (pixel & mask) >> mask.trailing_zeros();
+		let max = mask >> mask.trailing_zeros();
+		((value * 255 + max / 2) / max) as u8
 	};
-	Ok(data[start_idx..end_idx].to_string())
+	let mut rgba = Vec::with_capacity(stride * rows as usize);
+	for row in 0..rows as usize {
+		// a positive height means the rows are stored bottom-up
+		let src_row = if height > 0 {
+			rows as usize - 1 - row
+		} else {
+			row
+		};
+		for px in pixels[src_row * stride..(src_row + 1) * stride].chunks_exact(4) {
+			let pixel = u32::from_le_bytes([px[0], px[1], px[2], px[3]]);
+			rgba.extend_from_slice(&[
+				channel(pixel, red_mask),
+				channel(pixel, green_mask),
+				channel(pixel, blue_mask),
+				channel(pixel, alpha_mask),
+			]);
+		}
+	}
+	// many applications leave the alpha byte of BI_RGB bitmaps zeroed, treat those as opaque
+	if alpha_mask == 0 || rgba.chunks_exact(4).all(|px| px[3] == 0) {
+		rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+	}
+	Some(RgbaPixels {
+		width,
+		height: rows,
+		data: rgba,
+	})
+}
+
+/// Encode an image as a bottom-up 32 bit CF_DIBV5 with an alpha mask
+fn rgba_to_dibv5(image: &RgbaPixels, dpi: Option<(f32, f32)>) -> Vec<u8> {
+	let (width, height) = (image.width, image.height);
+	let stride = width as usize * 4;
+	let size_image = stride * height as usize;
+	let mut dib = Vec::with_capacity(BITMAPV5HEADER_SIZE + size_image);
+	dib.extend_from_slice(&(BITMAPV5HEADER_SIZE as u32).to_le_bytes());
+	dib.extend_from_slice(&(width as i32).to_le_bytes());
+	dib.extend_from_slice(&(height as i32).to_le_bytes());
+	// planes
+	dib.extend_from_slice(&1u16.to_le_bytes());
+	// bit count
+	dib.extend_from_slice(&32u16.to_le_bytes());
+	dib.extend_from_slice(&BI_BITFIELDS.to_le_bytes());
+	dib.extend_from_slice(&(size_image as u32).to_le_bytes());
+	// pixels per meter x/y
+	let (x_ppm, y_ppm) = dpi.map_or((0, 0), |(x, y)| (dpi::dpi_to_ppm(x), dpi::dpi_to_ppm(y)));
+	dib.extend_from_slice(&x_ppm.to_le_bytes());
+	dib.extend_from_slice(&y_ppm.to_le_bytes());
+	// colors used, colors important
+	dib.extend_from_slice(&[0u8; 8]);
+	// red, green, blue and alpha masks
+	for mask in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+		dib.extend_from_slice(&mask.to_le_bytes());
+	}
+	dib.extend_from_slice(&LCS_SRGB.to_le_bytes());
+	// endpoints and gamma are unused for sRGB
+	dib.extend_from_slice(&[0u8; 48]);
+	dib.extend_from_slice(&LCS_GM_IMAGES.to_le_bytes());
+	// profile data, profile size, reserved
+	dib.extend_from_slice(&[0u8; 12]);
+	debug_assert_eq!(dib.len(), BITMAPV5HEADER_SIZE);
+
+	for row in image.data.chunks_exact(stride).rev() {
+		for px in row.chunks_exact(4) {
+			dib.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+		}
+	}
+	dib
 }
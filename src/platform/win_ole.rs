@@ -0,0 +1,304 @@
+use super::win::read_u32;
+use crate::common::{string_to_utf16le, utf16le_to_string, Result, VirtualFile, VirtualFileSource};
+use std::ffi::c_void;
+use std::io::{self, Cursor, Read};
+use std::mem::ManuallyDrop;
+use windows::core::{implement, HRESULT};
+use windows::Win32::Foundation::{
+	BOOL, DATA_S_SAMEFORMATETC, DV_E_FORMATETC, DV_E_LINDEX, DV_E_TYMED, E_FAIL, E_NOTIMPL,
+	E_OUTOFMEMORY, HGLOBAL, OLE_E_ADVISENOTSUPPORTED, S_OK,
+};
+use windows::Win32::System::Com::{
+	IAdviseSink, IDataObject, IDataObject_Impl, IEnumFORMATETC, IEnumSTATDATA, IStream,
+	DATADIR_GET, DVASPECT_CONTENT, FORMATETC, STGMEDIUM, STGMEDIUM_0, TYMED_HGLOBAL, TYMED_ISTREAM,
+};
+use windows::Win32::System::Memory::{
+	GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE,
+};
+use windows::Win32::System::Ole::{
+	OleGetClipboard, OleInitialize, OleSetClipboard, ReleaseStgMedium,
+};
+use windows::Win32::UI::Shell::SHCreateStdEnumFmtEtc;
+
+static CF_FILE_DESCRIPTOR: &str = "FileGroupDescriptorW";
+static CF_FILE_CONTENTS: &str = "FileContents";
+
+// layout of FILEDESCRIPTORW
+const FILE_DESCRIPTOR_SIZE: usize = 592;
+const FILE_DESCRIPTOR_SIZE_HIGH_OFFSET: usize = 64;
+const FILE_DESCRIPTOR_SIZE_LOW_OFFSET: usize = 68;
+const FILE_DESCRIPTOR_NAME_OFFSET: usize = 72;
+const FILE_DESCRIPTOR_NAME_LEN: usize = 260;
+const FD_FILESIZE: u32 = 0x40;
+const FD_PROGRESSUI: u32 = 0x4000;
+
+/// Read the files described by `FileGroupDescriptorW`, their bytes come from `FileContents`.
+/// Both have to go through OLE, `GetClipboardData` can only return the first file.
+pub(super) fn get_virtual_files() -> Result<Vec<VirtualFile>> {
+	let descriptor_format = register_format(CF_FILE_DESCRIPTOR)?;
+	let contents_format = register_format(CF_FILE_CONTENTS)?;
+	// S_FALSE when the thread is already initialized, it stays initialized for later calls
+	unsafe { OleInitialize(None) }.map_err(|e| format!("OleInitialize error: {}", e))?;
+	let data_object =
+		unsafe { OleGetClipboard() }.map_err(|e| format!("OleGetClipboard error: {}", e))?;
+
+	let mut descriptor = Vec::new();
+	get_data(&data_object, descriptor_format, -1)?.read_to_end(&mut descriptor)?;
+	let count = read_u32(&descriptor, 0).ok_or("invalid file group descriptor")? as usize;
+	// the count comes from another process, only reserve for the entries the descriptor can hold
+	let mut files = Vec::with_capacity(count.min((descriptor.len() - 4) / FILE_DESCRIPTOR_SIZE));
+	for index in 0..count {
+		let offset = 4 + index * FILE_DESCRIPTOR_SIZE;
+		let entry = descriptor
+			.get(offset..offset + FILE_DESCRIPTOR_SIZE)
+			.ok_or("invalid file group descriptor")?;
+		let flags = read_u32(entry, 0).unwrap_or(0);
+		let size = if flags & FD_FILESIZE != 0 {
+			let high = read_u32(entry, FILE_DESCRIPTOR_SIZE_HIGH_OFFSET).unwrap_or(0) as u64;
+			let low = read_u32(entry, FILE_DESCRIPTOR_SIZE_LOW_OFFSET).unwrap_or(0) as u64;
+			Some(high << 32 | low)
+		} else {
+			None
+		};
+		let name = utf16le_to_string(&entry[FILE_DESCRIPTOR_NAME_OFFSET..]);
+		let reader = get_data(&data_object, contents_format, index as i32)?;
+		// an HGLOBAL can be larger than the file it holds
+		let reader: Box<dyn Read> = match size {
+			Some(size) => Box::new(reader.take(size)),
+			None => reader,
+		};
+		files.push(VirtualFile { name, size, reader });
+	}
+	Ok(files)
+}
+
+/// Put the files on the clipboard through OLE, their bytes are only produced when pasted.
+/// OLE serves paste requests on the calling thread, so it must pump window messages.
+pub(super) fn set_virtual_files(files: Vec<VirtualFileSource>) -> Result<()> {
+	let data_object: IDataObject = VirtualFileDataObject {
+		descriptor_format: register_format(CF_FILE_DESCRIPTOR)? as u16,
+		contents_format: register_format(CF_FILE_CONTENTS)? as u16,
+		files,
+	}
+	.into();
+	unsafe { OleInitialize(None) }.map_err(|e| format!("OleInitialize error: {}", e))?;
+	unsafe { OleSetClipboard(&data_object) }
+		.map_err(|e| format!("OleSetClipboard error: {}", e).into())
+}
+
+fn register_format(name: &str) -> Result<u32> {
+	clipboard_win::register_format(name)
+		.map(|f| f.get())
+		.ok_or_else(|| format!("register {} format error", name).into())
+}
+
+/// Get one format from the data object, `index` selects the file for `FileContents`
+fn get_data(data_object: &IDataObject, format: u32, index: i32) -> Result<Box<dyn Read>> {
+	let format_etc = FORMATETC {
+		cfFormat: format as u16,
+		ptd: std::ptr::null_mut(),
+		dwAspect: DVASPECT_CONTENT.0,
+		lindex: index,
+		tymed: (TYMED_HGLOBAL.0 | TYMED_ISTREAM.0) as u32,
+	};
+	let mut medium = unsafe { data_object.GetData(&format_etc) }
+		.map_err(|e| format!("IDataObject#GetData error: {}", e))?;
+	let reader = unsafe { medium_to_reader(&medium) };
+	unsafe { ReleaseStgMedium(&mut medium) };
+	reader
+}
+
+unsafe fn medium_to_reader(medium: &STGMEDIUM) -> Result<Box<dyn Read>> {
+	if medium.tymed == TYMED_ISTREAM.0 as u32 {
+		// keep our own reference, the medium is released by the caller
+		let stream = (*medium.u.pstm).clone().ok_or("empty stream")?;
+		return Ok(Box::new(StreamReader(stream)));
+	}
+	if medium.tymed == TYMED_HGLOBAL.0 as u32 {
+		let hglobal = medium.u.hGlobal;
+		let ptr = GlobalLock(hglobal) as *const u8;
+		if ptr.is_null() {
+			return Err("GlobalLock error".into());
+		}
+		let bytes = std::slice::from_raw_parts(ptr, GlobalSize(hglobal)).to_vec();
+		let _ = GlobalUnlock(hglobal);
+		return Ok(Box::new(Cursor::new(bytes)));
+	}
+	Err(format!("unsupported storage medium {}", medium.tymed).into())
+}
+
+struct StreamReader(IStream);
+
+impl Read for StreamReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let len = buf.len().min(u32::MAX as usize) as u32;
+		let mut read = 0u32;
+		unsafe {
+			self.0
+				.Read(buf.as_mut_ptr() as *mut c_void, len, Some(&mut read))
+				.ok()
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+		}
+		Ok(read as usize)
+	}
+}
+
+#[implement(IDataObject)]
+struct VirtualFileDataObject {
+	descriptor_format: u16,
+	contents_format: u16,
+	files: Vec<VirtualFileSource>,
+}
+
+impl VirtualFileDataObject {
+	fn formats(&self) -> [FORMATETC; 2] {
+		let format_etc = |format: u16, index: i32| FORMATETC {
+			cfFormat: format,
+			ptd: std::ptr::null_mut(),
+			dwAspect: DVASPECT_CONTENT.0,
+			lindex: index,
+			tymed: TYMED_HGLOBAL.0 as u32,
+		};
+		[
+			format_etc(self.descriptor_format, -1),
+			format_etc(self.contents_format, 0),
+		]
+	}
+
+	fn check(&self, format_etc: &FORMATETC) -> HRESULT {
+		if format_etc.tymed & TYMED_HGLOBAL.0 as u32 == 0 {
+			return DV_E_TYMED;
+		}
+		if format_etc.cfFormat == self.descriptor_format {
+			return S_OK;
+		}
+		if format_etc.cfFormat == self.contents_format {
+			if format_etc.lindex < 0 || format_etc.lindex as usize >= self.files.len() {
+				return DV_E_LINDEX;
+			}
+			return S_OK;
+		}
+		DV_E_FORMATETC
+	}
+
+	fn file_group_descriptor(&self) -> Vec<u8> {
+		let mut data = (self.files.len() as u32).to_le_bytes().to_vec();
+		for file in &self.files {
+			let mut entry = vec![0u8; FILE_DESCRIPTOR_SIZE];
+			let mut flags = FD_PROGRESSUI;
+			if let Some(size) = file.size {
+				flags |= FD_FILESIZE;
+				entry[FILE_DESCRIPTOR_SIZE_HIGH_OFFSET..FILE_DESCRIPTOR_SIZE_HIGH_OFFSET + 4]
+					.copy_from_slice(&((size >> 32) as u32).to_le_bytes());
+				entry[FILE_DESCRIPTOR_SIZE_LOW_OFFSET..FILE_DESCRIPTOR_SIZE_LOW_OFFSET + 4]
+					.copy_from_slice(&(size as u32).to_le_bytes());
+			}
+			entry[0..4].copy_from_slice(&flags.to_le_bytes());
+			// cFileName is a fixed WCHAR[MAX_PATH], keep room for the NUL
+			let name = string_to_utf16le(&file.name, false);
+			let len = name.len().min((FILE_DESCRIPTOR_NAME_LEN - 1) * 2);
+			entry[FILE_DESCRIPTOR_NAME_OFFSET..FILE_DESCRIPTOR_NAME_OFFSET + len]
+				.copy_from_slice(&name[..len]);
+			data.extend(entry);
+		}
+		data
+	}
+
+	fn file_contents(&self, index: usize) -> Result<Vec<u8>> {
+		let file = self.files.get(index).ok_or("invalid file index")?;
+		let mut bytes = Vec::new();
+		(file.provider)()?.read_to_end(&mut bytes)?;
+		Ok(bytes)
+	}
+}
+
+impl IDataObject_Impl for VirtualFileDataObject_Impl {
+	fn GetData(&self, pformatetcin: *const FORMATETC) -> windows::core::Result<STGMEDIUM> {
+		let format_etc = unsafe { pformatetcin.as_ref() }.ok_or(DV_E_FORMATETC)?;
+		self.check(format_etc).ok()?;
+		let bytes = if format_etc.cfFormat == self.descriptor_format {
+			self.file_group_descriptor()
+		} else {
+			// the provider runs here, at paste time
+			self.file_contents(format_etc.lindex as usize)
+				.map_err(|_| windows::core::Error::from(E_FAIL))?
+		};
+		Ok(STGMEDIUM {
+			tymed: TYMED_HGLOBAL.0 as u32,
+			u: STGMEDIUM_0 {
+				hGlobal: unsafe { bytes_to_hglobal(&bytes) }?,
+			},
+			pUnkForRelease: ManuallyDrop::new(None),
+		})
+	}
+
+	fn GetDataHere(
+		&self,
+		_pformatetc: *const FORMATETC,
+		_pmedium: *mut STGMEDIUM,
+	) -> windows::core::Result<()> {
+		Err(E_NOTIMPL.into())
+	}
+
+	fn QueryGetData(&self, pformatetc: *const FORMATETC) -> HRESULT {
+		match unsafe { pformatetc.as_ref() } {
+			Some(format_etc) => self.check(format_etc),
+			None => DV_E_FORMATETC,
+		}
+	}
+
+	fn GetCanonicalFormatEtc(
+		&self,
+		_pformatectin: *const FORMATETC,
+		pformatetcout: *mut FORMATETC,
+	) -> HRESULT {
+		if let Some(out) = unsafe { pformatetcout.as_mut() } {
+			out.ptd = std::ptr::null_mut();
+		}
+		DATA_S_SAMEFORMATETC
+	}
+
+	fn SetData(
+		&self,
+		_pformatetc: *const FORMATETC,
+		_pmedium: *const STGMEDIUM,
+		_frelease: BOOL,
+	) -> windows::core::Result<()> {
+		Err(E_NOTIMPL.into())
+	}
+
+	fn EnumFormatEtc(&self, dwdirection: u32) -> windows::core::Result<IEnumFORMATETC> {
+		if dwdirection != DATADIR_GET.0 as u32 {
+			return Err(E_NOTIMPL.into());
+		}
+		unsafe { SHCreateStdEnumFmtEtc(&self.formats()) }
+	}
+
+	fn DAdvise(
+		&self,
+		_pformatetc: *const FORMATETC,
+		_advf: u32,
+		_padvsink: Option<&IAdviseSink>,
+	) -> windows::core::Result<u32> {
+		Err(OLE_E_ADVISENOTSUPPORTED.into())
+	}
+
+	fn DUnadvise(&self, _dwconnection: u32) -> windows::core::Result<()> {
+		Err(OLE_E_ADVISENOTSUPPORTED.into())
+	}
+
+	fn EnumDAdvise(&self) -> windows::core::Result<IEnumSTATDATA> {
+		Err(OLE_E_ADVISENOTSUPPORTED.into())
+	}
+}
+
+unsafe fn bytes_to_hglobal(bytes: &[u8]) -> windows::core::Result<HGLOBAL> {
+	let hglobal = GlobalAlloc(GMEM_MOVEABLE, bytes.len())?;
+	let ptr = GlobalLock(hglobal) as *mut u8;
+	if ptr.is_null() {
+		return Err(E_OUTOFMEMORY.into());
+	}
+	std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+	let _ = GlobalUnlock(hglobal);
+	Ok(hglobal)
+}
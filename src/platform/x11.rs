@@ -1,21 +1,28 @@
 use crate::{
-	common::{Result, RustImage},
-	ClipboardContent, ClipboardHandler, ContentFormat, RustImageData,
+	common::{
+		appended_text, convert, decode_utf16le, decode_utf8, file_uri_to_path, format_mime,
+		latin1_to_string, notify_handlers, path_to_file_uri, string_to_utf16le, utf16le_to_string,
+		FileOperation, FormatHandle, FormatInfo, Result, Rgba, RustImage, TextDecoding,
+	},
+	ClipboardContent, ClipboardHandler, ContentFormat, RustImageData, Selection, Url,
 };
-use crate::{Clipboard, ClipboardWatcher};
-use std::sync::mpsc::{self, Receiver, Sender};
+use crate::{stats, Clipboard, ClipboardWatcher, WatcherShutdown};
+use std::io::{self, Cursor, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::{
-	sync::{Arc, RwLock},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, RwLock},
 	thread,
 	time::{Duration, Instant},
 };
 use x11rb::{
-	connection::Connection,
+	connection::{Connection, RequestConnection as _},
 	protocol::{
 		xfixes,
 		xproto::{
-			Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, Property,
-			SelectionNotifyEvent, SelectionRequestEvent, WindowClass, SELECTION_NOTIFY_EVENT,
+			Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt as _,
+			CreateWindowAux, EventMask, PropMode, Property, SelectionNotifyEvent,
+			SelectionRequestEvent, Window, WindowClass, SELECTION_NOTIFY_EVENT,
 		},
 		Event,
 	},
@@ -39,6 +46,8 @@ x11rb::atom_manager! {
 		UTF8_STRING,
 		UTF8_MIME_0: b"text/plain;charset=utf-8",
 		UTF8_MIME_1: b"text/plain;charset=UTF-8",
+		// published by Java
+		UTF16_MIME: b"text/plain;charset=utf-16",
 		// Text in ISO Latin-1 encoding
 		// See: https://tronche.com/gui/x/icccm/sec-2.html#s-2.6.2
 		STRING,
@@ -51,15 +60,82 @@ x11rb::atom_manager! {
 		RTF_1: b"text/richtext",
 		HTML: b"text/html",
 		PNG_MIME: b"image/png",
+		SVG_MIME: b"image/svg+xml",
+		MARKDOWN: b"text/markdown",
+		GIF_MIME: b"image/gif",
 		FILE_LIST: b"text/uri-list",
+		MOZ_URL: b"text/x-moz-url",
+		// the page html was copied from, by Firefox in UTF-16 and by Chromium
+		MOZ_URL_PRIV: b"text/x-moz-url-priv",
+		CHROMIUM_SOURCE_URL: b"chromium/x-source-url",
+		// colors of GIMP (GTK layout) and Krita (a streamed QColor)
+		COLOR: b"application/x-color",
+		PDF: b"application/pdf",
 		GNOME_COPY_FILES: b"x-special/gnome-copied-files",
 		NAUTILUS_FILE_LIST: b"x-special/nautilus-clipboard",
+		// "1" when the files were cut in dolphin
+		KDE_CUT_SELECTION: b"application/x-kde-cutselection",
 	}
 }
 
 const FILE_PATH_PREFIX: &str = "file://";
+const NAUTILUS_HEADER: &str = "x-special/nautilus-clipboard";
+const READ_ATTEMPTS: usize = 3;
+// an INCR requestor that stops deleting the property is given up after this long
+const INCR_TIMEOUT: Duration = Duration::from_secs(10);
+const PERSIST_TIMEOUT: Duration = Duration::from_secs(5);
+// clones share the connections and the content we serve
+#[derive(Clone)]
 pub struct ClipboardContext {
 	inner: Arc<InnerContext>,
+	// answers other applications' requests for the content we set, for as long as the process runs,
+	// so pastes work whatever the thread that set the content is doing
+	owner_thread: Arc<thread::JoinHandle<()>>,
+}
+
+/// The X11 objects a `ClipboardContext` reads the selection with
+#[cfg(feature = "raw-handle")]
+pub struct RawHandle<'a> {
+	pub connection: &'a RustConnection,
+	/// The window converted selections are delivered to
+	pub window: u32,
+	/// The CLIPBOARD atom
+	pub selection: Atom,
+	/// The property converted selections are stored in
+	pub property: Atom,
+}
+
+/// How the bytes of a text target are encoded
+#[derive(Clone, Copy)]
+enum TextCharset {
+	Utf8,
+	/// With a byte order mark, little endian without one
+	Utf16,
+	Latin1,
+	/// TEXT and bare text/plain, UTF-8 when valid and Latin-1 otherwise
+	Unknown,
+}
+
+fn decode_text(data: &[u8], charset: TextCharset, decoding: TextDecoding) -> Result<String> {
+	match charset {
+		TextCharset::Utf8 => decode_utf8(data, decoding),
+		TextCharset::Utf16 => {
+			let text = match data {
+				[0xfe, 0xff, rest @ ..] => {
+					let swapped: Vec<u8> =
+						rest.chunks_exact(2).flat_map(|c| [c[1], c[0]]).collect();
+					decode_utf16le(&swapped, decoding)?
+				}
+				[0xff, 0xfe, rest @ ..] => decode_utf16le(rest, decoding)?,
+				_ => decode_utf16le(data, decoding)?,
+			};
+			Ok(text)
+		}
+		TextCharset::Latin1 => Ok(latin1_to_string(data)),
+		TextCharset::Unknown => {
+			decode_utf8(data, TextDecoding::Strict).or_else(|_| Ok(latin1_to_string(data)))
+		}
+	}
 }
 
 struct ClipboardData {
@@ -67,12 +143,22 @@ struct ClipboardData {
 	data: Vec<u8>,
 }
 
+/// An outgoing INCR transfer, the next chunk is sent when the requestor deletes the property
+struct IncrTransfer {
+	requestor: Window,
+	property: Atom,
+	data: Arc<ClipboardData>,
+	offset: usize,
+	last_activity: Instant,
+}
+
 struct InnerContext {
 	server: XServerContext,
 	server_for_write: XServerContext,
 	ignore_formats: Vec<Atom>,
 	// 此刻待写入的剪贴板内容
-	wait_write_data: RwLock<Vec<ClipboardData>>,
+	wait_write_data: RwLock<Vec<Arc<ClipboardData>>>,
+	incr_transfers: Mutex<Vec<IncrTransfer>>,
 }
 
 impl InnerContext {
@@ -93,58 +179,53 @@ impl InnerContext {
 			server_for_write,
 			ignore_formats,
 			wait_write_data,
+			incr_transfers: Mutex::new(Vec::new()),
 		})
 	}
 
+	/// Largest property we write in one request, bigger data is sent with INCR
+	fn incr_chunk_size(&self) -> usize {
+		// a quarter of the request limit leaves plenty of room for the request header
+		self.server_for_write.conn.maximum_request_bytes() / 4
+	}
+
 	pub fn handle_selection_request(&self, event: SelectionRequestEvent) -> Result<()> {
-		let success;
 		let ctx = &self.server_for_write;
 		let atoms = ctx.atoms;
-		// we are asked for a list of supported conversion targets
-		if event.target == atoms.TARGETS {
-			let reader = self.wait_write_data.read();
-			match reader {
-				Ok(data_list) => {
-					let mut targets = Vec::with_capacity(10);
-					targets.push(atoms.TARGETS);
-					targets.push(atoms.SAVE_TARGETS);
-					if data_list.len() > 0 {
-						data_list.iter().for_each(|data| {
-							targets.push(data.format);
-						});
-					}
-					ctx.conn.change_property32(
-						PropMode::REPLACE,
-						event.requestor,
-						event.property,
-						AtomEnum::ATOM,
-						&targets,
-					)?;
-					success = true;
+		let success = if event.target == atoms.MULTIPLE {
+			// the property holds (target, property) pairs, failed conversions get a None property
+			// See: https://tronche.com/gui/x/icccm/sec-2.html#s-2.6.2
+			let reply = ctx
+				.conn
+				.get_property(
+					false,
+					event.requestor,
+					event.property,
+					AtomEnum::ANY,
+					0,
+					u32::MAX,
+				)?
+				.reply()?;
+			let mut pairs: Vec<Atom> = match reply.value32() {
+				Some(value) => value.collect(),
+				None => Vec::new(),
+			};
+			for pair in pairs.chunks_exact_mut(2) {
+				if !self.convert_target(event.requestor, pair[0], pair[1])? {
+					pair[1] = AtomEnum::NONE.into();
 				}
-				Err(_) => return Err("Failed to read clipboard data".into()),
 			}
+			ctx.conn.change_property32(
+				PropMode::REPLACE,
+				event.requestor,
+				event.property,
+				reply.type_,
+				&pairs,
+			)?;
+			!pairs.is_empty()
 		} else {
-			let reader = self.wait_write_data.read();
-			match reader {
-				Ok(data_list) => {
-					success = match data_list.iter().find(|d| d.format == event.target) {
-						Some(data) => {
-							ctx.conn.change_property8(
-								PropMode::REPLACE,
-								event.requestor,
-								event.property,
-								event.target,
-								&data.data,
-							)?;
-							true
-						}
-						None => false,
-					};
-				}
-				Err(_) => return Err("Failed to read clipboard data".into()),
-			}
-		}
+			self.convert_target(event.requestor, event.target, event.property)?
+		};
 		// on failure, we notify the requester of it
 		let property = if success {
 			event.property
@@ -170,6 +251,167 @@ impl InnerContext {
 		Ok(())
 	}
 
+	/// Write one target into the requestor's property, returns false when we don't hold it
+	fn convert_target(&self, requestor: Window, target: Atom, property: Atom) -> Result<bool> {
+		let ctx = &self.server_for_write;
+		let atoms = ctx.atoms;
+		let data_list = self
+			.wait_write_data
+			.read()
+			.map_err(|_| "Failed to read clipboard data")?;
+		// we are asked for a list of supported conversion targets
+		if target == atoms.TARGETS {
+			let mut targets = Vec::with_capacity(10);
+			targets.push(atoms.TARGETS);
+			targets.push(atoms.SAVE_TARGETS);
+			targets.push(atoms.MULTIPLE);
+			data_list.iter().for_each(|data| {
+				targets.push(data.format);
+			});
+			ctx.conn.change_property32(
+				PropMode::REPLACE,
+				requestor,
+				property,
+				AtomEnum::ATOM,
+				&targets,
+			)?;
+			return Ok(true);
+		}
+		match data_list.iter().find(|d| d.format == target) {
+			Some(data) if data.data.len() > self.incr_chunk_size() => {
+				self.start_incr_transfer(requestor, property, data.clone())?;
+				Ok(true)
+			}
+			Some(data) => {
+				ctx.conn.change_property8(
+					PropMode::REPLACE,
+					requestor,
+					property,
+					target,
+					&data.data,
+				)?;
+				Ok(true)
+			}
+			None => Ok(false),
+		}
+	}
+
+	/// Announce an INCR transfer, chunks follow each time the requestor deletes the property.
+	/// See: https://tronche.com/gui/x/icccm/sec-2.html#s-2.7.2
+	fn start_incr_transfer(
+		&self,
+		requestor: Window,
+		property: Atom,
+		data: Arc<ClipboardData>,
+	) -> Result<()> {
+		let ctx = &self.server_for_write;
+		let mut transfers = self
+			.incr_transfers
+			.lock()
+			.map_err(|_| "Failed to lock INCR transfers")?;
+		transfers.retain(|t| t.last_activity.elapsed() < INCR_TIMEOUT);
+		ctx.conn.change_window_attributes(
+			requestor,
+			&ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+		)?;
+		ctx.conn.change_property32(
+			PropMode::REPLACE,
+			requestor,
+			property,
+			ctx.atoms.INCR,
+			&[data.data.len() as u32],
+		)?;
+		transfers.push(IncrTransfer {
+			requestor,
+			property,
+			data,
+			offset: 0,
+			last_activity: Instant::now(),
+		});
+		Ok(())
+	}
+
+	/// Wait for a SelectionNotify without reading any data, returns whether the conversion succeeded
+	pub fn wait_for_notify(
+		&self,
+		selection: Atom,
+		timeout: Duration,
+		sequence_number: u64,
+	) -> Result<bool> {
+		let start_time = Instant::now();
+		let ctx = &self.server;
+		loop {
+			if Instant::now() - start_time >= timeout {
+				return Err("Timeout while waiting for the selection owner".into());
+			}
+
+			let (event, seq) = match ctx.conn.poll_for_event_with_sequence()? {
+				Some(event) => event,
+				None => {
+					thread::park_timeout(Duration::from_millis(50));
+					continue;
+				}
+			};
+
+			if seq < sequence_number {
+				continue;
+			}
+
+			if let Event::SelectionNotify(event) = event {
+				if event.selection != selection {
+					continue;
+				}
+				if event.property == u32::from(AtomEnum::NONE) {
+					return Ok(false);
+				}
+				ctx.conn
+					.delete_property(ctx.win_id, event.property)?
+					.check()?;
+				return Ok(true);
+			}
+		}
+	}
+
+	/// Send the next chunk of an INCR transfer once the requestor deleted the previous one
+	pub fn continue_incr_transfer(&self, requestor: Window, property: Atom) -> Result<()> {
+		let ctx = &self.server_for_write;
+		let mut transfers = self
+			.incr_transfers
+			.lock()
+			.map_err(|_| "Failed to lock INCR transfers")?;
+		let index = match transfers
+			.iter()
+			.position(|t| t.requestor == requestor && t.property == property)
+		{
+			Some(index) => index,
+			None => return Ok(()),
+		};
+		let chunk_size = self.incr_chunk_size();
+		let transfer = &mut transfers[index];
+		let data = &transfer.data;
+		let end = (transfer.offset + chunk_size).min(data.data.len());
+		ctx.conn.change_property8(
+			PropMode::REPLACE,
+			requestor,
+			property,
+			data.format,
+			&data.data[transfer.offset..end],
+		)?;
+		if transfer.offset == end {
+			// the zero length chunk just written ends the transfer
+			transfers.remove(index);
+			ctx.conn.change_window_attributes(
+				requestor,
+				&ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+			)?;
+		} else {
+			transfer.offset = end;
+			transfer.last_activity = Instant::now();
+		}
+		ctx.conn.flush()?;
+		Ok(())
+	}
+
 	pub fn process_event(
 		&self,
 		buff: &mut Vec<u8>,
@@ -179,22 +421,34 @@ impl InnerContext {
 		timeout: Option<Duration>,
 		sequence_number: u64,
 	) -> Result<()> {
-		let mut is_incr = false;
-		let start_time = if timeout.is_some() {
-			Some(Instant::now())
-		} else {
-			None
-		};
+		match self.wait_for_selection(selection, target, property, timeout, sequence_number)? {
+			Some(data) => buff.extend_from_slice(&data),
+			None => loop {
+				let chunk = self.next_incr_chunk(target, property, timeout)?;
+				if chunk.is_empty() {
+					break;
+				}
+				buff.extend_from_slice(&chunk);
+			},
+		}
+		Ok(())
+	}
+
+	/// Wait for the owner to answer a conversion. Returns the data, or `None` when the owner
+	/// starts an INCR transfer whose chunks then come from `next_incr_chunk`.
+	pub fn wait_for_selection(
+		&self,
+		selection: Atom,
+		target: Atom,
+		property: Atom,
+		timeout: Option<Duration>,
+		sequence_number: u64,
+	) -> Result<Option<Vec<u8>>> {
+		let start_time = Instant::now();
 		let ctx = &self.server;
 		let atoms = ctx.atoms;
 		loop {
-			if timeout
-				.into_iter()
-				.zip(start_time)
-				.next()
-				.map(|(timeout, time)| (Instant::now() - time) >= timeout)
-				.unwrap_or(false)
-			{
+			if timeout.map_or(false, |timeout| Instant::now() - start_time >= timeout) {
 				return Err("Timeout while waiting for clipboard data".into());
 			}
 
@@ -210,84 +464,147 @@ impl InnerContext {
 				continue;
 			}
 
-			match event {
-				Event::SelectionNotify(event) => {
-					if event.selection != selection {
-						continue;
-					};
+			if let Event::SelectionNotify(event) = event {
+				if event.selection != selection {
+					continue;
+				};
 
-					let target_type = {
-						if target == atoms.TARGETS {
-							atoms.ATOM
-						} else {
-							target
-						}
-					};
+				let target_type = {
+					if target == atoms.TARGETS {
+						atoms.ATOM
+					} else {
+						target
+					}
+				};
 
-					let reply = ctx
-						.conn
-						.get_property(
-							false,
-							event.requestor,
-							event.property,
-							target_type,
-							buff.len() as u32,
-							u32::MAX,
-						)?
-						.reply()?;
+				let reply = ctx
+					.conn
+					.get_property(
+						false,
+						event.requestor,
+						event.property,
+						target_type,
+						0,
+						u32::MAX,
+					)?
+					.reply()?;
+
+				if reply.type_ == atoms.INCR {
+					// deleting the property asks the owner for the first chunk
+					ctx.conn.delete_property(ctx.win_id, property)?.check()?;
+					return Ok(None);
+				} else if reply.type_ != target && reply.type_ != atoms.ATOM {
+					return Err("Clipboard data type mismatch".into());
+				}
+				return Ok(Some(reply.value));
+			}
+		}
+	}
 
-					if reply.type_ == atoms.INCR {
-						if let Some(mut value) = reply.value32() {
-							if let Some(size) = value.next() {
-								buff.reserve(size as usize);
-							}
-						}
-						ctx.conn.delete_property(ctx.win_id, property)?.check()?;
-						is_incr = true;
-						continue;
-					} else if reply.type_ != target && reply.type_ != atoms.ATOM {
-						return Err("Clipboard data type mismatch".into());
-					}
-					buff.extend_from_slice(&reply.value);
-					break;
+	/// Wait for the next chunk of an INCR transfer, an empty chunk ends the transfer
+	pub fn next_incr_chunk(
+		&self,
+		target: Atom,
+		property: Atom,
+		timeout: Option<Duration>,
+	) -> Result<Vec<u8>> {
+		let start_time = Instant::now();
+		let ctx = &self.server;
+		loop {
+			if timeout.map_or(false, |timeout| Instant::now() - start_time >= timeout) {
+				return Err("Timeout while waiting for clipboard data".into());
+			}
+
+			let event = match ctx.conn.poll_for_event()? {
+				Some(event) => event,
+				None => {
+					thread::park_timeout(Duration::from_millis(50));
+					continue;
 				}
+			};
 
-				Event::PropertyNotify(event) if is_incr => {
-					if event.state != Property::NEW_VALUE {
-						continue;
-					};
+			if let Event::PropertyNotify(event) = event {
+				if event.state != Property::NEW_VALUE || event.atom != property {
+					continue;
+				};
 
-					let cookie =
-						ctx.conn
-							.get_property(false, ctx.win_id, property, AtomEnum::ATOM, 0, 0)?;
+				let cookie =
+					ctx.conn
+						.get_property(false, ctx.win_id, property, AtomEnum::ATOM, 0, 0)?;
 
-					let length = cookie.reply()?.bytes_after;
+				let length = cookie.reply()?.bytes_after;
 
-					let cookie = ctx.conn.get_property(
-						true,
-						ctx.win_id,
-						property,
-						AtomEnum::NONE,
-						0,
-						length,
-					)?;
-					let reply = cookie.reply()?;
-					if reply.type_ != target {
-						continue;
-					};
+				// reading with delete asks the owner for the next chunk
+				let cookie =
+					ctx.conn
+						.get_property(true, ctx.win_id, property, AtomEnum::NONE, 0, length)?;
+				let reply = cookie.reply()?;
+				if reply.type_ != target {
+					continue;
+				};
+				return Ok(reply.value);
+			}
+		}
+	}
+
+	/// Wait for the answer to a conversion and report the size of the data without fetching it.
+	/// INCR transfers only announce a lower bound of their size.
+	pub fn process_size_event(
+		&self,
+		selection: Atom,
+		timeout: Duration,
+		sequence_number: u64,
+	) -> Result<usize> {
+		let start_time = Instant::now();
+		let ctx = &self.server;
+		let atoms = ctx.atoms;
+		loop {
+			if Instant::now() - start_time >= timeout {
+				return Err("Timeout while waiting for clipboard data".into());
+			}
 
-					let value = reply.value;
+			let (event, seq) = match ctx.conn.poll_for_event_with_sequence()? {
+				Some(event) => event,
+				None => {
+					thread::park_timeout(Duration::from_millis(50));
+					continue;
+				}
+			};
 
-					if !value.is_empty() {
-						buff.extend_from_slice(&value);
-					} else {
-						break;
-					}
+			if seq < sequence_number {
+				continue;
+			}
+
+			if let Event::SelectionNotify(event) = event {
+				if event.selection != selection {
+					continue;
 				}
-				_ => (),
+				if event.property == u32::from(AtomEnum::NONE) {
+					return Err("Clipboard data not available".into());
+				}
+				// a zero length request only returns the type and the number of bytes left
+				let reply = ctx
+					.conn
+					.get_property(false, event.requestor, event.property, AtomEnum::ANY, 0, 0)?
+					.reply()?;
+				if reply.type_ == atoms.INCR {
+					let reply = ctx
+						.conn
+						.get_property(false, event.requestor, event.property, atoms.INCR, 0, 1)?
+						.reply()?;
+					// the property is left in place, deleting it would start the transfer
+					return reply
+						.value32()
+						.and_then(|mut value| value.next())
+						.map(|size| size as usize)
+						.ok_or_else(|| "Invalid INCR size".into());
+				}
+				ctx.conn
+					.delete_property(event.requestor, event.property)?
+					.check()?;
+				return Ok(reply.bytes_after as usize);
 			}
 		}
-		Ok(())
 	}
 }
 
@@ -298,19 +615,100 @@ impl ClipboardContext {
 		let ctx_arc = Arc::new(ctx);
 		let ctx_clone = ctx_arc.clone();
 
-		thread::spawn(move || {
-			let res = process_server_req(&ctx_clone);
-			if let Err(e) = res {
-				println!("process_server_req error: {:?}", e);
+		let owner_thread = thread::Builder::new()
+			.name("clipboard-x11-owner".to_string())
+			.spawn(move || {
+				let res = process_server_req(&ctx_clone);
+				if let Err(e) = res {
+					eprintln!("process_server_req error: {:?}", e);
+				}
+			})?;
+		Ok(Self {
+			inner: ctx_arc,
+			owner_thread: Arc::new(owner_thread),
+		})
+	}
+
+	/// Whether the thread serving the content we set is still running, it only stops when the
+	/// connection to the X server is lost
+	pub fn is_serving(&self) -> bool {
+		!self.owner_thread.is_finished()
+	}
+
+	/// Hand the content we own over to the clipboard manager, so it outlives this process.
+	/// Call it before exiting, it does nothing when no manager runs or another application owns the clipboard.
+	/// Only X11 clipboard managers are supported, under Wayland this works through XWayland alone
+	/// and native Wayland managers (wlr-data-control) don't receive the content.
+	/// See: https://freedesktop.org/wiki/ClipboardManager/
+	pub fn persist(&self) -> Result<()> {
+		let ctx = &self.inner.server;
+		let atoms = ctx.atoms;
+		if self.selection_owner()? != self.inner.server_for_write.win_id {
+			return Ok(());
+		}
+		let manager = ctx
+			.conn
+			.get_selection_owner(atoms.CLIPBOARD_MANAGER)?
+			.reply()?
+			.owner;
+		if manager == x11rb::NONE {
+			return Ok(());
+		}
+		let cookie = ctx.conn.convert_selection(
+			ctx.win_id,
+			atoms.CLIPBOARD_MANAGER,
+			atoms.SAVE_TARGETS,
+			atoms.PROPERTY,
+			CURRENT_TIME,
+		)?;
+		let sequence_num = cookie.sequence_number();
+		cookie.check()?;
+		// the manager converts every target before answering, which takes a while for large content
+		if self
+			.inner
+			.wait_for_notify(atoms.CLIPBOARD_MANAGER, PERSIST_TIMEOUT, sequence_num)?
+		{
+			Ok(())
+		} else {
+			Err("Clipboard manager refused to save the clipboard".into())
+		}
+	}
+
+	/// The connection, window and atoms used for reading, to send requests the crate doesn't wrap
+	///
+	/// # Safety
+	/// The events of the connection are consumed by this context, requests that change the
+	/// window or its properties or wait for events can break its reads
+	#[cfg(feature = "raw-handle")]
+	pub unsafe fn raw_handle(&self) -> RawHandle<'_> {
+		let ctx = &self.inner.server;
+		RawHandle {
+			connection: &ctx.conn,
+			window: ctx.win_id,
+			selection: ctx.atoms.CLIPBOARD,
+			property: ctx.atoms.PROPERTY,
+		}
+	}
+
+	/// Get the text of the primary selection, the text last highlighted in any app
+	pub fn get_primary_text(&self) -> Result<String> {
+		let mut last_error = None;
+		for (atom, charset) in self.text_targets().iter() {
+			match self.read_selection(AtomEnum::PRIMARY.into(), atom) {
+				Ok(data) => return decode_text(&data, *charset, TextDecoding::Lossy),
+				Err(e) => last_error = Some(e),
 			}
-		});
-		Ok(Self { inner: ctx_arc })
+		}
+		Err(last_error.unwrap_or_else(|| "no text in primary selection".into()))
 	}
 
 	fn read(&self, format: &Atom) -> Result<Vec<u8>> {
+		self.read_selection(self.inner.server.atoms.CLIPBOARD, format)
+	}
+
+	fn read_selection(&self, clipboard: Atom, format: &Atom) -> Result<Vec<u8>> {
 		let ctx = &self.inner.server;
 		let atoms = ctx.atoms;
-		let clipboard = atoms.CLIPBOARD;
 		let win_id = ctx.win_id;
 		let cookie =
 			ctx.conn
@@ -329,16 +727,175 @@ impl ClipboardContext {
 		)?;
 
 		ctx.conn.delete_property(win_id, atoms.PROPERTY)?.check()?;
+		trace_debug!(target = *format, bytes = buff.len(), "selection read");
 
 		Ok(buff)
 	}
 
-	fn write(&self, data: Vec<ClipboardData>) -> Result<()> {
+	fn targets(&self) -> Result<Vec<Atom>> {
+		let ctx = &self.inner.server;
+		self.read(&ctx.atoms.TARGETS)
+			.map(|data| parse_atom_list(&data))
+	}
+
+	/// Whether the offered targets include `format`
+	fn targets_have(&self, formats: &[Atom], format: &ContentFormat) -> bool {
+		let ctx = &self.inner.server;
+		let atoms = ctx.atoms;
+		match format {
+			ContentFormat::Text => self
+				.text_targets()
+				.iter()
+				.any(|(atom, _)| formats.contains(atom)),
+			ContentFormat::Rtf => formats.contains(&atoms.RTF),
+			ContentFormat::Html => formats.contains(&atoms.HTML),
+			ContentFormat::Svg => formats.contains(&atoms.SVG_MIME),
+			ContentFormat::Markdown => formats.contains(&atoms.MARKDOWN),
+			ContentFormat::Image => formats.contains(&atoms.PNG_MIME),
+			ContentFormat::Files => {
+				formats.contains(&atoms.FILE_LIST)
+					|| formats.contains(&atoms.GNOME_COPY_FILES)
+					|| formats.contains(&atoms.NAUTILUS_FILE_LIST)
+			}
+			ContentFormat::Color => formats.contains(&atoms.COLOR),
+			ContentFormat::Pdf => formats.contains(&atoms.PDF),
+			ContentFormat::Url => {
+				formats.contains(&atoms.MOZ_URL)
+					|| (formats.contains(&atoms.FILE_LIST) && self.get_urls().is_ok())
+			}
+			ContentFormat::Other(format_name) => {
+				let atom = ctx.get_atom(format_name.as_str());
+				match atom {
+					Ok(atom) => formats.contains(&atom),
+					Err(_) => false,
+				}
+			}
+			ContentFormat::Custom(handle) => match handle.platform_id() {
+				Some(atom) => formats.contains(&atom),
+				None => ctx
+					.get_atom(handle.name())
+					.map_or(false, |atom| formats.contains(&atom)),
+			},
+		}
+	}
+
+	fn selection_owner(&self) -> Result<u32> {
+		let ctx = &self.inner.server;
+		Ok(ctx
+			.conn
+			.get_selection_owner(ctx.atoms.CLIPBOARD)?
+			.reply()?
+			.owner)
+	}
+
+	/// The text targets in the order `get_text` prefers them. Owners offering several targets
+	/// may fill the legacy ones lossily, e.g. STRING without the characters Latin-1 can't hold,
+	/// so the targets with a known Unicode charset come first
+	fn text_targets(&self) -> [(Atom, TextCharset); 7] {
+		let atoms = self.inner.server.atoms;
+		[
+			(atoms.UTF8_STRING, TextCharset::Utf8),
+			(atoms.UTF8_MIME_0, TextCharset::Utf8),
+			(atoms.UTF8_MIME_1, TextCharset::Utf8),
+			(atoms.UTF16_MIME, TextCharset::Utf16),
+			(atoms.STRING, TextCharset::Latin1),
+			(atoms.TEXT_MIME_UNKNOWN, TextCharset::Unknown),
+			(atoms.TEXT, TextCharset::Unknown),
+		]
+	}
+
+	/// Read the most preferred text target the owner offers, see `text_targets`
+	fn read_text(&self, decoding: TextDecoding) -> Result<String> {
+		let targets = self.targets().ok();
+		let mut last_error = None;
+		for (atom, charset) in self.text_targets().iter() {
+			// without a target list every target is tried in turn
+			if targets
+				.as_ref()
+				.map_or(false, |targets| !targets.contains(atom))
+			{
+				continue;
+			}
+			match self.read(atom) {
+				Ok(data) => return decode_text(&data, *charset, decoding),
+				Err(e) => last_error = Some(e),
+			}
+		}
+		Err(last_error.unwrap_or_else(|| "no text in clipboard".into()))
+	}
+
+	/// The `file://` URIs of the copied files, from `text/uri-list` or, for owners that only
+	/// offer the file manager targets, `x-special/gnome-copied-files` and `x-special/nautilus-clipboard`
+	fn file_uris(&self) -> Result<Vec<String>> {
+		let atoms = self.inner.server.atoms;
+		if let Ok(data) = self.read(&atoms.FILE_LIST) {
+			return Ok(String::from_utf8_lossy(&data)
+				.lines()
+				.map(str::trim)
+				.filter(|line| line.starts_with(FILE_PATH_PREFIX))
+				.map(str::to_owned)
+				.collect());
+		}
+		for target in [atoms.GNOME_COPY_FILES, atoms.NAUTILUS_FILE_LIST] {
+			if let Ok(data) = self.read(&target) {
+				return Ok(parse_gnome_copied_files(&data).1);
+			}
+		}
+		Err("No files found".into())
+	}
+
+	fn read_content(&self, format: &ContentFormat) -> Result<ClipboardContent> {
+		let atoms = self.inner.server.atoms;
+		match format {
+			ContentFormat::Text => self
+				.read_text(TextDecoding::Lossy)
+				.map(ClipboardContent::Text),
+			ContentFormat::Rtf => self
+				.read(&atoms.RTF)
+				.map(|data| ClipboardContent::Rtf(String::from_utf8_lossy(&data).to_string())),
+			ContentFormat::Html => self
+				.read(&atoms.HTML)
+				.map(|data| ClipboardContent::Html(String::from_utf8_lossy(&data).to_string())),
+			ContentFormat::Markdown => self
+				.read(&atoms.MARKDOWN)
+				.map(|data| ClipboardContent::Markdown(String::from_utf8_lossy(&data).to_string())),
+			ContentFormat::Svg => self
+				.read(&atoms.SVG_MIME)
+				.map(|data| ClipboardContent::Svg(String::from_utf8_lossy(&data).to_string())),
+			ContentFormat::Image => self.get_image().map(ClipboardContent::Image),
+			ContentFormat::Files => self.get_files().map(ClipboardContent::Files),
+			ContentFormat::Url => self.get_urls().map(ClipboardContent::Urls),
+			ContentFormat::Pdf => self.read(&atoms.PDF).map(ClipboardContent::Pdf),
+			ContentFormat::Color => self
+				.read(&atoms.COLOR)
+				.and_then(|data| Rgba::from_x_color(&data))
+				.map(ClipboardContent::Color),
+			ContentFormat::Other(format_name) => self
+				.get_buffer(format_name)
+				.map(|buffer| ClipboardContent::Other(format_name.clone(), buffer)),
+			ContentFormat::Custom(handle) => self
+				.get_buffer_by_handle(handle)
+				.map(|buffer| ClipboardContent::Custom(handle.clone(), buffer)),
+		}
+	}
+
+	/// Publish all data at once, the order of `data` is the order of the TARGETS we serve.
+	fn write(&self, mut data: Vec<ClipboardData>) -> Result<()> {
+		// a target can only be served once, the first occurrence wins
+		let mut seen = Vec::with_capacity(data.len());
+		data.retain(|d| {
+			if seen.contains(&d.format) {
+				false
+			} else {
+				seen.push(d.format);
+				true
+			}
+		});
 		let writer = self.inner.wait_write_data.write();
 		match writer {
 			Ok(mut writer) => {
 				writer.clear();
-				writer.extend(data);
+				writer.extend(data.into_iter().map(Arc::new));
 			}
 			Err(_) => return Err("Failed to write clipboard data".into()),
 		}
@@ -365,6 +922,37 @@ impl ClipboardContext {
 	}
 }
 
+/// Streams a selection, INCR chunks are pulled from the owner as they are read
+struct SelectionReader {
+	inner: Arc<InnerContext>,
+	target: Atom,
+	chunk: Cursor<Vec<u8>>,
+	// whether the owner has more INCR chunks to send
+	pending: bool,
+}
+
+impl Read for SelectionReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		loop {
+			let n = self.chunk.read(buf)?;
+			if n > 0 || !self.pending || buf.is_empty() {
+				return Ok(n);
+			}
+			let atoms = self.inner.server.atoms;
+			let chunk = self
+				.inner
+				.next_incr_chunk(
+					self.target,
+					atoms.PROPERTY,
+					Some(Duration::from_millis(500)),
+				)
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+			self.pending = !chunk.is_empty();
+			self.chunk = Cursor::new(chunk);
+		}
+	}
+}
+
 fn process_server_req(context: &InnerContext) -> Result<()> {
 	let atoms = context.server_for_write.atoms;
 	loop {
@@ -376,38 +964,46 @@ fn process_server_req(context: &InnerContext) -> Result<()> {
 		{
 			Event::DestroyNotify(_) => {
 				// This window is being destroyed.
-				println!("Clipboard server window is being destroyed x_x");
+				trace_warn!("clipboard owner window destroyed");
 				break;
 			}
-			Event::SelectionClear(event) => {
+			Event::SelectionClear(event) if event.selection == atoms.CLIPBOARD => {
 				// Someone else has new content in the clipboard, so it is
 				// notifying us that we should delete our data now.
-				println!("Somebody else owns the clipboard now");
-				if event.selection == atoms.CLIPBOARD {
-					// Clear the clipboard contents
-					context
-						.wait_write_data
-						.write()
-						.map(|mut writer| writer.clear())
-						.map_err(|e| format!("write clipboard data error: {:?}", e))?;
-				}
+				trace_debug!("clipboard ownership lost");
+				context
+					.wait_write_data
+					.write()
+					.map(|mut writer| writer.clear())
+					.map_err(|e| format!("write clipboard data error: {:?}", e))?;
 			}
 			Event::SelectionRequest(event) => {
 				// Someone is requesting the clipboard content from us.
-				context
-					.handle_selection_request(event)
-					.map_err(|e| format!("handle_selection_request error: {:?}", e))?;
-			}
-			Event::SelectionNotify(event) => {
-				// We've requested the clipboard content and this is the answer.
-				// Considering that this thread is not responsible for reading
-				// clipboard contents, this must come from the clipboard manager
-				// signaling that the data was handed over successfully.
-				if event.selection != atoms.CLIPBOARD_MANAGER {
-					println!("Received a `SelectionNotify` from a selection other than the CLIPBOARD_MANAGER. This is unexpected in this thread.");
-					continue;
+				// A requestor going away mid request only fails its own paste, the thread keeps serving.
+				if let Err(_e) = context.handle_selection_request(event) {
+					trace_warn!(error = %_e, "failed to answer a selection request");
 				}
 			}
+			Event::PropertyNotify(event) if event.state == Property::DELETE => {
+				// A requestor took the last chunk of an INCR transfer and wants the next one.
+				if let Err(_e) = context.continue_incr_transfer(event.window, event.atom) {
+					trace_warn!(error = %_e, "failed to continue an INCR transfer");
+				}
+			}
+			Event::Error(_e) => {
+				// Errors of requests sent for a requestor, e.g. BadWindow when it was destroyed
+				trace_debug!(error = ?_e, "X error while serving the clipboard");
+			}
+			// We've requested the clipboard content and this is the answer.
+			// Considering that this thread is not responsible for reading
+			// clipboard contents, this must come from the clipboard manager
+			// signaling that the data was handed over successfully.
+			Event::SelectionNotify(event) if event.selection != atoms.CLIPBOARD_MANAGER => {
+				trace_debug!(
+					selection = event.selection,
+					"unexpected SelectionNotify on the owner thread"
+				);
+			}
 			_event => {
 				// May be useful for debugging but nothing else really.
 				// trace!("Received unwanted event: {:?}", event);
@@ -437,25 +1033,49 @@ impl Clipboard for ClipboardContext {
 		})
 	}
 
-	fn has(&self, format: crate::ContentFormat) -> bool {
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
 		let ctx = &self.inner.server;
-		let atoms = ctx.atoms;
-		let atom_list = self.read(&atoms.TARGETS).map(|data| parse_atom_list(&data));
-		match atom_list {
-			Ok(formats) => match format {
-				ContentFormat::Text => formats.contains(&atoms.UTF8_STRING),
-				ContentFormat::Rtf => formats.contains(&atoms.RTF),
-				ContentFormat::Html => formats.contains(&atoms.HTML),
-				ContentFormat::Image => formats.contains(&atoms.PNG_MIME),
-				ContentFormat::Files => formats.contains(&atoms.FILE_LIST),
-				ContentFormat::Other(format_name) => {
-					let atom = ctx.get_atom(format_name.as_str());
-					match atom {
-						Ok(atom) => formats.contains(&atom),
-						Err(_) => false,
-					}
+		let data = self.read(&ctx.atoms.TARGETS)?;
+		let res: Vec<FormatInfo> = parse_atom_list(&data)
+			.into_iter()
+			.filter(|atom| !self.inner.ignore_formats.contains(atom))
+			.map(|atom| {
+				let name = ctx.get_atom_name(atom).unwrap_or("Unknown".to_string());
+				FormatInfo {
+					mime: format_mime(&name),
+					platform_id: Some(atom),
+					// the owner only tells the size by converting the selection
+					size_hint: None,
+					name,
 				}
-			},
+			})
+			.collect();
+		trace_debug!(count = res.len(), "formats enumerated");
+		Ok(res)
+	}
+
+	fn has(&self, format: crate::ContentFormat) -> bool {
+		match self.targets() {
+			Ok(targets) => self.targets_have(&targets, &format),
+			Err(_) => false,
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		match self.targets() {
+			Ok(targets) => targets
+				.iter()
+				.all(|atom| self.inner.ignore_formats.contains(atom)),
+			Err(_) => true,
+		}
+	}
+
+	fn has_any(&self, formats: &[ContentFormat]) -> bool {
+		// one TARGETS conversion for all the formats
+		match self.targets() {
+			Ok(targets) => formats
+				.iter()
+				.any(|format| self.targets_have(&targets, format)),
 			Err(_) => false,
 		}
 	}
@@ -472,13 +1092,65 @@ impl Clipboard for ClipboardContext {
 		}
 	}
 
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		let ctx = &self.inner.server;
+		let atoms = ctx.atoms;
+		let atom = ctx.get_atom(format).map_err(|_| "Invalid format")?;
+		let cookie = ctx.conn.convert_selection(
+			ctx.win_id,
+			atoms.CLIPBOARD,
+			atom,
+			atoms.PROPERTY,
+			CURRENT_TIME,
+		)?;
+		let sequence_num = cookie.sequence_number();
+		cookie.check()?;
+		let data = self.inner.wait_for_selection(
+			atoms.CLIPBOARD,
+			atom,
+			atoms.PROPERTY,
+			Some(Duration::from_millis(500)),
+			sequence_num,
+		)?;
+		let pending = data.is_none();
+		if !pending {
+			ctx.conn
+				.delete_property(ctx.win_id, atoms.PROPERTY)?
+				.check()?;
+		}
+		Ok(Box::new(SelectionReader {
+			inner: self.inner.clone(),
+			target: atom,
+			chunk: Cursor::new(data.unwrap_or_default()),
+			pending,
+		}))
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		let ctx = &self.inner.server;
+		let atoms = ctx.atoms;
+		let atom = ctx.get_atom(format).map_err(|_| "Invalid format")?;
+		let cookie = ctx.conn.convert_selection(
+			ctx.win_id,
+			atoms.CLIPBOARD,
+			atom,
+			atoms.PROPERTY,
+			CURRENT_TIME,
+		)?;
+		let sequence_num = cookie.sequence_number();
+		cookie.check()?;
+		self.inner
+			.process_size_event(atoms.CLIPBOARD, Duration::from_millis(500), sequence_num)
+	}
+
 	fn get_text(&self) -> Result<String> {
-		let atoms = self.inner.server.atoms;
-		let text_data = self.read(&atoms.UTF8_STRING);
-		text_data.map_or_else(
-			|_| Ok("".to_string()),
-			|data| Ok(String::from_utf8_lossy(&data).to_string()),
-		)
+		Ok(self
+			.read_text(TextDecoding::Lossy)
+			.unwrap_or_else(|_| "".to_string()))
+	}
+
+	fn get_text_with_decoding(&self, decoding: TextDecoding) -> Result<String> {
+		self.read_text(decoding)
 	}
 
 	fn get_rich_text(&self) -> Result<String> {
@@ -499,73 +1171,200 @@ impl Clipboard for ClipboardContext {
 		)
 	}
 
-	fn get_image(&self) -> Result<crate::RustImageData> {
+	fn get_html_with_source(&self) -> Result<(String, Option<Url>)> {
 		let atoms = self.inner.server.atoms;
-		let image_bytes = self.read(&atoms.PNG_MIME);
-		match image_bytes {
-			Ok(bytes) => {
-				let image = RustImageData::from_bytes(&bytes);
-				match image {
-					Ok(image) => Ok(image),
-					Err(_) => Err("Invalid image data".into()),
+		let html = String::from_utf8_lossy(&self.read(&atoms.HTML)?).to_string();
+		let source = [atoms.MOZ_URL_PRIV, atoms.CHROMIUM_SOURCE_URL]
+			.iter()
+			.filter_map(|atom| self.read(atom).ok())
+			.map(|data| {
+				if data.len() >= 2 && data[1] == 0 {
+					utf16le_to_string(&data)
+				} else {
+					String::from_utf8_lossy(&data).to_string()
 				}
+			})
+			.find_map(|text| {
+				let url = text.lines().next()?.trim_matches(char::from(0)).trim();
+				(!url.is_empty()).then(|| Url::new(url))
+			});
+		Ok((html, source))
+	}
+
+	fn set_html_with_source(&self, html: String, source: Url) -> Result<()> {
+		let ctx = &self.inner.server_for_write;
+		let atoms = ctx.atoms;
+		self.write(vec![
+			ClipboardData {
+				format: atoms.HTML,
+				data: html.into_bytes(),
+			},
+			ClipboardData {
+				format: atoms.MOZ_URL_PRIV,
+				data: string_to_utf16le(&source.url, false),
+			},
+		])
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		let atoms = self.inner.server.atoms;
+		let svg_data = self.read(&atoms.SVG_MIME)?;
+		Ok(String::from_utf8_lossy(&svg_data).to_string())
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		let atoms = self.inner.server.atoms;
+		let markdown_data = self.read(&atoms.MARKDOWN)?;
+		Ok(String::from_utf8_lossy(&markdown_data).to_string())
+	}
+
+	fn get_image(&self) -> Result<crate::RustImageData> {
+		// gif first, so animations are kept
+		let atoms = self.inner.server.atoms;
+		if let Ok(gif) = self.read(&atoms.GIF_MIME) {
+			if let Ok(image) = RustImageData::from_bytes(&gif) {
+				return Ok(image);
 			}
-			Err(_) => Err("No image data found".into()),
+		}
+		// png is preferred, other image targets such as image/webp are decoded by content
+		let (_, image_bytes) = self.get_image_raw().map_err(|_| "No image data found")?;
+		let image = RustImageData::from_bytes(&image_bytes);
+		match image {
+			Ok(image) => Ok(image),
+			Err(_) => Err("Invalid image data".into()),
 		}
 	}
 
 	fn get_files(&self) -> Result<Vec<String>> {
+		Ok(self.file_uris().unwrap_or_default())
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		let ctx = &self.inner.server;
+		let atoms = ctx.atoms;
+		let targets = self
+			.read(&atoms.TARGETS)
+			.map(|data| parse_atom_list(&data))?;
+		// prefer png, otherwise take the first image target the owner offers
+		let target = if targets.contains(&atoms.PNG_MIME) {
+			Some(atoms.PNG_MIME)
+		} else {
+			// svg is a vector format, it is served through `get_svg`
+			targets.into_iter().find(|atom| {
+				*atom != atoms.SVG_MIME
+					&& ctx
+						.get_atom_name(*atom)
+						.map(|name| name.starts_with("image/"))
+						.unwrap_or(false)
+			})
+		};
+		match target {
+			Some(target) => {
+				let bytes = self.read(&target)?;
+				Ok((ctx.get_atom_name(target)?, bytes))
+			}
+			None => Err("No image data found".into()),
+		}
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		let paths: Vec<PathBuf> = self
+			.file_uris()?
+			.iter()
+			.filter_map(|uri| file_uri_to_path(uri))
+			.collect();
+		if paths.is_empty() {
+			return Err("No files found".into());
+		}
+		Ok(paths)
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
 		let atoms = self.inner.server.atoms;
-		let file_list_data = self.read(&atoms.FILE_LIST);
-		file_list_data.map_or_else(
-			|_| Ok(vec![]),
-			|data| {
-				let file_list_str = String::from_utf8_lossy(&data).to_string();
-				let mut list = Vec::new();
-				for line in file_list_str.lines() {
-					if !line.starts_with(FILE_PATH_PREFIX) {
-						continue;
-					}
-					list.push(line.to_string())
-				}
-				Ok(list)
-			},
-		)
+		// gnome file managers put the operation on the first line
+		for target in [atoms.GNOME_COPY_FILES, atoms.NAUTILUS_FILE_LIST] {
+			if let Ok(data) = self.read(&target) {
+				return Ok(parse_gnome_copied_files(&data).0);
+			}
+		}
+		if let Ok(data) = self.read(&atoms.KDE_CUT_SELECTION) {
+			if data.first() == Some(&b'1') {
+				return Ok(FileOperation::Cut);
+			}
+		}
+		Ok(FileOperation::Copy)
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		let atoms = self.inner.server.atoms;
+		// text/x-moz-url holds "url\ntitle" pairs, usually in UTF-16
+		if let Ok(data) = self.read(&atoms.MOZ_URL) {
+			let text = if data.len() >= 2 && data[1] == 0 {
+				utf16le_to_string(&data)
+			} else {
+				String::from_utf8_lossy(&data).to_string()
+			};
+			let lines: Vec<&str> = text.lines().collect();
+			let urls: Vec<Url> = lines
+				.chunks(2)
+				.filter(|pair| !pair[0].is_empty())
+				.map(|pair| Url {
+					url: pair[0].to_string(),
+					title: pair.get(1).filter(|t| !t.is_empty()).map(|t| t.to_string()),
+				})
+				.collect();
+			if !urls.is_empty() {
+				return Ok(urls);
+			}
+		}
+		// text/uri-list is shared with files, skip comments and local files
+		let data = self.read(&atoms.FILE_LIST)?;
+		let urls: Vec<Url> = String::from_utf8_lossy(&data)
+			.lines()
+			.map(|line| line.trim())
+			.filter(|line| {
+				!line.is_empty() && !line.starts_with('#') && !line.starts_with(FILE_PATH_PREFIX)
+			})
+			.map(Url::new)
+			.collect();
+		if urls.is_empty() {
+			return Err("No urls found".into());
+		}
+		Ok(urls)
 	}
 
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
 	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
-		let mut contents = Vec::new();
-		for format in formats {
-			match format {
-				ContentFormat::Text => match self.get_text() {
-					Ok(text) => contents.push(ClipboardContent::Text(text)),
-					Err(_) => continue,
-				},
-				ContentFormat::Rtf => match self.get_rich_text() {
-					Ok(rtf) => contents.push(ClipboardContent::Rtf(rtf)),
-					Err(_) => continue,
-				},
-				ContentFormat::Html => match self.get_html() {
-					Ok(html) => contents.push(ClipboardContent::Html(html)),
-					Err(_) => continue,
-				},
-				ContentFormat::Image => match self.get_image() {
-					Ok(image) => contents.push(ClipboardContent::Image(image)),
-					Err(_) => continue,
-				},
-				ContentFormat::Files => match self.get_files() {
-					Ok(files) => contents.push(ClipboardContent::Files(files)),
-					Err(_) => continue,
-				},
-				ContentFormat::Other(format_name) => match self.get_buffer(format_name) {
-					Ok(buffer) => {
-						contents.push(ClipboardContent::Other(format_name.clone(), buffer))
-					}
-					Err(_) => continue,
-				},
+		// X11 has no clipboard lock, so retry when the selection owner changes during the read
+		for _ in 0..READ_ATTEMPTS {
+			let owner = self.selection_owner()?;
+			let contents = convert::rich_text_request(formats)
+				.iter()
+				.filter_map(|format| self.read_content(format).ok())
+				.collect();
+			if owner == self.selection_owner()? {
+				return Ok(convert::bridge_rich_text(formats, contents));
 			}
+			trace_debug!("selection owner changed while reading, retrying");
+			stats::record_retry();
 		}
-		Ok(contents)
+		Err("Clipboard owner changed while reading".into())
+	}
+
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		for _ in 0..READ_ATTEMPTS {
+			let owner = self.selection_owner()?;
+			let content = formats
+				.iter()
+				.find_map(|format| self.read_content(format).ok());
+			if owner == self.selection_owner()? {
+				return content.ok_or_else(|| "None of the formats is available".into());
+			}
+			trace_debug!("selection owner changed while reading, retrying");
+			stats::record_retry();
+		}
+		Err("Clipboard owner changed while reading".into())
 	}
 
 	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
@@ -577,6 +1376,29 @@ impl Clipboard for ClipboardContext {
 		self.write(vec![data])
 	}
 
+	fn register_format(&self, name: &str) -> Result<FormatHandle> {
+		let atom = self.inner.server.get_atom(name)?;
+		Ok(FormatHandle::new(name, Some(atom)))
+	}
+
+	fn get_buffer_by_handle(&self, format: &FormatHandle) -> Result<Vec<u8>> {
+		match format.platform_id() {
+			Some(atom) => self.read(&atom),
+			None => self.get_buffer(format.name()),
+		}
+	}
+
+	fn set_buffer_by_handle(&self, format: &FormatHandle, buffer: Vec<u8>) -> Result<()> {
+		let atom = match format.platform_id() {
+			Some(atom) => atom,
+			None => self.inner.server_for_write.get_atom(format.name())?,
+		};
+		self.write(vec![ClipboardData {
+			format: atom,
+			data: buffer,
+		}])
+	}
+
 	fn set_text(&self, text: String) -> Result<()> {
 		let atoms = self.inner.server_for_write.atoms;
 		let text_bytes = text.as_bytes().to_vec();
@@ -588,6 +1410,21 @@ impl Clipboard for ClipboardContext {
 		self.write(vec![data])
 	}
 
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		// a copy in another application takes the selection, retry when the owner changes during the
+		// read and check it again right before taking the selection over
+		for _ in 0..READ_ATTEMPTS {
+			let owner = self.selection_owner()?;
+			let current = self.get_text().unwrap_or_default();
+			if owner == self.selection_owner()? {
+				return self.set_text(appended_text(&current, separator, text));
+			}
+			trace_debug!("selection owner changed while appending, retrying");
+			stats::record_retry();
+		}
+		Err("clipboard changed while appending".into())
+	}
+
 	fn set_rich_text(&self, text: String) -> Result<()> {
 		let atoms = self.inner.server_for_write.atoms;
 		let text_bytes = text.as_bytes().to_vec();
@@ -610,22 +1447,57 @@ impl Clipboard for ClipboardContext {
 		self.write(vec![data])
 	}
 
-	fn set_image(&self, image: RustImageData) -> Result<()> {
+	fn set_markdown(&self, markdown: String) -> Result<()> {
 		let atoms = self.inner.server_for_write.atoms;
-		let image_png = image.to_png()?;
 		let data = ClipboardData {
-			format: atoms.PNG_MIME,
-			data: image_png.get_bytes().to_vec(),
+			format: atoms.MARKDOWN,
+			data: markdown.into_bytes(),
+		};
+		self.write(vec![data])
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		let atoms = self.inner.server_for_write.atoms;
+		let data = ClipboardData {
+			format: atoms.SVG_MIME,
+			data: svg.into_bytes(),
 		};
 		self.write(vec![data])
 	}
 
+	fn set_image(&self, image: RustImageData) -> Result<()> {
+		self.set(vec![ClipboardContent::Image(image)])
+	}
+
 	fn set_files(&self, files: Vec<String>) -> Result<()> {
 		let atoms = self.inner.server_for_write.atoms;
-		let data = file_uri_list_to_clipboard_data(files, atoms);
+		let data = file_uri_list_to_clipboard_data(files, FileOperation::Copy, atoms);
 		self.write(data)
 	}
 
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()> {
+		let atoms = self.inner.server_for_write.atoms;
+		let data = file_uri_list_to_clipboard_data(files, operation, atoms);
+		self.write(data)
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		self.set(vec![ClipboardContent::Urls(urls)])
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		let atoms = self.inner.server_for_write.atoms;
+		let data = ClipboardData {
+			format: atoms.PNG_MIME,
+			data: png,
+		};
+		self.write(vec![data])
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		self.set_buffer(format, bytes)
+	}
+
 	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
 		let mut data = Vec::new();
 		let atoms = self.inner.server_for_write.atoms;
@@ -649,7 +1521,25 @@ impl Clipboard for ClipboardContext {
 						data: html.as_bytes().to_vec(),
 					});
 				}
+				ClipboardContent::Markdown(markdown) => {
+					data.push(ClipboardData {
+						format: atoms.MARKDOWN,
+						data: markdown.into_bytes(),
+					});
+				}
+				ClipboardContent::Svg(svg) => {
+					data.push(ClipboardData {
+						format: atoms.SVG_MIME,
+						data: svg.into_bytes(),
+					});
+				}
 				ClipboardContent::Image(image) => {
+					if let Some(gif) = image.gif_bytes() {
+						data.push(ClipboardData {
+							format: atoms.GIF_MIME,
+							data: gif.to_vec(),
+						});
+					}
 					let image_png = image.to_png()?;
 					data.push(ClipboardData {
 						format: atoms.PNG_MIME,
@@ -657,9 +1547,38 @@ impl Clipboard for ClipboardContext {
 					});
 				}
 				ClipboardContent::Files(files) => {
-					let data_arr = file_uri_list_to_clipboard_data(files, atoms);
+					let data_arr =
+						file_uri_list_to_clipboard_data(files, FileOperation::Copy, atoms);
 					data.extend(data_arr);
 				}
+				ClipboardContent::Urls(urls) => {
+					let moz_url = urls
+						.iter()
+						.map(|u| format!("{}\n{}", u.url, u.title.as_deref().unwrap_or("")))
+						.collect::<Vec<String>>()
+						.join("\n");
+					let uri_list = urls
+						.iter()
+						.map(|u| u.url.as_str())
+						.collect::<Vec<&str>>()
+						.join("\r\n");
+					data.push(ClipboardData {
+						format: atoms.MOZ_URL,
+						data: string_to_utf16le(&moz_url, false),
+					});
+					data.push(ClipboardData {
+						format: atoms.FILE_LIST,
+						data: uri_list.into_bytes(),
+					});
+				}
+				ClipboardContent::Color(color) => data.push(ClipboardData {
+					format: atoms.COLOR,
+					data: color.to_x_color(),
+				}),
+				ClipboardContent::Pdf(pdf) => data.push(ClipboardData {
+					format: atoms.PDF,
+					data: pdf,
+				}),
 				ClipboardContent::Other(format_name, buffer) => {
 					let atom = self.inner.server_for_write.get_atom(&format_name)?;
 					data.push(ClipboardData {
@@ -667,6 +1586,16 @@ impl Clipboard for ClipboardContext {
 						data: buffer,
 					});
 				}
+				ClipboardContent::Custom(handle, buffer) => {
+					let atom = match handle.platform_id() {
+						Some(atom) => atom,
+						None => self.inner.server_for_write.get_atom(handle.name())?,
+					};
+					data.push(ClipboardData {
+						format: atom,
+						data: buffer,
+					});
+				}
 			}
 		}
 		self.write(data)
@@ -677,6 +1606,8 @@ pub struct ClipboardWatcherContext<T: ClipboardHandler> {
 	handlers: Vec<T>,
 	stop_signal: Sender<()>,
 	stop_receiver: Receiver<()>,
+	primary: bool,
+	polling: bool,
 }
 
 unsafe impl<T: ClipboardHandler> Send for ClipboardWatcherContext<T> {}
@@ -688,8 +1619,47 @@ impl<T: ClipboardHandler> ClipboardWatcherContext<T> {
 			handlers: Vec::new(),
 			stop_signal: tx,
 			stop_receiver: rx,
+			primary: false,
+			polling: false,
 		})
 	}
+
+	/// Poll the clipboard contents instead of waiting for XFixes events, the watcher falls back to
+	/// this by itself when the X server lacks XFixes. The primary selection isn't polled
+	pub fn with_polling(mut self) -> Self {
+		self.polling = true;
+		self
+	}
+
+	/// Also watch the primary selection, its changes reach `ClipboardHandler::on_selection_change`
+	/// with `Selection::Primary`. Selecting text changes it, so expect many events while the user drags
+	pub fn with_primary_selection(mut self) -> Self {
+		self.primary = true;
+		self
+	}
+
+	fn poll_watch(&mut self, update_frequency: Duration) {
+		let formats = [
+			ContentFormat::Text,
+			ContentFormat::Rtf,
+			ContentFormat::Html,
+			ContentFormat::Image,
+			ContentFormat::Files,
+			ContentFormat::Url,
+		];
+		let ctx = ClipboardContext::new().expect("Failed to create clipboard context");
+		let mut last_hash = ctx.current_hash(&formats).ok();
+		while self.stop_receiver.recv_timeout(update_frequency).is_err() {
+			let hash = ctx.current_hash(&formats).ok();
+			if hash != last_hash {
+				trace_debug!("clipboard hash changed");
+				last_hash = hash;
+				if notify_handlers(&mut self.handlers, Selection::Clipboard).is_break() {
+					break;
+				}
+			}
+		}
+	}
 }
 
 impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
@@ -698,65 +1668,106 @@ impl<T: ClipboardHandler> ClipboardWatcher<T> for ClipboardWatcherContext<T> {
 		self
 	}
 
+	/// Changes arrive through XFixes as they happen, `update_frequency` only bounds how long a stop
+	/// takes, and is the polling interval when XFixes is missing or `with_polling` was used
 	fn start_watch(&mut self, update_frequency: Duration) {
-		let watch_server = XServerContext::new().expect("Failed to create X server context");
-		let screen = watch_server
+		let watch_server =
+			Arc::new(XServerContext::new().expect("Failed to create X server context"));
+		let xfixes = !self.polling
+			&& xfixes::query_version(&watch_server.conn, 5, 0)
+				.ok()
+				.and_then(|cookie| cookie.reply().ok())
+				.is_some();
+		if !xfixes {
+			if !self.polling {
+				trace_warn!("XFixes is not available, polling the clipboard instead");
+			}
+			self.poll_watch(update_frequency);
+			return;
+		}
+		let root = watch_server
 			.conn
 			.setup()
 			.roots
 			.get(watch_server._screen)
-			.expect("Failed to get screen");
-
-		xfixes::query_version(&watch_server.conn, 5, 0)
-			.expect("Failed to query version xfixes is not available");
-		let cookie = xfixes::select_selection_input(
-			&watch_server.conn,
-			screen.root,
-			watch_server.atoms.CLIPBOARD,
-			xfixes::SelectionEventMask::SET_SELECTION_OWNER
-				| xfixes::SelectionEventMask::SELECTION_CLIENT_CLOSE
-				| xfixes::SelectionEventMask::SELECTION_WINDOW_DESTROY,
-		)
-		.expect("Failed to select selection input");
+			.expect("Failed to get screen")
+			.root;
 
-		cookie.check().unwrap();
+		let primary: Atom = AtomEnum::PRIMARY.into();
+		let mut selections = vec![watch_server.atoms.CLIPBOARD];
+		if self.primary {
+			selections.push(primary);
+		}
+		for selection in selections {
+			let cookie = xfixes::select_selection_input(
+				&watch_server.conn,
+				root,
+				selection,
+				xfixes::SelectionEventMask::SET_SELECTION_OWNER
+					| xfixes::SelectionEventMask::SELECTION_CLIENT_CLOSE
+					| xfixes::SelectionEventMask::SELECTION_WINDOW_DESTROY,
+			)
+			.expect("Failed to select selection input");
+
+			cookie.check().unwrap();
+		}
+
+		// the events are waited for on their own thread, so an idle watcher doesn't wake up for them
+		let (event_sender, event_receiver) = mpsc::channel();
+		let reader = {
+			let watch_server = Arc::clone(&watch_server);
+			thread::spawn(move || loop {
+				match watch_server.conn.wait_for_event() {
+					Ok(Event::XfixesSelectionNotify(event)) => {
+						trace_debug!(selection = event.selection, "selection owner changed");
+						let selection = if event.selection == primary {
+							Selection::Primary
+						} else {
+							Selection::Clipboard
+						};
+						if event_sender.send(selection).is_err() {
+							break;
+						}
+					}
+					// sent to ourselves to stop waiting
+					Ok(Event::ClientMessage(_)) | Err(_) => break,
+					Ok(_) => {}
+				}
+			})
+		};
 
 		loop {
-			if self.stop_receiver.recv_timeout(update_frequency).is_ok() {
+			if self.stop_receiver.try_recv().is_ok() {
 				break;
 			}
-			let event = match watch_server
-				.conn
-				.poll_for_event()
-				.expect("Failed to poll for event")
-			{
-				Some(event) => event,
-				None => {
-					continue;
+			match event_receiver.recv_timeout(update_frequency) {
+				Ok(selection) => {
+					if notify_handlers(&mut self.handlers, selection).is_break() {
+						break;
+					}
 				}
-			};
-			if let Event::XfixesSelectionNotify(_) = event {
-				self.handlers
-					.iter_mut()
-					.for_each(|handler| handler.on_clipboard_change());
+				Err(RecvTimeoutError::Timeout) => {}
+				Err(RecvTimeoutError::Disconnected) => break,
 			}
 		}
-	}
 
-	fn get_shutdown_channel(&self) -> WatcherShutdown {
-		WatcherShutdown {
-			sender: self.stop_signal.clone(),
+		let wake = ClientMessageEvent::new(
+			32,
+			watch_server.win_id,
+			watch_server.atoms.PROPERTY,
+			[0u32; 5],
+		);
+		let woken = watch_server
+			.conn
+			.send_event(false, watch_server.win_id, EventMask::NO_EVENT, wake)
+			.and_then(|_| watch_server.conn.flush());
+		if woken.is_ok() {
+			let _ = reader.join();
 		}
 	}
-}
 
-pub struct WatcherShutdown {
-	sender: Sender<()>,
-}
-
-impl Drop for WatcherShutdown {
-	fn drop(&mut self) {
-		let _ = self.sender.send(());
+	fn get_shutdown_channel(&self) -> WatcherShutdown {
+		WatcherShutdown::new(self.stop_signal.clone())
 	}
 }
 
@@ -820,14 +1831,39 @@ fn parse_atom_list(data: &[u8]) -> Vec<Atom> {
 		.collect()
 }
 
-fn file_uri_list_to_clipboard_data(file_list: Vec<String>, atoms: Atoms) -> Vec<ClipboardData> {
+/// The operation and the URIs of `x-special/gnome-copied-files` or `x-special/nautilus-clipboard`:
+/// an optional nautilus header, `copy` or `cut`, then one URI per line
+fn parse_gnome_copied_files(data: &[u8]) -> (FileOperation, Vec<String>) {
+	let text = String::from_utf8_lossy(data);
+	let lines: Vec<&str> = text
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && *line != NAUTILUS_HEADER)
+		.collect();
+	let operation = match lines.first() {
+		Some(&"cut") => FileOperation::Cut,
+		_ => FileOperation::Copy,
+	};
+	let uris = lines
+		.into_iter()
+		.filter(|line| line.starts_with(FILE_PATH_PREFIX))
+		.map(str::to_owned)
+		.collect();
+	(operation, uris)
+}
+
+fn file_uri_list_to_clipboard_data(
+	file_list: Vec<String>,
+	operation: FileOperation,
+	atoms: Atoms,
+) -> Vec<ClipboardData> {
 	let uri_list: Vec<String> = file_list
 		.iter()
 		.map(|f| {
 			if f.starts_with(FILE_PATH_PREFIX) {
 				f.to_owned()
 			} else {
-				format!("{}{}", FILE_PATH_PREFIX, f)
+				path_to_file_uri(Path::new(f))
 			}
 		})
 		.collect();
@@ -846,7 +1882,13 @@ fn file_uri_list_to_clipboard_data(file_list: Vec<String>, atoms: Atoms) -> Vec<
 	let data_text_plain = uri_str_list.join("\r\n");
 	let data_text_utf8 = uri_str_list.join("\n");
 	let data_text_uri_list = uri_list.join("\r\n");
-	let data_gnome_copied_files = ["copy\n", uri_list.join("\n").as_str()].concat();
+	let (gnome_operation, kde_cut) = match operation {
+		FileOperation::Copy => ("copy\n", "0"),
+		FileOperation::Cut => ("cut\n", "1"),
+	};
+	let data_gnome_copied_files = [gnome_operation, uri_list.join("\n").as_str()].concat();
+	// nautilus 3.30+ repeats its target name on the first line and ends with a newline
+	let data_nautilus = format!("{}\n{}\n", NAUTILUS_HEADER, data_gnome_copied_files);
 
 	vec![
 		ClipboardData {
@@ -879,7 +1921,11 @@ fn file_uri_list_to_clipboard_data(file_list: Vec<String>, atoms: Atoms) -> Vec<
 		},
 		ClipboardData {
 			format: atoms.NAUTILUS_FILE_LIST,
-			data: data_gnome_copied_files.as_bytes().to_vec(),
+			data: data_nautilus.into_bytes(),
+		},
+		ClipboardData {
+			format: atoms.KDE_CUT_SELECTION,
+			data: kde_cut.as_bytes().to_vec(),
 		},
 	]
 }
@@ -0,0 +1,99 @@
+//! en: A watcher that polls the clipboard and compares content hashes, for clipboards without change
+//! notifications, e.g. Wayland compositors without wlr-data-control or the OSC 52 clipboard
+//!
+//! zh: 轮询剪贴板并比较内容哈希的监视器，适用于没有变化通知的剪贴板，例如不支持 wlr-data-control 的
+//! Wayland 合成器或 OSC 52 剪贴板
+
+use crate::common::{notify_handlers, Selection, WatcherShutdown};
+use crate::{Clipboard, ClipboardHandler, ClipboardWatcher, ContentFormat};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// en: Calls the handlers when the hash of the watched formats changes, checked every `update_frequency`.
+/// Each check reads the contents, so keep the formats to the ones the handlers care about
+///
+/// zh: 被监视格式的内容哈希变化时调用处理器，每隔 `update_frequency` 检查一次。
+/// 每次检查都会读取内容，因此只监视处理器关心的格式
+pub struct PollingWatcher<T: ClipboardHandler> {
+	clipboard: Arc<dyn Clipboard + Send + Sync>,
+	formats: Vec<ContentFormat>,
+	handlers: Vec<T>,
+	stop_signal: Sender<()>,
+	stop_receiver: Receiver<()>,
+	running: bool,
+}
+
+impl<T: ClipboardHandler> PollingWatcher<T> {
+	/// en: Watch text, rich text, html, images, files and links
+	/// zh: 监视文本、富文本、html、图片、文件与链接
+	pub fn new<C: Clipboard + Sync + 'static>(clipboard: C) -> Self {
+		let (tx, rx) = mpsc::channel();
+		PollingWatcher {
+			clipboard: Arc::new(clipboard),
+			formats: vec![
+				ContentFormat::Text,
+				ContentFormat::Rtf,
+				ContentFormat::Html,
+				ContentFormat::Image,
+				ContentFormat::Files,
+				ContentFormat::Url,
+			],
+			handlers: Vec::new(),
+			stop_signal: tx,
+			stop_receiver: rx,
+			running: false,
+		}
+	}
+
+	/// en: Only changes in these formats call the handlers
+	/// zh: 只有这些格式的变化会调用处理器
+	pub fn with_formats(mut self, formats: Vec<ContentFormat>) -> Self {
+		self.formats = formats;
+		self
+	}
+
+	fn current_hash(&self) -> Option<u64> {
+		// an unreadable clipboard counts as empty, so reading again after a failure is a change
+		self.clipboard.current_hash(&self.formats).ok()
+	}
+}
+
+impl<T: ClipboardHandler + Send> ClipboardWatcher<T> for PollingWatcher<T> {
+	fn add_handler(&mut self, f: T) -> &mut Self {
+		self.handlers.push(f);
+		self
+	}
+
+	fn start_watch(&mut self, update_frequency: Duration) {
+		if self.running {
+			trace_debug!("already watching");
+			return;
+		}
+		if self.handlers.is_empty() {
+			trace_debug!("no handlers, not watching");
+			return;
+		}
+		self.running = true;
+		let mut last_hash = self.current_hash();
+		loop {
+			// if receive stop signal, break loop
+			if self.stop_receiver.recv_timeout(update_frequency).is_ok() {
+				break;
+			}
+			let hash = self.current_hash();
+			if hash != last_hash {
+				trace_debug!("clipboard hash changed");
+				last_hash = hash;
+				if notify_handlers(&mut self.handlers, Selection::Clipboard).is_break() {
+					break;
+				}
+			}
+		}
+		self.running = false;
+	}
+
+	fn get_shutdown_channel(&self) -> WatcherShutdown {
+		WatcherShutdown::new(self.stop_signal.clone())
+	}
+}
@@ -0,0 +1,212 @@
+//! en: A bounded queue between a watcher and a slow handler, so bursts of changes can't pile up
+//! while the handler is busy
+//!
+//! zh: 监视器与较慢处理器之间的有界队列，处理器繁忙时连续的变化不会无限堆积
+
+use crate::common::Selection;
+use crate::ClipboardHandler;
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// en: What happens to a change when the queue is full
+/// zh: 队列已满时如何处理新的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueuePolicy {
+	/// en: A change already waiting in the queue absorbs the new one, the handler reads the latest
+	/// content when it gets to it. The queue never holds more than one event of each kind
+	/// zh: 队列中已有的同类变化会吸收新的变化，处理器处理时读取的是最新内容。队列中每种事件最多只有一个
+	#[default]
+	CoalesceLatest,
+	/// en: The oldest waiting change is dropped to make room
+	/// zh: 丢弃最早等待的变化以腾出空间
+	DropOldest,
+	/// en: The watcher waits until the handler makes room, changes in the meantime may be missed
+	/// zh: 监视器等待处理器腾出空间，期间的变化可能会被错过
+	Block,
+}
+
+/// en: Options of a `QueuedHandler`
+/// zh: `QueuedHandler` 的选项
+#[derive(Debug, Clone, Copy)]
+pub struct QueueOptions {
+	/// en: The most events waiting for the handler, at least 1
+	/// zh: 等待处理器处理的最大事件数，至少为 1
+	pub capacity: usize,
+	pub policy: QueuePolicy,
+}
+
+impl Default for QueueOptions {
+	fn default() -> Self {
+		QueueOptions {
+			capacity: 16,
+			policy: QueuePolicy::default(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchEvent {
+	Change(Selection),
+	Reconnected,
+}
+
+struct Queue {
+	events: VecDeque<WatchEvent>,
+	closed: bool,
+}
+
+struct Shared {
+	queue: Mutex<Queue>,
+	// signalled when an event is pushed or the queue is closed
+	pushed: Condvar,
+	// signalled when the handler takes an event
+	popped: Condvar,
+	dropped: AtomicUsize,
+	// the handler returned `Break`, the watcher is stopped on the next change
+	stopped: AtomicBool,
+}
+
+/// en: A handler running another handler on its own thread behind a bounded queue. The watcher only
+/// pushes events, so a stalled handler doesn't hold up the watcher or exhaust memory. Dropping it
+/// lets the handler finish the waiting events and joins its thread
+///
+/// zh: 在独立线程上通过有界队列运行另一个处理器的处理器。监视器只负责推入事件，
+/// 因此卡住的处理器不会阻塞监视器或耗尽内存。释放时会让处理器处理完等待中的事件并等待其线程结束
+pub struct QueuedHandler {
+	shared: Arc<Shared>,
+	options: QueueOptions,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl QueuedHandler {
+	pub fn new<H: ClipboardHandler + Send + 'static>(
+		mut handler: H,
+		options: QueueOptions,
+	) -> Self {
+		let options = QueueOptions {
+			capacity: options.capacity.max(1),
+			..options
+		};
+		let shared = Arc::new(Shared {
+			queue: Mutex::new(Queue {
+				events: VecDeque::with_capacity(options.capacity),
+				closed: false,
+			}),
+			pushed: Condvar::new(),
+			popped: Condvar::new(),
+			dropped: AtomicUsize::new(0),
+			stopped: AtomicBool::new(false),
+		});
+		let consumer = shared.clone();
+		let thread = thread::spawn(move || loop {
+			let event = {
+				let mut queue = consumer.queue.lock().unwrap();
+				loop {
+					if let Some(event) = queue.events.pop_front() {
+						break event;
+					}
+					if queue.closed {
+						return;
+					}
+					queue = consumer.pushed.wait(queue).unwrap();
+				}
+			};
+			consumer.popped.notify_all();
+			match event {
+				WatchEvent::Change(selection) => {
+					if handler.on_change(selection).is_break() {
+						consumer.stopped.store(true, Ordering::SeqCst);
+					}
+				}
+				WatchEvent::Reconnected => handler.on_reconnected(),
+			}
+		});
+		QueuedHandler {
+			shared,
+			options,
+			thread: Some(thread),
+		}
+	}
+
+	/// en: The number of events dropped or absorbed because the queue was full
+	/// zh: 因队列已满而被丢弃或合并的事件数
+	pub fn dropped(&self) -> usize {
+		self.shared.dropped.load(Ordering::SeqCst)
+	}
+
+	/// en: The number of events waiting for the handler
+	/// zh: 等待处理器处理的事件数
+	pub fn pending(&self) -> usize {
+		self.shared.queue.lock().unwrap().events.len()
+	}
+
+	fn push(&self, event: WatchEvent) {
+		let mut queue = self.shared.queue.lock().unwrap();
+		match self.options.policy {
+			QueuePolicy::CoalesceLatest => {
+				if queue.events.contains(&event) {
+					self.shared.dropped.fetch_add(1, Ordering::SeqCst);
+					return;
+				}
+				if queue.events.len() >= self.options.capacity {
+					queue.events.pop_front();
+					self.shared.dropped.fetch_add(1, Ordering::SeqCst);
+				}
+			}
+			QueuePolicy::DropOldest => {
+				if queue.events.len() >= self.options.capacity {
+					queue.events.pop_front();
+					self.shared.dropped.fetch_add(1, Ordering::SeqCst);
+				}
+			}
+			QueuePolicy::Block => {
+				while queue.events.len() >= self.options.capacity {
+					queue = self.shared.popped.wait(queue).unwrap();
+				}
+			}
+		}
+		queue.events.push_back(event);
+		drop(queue);
+		self.shared.pushed.notify_one();
+	}
+}
+
+impl ClipboardHandler for QueuedHandler {
+	fn on_clipboard_change(&mut self) {
+		self.push(WatchEvent::Change(Selection::Clipboard));
+	}
+
+	fn on_selection_change(&mut self, selection: Selection) {
+		self.push(WatchEvent::Change(selection));
+	}
+
+	fn on_reconnected(&mut self) {
+		self.push(WatchEvent::Reconnected);
+	}
+
+	/// The handler runs later on its own thread, so a `Break` it returns stops the watcher on the
+	/// change after it
+	fn on_change(&mut self, selection: Selection) -> ControlFlow<()> {
+		self.push(WatchEvent::Change(selection));
+		if self.shared.stopped.load(Ordering::SeqCst) {
+			ControlFlow::Break(())
+		} else {
+			ControlFlow::Continue(())
+		}
+	}
+}
+
+impl Drop for QueuedHandler {
+	fn drop(&mut self) {
+		if let Ok(mut queue) = self.shared.queue.lock() {
+			queue.closed = true;
+		}
+		self.shared.pushed.notify_one();
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
@@ -0,0 +1,459 @@
+//! en: Opt-in counters of the traffic through a clipboard: reads, writes, bytes per format, retries
+//! and watcher events, e.g. to find out which formats an application moves around
+//!
+//! zh: 可选的剪贴板流量计数器：读写次数、各格式的字节数、重试次数与监视器事件数，例如用于查明应用传输了哪些格式
+
+use crate::common::{
+	ContentData, ContentMetadata, FileOperation, FormatHandle, FormatInfo, Result, RustImage, Url,
+};
+use crate::{
+	Clipboard, ClipboardContent, ClipboardHandler, ContentFormat, RustImageData, Selection,
+};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// reads retried by the platform backends, in the whole process
+static RETRIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Count a read the platform had to retry, e.g. because the clipboard changed while reading
+pub(crate) fn record_retry() {
+	RETRIES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// en: The counters of a `StatsClipboard`. Bytes are keyed by the MIME type of the format, or by the
+/// platform name for buffers. Text counts as UTF-8 and images as decoded RGBA, except raw image bytes
+/// zh: `StatsClipboard` 的计数。字节数以格式的 MIME 类型为键，缓冲区则以平台名称为键。
+/// 文本按 UTF-8 计算，图片按解码后的 RGBA 计算，原始图片数据除外
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipboardStats {
+	/// en: Read calls, failed ones included
+	/// zh: 读取调用次数，包括失败的调用
+	pub reads: u64,
+	/// en: Write calls, failed ones included
+	/// zh: 写入调用次数，包括失败的调用
+	pub writes: u64,
+	pub bytes_read: HashMap<String, u64>,
+	pub bytes_written: HashMap<String, u64>,
+	/// en: Reads the platform retried since the counters started. Retries aren't tied to a clipboard,
+	/// this counts those of every clipboard in the process
+	/// zh: 计数开始以来平台重试读取的次数。重试不属于某个剪贴板，这里统计的是进程中所有剪贴板的重试
+	pub retries: u64,
+	/// en: Events delivered to handlers wrapped by `StatsClipboard::handler`
+	/// zh: 传递给由 `StatsClipboard::handler` 包装的处理器的事件数
+	pub watcher_events: u64,
+}
+
+impl ClipboardStats {
+	pub fn total_bytes_read(&self) -> u64 {
+		self.bytes_read.values().sum()
+	}
+
+	pub fn total_bytes_written(&self) -> u64 {
+		self.bytes_written.values().sum()
+	}
+}
+
+struct Counters {
+	stats: ClipboardStats,
+	// `RETRIES` when the counters started
+	retries_from: usize,
+}
+
+impl Counters {
+	fn new() -> Self {
+		Counters {
+			stats: ClipboardStats::default(),
+			retries_from: RETRIES.load(Ordering::Relaxed),
+		}
+	}
+}
+
+fn add_bytes(bytes: &mut HashMap<String, u64>, format: String, size: usize) {
+	*bytes.entry(format).or_insert(0) += size as u64;
+}
+
+/// en: A clipboard counting the reads and writes going through it to another clipboard. Nothing is
+/// counted unless a clipboard is wrapped, clones share the counters
+///
+/// zh: 统计经由它读写另一个剪贴板的次数与数据量的剪贴板。只有被包装的剪贴板才会计数，克隆共享同一组计数
+#[derive(Clone)]
+pub struct StatsClipboard {
+	inner: Arc<dyn Clipboard + Send + Sync>,
+	counters: Arc<Mutex<Counters>>,
+}
+
+impl StatsClipboard {
+	pub fn new<C: Clipboard + Sync + 'static>(clipboard: C) -> Self {
+		StatsClipboard {
+			inner: Arc::new(clipboard),
+			counters: Arc::new(Mutex::new(Counters::new())),
+		}
+	}
+
+	/// en: The counters so far
+	/// zh: 目前为止的计数
+	pub fn stats(&self) -> ClipboardStats {
+		let counters = self.counters();
+		let retries = RETRIES
+			.load(Ordering::Relaxed)
+			.saturating_sub(counters.retries_from);
+		ClipboardStats {
+			retries: retries as u64,
+			..counters.stats.clone()
+		}
+	}
+
+	/// en: Start counting from zero again
+	/// zh: 将计数清零并重新开始
+	pub fn reset(&self) {
+		*self.counters() = Counters::new();
+	}
+
+	/// en: Wrap a watcher handler so the events it gets count as `watcher_events`
+	/// zh: 包装监视器处理器，使其收到的事件计入 `watcher_events`
+	pub fn handler<H: ClipboardHandler>(&self, handler: H) -> StatsHandler<H> {
+		StatsHandler {
+			inner: handler,
+			counters: self.counters.clone(),
+		}
+	}
+
+	fn counters(&self) -> MutexGuard<'_, Counters> {
+		self.counters.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn read<T>(
+		&self,
+		format: impl ToString,
+		result: Result<T>,
+		size: impl FnOnce(&T) -> usize,
+	) -> Result<T> {
+		let mut counters = self.counters();
+		counters.stats.reads += 1;
+		if let Ok(value) = &result {
+			add_bytes(
+				&mut counters.stats.bytes_read,
+				format.to_string(),
+				size(value),
+			);
+		}
+		result
+	}
+
+	fn read_contents(&self, contents: Option<&[ClipboardContent]>) {
+		let mut counters = self.counters();
+		counters.stats.reads += 1;
+		if let Some(contents) = contents {
+			for content in contents {
+				let format = content.get_format().to_string();
+				add_bytes(&mut counters.stats.bytes_read, format, content.size());
+			}
+		}
+	}
+
+	fn write(&self, sizes: Vec<(String, usize)>, result: Result<()>) -> Result<()> {
+		let mut counters = self.counters();
+		counters.stats.writes += 1;
+		if result.is_ok() {
+			for (format, size) in sizes {
+				add_bytes(&mut counters.stats.bytes_written, format, size);
+			}
+		}
+		result
+	}
+
+	fn write_one(&self, format: impl ToString, size: usize, result: Result<()>) -> Result<()> {
+		self.write(vec![(format.to_string(), size)], result)
+	}
+}
+
+fn files_size(files: &[String]) -> usize {
+	files.iter().map(String::len).sum()
+}
+
+fn image_size(image: &RustImageData) -> usize {
+	let (width, height) = image.get_size();
+	width as usize * height as usize * 4
+}
+
+fn urls_size(urls: &[Url]) -> usize {
+	urls.iter()
+		.map(|url| url.url.len() + url.title.as_ref().map_or(0, String::len))
+		.sum()
+}
+
+/// Adds the bytes coming through to `bytes_read` of its format
+struct CountingReader {
+	inner: Box<dyn Read>,
+	format: String,
+	counters: Arc<Mutex<Counters>>,
+}
+
+impl Read for CountingReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let len = self.inner.read(buf)?;
+		let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+		add_bytes(&mut counters.stats.bytes_read, self.format.clone(), len);
+		Ok(len)
+	}
+}
+
+impl Clipboard for StatsClipboard {
+	fn available_formats(&self) -> Result<Vec<String>> {
+		self.inner.available_formats()
+	}
+
+	fn available_formats_detailed(&self) -> Result<Vec<FormatInfo>> {
+		self.inner.available_formats_detailed()
+	}
+
+	fn has(&self, format: ContentFormat) -> bool {
+		self.inner.has(format)
+	}
+
+	fn has_any(&self, formats: &[ContentFormat]) -> bool {
+		self.inner.has_any(formats)
+	}
+
+	fn clear(&self) -> Result<()> {
+		self.inner.clear()
+	}
+
+	fn get_buffer(&self, format: &str) -> Result<Vec<u8>> {
+		self.read(format, self.inner.get_buffer(format), Vec::len)
+	}
+
+	fn get_size(&self, format: &str) -> Result<usize> {
+		self.inner.get_size(format)
+	}
+
+	fn get_reader(&self, format: &str) -> Result<Box<dyn Read>> {
+		// the bytes are counted as the reader is drained
+		let reader = self.read(format, self.inner.get_reader(format), |_| 0)?;
+		Ok(Box::new(CountingReader {
+			inner: reader,
+			format: format.to_owned(),
+			counters: self.counters.clone(),
+		}))
+	}
+
+	fn get_text(&self) -> Result<String> {
+		self.read(ContentFormat::Text, self.inner.get_text(), String::len)
+	}
+
+	fn get_rich_text(&self) -> Result<String> {
+		self.read(ContentFormat::Rtf, self.inner.get_rich_text(), String::len)
+	}
+
+	fn get_html(&self) -> Result<String> {
+		self.read(ContentFormat::Html, self.inner.get_html(), String::len)
+	}
+
+	fn get_svg(&self) -> Result<String> {
+		self.read(ContentFormat::Svg, self.inner.get_svg(), String::len)
+	}
+
+	fn get_markdown(&self) -> Result<String> {
+		self.read(
+			ContentFormat::Markdown,
+			self.inner.get_markdown(),
+			String::len,
+		)
+	}
+
+	fn get_image(&self) -> Result<RustImageData> {
+		self.read(ContentFormat::Image, self.inner.get_image(), image_size)
+	}
+
+	fn get_files(&self) -> Result<Vec<String>> {
+		self.read(ContentFormat::Files, self.inner.get_files(), |files| {
+			files_size(files)
+		})
+	}
+
+	fn get_file_paths(&self) -> Result<Vec<PathBuf>> {
+		self.read(ContentFormat::Files, self.inner.get_file_paths(), |paths| {
+			paths.iter().map(|path| path.as_os_str().len()).sum()
+		})
+	}
+
+	fn get_file_operation(&self) -> Result<FileOperation> {
+		self.inner.get_file_operation()
+	}
+
+	fn get_urls(&self) -> Result<Vec<Url>> {
+		self.read(ContentFormat::Url, self.inner.get_urls(), |urls| {
+			urls_size(urls)
+		})
+	}
+
+	fn get_image_raw(&self) -> Result<(String, Vec<u8>)> {
+		let result = self.inner.get_image_raw();
+		let mut counters = self.counters();
+		counters.stats.reads += 1;
+		if let Ok((format, bytes)) = &result {
+			add_bytes(&mut counters.stats.bytes_read, format.clone(), bytes.len());
+		}
+		result
+	}
+
+	fn get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		let result = self.inner.get(formats);
+		self.read_contents(result.as_deref().ok());
+		result
+	}
+
+	fn try_get(&self, formats: &[ContentFormat]) -> Result<Vec<ClipboardContent>> {
+		let result = self.inner.try_get(formats);
+		self.read_contents(result.as_deref().ok());
+		result
+	}
+
+	fn get_best(&self, formats: &[ContentFormat]) -> Result<ClipboardContent> {
+		let result = self.inner.get_best(formats);
+		self.read_contents(result.as_ref().ok().map(std::slice::from_ref));
+		result
+	}
+
+	fn get_with_metadata(
+		&self,
+		formats: &[ContentFormat],
+	) -> Result<(Vec<ClipboardContent>, ContentMetadata)> {
+		let result = self.inner.get_with_metadata(formats);
+		self.read_contents(
+			result
+				.as_ref()
+				.ok()
+				.map(|(contents, _)| contents.as_slice()),
+		);
+		result
+	}
+
+	fn set_buffer(&self, format: &str, buffer: Vec<u8>) -> Result<()> {
+		let size = buffer.len();
+		self.write_one(format, size, self.inner.set_buffer(format, buffer))
+	}
+
+	fn register_format(&self, name: &str) -> Result<FormatHandle> {
+		self.inner.register_format(name)
+	}
+
+	fn set_text(&self, text: String) -> Result<()> {
+		let size = text.len();
+		self.write_one(ContentFormat::Text, size, self.inner.set_text(text))
+	}
+
+	fn append_text(&self, separator: &str, text: &str) -> Result<()> {
+		// the text already in the clipboard isn't read through the wrapper, only the appended part counts
+		let size = separator.len() + text.len();
+		self.write_one(
+			ContentFormat::Text,
+			size,
+			self.inner.append_text(separator, text),
+		)
+	}
+
+	fn set_rich_text(&self, text: String) -> Result<()> {
+		let size = text.len();
+		self.write_one(ContentFormat::Rtf, size, self.inner.set_rich_text(text))
+	}
+
+	fn set_html(&self, html: String) -> Result<()> {
+		let size = html.len();
+		self.write_one(ContentFormat::Html, size, self.inner.set_html(html))
+	}
+
+	fn set_svg(&self, svg: String) -> Result<()> {
+		let size = svg.len();
+		self.write_one(ContentFormat::Svg, size, self.inner.set_svg(svg))
+	}
+
+	fn set_markdown(&self, markdown: String) -> Result<()> {
+		let size = markdown.len();
+		self.write_one(
+			ContentFormat::Markdown,
+			size,
+			self.inner.set_markdown(markdown),
+		)
+	}
+
+	fn set_image(&self, image: RustImageData) -> Result<()> {
+		let size = image_size(&image);
+		self.write_one(ContentFormat::Image, size, self.inner.set_image(image))
+	}
+
+	fn set_files(&self, files: Vec<String>) -> Result<()> {
+		let size = files_size(&files);
+		self.write_one(ContentFormat::Files, size, self.inner.set_files(files))
+	}
+
+	fn set_files_with_operation(&self, files: Vec<String>, operation: FileOperation) -> Result<()> {
+		let size = files_size(&files);
+		let result = self.inner.set_files_with_operation(files, operation);
+		self.write_one(ContentFormat::Files, size, result)
+	}
+
+	fn set_urls(&self, urls: Vec<Url>) -> Result<()> {
+		let size = urls_size(&urls);
+		self.write_one(ContentFormat::Url, size, self.inner.set_urls(urls))
+	}
+
+	fn set_image_png(&self, png: Vec<u8>) -> Result<()> {
+		let size = png.len();
+		self.write_one(ContentFormat::Image, size, self.inner.set_image_png(png))
+	}
+
+	fn set_image_raw(&self, format: &str, bytes: Vec<u8>) -> Result<()> {
+		let size = bytes.len();
+		self.write_one(format, size, self.inner.set_image_raw(format, bytes))
+	}
+
+	fn set(&self, contents: Vec<ClipboardContent>) -> Result<()> {
+		let sizes = contents
+			.iter()
+			.map(|content| (content.get_format().to_string(), content.size()))
+			.collect();
+		self.write(sizes, self.inner.set(contents))
+	}
+}
+
+/// en: A handler counting the events it forwards to another handler, made by `StatsClipboard::handler`
+/// zh: 统计转发给另一个处理器的事件数的处理器，由 `StatsClipboard::handler` 创建
+pub struct StatsHandler<H> {
+	inner: H,
+	counters: Arc<Mutex<Counters>>,
+}
+
+impl<H> StatsHandler<H> {
+	fn count(&self) {
+		let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+		counters.stats.watcher_events += 1;
+	}
+}
+
+impl<H: ClipboardHandler> ClipboardHandler for StatsHandler<H> {
+	fn on_clipboard_change(&mut self) {
+		self.count();
+		self.inner.on_clipboard_change();
+	}
+
+	fn on_selection_change(&mut self, selection: Selection) {
+		self.count();
+		self.inner.on_selection_change(selection);
+	}
+
+	fn on_reconnected(&mut self) {
+		self.count();
+		self.inner.on_reconnected();
+	}
+
+	fn on_change(&mut self, selection: Selection) -> ControlFlow<()> {
+		self.count();
+		self.inner.on_change(selection)
+	}
+}
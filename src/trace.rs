@@ -0,0 +1,26 @@
+// Events through `tracing` when the `tracing` feature is on, without it the macros expand to nothing
+// and their arguments aren't evaluated
+
+#[cfg(feature = "tracing")]
+macro_rules! trace_debug {
+	($($arg:tt)*) => {
+		tracing::debug!($($arg)*)
+	};
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_debug {
+	($($arg:tt)*) => {};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! trace_warn {
+	($($arg:tt)*) => {
+		tracing::warn!($($arg)*)
+	};
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_warn {
+	($($arg:tt)*) => {};
+}
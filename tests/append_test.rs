@@ -0,0 +1,55 @@
+use clipboard_rs::middleware::{ClipboardMiddleware, MiddlewareClipboard};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::stats::StatsClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent};
+
+#[test]
+fn test_append_text() {
+	let clipboard = MockClipboard::new();
+
+	// nothing to separate from yet
+	clipboard.append_text("\n", "one").unwrap();
+	assert_eq!(clipboard.get_text().unwrap(), "one");
+
+	clipboard.append_text("\n", "two").unwrap();
+	assert_eq!(clipboard.get_text().unwrap(), "one\ntwo");
+
+	// other formats are replaced by the text
+	clipboard.inject(vec![
+		ClipboardContent::Html("<b>three</b>".to_string()),
+		ClipboardContent::Text("three".to_string()),
+	]);
+	clipboard.append_text(", ", "four").unwrap();
+	assert_eq!(clipboard.get_text().unwrap(), "three, four");
+	assert!(clipboard.get_html().is_err());
+}
+
+struct Upper;
+
+impl ClipboardMiddleware for Upper {
+	fn on_write(&self, content: ClipboardContent) -> ClipboardContent {
+		match content {
+			ClipboardContent::Text(text) => ClipboardContent::Text(text.to_uppercase()),
+			content => content,
+		}
+	}
+}
+
+#[test]
+fn test_append_text_through_wrappers() {
+	let mock = MockClipboard::new();
+	mock.set_text("one".to_string()).unwrap();
+
+	// only the appended part goes through the middlewares
+	let clipboard = MiddlewareClipboard::new(mock.clone()).with(Upper);
+	clipboard.append_text("\n", "two").unwrap();
+	assert_eq!(mock.get_text().unwrap(), "one\nTWO");
+
+	// the append is a single write of the inner clipboard
+	let clipboard = StatsClipboard::new(mock.clone());
+	clipboard.append_text(", ", "three").unwrap();
+	assert_eq!(mock.get_text().unwrap(), "one\nTWO, three");
+	let stats = clipboard.stats();
+	assert_eq!((stats.reads, stats.writes), (0, 1));
+	assert_eq!(stats.bytes_written["text/plain"], 7);
+}
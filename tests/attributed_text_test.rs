@@ -0,0 +1,56 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{AttributedText, Clipboard, Rgba, TextRun};
+
+#[test]
+fn test_attributed_text() {
+	let text = AttributedText {
+		runs: vec![
+			TextRun::new("plain "),
+			TextRun {
+				bold: true,
+				italic: true,
+				..TextRun::new("bold <italic>")
+			},
+			TextRun::new("\n"),
+			TextRun {
+				link: Some("https://example.com/?a=1&b=2".to_string()),
+				color: Some(Rgba::from_rgba8(255, 0, 0, 255)),
+				underline: true,
+				..TextRun::new("link")
+			},
+		],
+	};
+	assert_eq!(text.text(), "plain bold <italic>\nlink");
+	assert_eq!(
+		text.to_html(),
+		"plain <b><i>bold &lt;italic&gt;</i></b><br>\
+		<a href=\"https://example.com/?a=1&amp;b=2\"><span style=\"color:#ff0000\"><u>link</u></span></a>"
+	);
+
+	let rtf = text.to_rtf();
+	assert!(rtf.contains("{\\colortbl;\\red255\\green0\\blue0;}"));
+	assert!(rtf.contains("{\\b\\i bold <italic>}"));
+	assert!(rtf.contains("HYPERLINK \"https://example.com/?a=1&b=2\""));
+	assert!(rtf.contains("{\\ul\\cf1 link}"));
+
+	// the portable reader keeps the styles, not links and colors
+	let read = AttributedText::from_rtf(&rtf);
+	assert_eq!(read.text(), "plain bold <italic>\nlink");
+	assert!(read.runs.iter().any(|run| run.bold && run.italic));
+	assert!(read
+		.runs
+		.iter()
+		.any(|run| run.underline && run.text == "link"));
+
+	let clipboard = MockClipboard::new();
+	clipboard.set_attributed_text(&text).unwrap();
+	assert_eq!(clipboard.get_text().unwrap(), text.text());
+	assert_eq!(clipboard.get_html().unwrap(), text.to_html());
+	assert_eq!(clipboard.get_attributed_text().unwrap().text(), text.text());
+
+	clipboard.set_text("only text".to_string()).unwrap();
+	assert_eq!(
+		clipboard.get_attributed_text().unwrap(),
+		AttributedText::plain("only text")
+	);
+}
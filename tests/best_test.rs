@@ -0,0 +1,23 @@
+use clipboard_rs::common::ContentData;
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext, ContentFormat};
+
+#[test]
+fn test_get_best() {
+	let ctx = ClipboardContext::new().unwrap();
+
+	ctx.set(vec![
+		ClipboardContent::Text("hello".to_string()),
+		ClipboardContent::Html("<b>hello</b>".to_string()),
+	])
+	.unwrap();
+
+	let best = ctx
+		.get_best(&[ContentFormat::Rtf, ContentFormat::Html, ContentFormat::Text])
+		.unwrap();
+	assert_eq!(best.get_format(), ContentFormat::Html);
+	assert_eq!(best.as_str().unwrap(), "<b>hello</b>");
+
+	ctx.clear().unwrap();
+
+	assert!(ctx.get_best(&[ContentFormat::Rtf]).is_err());
+}
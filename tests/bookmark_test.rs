@@ -0,0 +1,29 @@
+#![cfg(target_os = "macos")]
+use clipboard_rs::{Clipboard, ClipboardContext, FileBookmark};
+use std::fs;
+
+#[test]
+fn test_file_bookmarks() {
+	let ctx = ClipboardContext::new().unwrap();
+	let path = std::env::temp_dir().join("clipboard-rs-bookmark.txt");
+	fs::write(&path, "bookmarked").unwrap();
+	// /var is a symlink to /private/var, the pasteboard hands out the resolved path
+	let path = path.canonicalize().unwrap();
+
+	ctx.set_file_bookmarks(&[FileBookmark {
+		path: path.clone(),
+		bookmark: None,
+	}])
+	.unwrap();
+	let files = ctx.get_file_bookmarks().unwrap();
+	assert_eq!(files.len(), 1);
+	assert_eq!(files[0].path, path);
+	assert_eq!(ctx.get_file_paths().unwrap(), vec![path.clone()]);
+
+	// outside a sandbox the bookmark may be missing, the path is used then
+	let access = files[0].access().unwrap();
+	assert_eq!(fs::read_to_string(access.path()).unwrap(), "bookmarked");
+	drop(access);
+
+	let _ = fs::remove_file(&path);
+}
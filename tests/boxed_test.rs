@@ -0,0 +1,20 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContext};
+
+struct AppState {
+	clipboard: Box<dyn Clipboard + Send + Sync>,
+}
+
+#[test]
+fn test_boxed_clipboard() {
+	let mut state = AppState {
+		clipboard: ClipboardContext::new_boxed().unwrap(),
+	};
+	state.clipboard.set_text("real".to_string()).unwrap();
+	assert_eq!(state.clipboard.get_text().unwrap(), "real");
+
+	// swap in the mock without changing the type of the field
+	state.clipboard = Box::new(MockClipboard::new());
+	state.clipboard.set_text("mock".to_string()).unwrap();
+	assert_eq!(state.clipboard.get_text().unwrap(), "mock");
+}
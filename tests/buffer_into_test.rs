@@ -0,0 +1,24 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::Clipboard;
+
+#[test]
+fn test_get_buffer_into() {
+	let clipboard = MockClipboard::new();
+	let mut buffer = Vec::with_capacity(64);
+	buffer.extend_from_slice(b"stale");
+
+	clipboard
+		.set_buffer("application/x-blob", b"fresh bytes".to_vec())
+		.unwrap();
+	let len = clipboard
+		.get_buffer_into("application/x-blob", &mut buffer)
+		.unwrap();
+	assert_eq!(len, 11);
+	assert_eq!(buffer, b"fresh bytes");
+	// the allocation is reused
+	assert!(buffer.capacity() >= 64);
+
+	assert!(clipboard
+		.get_buffer_into("application/x-missing", &mut buffer)
+		.is_err());
+}
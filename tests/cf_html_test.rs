@@ -0,0 +1,56 @@
+use clipboard_rs::common::cf_html;
+
+fn header_value(payload: &str, key: &str) -> usize {
+	payload
+		.lines()
+		.find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+		.unwrap()
+		.parse()
+		.unwrap()
+}
+
+#[test]
+fn test_build_offsets() {
+	let fragment = "<b>你好 clipboard-rs</b>";
+	let payload = cf_html::build(fragment);
+	let start_html = header_value(&payload, "StartHTML");
+	let end_html = header_value(&payload, "EndHTML");
+	let start_fragment = header_value(&payload, "StartFragment");
+	let end_fragment = header_value(&payload, "EndFragment");
+
+	// offsets are in bytes, not chars
+	assert_eq!(end_html, payload.len());
+	assert!(payload[start_html..].starts_with("<html>"));
+	assert_eq!(&payload[start_fragment..end_fragment], fragment);
+}
+
+#[test]
+fn test_build_document() {
+	let html = "<html><body class=\"a\"><p>hello</p></body></html>";
+	let payload = cf_html::build_with_source_url(html, Some("https://example.com/a"));
+	let parsed = cf_html::parse(payload.as_bytes()).unwrap();
+	assert_eq!(parsed.fragment, "<p>hello</p>");
+	assert_eq!(parsed.source_url.as_deref(), Some("https://example.com/a"));
+	assert_eq!(parsed.raw, payload);
+}
+
+#[test]
+fn test_round_trip() {
+	let fragment = "<a href=\"https://example.com\">link</a>";
+	let parsed = cf_html::parse(cf_html::build(fragment).as_bytes()).unwrap();
+	assert_eq!(parsed.fragment, fragment);
+	assert!(parsed.html.starts_with("<html>"));
+	assert!(parsed.html.ends_with("</html>"));
+
+	// building from a payload read back keeps the same fragment
+	let rebuilt = cf_html::parse(cf_html::build(&parsed.html).as_bytes()).unwrap();
+	assert_eq!(rebuilt.fragment, fragment);
+}
+
+#[test]
+fn test_parse_invalid() {
+	assert!(
+		cf_html::parse(b"Version:0.9\r\nStartFragment:100\r\nEndFragment:200\r\n<b></b>").is_err()
+	);
+	assert!(cf_html::parse(b"<b>no header</b>").is_err());
+}
@@ -0,0 +1,23 @@
+#![cfg(target_os = "windows")]
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext, CloudClipboardOptions};
+
+#[test]
+fn test_set_with_options() {
+	let ctx = ClipboardContext::new().unwrap();
+	let test_text = "clipboard-rs private text";
+	ctx.set_with_options(
+		vec![ClipboardContent::Text(test_text.to_string())],
+		&CloudClipboardOptions::private(),
+	)
+	.unwrap();
+	assert_eq!(ctx.get_text().unwrap(), test_text);
+
+	// the refusals are stored as DWORD 0, the allocation may be rounded up
+	let history = ctx.get_buffer("CanIncludeInClipboardHistory").unwrap();
+	assert_eq!(history[..4], 0u32.to_le_bytes());
+	let cloud = ctx.get_buffer("CanUploadToCloudClipboard").unwrap();
+	assert_eq!(cloud[..4], 0u32.to_le_bytes());
+
+	ctx.set_text(test_text.to_string()).unwrap();
+	assert!(ctx.get_buffer("CanIncludeInClipboardHistory").is_err());
+}
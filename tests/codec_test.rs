@@ -0,0 +1,60 @@
+use clipboard_rs::common::codec::{image_codec, set_image_codec, ImageCodec, RgbaPixels};
+use clipboard_rs::common::RustImage;
+use clipboard_rs::{Result, RustImageData};
+
+// hands out a single red pixel whatever it is given
+struct RedPixel;
+
+impl ImageCodec for RedPixel {
+	fn decode(&self, _bytes: &[u8]) -> Result<RgbaPixels> {
+		Ok(RgbaPixels {
+			width: 1,
+			height: 1,
+			data: vec![255, 0, 0, 255],
+		})
+	}
+
+	fn encode(&self, _pixels: &RgbaPixels, mime: &str) -> Result<Vec<u8>> {
+		Ok(mime.as_bytes().to_vec())
+	}
+}
+
+#[test]
+fn test_set_image_codec() {
+	set_image_codec(RedPixel);
+	let codec = image_codec().unwrap();
+	assert_eq!(
+		codec.decode(b"anything").unwrap().data,
+		vec![255, 0, 0, 255]
+	);
+	assert_eq!(
+		codec
+			.encode(&codec.decode(&[]).unwrap(), "image/png")
+			.unwrap(),
+		b"image/png"
+	);
+}
+
+#[test]
+fn test_rgba_pixels() {
+	let image = RustImageData::from_rgba(2, 1, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+	let pixels = image.to_rgba_pixels().unwrap();
+	assert_eq!((pixels.width, pixels.height), (2, 1));
+	assert_eq!(pixels.data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_image_crate_codec() {
+	use clipboard_rs::common::codec::ImageCrateCodec;
+
+	let pixels = RgbaPixels {
+		width: 2,
+		height: 2,
+		data: [10, 20, 30, 255].repeat(4),
+	};
+	let png = ImageCrateCodec.encode(&pixels, "image/png").unwrap();
+	assert!(png.starts_with(b"\x89PNG"));
+	assert_eq!(ImageCrateCodec.decode(&png).unwrap(), pixels);
+	assert!(ImageCrateCodec.encode(&pixels, "text/plain").is_err());
+}
@@ -0,0 +1,31 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat, Rgba};
+
+#[test]
+fn test_rgba() {
+	let color = Rgba::from_rgba8(255, 128, 0, 255);
+	assert_eq!(color.to_rgba8(), [255, 128, 0, 255]);
+	assert_eq!(Rgba::new(1.5, -0.5, 0.0, 1.0).to_rgba8(), [255, 0, 0, 255]);
+
+	// colors that differ below 8 bits hash alike
+	assert_eq!(
+		ClipboardContent::Color(color).content_hash(),
+		ClipboardContent::Color(Rgba::new(1.0, 0.502, 0.0001, 1.0)).content_hash()
+	);
+}
+
+#[test]
+fn test_color_content() {
+	let clipboard = MockClipboard::new();
+	assert!(clipboard.get_color().is_err());
+
+	let color = Rgba::new(0.2, 0.4, 0.6, 0.8);
+	clipboard.set_color(color).unwrap();
+	assert!(clipboard.has(ContentFormat::Color));
+	assert_eq!(clipboard.get_color().unwrap(), color);
+	// the GTK layout, four 16-bit components
+	assert_eq!(
+		clipboard.get_buffer("application/x-color").unwrap().len(),
+		8
+	);
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "compress")]
+use clipboard_rs::common::compress::{
+	compress, compress_content, decompress, decompress_content, is_compressible,
+};
+use clipboard_rs::{ClipboardContent, ContentFormat};
+
+#[test]
+fn test_compress() {
+	let rtf = "{\\rtf1 \\b bold\\b0  text\\par}".repeat(500);
+	let content = ClipboardContent::Rtf(rtf);
+	let frame = compress_content(&content).unwrap();
+	assert!(frame.len() < 2000);
+	assert_eq!(
+		decompress_content(ContentFormat::Rtf, &frame).unwrap(),
+		content
+	);
+
+	// small data isn't worth a frame
+	let small = compress(&ContentFormat::Text, b"hi");
+	assert_eq!(small, b"\0hi");
+	assert_eq!(decompress(&small).unwrap(), b"hi");
+
+	// PNG is compressed already, also when it comes as a custom format
+	let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+	png.resize(4096, 0);
+	let custom = ContentFormat::Other("my.png".to_string());
+	assert!(!is_compressible(&custom, &png));
+	assert_eq!(compress(&custom, &png)[1..], png[..]);
+	assert!(is_compressible(&ContentFormat::Html, &[b'a'; 4096]));
+
+	assert!(decompress(&[9, 1, 2]).is_err());
+	assert!(decompress(&[]).is_err());
+}
@@ -0,0 +1,78 @@
+use clipboard_rs::common::ContentData;
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat, Url};
+
+#[test]
+fn test_files_round_trip() {
+	let files = ClipboardContent::Files(vec![
+		"/tmp/a b.txt".to_string(),
+		"/tmp/c.png".to_string(),
+		"file:///tmp/d.pdf".to_string(),
+	]);
+	assert_eq!(
+		files.as_lines(),
+		vec!["/tmp/a b.txt", "/tmp/c.png", "file:///tmp/d.pdf"]
+	);
+	// as_str keeps only the first entry
+	assert_eq!(files.as_str().unwrap(), "/tmp/a b.txt");
+
+	let bytes = files.to_bytes().unwrap();
+	assert_eq!(
+		bytes,
+		b"file:///tmp/a%20b.txt\r\nfile:///tmp/c.png\r\nfile:///tmp/d.pdf\r\n"
+	);
+	let decoded = ClipboardContent::from_bytes(ContentFormat::Files, &bytes).unwrap();
+	assert_eq!(
+		decoded.as_lines(),
+		vec!["/tmp/a b.txt", "/tmp/c.png", "/tmp/d.pdf"]
+	);
+
+	// comments are skipped, other schemes kept as they are
+	let decoded = ClipboardContent::from_bytes(
+		ContentFormat::Files,
+		b"# copied\r\nhttps://example.com/e.txt\r\n",
+	)
+	.unwrap();
+	assert_eq!(decoded.as_lines(), vec!["https://example.com/e.txt"]);
+}
+
+#[test]
+fn test_urls_round_trip() {
+	let urls = ClipboardContent::Urls(vec![
+		Url {
+			url: "https://example.com/".to_string(),
+			title: Some("Example".to_string()),
+		},
+		Url::new("https://rust-lang.org/"),
+	]);
+	assert_eq!(
+		urls.as_lines(),
+		vec!["https://example.com/", "https://rust-lang.org/"]
+	);
+	let decoded =
+		ClipboardContent::from_bytes(ContentFormat::Url, &urls.to_bytes().unwrap()).unwrap();
+	assert_eq!(decoded, urls);
+}
+
+#[test]
+fn test_other_formats() {
+	let text = ClipboardContent::Text("one\ntwo".to_string());
+	assert_eq!(text.as_lines(), vec!["one\ntwo"]);
+	assert_eq!(
+		ClipboardContent::from_bytes(ContentFormat::Text, &text.to_bytes().unwrap()).unwrap(),
+		text
+	);
+	assert!(ClipboardContent::Pdf(vec![0xff]).as_lines().is_empty());
+	assert!(ClipboardContent::from_bytes(ContentFormat::Html, &[0xff]).is_err());
+}
+
+#[test]
+fn test_mock_files_buffer() {
+	let clipboard = MockClipboard::new();
+	clipboard
+		.set_files(vec!["/tmp/a".to_string(), "/tmp/b".to_string()])
+		.unwrap();
+	let buffer = clipboard.get_buffer("text/uri-list").unwrap();
+	assert_eq!(buffer, b"file:///tmp/a\r\nfile:///tmp/b\r\n");
+	assert_eq!(clipboard.get_size("text/uri-list").unwrap(), buffer.len());
+}
@@ -0,0 +1,70 @@
+use clipboard_rs::common::convert::{
+	html_to_markdown, html_to_text, markdown_to_html, rtf_to_text,
+};
+
+#[test]
+fn test_html_to_text() {
+	let html = "<html><head><style>p { color: red; }</style></head><body>\
+		<p>Hello&nbsp;<b>clipboard</b>-rs &amp; friends</p>\
+		<!-- a > b --><ul><li>one</li><li>two</li></ul>\
+		<script>alert('x')</script>line<br>break &#x4f60;&#22909;</body></html>";
+	assert_eq!(
+		html_to_text(html),
+		"Hello\u{a0}clipboard-rs & friends\none\ntwo\nline\nbreak 你好"
+	);
+}
+
+#[test]
+fn test_rtf_to_text() {
+	let rtf = r"{\rtf1\ansi\deff0{\fonttbl{\f0 Times New Roman;}}{\colortbl;\red255\green0\blue0;}
+{\*\generator Writer;}\f0 Hello {\b bold} \'e9t\'e9\par
+\uc1\u20320?\u22909?\tab end\}}";
+	assert_eq!(rtf_to_text(rtf), "Hello bold été\n你好\tend}");
+}
+
+#[test]
+fn test_rtf_non_ascii_after_backslash() {
+	assert_eq!(rtf_to_text(r"{\rtf1 \é}"), "");
+	assert_eq!(rtf_to_text(r"{\rtf1 \'中}"), "中");
+	assert_eq!(rtf_to_text(r"{\rtf1 \'e中}"), "\u{e}中");
+}
+
+#[test]
+fn test_markdown_to_html() {
+	let markdown = "# Title\n\nSome **bold**, *em* and `a<b` with snake_case_name.\n\n- one\n- [two](https://example.com)\n\n1. first\n\n```\nlet x = 1 < 2;\n```";
+	assert_eq!(
+		markdown_to_html(markdown),
+		"<h1>Title</h1>\n\
+		<p>Some <strong>bold</strong>, <em>em</em> and <code>a&lt;b</code> with snake_case_name.</p>\n\
+		<ul>\n<li>one</li>\n<li><a href=\"https://example.com\">two</a></li>\n</ul>\n\
+		<ol>\n<li>first</li>\n</ol>\n\
+		<pre><code>let x = 1 &lt; 2;</code></pre>"
+	);
+}
+
+#[test]
+fn test_html_to_markdown() {
+	let html =
+		"<h2>Title</h2><p>Some <b>bold</b> and <a href=\"https://example.com\">a link</a></p>\
+		<ol><li>first</li><li>second</li></ol><img src=\"a.png\" alt=\"A\">";
+	assert_eq!(
+		html_to_markdown(html),
+		"## Title\n\nSome **bold** and [a link](https://example.com)\n\n1. first\n2. second\n\n![A](a.png)"
+	);
+}
+
+#[cfg(feature = "convert")]
+#[test]
+fn test_rtf_html_bridge() {
+	use clipboard_rs::common::convert::{html_to_rtf, rtf_to_html};
+
+	let html = "<p>Hello <b>bold</b> {braces} 你好</p><p>second</p>";
+	let rtf = html_to_rtf(html);
+	assert!(rtf.starts_with("{\\rtf1"));
+	assert!(rtf.contains("\\u20320?"));
+	assert_eq!(rtf_to_text(&rtf), "Hello bold {braces} 你好\nsecond");
+	assert_eq!(
+		rtf_to_html(&rtf),
+		"<p>Hello <b>bold</b> {braces} 你好</p>\n<p>second</p>"
+	);
+}
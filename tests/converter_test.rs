@@ -0,0 +1,54 @@
+use clipboard_rs::converter::{ConverterRegistry, ConvertingClipboard};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat};
+
+#[test]
+fn test_default_converters() {
+	let clipboard = MockClipboard::new();
+	let converting =
+		ConvertingClipboard::new(clipboard.clone(), ConverterRegistry::with_defaults());
+	clipboard
+		.set_rich_text("{\\rtf1\\ansi hello {\\b world}}".to_string())
+		.unwrap();
+
+	assert!(!clipboard.has(ContentFormat::Text));
+	assert!(converting.has(ContentFormat::Text));
+	assert_eq!(converting.get_text().unwrap(), "hello world");
+	assert_eq!(
+		converting.get_best(&[ContentFormat::Text]).unwrap(),
+		ClipboardContent::Text("hello world".to_string())
+	);
+
+	// a native content always wins over a converted one
+	clipboard
+		.set(vec![
+			ClipboardContent::Text("native".to_string()),
+			ClipboardContent::Html("<p>converted</p>".to_string()),
+		])
+		.unwrap();
+	assert_eq!(converting.get_text().unwrap(), "native");
+	let contents = converting
+		.get(&[ContentFormat::Text, ContentFormat::Markdown])
+		.unwrap();
+	assert_eq!(contents.len(), 2);
+	assert!(contents.contains(&ClipboardContent::Text("native".to_string())));
+}
+
+#[test]
+fn test_custom_converter() {
+	let clipboard = MockClipboard::new();
+	let registry = ConverterRegistry::new().with(
+		ContentFormat::Other("application/x-shout".to_string()),
+		ContentFormat::Text,
+		|bytes| Ok(bytes.to_ascii_uppercase()),
+	);
+	let converting = ConvertingClipboard::new(clipboard.clone(), registry);
+	clipboard
+		.set_buffer("application/x-shout", b"quiet".to_vec())
+		.unwrap();
+
+	assert_eq!(converting.get_text().unwrap(), "QUIET");
+	// nothing converts into html
+	assert!(converting.get_html().is_err());
+	assert!(converting.get(&[ContentFormat::Html]).unwrap().is_empty());
+}
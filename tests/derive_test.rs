@@ -0,0 +1,39 @@
+use clipboard_rs::common::RustImage;
+use clipboard_rs::{ClipboardContent, ContentFormat, RustImageData, Url};
+
+#[test]
+fn test_content_derives() {
+	let contents = vec![
+		ClipboardContent::Text("hello".to_string()),
+		ClipboardContent::Urls(vec![Url::with_title("https://example.com", "example")]),
+		ClipboardContent::Other("custom".to_string(), vec![1, 2, 3]),
+	];
+	let snapshot = contents.clone();
+	assert_eq!(contents, snapshot);
+	assert_ne!(contents[0], ClipboardContent::Text("world".to_string()));
+
+	assert_eq!(
+		ContentFormat::Other("a".to_string()),
+		ContentFormat::Other("a".to_string())
+	);
+	assert_ne!(
+		ContentFormat::Other("a".to_string()),
+		ContentFormat::Other("b".to_string())
+	);
+	assert_eq!(format!("{:?}", ContentFormat::Html), "Html");
+}
+
+#[test]
+fn test_image_derives() {
+	let image = RustImageData::from_rgba(64, 64, vec![255; 64 * 64 * 4]).unwrap();
+	assert_eq!(image.clone(), image);
+
+	// pixels are left out of the debug output
+	let debug = format!("{:?}", image);
+	assert!(debug.contains("width: 64"));
+	assert!(debug.len() < 200);
+
+	let png = image.to_png().unwrap();
+	assert_eq!(png.clone(), png);
+	assert!(format!("{:?}", png).ends_with("bytes)"));
+}
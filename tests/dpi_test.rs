@@ -0,0 +1,71 @@
+use clipboard_rs::common::RustImage;
+use clipboard_rs::RustImageData;
+
+fn assert_dpi(image: &RustImageData, x: f32, y: f32) {
+	let (dpi_x, dpi_y) = image.dpi().expect("no dpi");
+	assert!((dpi_x - x).abs() < 0.1, "{} != {}", dpi_x, x);
+	assert!((dpi_y - y).abs() < 0.1, "{} != {}", dpi_y, y);
+}
+
+#[test]
+fn test_png_keeps_dpi() {
+	let image = RustImageData::from_rgba(2, 1, vec![255; 8]).unwrap();
+	assert_eq!(image.dpi(), None);
+
+	let png = image.with_dpi(Some((144.0, 72.0))).to_png().unwrap();
+	let image = RustImageData::from_bytes(png.get_bytes()).unwrap();
+	assert_eq!(image.get_size(), (2, 1));
+	assert_dpi(&image, 144.0, 72.0);
+
+	// the pHYs chunk is replaced, not duplicated
+	let png = image.with_dpi(Some((300.0, 300.0))).to_png().unwrap();
+	let image = RustImageData::from_bytes(png.get_bytes()).unwrap();
+	assert_dpi(&image, 300.0, 300.0);
+}
+
+#[test]
+fn test_png_without_dpi() {
+	let png = RustImageData::from_rgba(1, 1, vec![0, 0, 0, 255])
+		.unwrap()
+		.to_png()
+		.unwrap();
+	let image = RustImageData::from_bytes(png.get_bytes()).unwrap();
+	assert_eq!(image.dpi(), None);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_transforms_keep_dpi() {
+	let image = RustImageData::from_rgba(2, 2, vec![255; 16])
+		.unwrap()
+		.with_dpi(Some((144.0, 72.0)));
+	assert_dpi(&image.crop(0, 0, 1, 1).unwrap(), 144.0, 72.0);
+	assert_dpi(&image.rotate90().unwrap(), 72.0, 144.0);
+	assert_eq!(image.thumbnail(1, 1).unwrap().dpi(), None);
+}
+
+// EXIF orientation is applied when the image crate decodes
+#[cfg(feature = "image")]
+#[test]
+fn test_exif_rotation_swaps_dpi() {
+	let image = RustImageData::from_rgba(4, 2, vec![255; 32]).unwrap();
+	let mut jpeg = image.to_jpeg().unwrap().get_bytes().to_vec();
+	// a JFIF header of 300 x 150 dots per inch
+	assert_eq!(&jpeg[6..11], b"JFIF\0");
+	jpeg[13] = 1;
+	jpeg[14..18].copy_from_slice(&[1, 44, 0, 150]);
+	let app0_end = 4 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+
+	for (orientation, size, x, y) in [(1, (4, 2), 300.0, 150.0), (6, (2, 4), 150.0, 300.0)] {
+		// an APP1 segment with a little endian TIFF header and one IFD holding the orientation
+		let mut exif = vec![0xFF, 0xE1, 0, 34];
+		exif.extend_from_slice(b"Exif\0\0II*\0");
+		exif.extend_from_slice(&[8, 0, 0, 0, 1, 0, 0x12, 0x01, 3, 0, 1, 0, 0, 0]);
+		exif.extend_from_slice(&[orientation, 0, 0, 0, 0, 0, 0, 0]);
+		let bytes = [&jpeg[..app0_end], &exif, &jpeg[app0_end..]].concat();
+
+		let image = RustImageData::from_bytes(&bytes).unwrap();
+		assert_eq!(image.get_size(), size);
+		assert_dpi(&image, x, y);
+	}
+}
@@ -0,0 +1,33 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent};
+
+#[test]
+fn test_dump() {
+	let clipboard = MockClipboard::new();
+	assert_eq!(
+		clipboard.dump(16).unwrap().to_string(),
+		"clipboard is empty\n"
+	);
+
+	clipboard
+		.set(vec![
+			ClipboardContent::Text("hello clipboard, a longer text".to_string()),
+			ClipboardContent::Other("application/x-blob".to_string(), vec![0, 1, 2, 0xff]),
+		])
+		.unwrap();
+	let dump = clipboard.dump(16).unwrap();
+	assert_eq!(dump.formats.len(), 2);
+
+	let text = &dump.formats[0];
+	assert_eq!(text.info.name, "text/plain");
+	assert_eq!(text.size, Some(30));
+	assert_eq!(text.preview, b"hello clipboard,");
+	assert_eq!(text.preview_text(), "hello clipboard,");
+	assert!(text.error.is_none());
+
+	let printed = dump.to_string();
+	assert!(printed.contains("[0] text/plain (text/plain), 30 bytes"));
+	assert!(printed.contains("|hello clipboard,|"));
+	assert!(printed.contains("[1] application/x-blob (application/x-blob), 4 bytes"));
+	assert!(printed.contains("00 01 02 ff"));
+}
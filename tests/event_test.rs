@@ -0,0 +1,72 @@
+use clipboard_rs::event::{ClipboardEvent, EventOptions, EventWriter, CLIPBOARD_EVENT_VERSION};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardHandler};
+
+#[test]
+fn test_event_capture() {
+	let clipboard = MockClipboard::new();
+	clipboard
+		.set(vec![
+			ClipboardContent::Text("hello".to_string()),
+			ClipboardContent::Other("application/x-blob".to_string(), vec![0, 1, 2, 0xff]),
+		])
+		.unwrap();
+
+	let options = EventOptions {
+		preview_len: 3,
+		include_content: true,
+	};
+	let event = ClipboardEvent::capture(&clipboard, &options).unwrap();
+	assert_eq!(event.version, CLIPBOARD_EVENT_VERSION);
+	assert_eq!(event.hash.len(), 16);
+	assert_eq!(event.formats.len(), 2);
+
+	let text = &event.formats[0];
+	assert_eq!(text.name, "text/plain");
+	assert_eq!(text.size, Some(5));
+	assert_eq!(text.preview.as_deref(), Some("aGVs"));
+	assert_eq!(text.content.as_deref(), Some("aGVsbG8="));
+	assert!(text.error.is_none());
+
+	let blob = &event.formats[1];
+	assert_eq!(blob.content.as_deref(), Some("AAEC/w=="));
+	assert_ne!(blob.hash, text.hash);
+
+	// the same clipboard always gives the same hashes
+	let again = ClipboardEvent::capture(&clipboard, &EventOptions::default()).unwrap();
+	assert_eq!(again.hash, event.hash);
+	assert!(again.formats[0].content.is_none());
+}
+
+#[test]
+fn test_event_writer() {
+	let clipboard = MockClipboard::new();
+	clipboard.set_text("say \"hi\"\n".to_string()).unwrap();
+
+	let mut writer = EventWriter::new(clipboard, Vec::new(), EventOptions::default());
+	writer.on_clipboard_change();
+	let output = String::from_utf8(writer.into_inner()).unwrap();
+	assert!(output.ends_with("}\n"));
+	assert_eq!(output.lines().count(), 1);
+	assert!(output.starts_with("{\"version\":1,\"timestamp\":"));
+	assert!(output.contains("\"name\":\"text/plain\""));
+	assert!(output.contains("\"content\":null"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_event_json_matches_serde() {
+	let clipboard = MockClipboard::new();
+	clipboard.set_text("tab\there".to_string()).unwrap();
+	let event = ClipboardEvent::capture(&clipboard, &EventOptions::default()).unwrap();
+
+	let json = event.to_json();
+	assert_eq!(
+		serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+		serde_json::to_value(&event).unwrap()
+	);
+	assert_eq!(
+		serde_json::from_str::<ClipboardEvent>(&json).unwrap(),
+		event
+	);
+}
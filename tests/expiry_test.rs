@@ -0,0 +1,44 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat};
+use std::thread;
+use std::time::Duration;
+
+fn secret() -> Vec<ClipboardContent> {
+	vec![ClipboardContent::Text("hunter2".to_string())]
+}
+
+#[test]
+fn test_expiry_clears() {
+	let clipboard = MockClipboard::new();
+	// dropping the handle keeps the timer running
+	drop(
+		clipboard
+			.set_with_expiry(secret(), Duration::from_millis(50))
+			.unwrap(),
+	);
+	assert_eq!(clipboard.get_text().unwrap(), "hunter2");
+	thread::sleep(Duration::from_millis(300));
+	assert!(!clipboard.has(ContentFormat::Text));
+}
+
+#[test]
+fn test_expiry_keeps_newer_contents() {
+	let clipboard = MockClipboard::new();
+	let _expiry = clipboard
+		.set_with_expiry(secret(), Duration::from_millis(50))
+		.unwrap();
+	clipboard.inject(vec![ClipboardContent::Text("newer".to_string())]);
+	thread::sleep(Duration::from_millis(300));
+	assert_eq!(clipboard.get_text().unwrap(), "newer");
+}
+
+#[test]
+fn test_expiry_cancel() {
+	let clipboard = MockClipboard::new();
+	let expiry = clipboard
+		.set_with_expiry(secret(), Duration::from_millis(50))
+		.unwrap();
+	expiry.cancel();
+	thread::sleep(Duration::from_millis(300));
+	assert_eq!(clipboard.get_text().unwrap(), "hunter2");
+}
@@ -0,0 +1,24 @@
+#![cfg(feature = "ffi")]
+use clipboard_rs::ffi::*;
+use std::ffi::{CStr, CString};
+
+#[test]
+fn test_ffi_text() {
+	unsafe {
+		let clipboard = clipboard_new();
+		assert!(!clipboard.is_null());
+
+		let text = CString::new("ffi text").unwrap();
+		assert_eq!(clipboard_set_text(clipboard, text.as_ptr()), 0);
+		assert!(clipboard_has(clipboard, ClipboardFormat::Text));
+		let read = clipboard_get_text(clipboard);
+		assert_eq!(CStr::from_ptr(read).to_str().unwrap(), "ffi text");
+		clipboard_string_free(read);
+
+		// failures are reported through the last error
+		assert_eq!(clipboard_set_text(clipboard, std::ptr::null()), -1);
+		assert!(!clipboard_last_error().is_null());
+
+		clipboard_free(clipboard);
+	}
+}
@@ -0,0 +1,58 @@
+#![cfg(all(
+	unix,
+	not(any(
+		target_os = "macos",
+		target_os = "ios",
+		target_os = "android",
+		target_os = "emscripten"
+	))
+))]
+use clipboard_rs::{Clipboard, ClipboardContext, ContentFormat, FileOperation};
+use std::path::PathBuf;
+
+#[test]
+fn test_file_manager_targets() {
+	let ctx = ClipboardContext::new().unwrap();
+
+	// only the target nautilus reads, as some file managers publish it
+	ctx.set_buffer(
+		"x-special/gnome-copied-files",
+		b"cut\nfile:///tmp/a%20b.txt\nfile:///tmp/c.txt".to_vec(),
+	)
+	.unwrap();
+	assert!(ctx.has(ContentFormat::Files));
+	assert_eq!(
+		ctx.get_files().unwrap(),
+		vec!["file:///tmp/a%20b.txt", "file:///tmp/c.txt"]
+	);
+	assert_eq!(
+		ctx.get_file_paths().unwrap(),
+		vec![PathBuf::from("/tmp/a b.txt"), PathBuf::from("/tmp/c.txt")]
+	);
+	assert_eq!(ctx.get_file_operation().unwrap(), FileOperation::Cut);
+
+	// the nautilus 3.30+ layout starts with its target name
+	ctx.set_buffer(
+		"x-special/nautilus-clipboard",
+		b"x-special/nautilus-clipboard\ncopy\nfile:///tmp/d.txt\n".to_vec(),
+	)
+	.unwrap();
+	assert_eq!(ctx.get_files().unwrap(), vec!["file:///tmp/d.txt"]);
+	assert_eq!(ctx.get_file_operation().unwrap(), FileOperation::Copy);
+
+	// writing offers every file manager target with the operation
+	ctx.set_files_with_operation(vec!["/tmp/e.txt".to_string()], FileOperation::Cut)
+		.unwrap();
+	assert_eq!(
+		ctx.get_buffer("x-special/gnome-copied-files").unwrap(),
+		b"cut\nfile:///tmp/e.txt"
+	);
+	assert_eq!(
+		ctx.get_buffer("x-special/nautilus-clipboard").unwrap(),
+		b"x-special/nautilus-clipboard\ncut\nfile:///tmp/e.txt\n"
+	);
+	assert_eq!(
+		ctx.get_buffer("application/x-kde-cutselection").unwrap(),
+		b"1"
+	);
+}
@@ -1,4 +1,4 @@
-use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext, ContentFormat};
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext, ContentFormat, FileOperation};
 
 #[cfg(target_os = "macos")]
 const TMP_PATH: &str = "/tmp/";
@@ -74,6 +74,19 @@ fn test_file() {
 			_ => panic!("unexpected format"),
 		}
 	}
+
+	ctx.set_files(file_list.clone()).unwrap();
+	let paths = ctx.get_file_paths().unwrap();
+	assert_eq!(paths.len(), 2);
+	assert!(paths.iter().all(|p| p.exists()));
+	assert_eq!(ctx.get_file_operation().unwrap(), FileOperation::Copy);
+
+	ctx.set_files_with_operation(file_list.clone(), FileOperation::Cut)
+		.unwrap();
+	assert_eq!(ctx.get_files().unwrap().len(), 2);
+	// macOS can't mark files as cut
+	#[cfg(not(target_os = "macos"))]
+	assert_eq!(ctx.get_file_operation().unwrap(), FileOperation::Cut);
 }
 
 fn get_files() -> Vec<String> {
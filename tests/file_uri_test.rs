@@ -0,0 +1,37 @@
+use clipboard_rs::common::{file_uri_to_path, path_to_file_uri};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_file_uri_to_path() {
+	assert_eq!(
+		file_uri_to_path("file:///tmp/a%20b%23c.txt"),
+		Some(PathBuf::from("/tmp/a b#c.txt"))
+	);
+	assert_eq!(
+		file_uri_to_path("file://localhost/tmp/a.txt"),
+		Some(PathBuf::from("/tmp/a.txt"))
+	);
+	assert_eq!(file_uri_to_path("https://example.com/a.txt"), None);
+	assert_eq!(file_uri_to_path("file://host/a.txt"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_path() {
+	use std::ffi::OsStr;
+	use std::os::unix::ffi::OsStrExt;
+
+	let path = Path::new(OsStr::from_bytes(b"/tmp/\xff.txt"));
+	let uri = path_to_file_uri(path);
+	assert_eq!(uri, "file:///tmp/%FF.txt");
+	assert_eq!(file_uri_to_path(&uri).unwrap(), path);
+}
+
+#[test]
+fn test_path_round_trip() {
+	let path = Path::new("/tmp/clipboard rs/#1 100%/文件.txt");
+	let uri = path_to_file_uri(path);
+	assert!(!uri.contains(' '));
+	assert!(!uri.contains('#'));
+	assert_eq!(file_uri_to_path(&uri).unwrap(), path);
+}
@@ -0,0 +1,46 @@
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext, ContentFormat};
+
+#[test]
+fn test_format_handle() {
+	let ctx = ClipboardContext::new().unwrap();
+	let format = ctx
+		.register_format("application/x-clipboard-rs-handle-test")
+		.unwrap();
+	assert_eq!(format.name(), "application/x-clipboard-rs-handle-test");
+	assert_eq!(
+		ctx.register_format(format.name()).unwrap(),
+		format,
+		"registering twice should give the same handle"
+	);
+
+	let buffer = "handle test".as_bytes().to_vec();
+	ctx.set_buffer_by_handle(&format, buffer.clone()).unwrap();
+	let read = ctx.get_buffer_by_handle(&format).unwrap();
+	assert_eq!(&read[..buffer.len()], buffer.as_slice());
+	// the handle and the name refer to the same format
+	let read = ctx.get_buffer(format.name()).unwrap();
+	assert_eq!(&read[..buffer.len()], buffer.as_slice());
+}
+
+#[test]
+fn test_custom_content_format() {
+	let ctx = ClipboardContext::new().unwrap();
+	let format = ctx
+		.register_format("application/x-clipboard-rs-custom-test")
+		.unwrap();
+	ctx.set(vec![ClipboardContent::Custom(
+		format.clone(),
+		"custom test".as_bytes().to_vec(),
+	)])
+	.unwrap();
+	assert!(ctx.has(ContentFormat::Custom(format.clone())));
+
+	let contents = ctx.get(&[ContentFormat::Custom(format.clone())]).unwrap();
+	match contents.as_slice() {
+		[ClipboardContent::Custom(read_format, data)] => {
+			assert_eq!(read_format, &format);
+			assert!(data.starts_with(b"custom test"));
+		}
+		other => panic!("unexpected contents {:?}", other),
+	}
+}
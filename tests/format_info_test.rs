@@ -0,0 +1,18 @@
+use clipboard_rs::{Clipboard, ClipboardContext};
+
+#[test]
+fn test_available_formats_detailed() {
+	let ctx = ClipboardContext::new().unwrap();
+
+	ctx.set_html("<b>hello</b>".to_string()).unwrap();
+
+	let names = ctx.available_formats().unwrap();
+	let formats = ctx.available_formats_detailed().unwrap();
+	assert_eq!(
+		formats.iter().map(|f| f.name.clone()).collect::<Vec<_>>(),
+		names
+	);
+	assert!(formats
+		.iter()
+		.any(|f| f.mime.as_deref() == Some("text/html")));
+}
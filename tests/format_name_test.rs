@@ -0,0 +1,78 @@
+use clipboard_rs::{ContentFormat, Platform};
+
+#[test]
+fn test_format_display_round_trip() {
+	let formats = [
+		ContentFormat::Text,
+		ContentFormat::Rtf,
+		ContentFormat::Html,
+		ContentFormat::Svg,
+		ContentFormat::Markdown,
+		ContentFormat::Image,
+		ContentFormat::Files,
+		ContentFormat::Url,
+		ContentFormat::Color,
+		ContentFormat::Pdf,
+		ContentFormat::Other("application/x-custom".to_string()),
+	];
+	for format in formats {
+		let parsed: ContentFormat = format.to_string().parse().unwrap();
+		assert_eq!(parsed, format);
+	}
+}
+
+#[test]
+fn test_format_parse_aliases() {
+	assert_eq!(
+		"HTML".parse::<ContentFormat>().unwrap(),
+		ContentFormat::Html
+	);
+	assert_eq!(
+		"Text/Plain".parse::<ContentFormat>().unwrap(),
+		ContentFormat::Text
+	);
+	assert_eq!(
+		"my-format".parse::<ContentFormat>().unwrap(),
+		ContentFormat::Other("my-format".to_string())
+	);
+	assert!("".parse::<ContentFormat>().is_err());
+}
+
+#[test]
+fn test_platform_names() {
+	assert_eq!(
+		ContentFormat::Html.to_platform_name_for(Platform::Windows),
+		Some("HTML Format".to_string())
+	);
+	assert_eq!(
+		ContentFormat::Html.to_platform_name_for(Platform::MacOs),
+		Some("public.html".to_string())
+	);
+	assert_eq!(
+		ContentFormat::Html.to_platform_name_for(Platform::Linux),
+		Some("text/html".to_string())
+	);
+	assert_eq!(
+		ContentFormat::from_platform_name_for("public.rtf", Platform::MacOs),
+		ContentFormat::Rtf
+	);
+	assert_eq!(
+		ContentFormat::from_platform_name_for("custom", Platform::Linux),
+		ContentFormat::Other("custom".to_string())
+	);
+	assert_eq!(
+		ContentFormat::Other("custom".to_string()).to_platform_name_for(Platform::Windows),
+		Some("custom".to_string())
+	);
+	for platform in [Platform::Windows, Platform::MacOs, Platform::Linux] {
+		let name = ContentFormat::Url.to_platform_name_for(platform).unwrap();
+		assert_eq!(
+			ContentFormat::from_platform_name_for(&name, platform),
+			ContentFormat::Url
+		);
+	}
+	assert_eq!(
+		ContentFormat::from_platform_name(&ContentFormat::Image.to_platform_name().unwrap()),
+		ContentFormat::Image
+	);
+}
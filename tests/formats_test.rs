@@ -0,0 +1,100 @@
+use clipboard_rs::formats::{
+	decode_web_custom_data, encode_moz_url, encode_web_custom_data, parse_moz_url,
+	parse_source_url, read_web_custom_data, VsCodeEditorData, CHROMIUM_SOURCE_URL_LINUX, MOZ_URL,
+	VSCODE_EDITOR_DATA, WEB_CUSTOM_DATA,
+};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, Url};
+use std::collections::HashMap;
+
+#[test]
+fn test_moz_url() {
+	let urls = vec![
+		Url {
+			url: "https://example.com/".to_string(),
+			title: Some("Example".to_string()),
+		},
+		Url::new("https://rust-lang.org/"),
+	];
+	let clipboard = MockClipboard::new();
+	clipboard
+		.set(vec![
+			ClipboardContent::Other(MOZ_URL.to_string(), encode_moz_url(&urls)),
+			ClipboardContent::Other(
+				CHROMIUM_SOURCE_URL_LINUX.to_string(),
+				b"https://example.com/page\0".to_vec(),
+			),
+		])
+		.unwrap();
+	assert_eq!(parse_moz_url(&clipboard.get_buffer(MOZ_URL).unwrap()), urls);
+	assert_eq!(
+		parse_moz_url(b"https://example.com/\nExample"),
+		urls[..1].to_vec()
+	);
+	assert_eq!(
+		parse_source_url(&clipboard.get_buffer(CHROMIUM_SOURCE_URL_LINUX).unwrap()).as_deref(),
+		Some("https://example.com/page")
+	);
+	assert!(parse_source_url(b"").is_none());
+}
+
+#[test]
+fn test_vscode_editor_data() {
+	let data = VsCodeEditorData::parse(
+		r#"{"version":1,"isFromEmptySelection":true,"multicursorText":["a\"b","é"],"mode":"rust","extra":null}"#,
+	)
+	.unwrap();
+	assert_eq!(
+		data,
+		VsCodeEditorData {
+			version: 1,
+			is_from_empty_selection: true,
+			multicursor_text: Some(vec!["a\"b".to_string(), "é".to_string()]),
+			mode: Some("rust".to_string()),
+		}
+	);
+	assert_eq!(VsCodeEditorData::parse(&data.to_json()).unwrap(), data);
+	assert!(VsCodeEditorData::parse("{\"mode\":").is_err());
+
+	// a lone surrogate becomes U+FFFD without swallowing what follows it
+	let data = VsCodeEditorData::parse(
+		r#"{"mode":"\ud800","multicursorText":["\ud83d\ude00","\ud800\n"],"version":2}"#,
+	)
+	.unwrap();
+	assert_eq!(data.mode.as_deref(), Some("\u{fffd}"));
+	assert_eq!(
+		data.multicursor_text,
+		Some(vec!["😀".to_string(), "\u{fffd}\n".to_string()])
+	);
+	assert_eq!(data.version, 2);
+}
+
+#[test]
+fn test_web_custom_data() {
+	// a pickle as Chromium writes it: one entry, "a" = "xyz", each string padded to 4 bytes
+	let pickle = [
+		24, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, b'a', 0, 0, 0, 3, 0, 0, 0, b'x', 0, b'y', 0, b'z', 0,
+		0, 0,
+	];
+	let entries = decode_web_custom_data(&pickle).unwrap();
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries["a"], "xyz");
+	assert_eq!(encode_web_custom_data(&entries), pickle.to_vec());
+	assert!(decode_web_custom_data(&pickle[..16]).is_err());
+
+	let mut entries = HashMap::new();
+	entries.insert(
+		VSCODE_EDITOR_DATA.to_string(),
+		"{\"version\":1,\"mode\":\"rust\"}".to_string(),
+	);
+	entries.insert("text/x-custom".to_string(), "héllo".to_string());
+	let clipboard = MockClipboard::new();
+	assert!(read_web_custom_data(&clipboard).unwrap().is_empty());
+	clipboard
+		.set_buffer(WEB_CUSTOM_DATA, encode_web_custom_data(&entries))
+		.unwrap();
+	let read = read_web_custom_data(&clipboard).unwrap();
+	assert_eq!(read, entries);
+	let editor = VsCodeEditorData::parse(&read[VSCODE_EDITOR_DATA]).unwrap();
+	assert_eq!(editor.mode.as_deref(), Some("rust"));
+}
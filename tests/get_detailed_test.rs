@@ -0,0 +1,34 @@
+use clipboard_rs::common::is_missing_format;
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat};
+
+#[test]
+fn test_get_detailed() {
+	let clipboard = MockClipboard::new();
+	clipboard
+		.set(vec![
+			ClipboardContent::Text("text".to_string()),
+			// data under the html name that isn't an html content
+			ClipboardContent::Other("text/html".to_string(), vec![0xff, 0xfe]),
+		])
+		.unwrap();
+
+	let results =
+		clipboard.get_detailed(&[ContentFormat::Rtf, ContentFormat::Text, ContentFormat::Html]);
+	assert_eq!(results.len(), 3);
+
+	let (format, rtf) = &results[0];
+	assert_eq!(*format, ContentFormat::Rtf);
+	assert!(is_missing_format(rtf.as_ref().unwrap_err().as_ref()));
+
+	let (format, text) = &results[1];
+	assert_eq!(*format, ContentFormat::Text);
+	assert_eq!(
+		text.as_ref().unwrap(),
+		&ClipboardContent::Text("text".to_string())
+	);
+
+	let (format, html) = &results[2];
+	assert_eq!(*format, ContentFormat::Html);
+	assert!(!is_missing_format(html.as_ref().unwrap_err().as_ref()));
+}
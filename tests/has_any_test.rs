@@ -0,0 +1,24 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat};
+
+#[test]
+fn test_is_empty() {
+	let clipboard = MockClipboard::new();
+	assert!(clipboard.is_empty());
+
+	clipboard.inject(vec![ClipboardContent::Text("text".to_string())]);
+	assert!(!clipboard.is_empty());
+
+	clipboard.clear().unwrap();
+	assert!(clipboard.is_empty());
+}
+
+#[test]
+fn test_has_any() {
+	let clipboard = MockClipboard::new();
+	clipboard.inject(vec![ClipboardContent::Html("<b>text</b>".to_string())]);
+
+	assert!(clipboard.has_any(&[ContentFormat::Text, ContentFormat::Html]));
+	assert!(!clipboard.has_any(&[ContentFormat::Text, ContentFormat::Image]));
+	assert!(!clipboard.has_any(&[]));
+}
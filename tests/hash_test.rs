@@ -0,0 +1,63 @@
+use clipboard_rs::common::RustImage;
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat, RustImageData};
+
+#[test]
+fn test_content_hash() {
+	let text = ClipboardContent::Text("line\nline".to_string());
+	assert_eq!(text.content_hash(), text.clone().content_hash());
+	// line endings and trailing NULs are normalized
+	assert_eq!(
+		text.content_hash(),
+		ClipboardContent::Text("line\r\nline\0".to_string()).content_hash()
+	);
+	// the same bytes in another format differ
+	assert_ne!(
+		text.content_hash(),
+		ClipboardContent::Html("line\nline".to_string()).content_hash()
+	);
+}
+
+#[test]
+fn test_image_hash() {
+	let red = RustImageData::from_rgba(2, 2, [255, 0, 0, 255].repeat(4)).unwrap();
+	let blue = RustImageData::from_rgba(2, 2, [0, 0, 255, 255].repeat(4)).unwrap();
+	let red_png = RustImageData::from_bytes(red.to_png().unwrap().get_bytes()).unwrap();
+	let blue_png = RustImageData::from_bytes(blue.to_png().unwrap().get_bytes()).unwrap();
+	let hash = |image: &RustImageData| ClipboardContent::Image(image.clone()).content_hash();
+
+	// images read from bytes are told apart with or without a codec to decode them
+	assert_eq!(hash(&red_png), hash(&red_png.clone()));
+	assert_ne!(hash(&red_png), hash(&blue_png));
+	assert_ne!(hash(&red), hash(&blue));
+	// with the `image` crate they hash by their pixels, not their encoding
+	if cfg!(feature = "image") {
+		assert_eq!(hash(&red), hash(&red_png));
+	}
+}
+
+#[test]
+fn test_current_hash() {
+	let clipboard = MockClipboard::new();
+	clipboard.inject(vec![
+		ClipboardContent::Text("text".to_string()),
+		ClipboardContent::Html("<b>text</b>".to_string()),
+	]);
+	let hash = clipboard
+		.current_hash(&[ContentFormat::Text, ContentFormat::Html])
+		.unwrap();
+	assert_eq!(
+		hash,
+		clipboard
+			.current_hash(&[ContentFormat::Html, ContentFormat::Text])
+			.unwrap()
+	);
+
+	clipboard.inject(vec![ClipboardContent::Text("changed".to_string())]);
+	assert_ne!(
+		hash,
+		clipboard
+			.current_hash(&[ContentFormat::Text, ContentFormat::Html])
+			.unwrap()
+	);
+}
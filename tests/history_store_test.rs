@@ -0,0 +1,145 @@
+#![cfg(feature = "history")]
+use clipboard_rs::common::RustImage;
+use clipboard_rs::history::{
+	ClipboardHistory, FileHistoryStore, HistoryEntry, HistoryOptions, HistoryStore,
+};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{ClipboardContent, RustImageData, Url};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+fn store_path(name: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!(
+		"clipboard-rs-{}-{}.history",
+		name,
+		std::process::id()
+	));
+	let _ = std::fs::remove_file(&path);
+	path
+}
+
+#[test]
+fn test_file_store_round_trip() {
+	let path = store_path("round-trip");
+	let store = FileHistoryStore::new(&path).with_thumbnail_size(8);
+	let image = RustImageData::from_rgba(32, 16, vec![255; 32 * 16 * 4]).unwrap();
+	let entries = vec![
+		HistoryEntry {
+			contents: vec![
+				ClipboardContent::Text("text".to_string()),
+				ClipboardContent::Html("<b>html</b>".to_string()),
+				ClipboardContent::Urls(vec![Url::with_title("https://example.com", "title")]),
+				ClipboardContent::Other("custom".to_string(), vec![1, 2, 3]),
+			],
+			time: SystemTime::now(),
+			thumbnail: None,
+		},
+		HistoryEntry {
+			contents: vec![
+				ClipboardContent::Image(image),
+				ClipboardContent::Files(vec!["/tmp/a".to_string()]),
+			],
+			time: SystemTime::now(),
+			thumbnail: None,
+		},
+	];
+	store.save(&entries).unwrap();
+
+	let loaded = store.load().unwrap();
+	assert_eq!(loaded.len(), 2);
+	assert_eq!(loaded[0], entries[0]);
+	match &loaded[1].contents[0] {
+		ClipboardContent::Image(image) => assert_eq!(image.get_size(), (8, 4)),
+		content => panic!("unexpected content {:?}", content),
+	}
+	assert_eq!(loaded[1].contents[1], entries[1].contents[1]);
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file_store_pruning() {
+	let path = store_path("pruning");
+	let old = HistoryEntry {
+		contents: vec![ClipboardContent::Text("old".to_string())],
+		time: SystemTime::now() - Duration::from_secs(3600),
+		thumbnail: None,
+	};
+	let new = HistoryEntry {
+		contents: vec![ClipboardContent::Text("new".to_string())],
+		time: SystemTime::now(),
+		thumbnail: None,
+	};
+
+	let store = FileHistoryStore::new(&path).with_max_age(Duration::from_secs(60));
+	store.save(&[new.clone(), old.clone()]).unwrap();
+	assert_eq!(store.load().unwrap(), vec![new.clone()]);
+
+	// room for the header and one entry only
+	let store = FileHistoryStore::new(&path).with_max_size(40);
+	store.save(&[new.clone(), old]).unwrap();
+	assert_eq!(store.load().unwrap(), vec![new]);
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_history_with_store() {
+	let path = store_path("history");
+	let clipboard = MockClipboard::new();
+	let history = ClipboardHistory::with_store(
+		clipboard.clone(),
+		HistoryOptions::default(),
+		FileHistoryStore::new(&path),
+	)
+	.unwrap();
+	history
+		.push(vec![ClipboardContent::Text("kept".to_string())])
+		.unwrap();
+
+	// a new history, e.g. after a restart, starts with the saved entries
+	let history = ClipboardHistory::with_store(
+		clipboard,
+		HistoryOptions::default(),
+		FileHistoryStore::new(&path),
+	)
+	.unwrap();
+	assert_eq!(history.len(), 1);
+	assert_eq!(
+		history.get(0).unwrap().contents,
+		vec![ClipboardContent::Text("kept".to_string())]
+	);
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn test_file_store_compression() {
+	let plain_path = store_path("uncompressed");
+	let path = store_path("compressed");
+	let html = "<p>repeated paragraph</p>".repeat(1000);
+	let entries = vec![HistoryEntry {
+		contents: vec![
+			ClipboardContent::Html(html.clone()),
+			ClipboardContent::Other("custom".to_string(), vec![7; 4096]),
+			ClipboardContent::Text("short".to_string()),
+		],
+		time: SystemTime::now(),
+		thumbnail: None,
+	}];
+
+	FileHistoryStore::new(&plain_path).save(&entries).unwrap();
+	let store = FileHistoryStore::new(&path).with_compression();
+	store.save(&entries).unwrap();
+	let plain_size = std::fs::metadata(&plain_path).unwrap().len();
+	let size = std::fs::metadata(&path).unwrap().len();
+	assert!(size * 10 < plain_size);
+
+	let loaded = store.load().unwrap();
+	assert_eq!(loaded[0].contents, entries[0].contents);
+	// uncompressed files still load
+	assert_eq!(
+		FileHistoryStore::new(&plain_path).load().unwrap()[0].contents,
+		entries[0].contents
+	);
+	let _ = std::fs::remove_file(&path);
+	let _ = std::fs::remove_file(&plain_path);
+}
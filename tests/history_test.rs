@@ -0,0 +1,99 @@
+#![cfg(feature = "history")]
+use clipboard_rs::common::RustImage;
+use clipboard_rs::history::{ClipboardHistory, HistoryOptions};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardHandler, RustImageData};
+
+fn text(s: &str) -> Vec<ClipboardContent> {
+	vec![ClipboardContent::Text(s.to_string())]
+}
+
+#[test]
+fn test_history_records_changes() {
+	let clipboard = MockClipboard::new();
+	let mut history = ClipboardHistory::new(
+		clipboard.clone(),
+		HistoryOptions {
+			capacity: 2,
+			..Default::default()
+		},
+	);
+
+	for s in ["one", "two", "three"] {
+		clipboard.inject(text(s));
+		history.on_clipboard_change();
+	}
+	// the same copy reported again is not recorded twice
+	history.on_clipboard_change();
+
+	assert_eq!(history.len(), 2);
+	assert_eq!(history.get(0).unwrap().contents, text("three"));
+	assert_eq!(history.get(1).unwrap().contents, text("two"));
+}
+
+#[test]
+fn test_history_dedupe_and_size_cap() {
+	let clipboard = MockClipboard::new();
+	let history = ClipboardHistory::new(
+		clipboard.clone(),
+		HistoryOptions {
+			max_content_size: 8,
+			..Default::default()
+		},
+	);
+
+	assert!(history.push(text("a")).unwrap());
+	assert!(history.push(text("b")).unwrap());
+	assert!(history.push(text("a")).unwrap());
+	assert_eq!(history.len(), 2);
+	assert_eq!(history.get(0).unwrap().contents, text("a"));
+
+	clipboard.inject(text("larger than the cap"));
+	assert!(!history.record().unwrap());
+	assert_eq!(history.len(), 2);
+}
+
+#[test]
+fn test_history_restore() {
+	let clipboard = MockClipboard::new();
+	let history = ClipboardHistory::new(clipboard.clone(), HistoryOptions::default());
+	history.push(text("old")).unwrap();
+	history.push(text("new")).unwrap();
+
+	history.restore(1).unwrap();
+	assert_eq!(clipboard.get_text().unwrap(), "old");
+	assert_eq!(history.get(0).unwrap().contents, text("old"));
+	assert_eq!(history.len(), 2);
+	// the watcher sees the restored entry as the newest one already
+	assert!(!history.record().unwrap());
+
+	assert!(history.restore(5).is_err());
+}
+
+#[test]
+fn test_history_thumbnail() {
+	let clipboard = MockClipboard::new();
+	let history = ClipboardHistory::new(
+		clipboard.clone(),
+		HistoryOptions {
+			thumbnail_size: Some(16),
+			..Default::default()
+		},
+	);
+	let image = RustImageData::from_rgba(64, 32, vec![255; 64 * 32 * 4]).unwrap();
+	clipboard.set_image(image).unwrap();
+	assert!(history.record().unwrap());
+
+	let entry = history.get(0).unwrap();
+	assert_eq!(entry.thumbnail.as_ref().unwrap().get_size(), (16, 8));
+	assert_eq!(entry.preview().unwrap().get_size(), (16, 8));
+	// the entry still holds the full image to restore
+	match &entry.contents[0] {
+		ClipboardContent::Image(image) => assert_eq!(image.get_size(), (64, 32)),
+		content => panic!("unexpected content {:?}", content),
+	}
+
+	// text has nothing to preview
+	history.push(text("words")).unwrap();
+	assert!(history.get(0).unwrap().preview().is_none());
+}
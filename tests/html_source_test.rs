@@ -0,0 +1,17 @@
+use clipboard_rs::{Clipboard, ClipboardContext, Url};
+
+#[test]
+fn test_html_with_source() {
+	let ctx = ClipboardContext::new().unwrap();
+	let html = "<p>see <a href=\"/docs\">the docs</a></p>";
+
+	ctx.set_html_with_source(html.to_string(), Url::new("https://example.com/page"))
+		.unwrap();
+	let (read, source) = ctx.get_html_with_source().unwrap();
+	assert!(read.contains("the docs"));
+	assert_eq!(source, Some(Url::new("https://example.com/page")));
+
+	// html copied without a source has none
+	ctx.set_html(html.to_string()).unwrap();
+	assert_eq!(ctx.get_html_with_source().unwrap().1, None);
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "image")]
+use clipboard_rs::common::{RustImage, RustImageData};
+use clipboard_rs::{EncodeOptions, PngCompression};
+
+#[test]
+fn test_rgba_access() {
+	let pixels: Vec<u8> = (0..4 * 3 * 4).map(|i| i as u8).collect();
+	let image = RustImageData::from_rgba(4, 3, pixels.clone()).unwrap();
+	assert_eq!(image.get_size(), (4, 3));
+	assert_eq!(image.as_rgba8().unwrap().as_raw(), &pixels);
+
+	let dynamic_image = image.into_dynamic_image().unwrap();
+	assert_eq!(dynamic_image.to_rgba8().into_raw(), pixels);
+
+	assert!(RustImageData::from_rgba(4, 4, pixels).is_err());
+	assert!(RustImageData::empty().as_rgba8().is_none());
+}
+
+#[test]
+fn test_encode_options() {
+	let image = RustImageData::from_path("tests/test.png").unwrap();
+
+	let low = image.to_jpeg_with_quality(10).unwrap();
+	let high = image.to_jpeg_with_quality(95).unwrap();
+	assert!(low.get_bytes().len() < high.get_bytes().len());
+
+	let options = EncodeOptions {
+		png_compression: PngCompression::Best,
+		..EncodeOptions::default()
+	};
+	let png = image.to_png_with_options(&options).unwrap();
+	let decoded = RustImageData::from_bytes(png.get_bytes()).unwrap();
+	assert_eq!(decoded.get_size(), image.get_size());
+}
+
+#[test]
+fn test_transforms() {
+	let pixels: Vec<u8> = (0..4 * 2 * 4).map(|i| i as u8).collect();
+	let image = RustImageData::from_rgba(4, 2, pixels).unwrap();
+
+	assert_eq!(image.rotate90().unwrap().get_size(), (2, 4));
+	assert_eq!(image.rotate180().unwrap().get_size(), (4, 2));
+	assert_eq!(image.rotate270().unwrap().get_size(), (2, 4));
+
+	let flipped = image.flip_horizontal().unwrap().flip_horizontal().unwrap();
+	assert_eq!(flipped.to_rgba8().unwrap(), image.to_rgba8().unwrap());
+	let flipped = image.flip_vertical().unwrap();
+	assert_eq!(
+		flipped.to_rgba8().unwrap().get_pixel(0, 0),
+		image.to_rgba8().unwrap().get_pixel(0, 1)
+	);
+
+	let cropped = image.crop(1, 1, 10, 10).unwrap();
+	assert_eq!(cropped.get_size(), (3, 1));
+	assert!(image.crop(4, 0, 1, 1).is_err());
+
+	let gray = image.grayscale().unwrap().to_rgba8().unwrap();
+	assert!(gray.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+}
+
+/// A 16x8 JPEG, dark on the left half and bright on the right, tagged with an EXIF orientation
+fn oriented_jpeg(orientation: u8) -> Vec<u8> {
+	let pixels = (0..8)
+		.flat_map(|_| (0..16).flat_map(|x| if x < 8 { [0, 0, 0, 255] } else { [255; 4] }))
+		.collect();
+	let image = RustImageData::from_rgba(16, 8, pixels).unwrap();
+	let jpeg = image
+		.to_jpeg_with_quality(100)
+		.unwrap()
+		.get_bytes()
+		.to_vec();
+
+	// an APP1 segment holding a little endian TIFF header and one IFD with the orientation tag
+	let mut exif = vec![0xFF, 0xE1, 0, 34];
+	exif.extend_from_slice(b"Exif\0\0II*\0");
+	exif.extend_from_slice(&[8, 0, 0, 0, 1, 0, 0x12, 0x01, 3, 0, 1, 0, 0, 0]);
+	exif.extend_from_slice(&[orientation, 0, 0, 0, 0, 0, 0, 0]);
+	[&jpeg[..2], &exif, &jpeg[2..]].concat()
+}
+
+#[test]
+fn test_exif_orientation() {
+	let dark = |image: &RustImageData, x, y| image.to_rgba8().unwrap().get_pixel(x, y)[0] < 64;
+	for (orientation, size, top_left_dark, far_corner_dark) in [
+		(1, (16, 8), true, false),
+		(3, (16, 8), false, true),
+		(6, (8, 16), true, false),
+		(8, (8, 16), false, true),
+	] {
+		let image = RustImageData::from_bytes(&oriented_jpeg(orientation)).unwrap();
+		assert_eq!(image.get_size(), size, "orientation {orientation}");
+		let (width, height) = size;
+		assert_eq!(
+			dark(&image, 0, 0),
+			top_left_dark,
+			"orientation {orientation}"
+		);
+		assert_eq!(
+			dark(&image, width - 1, height - 1),
+			far_corner_dark,
+			"orientation {orientation}"
+		);
+	}
+}
+
+#[test]
+fn test_gif_round_trip() {
+	use image::codecs::gif::GifEncoder;
+	use image::{Frame, Rgba, RgbaImage};
+
+	let colors = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])];
+	let mut gif = Vec::new();
+	{
+		let mut encoder = GifEncoder::new(&mut gif);
+		let frames = colors
+			.iter()
+			.map(|color| Frame::new(RgbaImage::from_pixel(3, 2, *color)));
+		encoder.encode_frames(frames).unwrap();
+	}
+
+	let image = RustImageData::from_bytes(&gif).unwrap();
+	assert_eq!(image.gif_bytes(), Some(gif.as_slice()));
+	let frames = image.frames().unwrap();
+	assert_eq!(frames.len(), 2);
+	for (frame, color) in frames.iter().zip(colors) {
+		assert_eq!(frame.buffer().dimensions(), (3, 2));
+		assert!(frame.buffer().pixels().all(|pixel| *pixel == color));
+	}
+	assert_eq!(image.to_rgba8().unwrap().get_pixel(0, 0), &colors[0]);
+}
@@ -0,0 +1,34 @@
+use clipboard_rs::common::RustImage;
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ContentFormat, RustImageData};
+use std::path::Path;
+
+#[test]
+fn test_set_image_from_path() {
+	let clipboard = MockClipboard::new();
+	let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test.png");
+	clipboard.set_image_from_path(&path).unwrap();
+
+	let expected = RustImageData::from_path(path.to_str().unwrap()).unwrap();
+	assert_eq!(
+		clipboard.get_image().unwrap().get_size(),
+		expected.get_size()
+	);
+	assert_eq!(clipboard.get_file_paths().unwrap(), vec![path.clone()]);
+	assert!(clipboard
+		.set_image_from_path(Path::new("missing.png"))
+		.is_err());
+
+	clipboard
+		.set_files_then_image(vec![path.to_str().unwrap().to_string()], expected)
+		.unwrap();
+	// the files come first, file managers take them over the image
+	assert_eq!(
+		clipboard.available_formats().unwrap(),
+		vec![
+			ContentFormat::Files.to_string(),
+			ContentFormat::Image.to_string()
+		]
+	);
+	assert_eq!(clipboard.get_file_paths().unwrap(), vec![path]);
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "image")]
+use clipboard_rs::common::RustImage;
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, RustImageData};
+
+#[test]
+fn test_get_image_scaled() {
+	let clipboard = MockClipboard::new();
+	let image = RustImageData::from_rgba(400, 100, vec![128; 400 * 100 * 4]).unwrap();
+	clipboard.set_image(image).unwrap();
+
+	let scaled = clipboard.get_image_scaled(100, 100).unwrap();
+	assert_eq!(scaled.get_size(), (100, 25));
+	// an image already within the bounds keeps its size
+	let same = clipboard.get_image_scaled(1000, 1000).unwrap();
+	assert_eq!(same.get_size(), (400, 100));
+
+	let png = include_bytes!("test.png");
+	let full = RustImageData::from_bytes(png).unwrap();
+	let (width, height) = full.get_size();
+	let scaled = RustImageData::from_bytes_scaled(png, width / 2, height).unwrap();
+	assert!(scaled.get_size().0 <= width / 2);
+}
@@ -23,4 +23,14 @@ fn test_image() {
 		clipboard_img.to_png().unwrap().get_bytes().len(),
 		rust_img_bytes.get_bytes().len()
 	);
+
+	let png_bytes = std::fs::read("tests/test.png").unwrap();
+
+	ctx.set_image_png(png_bytes.clone()).unwrap();
+
+	assert!(ctx.has(ContentFormat::Image));
+
+	let (_format, raw) = ctx.get_image_raw().unwrap();
+
+	assert_eq!(raw, png_bytes);
 }
@@ -0,0 +1,16 @@
+use clipboard_rs::{Clipboard, ClipboardContext};
+
+#[test]
+fn test_large_buffer() {
+	let ctx = ClipboardContext::new().unwrap();
+	let format = "clipboard-rs-large-buffer-test";
+	// larger than a single X11 request, so it is sent with INCR
+	let buffer: Vec<u8> = (0..16 * 1024 * 1024).map(|i| (i % 241) as u8).collect();
+
+	ctx.set_buffer(format, buffer.clone()).unwrap();
+
+	let data = ctx.get_buffer(format).unwrap();
+	// windows may round the allocation up
+	assert!(data.len() >= buffer.len());
+	assert!(data[..buffer.len()] == buffer[..]);
+}
@@ -0,0 +1,81 @@
+use clipboard_rs::common::RustImage;
+use clipboard_rs::limit::{ClipboardOptions, LimitedClipboard, OversizePolicy};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat, RustImageData};
+use std::io::Read;
+
+fn limited(policy: OversizePolicy) -> LimitedClipboard {
+	let mock = MockClipboard::new();
+	mock.set(vec![
+		ClipboardContent::Text("héllo world".to_string()),
+		ClipboardContent::Html("<b>hi</b>".to_string()),
+		ClipboardContent::Image(RustImageData::from_rgba(8, 8, vec![255; 8 * 8 * 4]).unwrap()),
+	])
+	.unwrap();
+	LimitedClipboard::new(mock, ClipboardOptions::new().max_read_size(10, policy))
+}
+
+#[test]
+fn test_limit_error() {
+	let clipboard = limited(OversizePolicy::Error);
+	assert!(clipboard.get_text().is_err());
+	assert_eq!(clipboard.get_html().unwrap(), "<b>hi</b>");
+	assert!(clipboard.get_image().is_err());
+	assert!(clipboard
+		.get(&[ContentFormat::Text, ContentFormat::Html])
+		.is_err());
+	assert!(clipboard.get_buffer("text/plain").is_err());
+
+	let mut data = Vec::new();
+	let read = clipboard
+		.get_reader("text/plain")
+		.unwrap()
+		.read_to_end(&mut data);
+	assert!(read.is_err());
+	assert!(clipboard.has(ContentFormat::Text));
+}
+
+#[test]
+fn test_limit_truncate() {
+	let clipboard = limited(OversizePolicy::Truncate);
+	// cut on a character boundary, "é" takes two bytes
+	assert_eq!(clipboard.get_text().unwrap(), "héllo wor");
+	assert_eq!(clipboard.get_buffer("text/plain").unwrap().len(), 10);
+	// images can't be cut
+	assert!(clipboard.get_image().is_err());
+	assert!(!clipboard.has(ContentFormat::Image));
+
+	let contents = clipboard
+		.get(&[
+			ContentFormat::Text,
+			ContentFormat::Html,
+			ContentFormat::Image,
+		])
+		.unwrap();
+	assert_eq!(
+		contents,
+		vec![
+			ClipboardContent::Text("héllo wor".to_string()),
+			ClipboardContent::Html("<b>hi</b>".to_string()),
+		]
+	);
+}
+
+#[test]
+fn test_limit_skip() {
+	let clipboard = limited(OversizePolicy::Skip);
+	assert!(clipboard.get_text().is_err());
+	assert!(!clipboard.has(ContentFormat::Text));
+	assert!(clipboard.has(ContentFormat::Html));
+	let contents = clipboard
+		.get(&[ContentFormat::Text, ContentFormat::Html])
+		.unwrap();
+	assert_eq!(
+		contents,
+		vec![ClipboardContent::Html("<b>hi</b>".to_string())]
+	);
+
+	let unlimited = LimitedClipboard::new(MockClipboard::new(), ClipboardOptions::new());
+	unlimited.set_text("a".repeat(100)).unwrap();
+	assert_eq!(unlimited.get_text().unwrap().len(), 100);
+}
@@ -0,0 +1,24 @@
+#![cfg(target_os = "windows")]
+use clipboard_rs::{Clipboard, ClipboardContext};
+
+#[test]
+fn test_long_and_unc_paths() {
+	let ctx = ClipboardContext::new().unwrap();
+	let long = format!("\\\\?\\C:\\{}\\file.txt", "d".repeat(300));
+	let files = vec![
+		long,
+		"\\\\server\\share\\报告.docx".to_string(),
+		"\\\\?\\UNC\\server\\share\\ñandú.txt".to_string(),
+		"C:\\Users\\Public\\Ünïcödé.txt".to_string(),
+	];
+	ctx.set_files(files.clone()).unwrap();
+	assert_eq!(ctx.get_files().unwrap(), files);
+
+	// a wide DROPFILES block, the paths start after the 20 byte header
+	let handle = ctx.register_format("CF_HDROP").unwrap();
+	let hdrop = ctx.get_buffer_by_handle(&handle).unwrap();
+	assert_eq!(hdrop[..4], 20u32.to_le_bytes());
+	assert_eq!(hdrop[16..20], 1u32.to_le_bytes());
+
+	assert!(ctx.set_files(vec!["C:\\a\0b".to_string()]).is_err());
+}
@@ -0,0 +1,61 @@
+use clipboard_rs::middleware::{ClipboardMiddleware, MiddlewareClipboard};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat, ContentMetadata};
+
+#[test]
+fn test_mock_metadata() {
+	let clipboard = MockClipboard::new();
+	clipboard.set_text("typed".to_string()).unwrap();
+	let (contents, metadata) = clipboard.get_with_metadata(&[ContentFormat::Text]).unwrap();
+	assert_eq!(contents, vec![ClipboardContent::Text("typed".to_string())]);
+	assert_eq!(metadata, ContentMetadata::default());
+
+	let metadata = ContentMetadata {
+		source: Some("com.example.passwords".to_string()),
+		auto_generated: true,
+		concealed: true,
+		..ContentMetadata::default()
+	};
+	clipboard.inject_with_metadata(
+		vec![ClipboardContent::Text("secret".to_string())],
+		metadata.clone(),
+	);
+	let (contents, read) = clipboard
+		.get_with_metadata(&[ContentFormat::Text, ContentFormat::Html])
+		.unwrap();
+	assert_eq!(contents.len(), 1);
+	assert_eq!(read, metadata);
+
+	// a new write belongs to this process, the old markers don't carry over
+	clipboard.set_text("next".to_string()).unwrap();
+	let (_, read) = clipboard.get_with_metadata(&[ContentFormat::Text]).unwrap();
+	assert!(!read.concealed);
+}
+
+struct Upper;
+
+impl ClipboardMiddleware for Upper {
+	fn on_read(&self, content: ClipboardContent) -> ClipboardContent {
+		match content {
+			ClipboardContent::Text(text) => ClipboardContent::Text(text.to_uppercase()),
+			other => other,
+		}
+	}
+}
+
+#[test]
+fn test_middleware_metadata() {
+	let mock = MockClipboard::new();
+	let metadata = ContentMetadata {
+		transient: true,
+		..ContentMetadata::default()
+	};
+	mock.inject_with_metadata(
+		vec![ClipboardContent::Text("draft".to_string())],
+		metadata.clone(),
+	);
+	let clipboard = MiddlewareClipboard::new(mock).with(Upper);
+	let (contents, read) = clipboard.get_with_metadata(&[ContentFormat::Text]).unwrap();
+	assert_eq!(contents, vec![ClipboardContent::Text("DRAFT".to_string())]);
+	assert_eq!(read, metadata);
+}
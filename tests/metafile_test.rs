@@ -0,0 +1,47 @@
+#![cfg(target_os = "windows")]
+use clipboard_rs::common::RustImage;
+use clipboard_rs::ClipboardContext;
+
+// an empty one inch square EMF: an ENHMETAHEADER without description and the EMR_EOF record
+fn empty_emf() -> Vec<u8> {
+	let mut emf = Vec::new();
+	let mut push = |values: &[u32]| {
+		for value in values {
+			emf.extend_from_slice(&value.to_le_bytes());
+		}
+	};
+	// iType, nSize, rclBounds
+	push(&[1, 88, 0, 0, 95, 95]);
+	// rclFrame in hundredths of a millimeter
+	push(&[0, 0, 2540, 2540]);
+	// dSignature, nVersion, nBytes, nRecords, nHandles and sReserved
+	push(&[0x464d_4520, 0x10000, 108, 2, 1]);
+	// nDescription, offDescription, nPalEntries, szlDevice, szlMillimeters
+	push(&[0, 0, 0, 1920, 1080, 508, 286]);
+	// EMR_EOF: iType, nSize, nPalEntries, offPalEntries, nSizeLast
+	push(&[14, 20, 0, 16, 20]);
+	emf
+}
+
+#[test]
+fn test_set_metafile() {
+	let ctx = ClipboardContext::new().unwrap();
+	ctx.set_metafile(empty_emf()).unwrap();
+
+	let emf = ctx.get_metafile().unwrap();
+	assert_eq!(emf[40..44], 0x464d_4520u32.to_le_bytes());
+
+	let image = ctx.get_metafile_image(96.0).unwrap();
+	assert_eq!(image.get_size(), (96, 96));
+	assert_eq!(image.dpi(), Some((96.0, 96.0)));
+	assert_eq!(
+		ctx.get_metafile_image(192.0).unwrap().get_size(),
+		(192, 192)
+	);
+}
+
+#[test]
+fn test_set_invalid_metafile() {
+	let ctx = ClipboardContext::new().unwrap();
+	assert!(ctx.set_metafile(b"not a metafile".to_vec()).is_err());
+}
@@ -0,0 +1,95 @@
+use clipboard_rs::middleware::{ClipboardMiddleware, MiddlewareClipboard};
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat, Url};
+
+struct LineEndings;
+
+impl ClipboardMiddleware for LineEndings {
+	fn on_read(&self, content: ClipboardContent) -> ClipboardContent {
+		match content {
+			ClipboardContent::Text(text) => ClipboardContent::Text(text.replace("\r\n", "\n")),
+			content => content,
+		}
+	}
+}
+
+struct StripTracking;
+
+impl ClipboardMiddleware for StripTracking {
+	fn on_write(&self, content: ClipboardContent) -> ClipboardContent {
+		match content {
+			ClipboardContent::Urls(urls) => ClipboardContent::Urls(
+				urls.into_iter()
+					.map(|url| Url {
+						url: url
+							.url
+							.split("?utm_")
+							.next()
+							.unwrap_or_default()
+							.to_string(),
+						..url
+					})
+					.collect(),
+			),
+			content => content,
+		}
+	}
+}
+
+struct Tag(&'static str);
+
+impl ClipboardMiddleware for Tag {
+	fn on_write(&self, content: ClipboardContent) -> ClipboardContent {
+		match content {
+			ClipboardContent::Text(text) => ClipboardContent::Text(format!("{}{}", text, self.0)),
+			content => content,
+		}
+	}
+
+	fn on_read(&self, content: ClipboardContent) -> ClipboardContent {
+		match content {
+			ClipboardContent::Text(text) => {
+				ClipboardContent::Text(text.strip_suffix(self.0).unwrap_or(&text).to_string())
+			}
+			content => content,
+		}
+	}
+}
+
+#[test]
+fn test_middleware_read_and_write() {
+	let inner = MockClipboard::new();
+	let clipboard = MiddlewareClipboard::new(inner.clone())
+		.with(LineEndings)
+		.with(StripTracking);
+
+	inner.inject(vec![ClipboardContent::Text("a\r\nb".to_string())]);
+	assert_eq!(clipboard.get_text().unwrap(), "a\nb");
+	assert_eq!(
+		clipboard.get(&[ContentFormat::Text]).unwrap(),
+		vec![ClipboardContent::Text("a\nb".to_string())]
+	);
+	// the byte level methods see the clipboard as it is
+	assert_eq!(inner.get_text().unwrap(), "a\r\nb");
+
+	clipboard
+		.set_urls(vec![Url {
+			url: "https://example.com/page?utm_source=mail".to_string(),
+			title: None,
+		}])
+		.unwrap();
+	assert_eq!(inner.get_urls().unwrap()[0].url, "https://example.com/page");
+}
+
+#[test]
+fn test_middleware_order() {
+	let inner = MockClipboard::new();
+	let clipboard = MiddlewareClipboard::new(inner.clone())
+		.with(Tag(" [1]"))
+		.with(Tag(" [2]"));
+
+	clipboard.set_text("text".to_string()).unwrap();
+	assert_eq!(inner.get_text().unwrap(), "text [1] [2]");
+	// reads undo the writes in reverse order
+	assert_eq!(clipboard.get_text().unwrap(), "text");
+}
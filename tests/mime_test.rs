@@ -0,0 +1,58 @@
+use clipboard_rs::common::detect_mime;
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat};
+
+#[test]
+fn test_detect_mime() {
+	assert_eq!(detect_mime(include_bytes!("test.png")), Some("image/png"));
+	assert_eq!(detect_mime(b"{\\rtf1\\ansi hello}"), Some("text/rtf"));
+	assert_eq!(
+		detect_mime(b"PK\x03\x04\x14\0\0\0"),
+		Some("application/zip")
+	);
+	assert_eq!(detect_mime(b"%PDF-1.7\n"), Some("application/pdf"));
+	assert_eq!(
+		detect_mime(b"\xef\xbb\xbf  <!DOCTYPE html><html></html>"),
+		Some("text/html")
+	);
+	assert_eq!(
+		detect_mime(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+		Some("image/svg+xml")
+	);
+	assert_eq!(detect_mime(b"RIFF\x24\0\0\0WEBPVP8 "), Some("image/webp"));
+	// text that merely starts like a signature
+	assert_eq!(detect_mime(b"BMW is a car maker"), None);
+	assert_eq!(detect_mime(b"plain words"), None);
+	assert_eq!(detect_mime(b""), None);
+}
+
+#[test]
+fn test_content_mime() {
+	let clipboard = MockClipboard::new();
+	clipboard.inject(vec![
+		ClipboardContent::Other(
+			"Art::GVML ClipFormat".to_string(),
+			b"PK\x03\x04....".to_vec(),
+		),
+		ClipboardContent::Other("application/x-custom".to_string(), b"%PDF-".to_vec()),
+	]);
+	let contents = clipboard
+		.get(&[
+			ContentFormat::Other("Art::GVML ClipFormat".to_string()),
+			ContentFormat::Other("application/x-custom".to_string()),
+		])
+		.unwrap();
+	// the name wins when it already is a MIME type
+	let mimes: Vec<Option<String>> = contents.iter().map(ClipboardContent::mime).collect();
+	assert_eq!(
+		mimes,
+		vec![
+			Some("application/zip".to_string()),
+			Some("application/x-custom".to_string())
+		]
+	);
+	assert_eq!(
+		ClipboardContent::Text("hi".to_string()).mime().as_deref(),
+		Some("text/plain")
+	);
+}
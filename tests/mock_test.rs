@@ -0,0 +1,158 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{
+	Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat, Selection, Url,
+};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+struct Counter(Arc<AtomicUsize>);
+
+impl ClipboardHandler for Counter {
+	fn on_clipboard_change(&mut self) {
+		self.0.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+#[test]
+fn test_mock_clipboard() {
+	let clipboard = MockClipboard::new();
+
+	clipboard.set_text("hello".to_string()).unwrap();
+	assert!(clipboard.has(ContentFormat::Text));
+	assert_eq!(clipboard.get_text().unwrap(), "hello");
+	assert_eq!(clipboard.available_formats().unwrap(), vec!["text/plain"]);
+
+	// clones share the contents
+	let other = clipboard.clone();
+	other.inject(vec![
+		ClipboardContent::Html("<b>hi</b>".to_string()),
+		ClipboardContent::Text("hi".to_string()),
+	]);
+	assert_eq!(clipboard.get_html().unwrap(), "<b>hi</b>");
+	assert_eq!(clipboard.get_buffer("text/plain").unwrap(), b"hi");
+	assert_eq!(clipboard.change_count(), 2);
+
+	clipboard.clear().unwrap();
+	assert!(clipboard.get_text().is_err());
+}
+
+#[test]
+fn test_mock_watcher_pending() {
+	let clipboard = MockClipboard::new();
+	let count = Arc::new(AtomicUsize::new(0));
+	let mut watcher = clipboard.watcher();
+	watcher.add_handler(Counter(count.clone()));
+
+	clipboard.set_text("one".to_string()).unwrap();
+	clipboard.trigger_change();
+
+	assert_eq!(watcher.process_pending(), 2);
+	assert_eq!(count.load(Ordering::SeqCst), 2);
+	assert_eq!(watcher.process_pending(), 0);
+}
+
+#[test]
+fn test_mock_watcher_thread() {
+	let clipboard = MockClipboard::new();
+	let count = Arc::new(AtomicUsize::new(0));
+	let mut watcher = clipboard.watcher();
+	watcher.add_handler(Counter(count.clone()));
+	let shutdown = watcher.get_shutdown_channel();
+
+	let handle = thread::spawn(move || watcher.start_watch(Duration::from_millis(10)));
+	clipboard.set_text("one".to_string()).unwrap();
+	while count.load(Ordering::SeqCst) == 0 {
+		thread::sleep(Duration::from_millis(5));
+	}
+	shutdown.stop();
+	handle.join().unwrap();
+
+	assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+// stops the watcher once it saw `limit` changes
+struct StopAfter(Arc<AtomicUsize>, usize);
+
+impl ClipboardHandler for StopAfter {
+	fn on_clipboard_change(&mut self) {
+		self.0.fetch_add(1, Ordering::SeqCst);
+	}
+
+	fn on_change(&mut self, selection: Selection) -> ControlFlow<()> {
+		self.on_selection_change(selection);
+		if self.0.load(Ordering::SeqCst) >= self.1 {
+			ControlFlow::Break(())
+		} else {
+			ControlFlow::Continue(())
+		}
+	}
+}
+
+#[test]
+fn test_handler_stops_watcher() {
+	let clipboard = MockClipboard::new();
+	let stopping = Arc::new(AtomicUsize::new(0));
+	let other = Arc::new(AtomicUsize::new(0));
+	let mut watcher = clipboard.watcher();
+	watcher.add_handler(StopAfter(stopping.clone(), 2));
+	watcher.add_handler(StopAfter(other.clone(), usize::MAX));
+
+	for _ in 0..3 {
+		clipboard.trigger_change();
+	}
+	// the change that stopped it still reached every handler
+	assert_eq!(watcher.process_pending(), 2);
+	assert_eq!(other.load(Ordering::SeqCst), 2);
+
+	// the watch loop ends by itself on the change still waiting
+	let handle = thread::spawn(move || watcher.start_watch(Duration::from_millis(10)));
+	handle.join().unwrap();
+	assert_eq!(stopping.load(Ordering::SeqCst), 3);
+	assert_eq!(other.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_mock_custom_format() {
+	let clipboard = MockClipboard::new();
+	let handle = clipboard.register_format("application/x-custom").unwrap();
+	clipboard
+		.set(vec![ClipboardContent::Custom(
+			handle.clone(),
+			b"custom".to_vec(),
+		)])
+		.unwrap();
+	assert!(clipboard.has(ContentFormat::Custom(handle.clone())));
+	assert!(clipboard.has(ContentFormat::Other("application/x-custom".into())));
+	assert_eq!(
+		clipboard
+			.get(&[ContentFormat::Custom(handle.clone())])
+			.unwrap(),
+		vec![ClipboardContent::Custom(handle.clone(), b"custom".to_vec())]
+	);
+
+	// written by name, read through the handle
+	clipboard
+		.set_buffer("application/x-custom", b"by name".to_vec())
+		.unwrap();
+	assert_eq!(
+		clipboard
+			.get_best(&[ContentFormat::Custom(handle.clone())])
+			.unwrap(),
+		ClipboardContent::Custom(handle, b"by name".to_vec())
+	);
+}
+
+#[test]
+fn test_mock_multiple_urls() {
+	let clipboard = MockClipboard::new();
+	let urls = vec![
+		Url::with_title("https://github.com/ChurchTao/clipboard-rs", "clipboard-rs"),
+		Url::new("https://docs.rs/clipboard-rs"),
+	];
+	clipboard.set_urls(urls.clone()).unwrap();
+	assert!(clipboard.has(ContentFormat::Url));
+	assert_eq!(clipboard.get_urls().unwrap(), urls);
+}
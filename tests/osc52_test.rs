@@ -0,0 +1,67 @@
+use clipboard_rs::osc52::Osc52Selection;
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext};
+use std::io::{Cursor, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct Terminal(Arc<Mutex<Vec<u8>>>);
+
+impl Terminal {
+	fn output(&self) -> String {
+		String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+	}
+}
+
+impl Write for Terminal {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.lock().unwrap().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+#[test]
+fn test_osc52_set() {
+	let terminal = Terminal::default();
+	let ctx = ClipboardContext::new_osc52(terminal.clone()).with_tmux(false);
+	ctx.set_text("hello".to_string()).unwrap();
+	assert_eq!(terminal.output(), "\x1b]52;c;aGVsbG8=\x07");
+
+	// only the text fallback reaches the terminal
+	let terminal = Terminal::default();
+	let ctx = ClipboardContext::new_osc52(terminal.clone())
+		.with_tmux(false)
+		.with_selection(Osc52Selection::Primary);
+	ctx.set(vec![
+		ClipboardContent::Html("<b>hi</b>".to_string()),
+		ClipboardContent::Text("hi".to_string()),
+	])
+	.unwrap();
+	assert_eq!(terminal.output(), "\x1b]52;p;aGk=\x07");
+	assert!(ctx.set_html("<b>hi</b>".to_string()).is_err());
+}
+
+#[test]
+fn test_osc52_tmux() {
+	let terminal = Terminal::default();
+	let ctx = ClipboardContext::new_osc52(terminal.clone()).with_tmux(true);
+	ctx.set_text("a".to_string()).unwrap();
+	assert_eq!(terminal.output(), "\x1bPtmux;\x1b\x1b]52;c;YQ==\x07\x1b\\");
+}
+
+#[test]
+fn test_osc52_get() {
+	let terminal = Terminal::default();
+	let answer = Cursor::new(b"\x1b]52;c;Y2xpcGJvYXJk\x1b\\".to_vec());
+	let ctx = ClipboardContext::new_osc52(terminal.clone())
+		.with_tmux(false)
+		.with_reader(answer);
+	assert_eq!(ctx.get_text().unwrap(), "clipboard");
+	assert_eq!(terminal.output(), "\x1b]52;c;?\x07");
+
+	let ctx = ClipboardContext::new_osc52(Terminal::default());
+	assert!(ctx.get_text().is_err());
+}
@@ -0,0 +1,32 @@
+#![cfg(all(
+	unix,
+	not(any(
+		target_os = "macos",
+		target_os = "ios",
+		target_os = "android",
+		target_os = "emscripten"
+	))
+))]
+use clipboard_rs::{Clipboard, ClipboardContext};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_served_while_owner_is_busy() {
+	let (set_tx, set_rx) = mpsc::channel();
+	let busy = thread::spawn(move || {
+		let ctx = ClipboardContext::new().unwrap();
+		ctx.set_text("served".to_string()).unwrap();
+		set_tx.send(ctx.is_serving()).unwrap();
+		// a long computation, nothing here pumps X events
+		thread::sleep(Duration::from_secs(1));
+	});
+	assert!(set_rx.recv().unwrap());
+
+	let reader = ClipboardContext::new().unwrap();
+	assert_eq!(reader.get_text().unwrap(), "served");
+	busy.join().unwrap();
+	// the content outlives the context that set it
+	assert_eq!(reader.get_text().unwrap(), "served");
+}
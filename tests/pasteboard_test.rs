@@ -0,0 +1,15 @@
+#![cfg(target_os = "macos")]
+use clipboard_rs::{Clipboard, ClipboardContext, PasteboardName};
+
+#[test]
+fn test_custom_pasteboard() {
+	let ctx =
+		ClipboardContext::with_pasteboard(PasteboardName::Custom("com.clipboard-rs.test".into()))
+			.unwrap();
+	let test_text = "clipboard-rs private pasteboard";
+	ctx.set_text(test_text.to_string()).unwrap();
+	assert_eq!(ctx.get_text().unwrap(), test_text);
+
+	ctx.clear().unwrap();
+	assert!(ctx.get_text().is_err());
+}
@@ -0,0 +1,15 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ContentFormat};
+
+#[test]
+fn test_pdf_content() {
+	let clipboard = MockClipboard::new();
+	assert!(clipboard.get_pdf().is_err());
+
+	let pdf = b"%PDF-1.4\n%%EOF\n".to_vec();
+	clipboard.set_pdf(pdf.clone()).unwrap();
+	assert!(clipboard.has(ContentFormat::Pdf));
+	assert_eq!(clipboard.get_pdf().unwrap(), pdf);
+	assert_eq!(clipboard.get_buffer("application/pdf").unwrap(), pdf);
+	assert!(clipboard.get_text().is_err());
+}
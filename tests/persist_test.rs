@@ -0,0 +1,21 @@
+#![cfg(all(
+	unix,
+	not(any(
+		target_os = "macos",
+		target_os = "ios",
+		target_os = "android",
+		target_os = "emscripten"
+	))
+))]
+use clipboard_rs::{Clipboard, ClipboardContext};
+
+#[test]
+fn test_persist() {
+	let ctx = ClipboardContext::new().unwrap();
+
+	ctx.set_text("persisted".to_string()).unwrap();
+	// succeeds without a clipboard manager as well, there is just nobody to hand over to
+	ctx.persist().unwrap();
+
+	assert_eq!(ctx.get_text().unwrap(), "persisted");
+}
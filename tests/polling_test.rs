@@ -0,0 +1,52 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::polling::PollingWatcher;
+use clipboard_rs::{Clipboard, ClipboardHandler, ClipboardWatcher, ContentFormat};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+struct Counter(Arc<AtomicUsize>);
+
+impl ClipboardHandler for Counter {
+	fn on_clipboard_change(&mut self) {
+		self.0.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+fn wait_for(count: &AtomicUsize, expected: usize) {
+	for _ in 0..200 {
+		if count.load(Ordering::SeqCst) >= expected {
+			return;
+		}
+		thread::sleep(Duration::from_millis(5));
+	}
+}
+
+#[test]
+fn test_polling_watcher() {
+	let clipboard = MockClipboard::new();
+	let count = Arc::new(AtomicUsize::new(0));
+	let mut watcher =
+		PollingWatcher::new(clipboard.clone()).with_formats(vec![ContentFormat::Text]);
+	watcher.add_handler(Counter(count.clone()));
+	let shutdown = watcher.get_shutdown_channel();
+
+	clipboard.set_text("one".to_string()).unwrap();
+	let handle = thread::spawn(move || watcher.start_watch(Duration::from_millis(10)));
+	// the contents found at the start are not a change
+	thread::sleep(Duration::from_millis(50));
+	assert_eq!(count.load(Ordering::SeqCst), 0);
+
+	clipboard.set_text("two".to_string()).unwrap();
+	wait_for(&count, 1);
+	assert_eq!(count.load(Ordering::SeqCst), 1);
+
+	// copying the same text again leaves the hash alone
+	clipboard.set_text("two".to_string()).unwrap();
+	thread::sleep(Duration::from_millis(50));
+	assert_eq!(count.load(Ordering::SeqCst), 1);
+
+	shutdown.stop();
+	handle.join().unwrap();
+}
@@ -0,0 +1,115 @@
+use clipboard_rs::queue::{QueueOptions, QueuePolicy, QueuedHandler};
+use clipboard_rs::{ClipboardHandler, Selection};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// a handler that waits for a release before taking each event
+struct Gate {
+	release: Receiver<()>,
+	seen: Arc<Mutex<Vec<Selection>>>,
+}
+
+impl ClipboardHandler for Gate {
+	fn on_clipboard_change(&mut self) {
+		self.on_selection_change(Selection::Clipboard);
+	}
+
+	fn on_selection_change(&mut self, selection: Selection) {
+		self.release.recv().unwrap();
+		self.seen.lock().unwrap().push(selection);
+	}
+}
+
+fn queued(
+	capacity: usize,
+	policy: QueuePolicy,
+) -> (QueuedHandler, Sender<()>, Arc<Mutex<Vec<Selection>>>) {
+	let (release_tx, release) = mpsc::channel();
+	let seen = Arc::new(Mutex::new(Vec::new()));
+	let mut handler = QueuedHandler::new(
+		Gate {
+			release,
+			seen: seen.clone(),
+		},
+		QueueOptions { capacity, policy },
+	);
+	// the first event is taken right away and holds the handler
+	handler.on_clipboard_change();
+	while handler.pending() > 0 {
+		thread::sleep(Duration::from_millis(1));
+	}
+	(handler, release_tx, seen)
+}
+
+fn finish(handler: QueuedHandler, release: Sender<()>, events: usize) {
+	for _ in 0..events {
+		release.send(()).unwrap();
+	}
+	drop(handler);
+}
+
+#[test]
+fn test_queue_coalesce() {
+	let (mut handler, release, seen) = queued(16, QueuePolicy::CoalesceLatest);
+	for _ in 0..5 {
+		handler.on_clipboard_change();
+	}
+	handler.on_selection_change(Selection::Primary);
+	assert_eq!(handler.pending(), 2);
+	assert_eq!(handler.dropped(), 4);
+	finish(handler, release, 3);
+	assert_eq!(
+		*seen.lock().unwrap(),
+		vec![
+			Selection::Clipboard,
+			Selection::Clipboard,
+			Selection::Primary
+		]
+	);
+}
+
+#[test]
+fn test_queue_drop_oldest() {
+	let (mut handler, release, seen) = queued(2, QueuePolicy::DropOldest);
+	handler.on_selection_change(Selection::Primary);
+	handler.on_clipboard_change();
+	handler.on_selection_change(Selection::Primary);
+	assert_eq!(handler.pending(), 2);
+	assert_eq!(handler.dropped(), 1);
+	finish(handler, release, 3);
+	assert_eq!(
+		*seen.lock().unwrap(),
+		vec![
+			Selection::Clipboard,
+			Selection::Clipboard,
+			Selection::Primary
+		]
+	);
+}
+
+#[test]
+fn test_queue_block() {
+	let (mut handler, release, seen) = queued(1, QueuePolicy::Block);
+	handler.on_selection_change(Selection::Primary);
+	let watcher = thread::spawn(move || {
+		handler.on_clipboard_change();
+		handler
+	});
+	thread::sleep(Duration::from_millis(50));
+	assert!(!watcher.is_finished());
+
+	release.send(()).unwrap();
+	let handler = watcher.join().unwrap();
+	assert_eq!(handler.dropped(), 0);
+	finish(handler, release, 2);
+	assert_eq!(
+		*seen.lock().unwrap(),
+		vec![
+			Selection::Clipboard,
+			Selection::Primary,
+			Selection::Clipboard
+		]
+	);
+}
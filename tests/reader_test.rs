@@ -0,0 +1,27 @@
+use clipboard_rs::{Clipboard, ClipboardContext};
+use std::io::Read;
+
+#[test]
+fn test_get_reader() {
+	let ctx = ClipboardContext::new().unwrap();
+	let format = "clipboard-rs-reader-test";
+	let buffer: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+
+	ctx.set_buffer(format, buffer.clone()).unwrap();
+
+	let mut data = Vec::new();
+	{
+		let mut reader = ctx.get_reader(format).unwrap();
+		let mut chunk = [0u8; 1000];
+		loop {
+			let n = reader.read(&mut chunk).unwrap();
+			if n == 0 {
+				break;
+			}
+			data.extend_from_slice(&chunk[..n]);
+		}
+	}
+	// windows may round the allocation up
+	assert!(data.len() >= buffer.len());
+	assert_eq!(&data[..buffer.len()], buffer.as_slice());
+}
@@ -0,0 +1,23 @@
+use clipboard_rs::{ClipboardHandler, Selection};
+
+struct Counter {
+	changes: usize,
+}
+
+impl ClipboardHandler for Counter {
+	fn on_clipboard_change(&mut self) {
+		self.changes += 1;
+	}
+}
+
+#[test]
+fn test_selection_change_default() {
+	let mut handler = Counter { changes: 0 };
+
+	// handlers that only know the clipboard never hear about the primary selection
+	handler.on_selection_change(Selection::Primary);
+	assert_eq!(handler.changes, 0);
+
+	handler.on_selection_change(Selection::Clipboard);
+	assert_eq!(handler.changes, 1);
+}
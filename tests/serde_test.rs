@@ -0,0 +1,39 @@
+#![cfg(feature = "serde")]
+use clipboard_rs::common::RustImage;
+use clipboard_rs::{ClipboardContent, ContentFormat, RustImageData, Url};
+
+#[test]
+fn test_serde_contents() {
+	let contents = vec![
+		ClipboardContent::Text("hello".to_string()),
+		ClipboardContent::Html("<b>hello</b>".to_string()),
+		ClipboardContent::Urls(vec![Url::with_title("https://example.com", "example")]),
+		ClipboardContent::Other("custom".to_string(), vec![0, 1, 2]),
+	];
+	let json = serde_json::to_string(&contents).unwrap();
+	let restored: Vec<ClipboardContent> = serde_json::from_str(&json).unwrap();
+	assert_eq!(restored, contents);
+
+	let format = ContentFormat::Other("application/x-test".to_string());
+	let json = serde_json::to_string(&format).unwrap();
+	assert_eq!(
+		serde_json::from_str::<ContentFormat>(&json).unwrap(),
+		format
+	);
+}
+
+#[test]
+fn test_serde_image() {
+	let image = RustImageData::from_rgba(2, 2, [10, 20, 30, 255].repeat(4)).unwrap();
+	let json = serde_json::to_string(&ClipboardContent::Image(image.clone())).unwrap();
+	let restored: ClipboardContent = serde_json::from_str(&json).unwrap();
+	match restored {
+		ClipboardContent::Image(restored) => {
+			assert_eq!(
+				restored.to_rgba_pixels().unwrap(),
+				image.to_rgba_pixels().unwrap()
+			);
+		}
+		_ => panic!("expected an image"),
+	}
+}
@@ -0,0 +1,21 @@
+use clipboard_rs::{Clipboard, ClipboardContext};
+
+#[test]
+fn test_session() {
+	let ctx = ClipboardContext::new().unwrap();
+
+	let (text, html) = ctx
+		.session(|clip| {
+			clip.set_text("session".to_string())?;
+			let text = clip.get_text()?;
+			// nested sessions share the clipboard the outer one opened
+			clip.session(|clip| clip.set_html("<b>session</b>".to_string()))?;
+			Ok((text, clip.get_html()?))
+		})
+		.unwrap();
+	assert_eq!(text, "session");
+	assert_eq!(html, "<b>session</b>");
+
+	// the clipboard is closed again, a plain call opens it by itself
+	assert_eq!(ctx.get_html().unwrap(), "<b>session</b>");
+}
@@ -0,0 +1,18 @@
+use clipboard_rs::{Clipboard, ClipboardContext};
+use std::io::Cursor;
+
+#[test]
+fn test_set_from_reader() {
+	let ctx = ClipboardContext::new().unwrap();
+	let format = "clipboard-rs-set-reader-test";
+	let buffer: Vec<u8> = (0..200 * 1024).map(|i| (i % 253) as u8).collect();
+
+	// a short hint forces the allocation to grow while reading
+	ctx.set_from_reader(format, &mut Cursor::new(buffer.clone()), Some(1024))
+		.unwrap();
+
+	let data = ctx.get_buffer(format).unwrap();
+	// windows may round the allocation up
+	assert!(data.len() >= buffer.len());
+	assert_eq!(&data[..buffer.len()], buffer.as_slice());
+}
@@ -0,0 +1,9 @@
+use clipboard_rs::ClipboardContext;
+
+fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+#[test]
+fn test_context_is_shareable() {
+	// a single context can be stored in shared state and cloned into every thread
+	assert_shareable::<ClipboardContext>();
+}
@@ -0,0 +1,17 @@
+use clipboard_rs::{Clipboard, ClipboardContext};
+
+#[test]
+fn test_get_size() {
+	let ctx = ClipboardContext::new().unwrap();
+	let format = "clipboard-rs-size-test";
+	let buffer = vec![7u8; 4096];
+
+	ctx.set_buffer(format, buffer.clone()).unwrap();
+
+	// windows reports the size of the global allocation, which may be rounded up
+	let size = ctx.get_size(format).unwrap();
+	assert!(size >= buffer.len());
+
+	ctx.clear().unwrap();
+	assert!(ctx.get_size(format).is_err());
+}
@@ -0,0 +1,37 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, SnapshotDiff};
+
+#[test]
+fn test_snapshot_diff() {
+	let clipboard = MockClipboard::new();
+	clipboard
+		.set(vec![
+			ClipboardContent::Text("hello".to_string()),
+			ClipboardContent::Html("<b>hello</b>".to_string()),
+		])
+		.unwrap();
+	let before = clipboard.snapshot().unwrap();
+	assert_eq!(before.get("text/plain"), Some(&b"hello"[..]));
+	assert_eq!(before.size(), 17);
+	assert!(before.diff(&clipboard.snapshot().unwrap()).is_empty());
+
+	clipboard
+		.set(vec![
+			ClipboardContent::Text("hello".to_string()),
+			ClipboardContent::Rtf("{\\rtf1 hello}".to_string()),
+			ClipboardContent::Html("<i>hello</i>".to_string()),
+		])
+		.unwrap();
+	let after = clipboard.snapshot().unwrap();
+	assert_eq!(
+		before.diff(&after),
+		SnapshotDiff {
+			added: vec!["text/rtf".to_string()],
+			removed: vec![],
+			changed: vec!["text/html".to_string()],
+		}
+	);
+	let back = after.diff(&before);
+	assert_eq!(back.removed, vec!["text/rtf".to_string()]);
+	assert!(back.added.is_empty());
+}
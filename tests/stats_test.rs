@@ -0,0 +1,87 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::stats::StatsClipboard;
+use clipboard_rs::{
+	Clipboard, ClipboardContent, ClipboardHandler, ClipboardWatcher, ContentFormat,
+};
+use std::io::Read;
+
+struct Noop;
+
+impl ClipboardHandler for Noop {
+	fn on_clipboard_change(&mut self) {}
+}
+
+#[test]
+fn test_stats_count_reads_and_writes() {
+	let clipboard = StatsClipboard::new(MockClipboard::new());
+	clipboard.set_text("hello".to_owned()).unwrap();
+	assert_eq!(clipboard.get_text().unwrap(), "hello");
+	clipboard
+		.set_buffer("application/x-test", vec![1, 2, 3])
+		.unwrap();
+	clipboard.get_buffer("application/x-test").unwrap();
+	assert!(clipboard.get_html().is_err());
+
+	let stats = clipboard.stats();
+	assert_eq!(stats.reads, 3);
+	assert_eq!(stats.writes, 2);
+	assert_eq!(stats.bytes_written["text/plain"], 5);
+	assert_eq!(stats.bytes_read["text/plain"], 5);
+	assert_eq!(stats.bytes_read["application/x-test"], 3);
+	assert!(!stats.bytes_read.contains_key("text/html"));
+	assert_eq!(stats.total_bytes_written(), 8);
+}
+
+#[test]
+fn test_stats_count_contents() {
+	let clipboard = StatsClipboard::new(MockClipboard::new());
+	clipboard
+		.set(vec![
+			ClipboardContent::Text("text".to_owned()),
+			ClipboardContent::Html("<b>html</b>".to_owned()),
+		])
+		.unwrap();
+	clipboard
+		.get(&[ContentFormat::Text, ContentFormat::Html])
+		.unwrap();
+
+	let stats = clipboard.stats();
+	assert_eq!((stats.reads, stats.writes), (1, 1));
+	assert_eq!(stats.bytes_read["text/plain"], 4);
+	assert_eq!(stats.bytes_read["text/html"], 11);
+	assert_eq!(stats.bytes_read, stats.bytes_written);
+}
+
+#[test]
+fn test_stats_count_reader_bytes() {
+	let clipboard = StatsClipboard::new(MockClipboard::new());
+	clipboard
+		.set_buffer("application/x-test", vec![0; 100])
+		.unwrap();
+	let mut buffer = Vec::new();
+	clipboard
+		.get_reader("application/x-test")
+		.unwrap()
+		.read_to_end(&mut buffer)
+		.unwrap();
+	assert_eq!(clipboard.stats().bytes_read["application/x-test"], 100);
+
+	clipboard.reset();
+	assert_eq!(clipboard.stats().reads, 0);
+	assert!(clipboard.stats().bytes_read.is_empty());
+}
+
+#[test]
+fn test_stats_count_watcher_events() {
+	let mock = MockClipboard::new();
+	let clipboard = StatsClipboard::new(mock.clone());
+	let mut watcher = mock.watcher();
+	watcher.add_handler(clipboard.handler(Noop));
+
+	mock.inject(vec![ClipboardContent::Text("one".to_owned())]);
+	mock.trigger_change();
+	assert_eq!(watcher.process_pending(), 2);
+	assert_eq!(clipboard.stats().watcher_events, 2);
+	// injected content isn't read through the wrapper
+	assert_eq!(clipboard.stats().reads, 0);
+}
@@ -24,6 +24,16 @@ fn test_string() {
 	assert!(ctx.has(ContentFormat::Html));
 	assert_eq!(ctx.get_html().unwrap(), test_html);
 
+	let test_svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"#;
+	ctx.set_svg(test_svg.to_string()).unwrap();
+	assert!(ctx.has(ContentFormat::Svg));
+	assert_eq!(ctx.get_svg().unwrap(), test_svg);
+
+	let test_markdown = "# clipboard-rs\n\n**bold** and `code`";
+	ctx.set_markdown(test_markdown.to_string()).unwrap();
+	assert!(ctx.has(ContentFormat::Markdown));
+	assert_eq!(ctx.get_markdown().unwrap(), test_markdown);
+
 	let contents: Vec<ClipboardContent> = vec![
 		ClipboardContent::Text(test_plain_txt.to_string()),
 		ClipboardContent::Rtf(test_rich_txt.to_string()),
@@ -0,0 +1,18 @@
+use clipboard_rs::{Clipboard, ClipboardContext, TextDecoding};
+
+#[test]
+fn test_text_decoding() {
+	let ctx = ClipboardContext::new().unwrap();
+	let text = "héllo wörld 你好";
+
+	ctx.set_text(text.to_string()).unwrap();
+
+	assert_eq!(
+		ctx.get_text_with_decoding(TextDecoding::Strict).unwrap(),
+		text
+	);
+	assert_eq!(
+		ctx.get_text_with_decoding(TextDecoding::Lossy).unwrap(),
+		text
+	);
+}
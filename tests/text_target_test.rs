@@ -0,0 +1,36 @@
+#![cfg(all(
+	unix,
+	not(any(
+		target_os = "macos",
+		target_os = "ios",
+		target_os = "android",
+		target_os = "emscripten"
+	))
+))]
+use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext, ContentFormat};
+
+#[test]
+fn test_text_targets() {
+	let ctx = ClipboardContext::new().unwrap();
+	let test_text = "héllo wörld ✓";
+
+	// only the MIME target, as some Electron apps publish it
+	ctx.set_buffer("text/plain;charset=utf-8", test_text.as_bytes().to_vec())
+		.unwrap();
+	assert!(ctx.has(ContentFormat::Text));
+	assert_eq!(ctx.get_text().unwrap(), test_text);
+
+	// UTF-16 with a byte order mark, as Java publishes it
+	let mut utf16 = vec![0xff, 0xfe];
+	utf16.extend(test_text.encode_utf16().flat_map(u16::to_le_bytes));
+	ctx.set_buffer("text/plain;charset=utf-16", utf16).unwrap();
+	assert_eq!(ctx.get_text().unwrap(), test_text);
+
+	// UTF8_STRING wins over a lossy Latin-1 STRING
+	ctx.set(vec![
+		ClipboardContent::Other("STRING".to_string(), b"h?llo".to_vec()),
+		ClipboardContent::Other("UTF8_STRING".to_string(), test_text.as_bytes().to_vec()),
+	])
+	.unwrap();
+	assert_eq!(ctx.get_text().unwrap(), test_text);
+}
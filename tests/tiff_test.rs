@@ -0,0 +1,35 @@
+#![cfg(target_os = "macos")]
+use clipboard_rs::common::RustImage;
+use clipboard_rs::{Clipboard, ClipboardContext, PasteboardName, RustImageData};
+
+fn context() -> ClipboardContext {
+	ClipboardContext::with_pasteboard(PasteboardName::Custom("com.clipboard-rs.tiff-test".into()))
+		.unwrap()
+}
+
+#[test]
+fn test_image_is_offered_as_tiff() {
+	let ctx = context();
+	let image = RustImageData::from_rgba(2, 2, vec![255; 16]).unwrap();
+	ctx.set_image(image).unwrap();
+
+	let tiff = ctx.get_image_tiff().unwrap();
+	assert!(tiff.starts_with(b"II*\0") || tiff.starts_with(b"MM\0*"));
+	ctx.clear().unwrap();
+}
+
+#[test]
+fn test_set_image_tiff() {
+	let ctx = context();
+	let image = RustImageData::from_rgba(3, 1, vec![255; 12]).unwrap();
+	ctx.set_image(image).unwrap();
+	let tiff = ctx.get_image_tiff().unwrap();
+
+	ctx.clear().unwrap();
+	ctx.set_image_tiff(tiff.clone()).unwrap();
+	assert_eq!(ctx.get_image_tiff().unwrap(), tiff);
+	// apps reading PNG get the same image
+	assert_eq!(ctx.get_image().unwrap().get_size(), (3, 1));
+	assert!(ctx.get_buffer("public.png").is_ok());
+	ctx.clear().unwrap();
+}
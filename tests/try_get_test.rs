@@ -0,0 +1,62 @@
+use clipboard_rs::common::is_would_block;
+use clipboard_rs::converter::{ConverterRegistry, ConvertingClipboard};
+use clipboard_rs::limit::{ClipboardOptions, LimitedClipboard};
+use clipboard_rs::middleware::MiddlewareClipboard;
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::stats::StatsClipboard;
+use clipboard_rs::{Clipboard, ClipboardContent, ContentFormat};
+
+#[test]
+fn test_try_get() {
+	let clipboard = MockClipboard::new();
+	clipboard.set_text("frame".to_string()).unwrap();
+	assert_eq!(clipboard.try_get_text().unwrap(), "frame");
+
+	clipboard.set_held(true);
+	let err = clipboard.try_get_text().unwrap_err();
+	assert!(is_would_block(err.as_ref()));
+	assert!(is_would_block(
+		clipboard
+			.try_get(&[ContentFormat::Text])
+			.unwrap_err()
+			.as_ref()
+	));
+	// the blocking getters still wait their turn
+	assert_eq!(clipboard.get_text().unwrap(), "frame");
+
+	clipboard.set_held(false);
+	assert_eq!(
+		clipboard.try_get(&[ContentFormat::Text]).unwrap(),
+		vec![ClipboardContent::Text("frame".to_string())]
+	);
+	// a missing format is an ordinary error
+	let err = clipboard.try_get_html().unwrap_err();
+	assert!(!is_would_block(err.as_ref()));
+}
+
+#[test]
+fn test_try_get_through_wrappers() {
+	let mock = MockClipboard::new();
+	mock.set_text("frame".to_string()).unwrap();
+	let wrappers: Vec<Box<dyn Clipboard + Send + Sync>> = vec![
+		Box::new(MiddlewareClipboard::new(mock.clone())),
+		Box::new(ConvertingClipboard::new(
+			mock.clone(),
+			ConverterRegistry::with_defaults(),
+		)),
+		Box::new(LimitedClipboard::new(mock.clone(), ClipboardOptions::new())),
+		Box::new(StatsClipboard::new(mock.clone())),
+	];
+
+	mock.set_held(true);
+	for clipboard in &wrappers {
+		let err = clipboard.try_get_text().unwrap_err();
+		assert!(is_would_block(err.as_ref()));
+		assert!(clipboard.has_any(&[ContentFormat::Html, ContentFormat::Text]));
+	}
+
+	mock.set_held(false);
+	for clipboard in &wrappers {
+		assert_eq!(clipboard.try_get_text().unwrap(), "frame");
+	}
+}
@@ -0,0 +1,21 @@
+use clipboard_rs::{Clipboard, ClipboardContext, ContentFormat, Url};
+
+#[test]
+fn test_url() {
+	let ctx = ClipboardContext::new().unwrap();
+
+	let url = Url::with_title("https://github.com/ChurchTao/clipboard-rs", "clipboard-rs");
+	ctx.set_urls(vec![url.clone()]).unwrap();
+
+	let has = ctx.has(ContentFormat::Url);
+	assert!(has);
+
+	let urls = ctx.get_urls().unwrap();
+	assert!(!urls.is_empty());
+	assert_eq!(urls[0].url, url.url);
+
+	ctx.clear().unwrap();
+
+	let has = ctx.has(ContentFormat::Url);
+	assert!(!has);
+}
@@ -0,0 +1,20 @@
+use clipboard_rs::{common::ContentData, Clipboard, ClipboardContent, ClipboardContext};
+
+#[test]
+fn test_utf16_buffer() {
+	let content = ClipboardContent::other_utf16("FileNameW", "报告.txt");
+	assert_eq!(
+		content.as_bytes().len(),
+		("报告.txt".encode_utf16().count() + 1) * 2
+	);
+	assert_eq!(content.as_utf16_str().unwrap(), "报告.txt");
+	assert!(ClipboardContent::Text("text".to_string())
+		.as_utf16_str()
+		.is_err());
+
+	let ctx = ClipboardContext::new().unwrap();
+	let format = "clipboard-rs-utf16-test";
+
+	ctx.set_buffer_utf16(format, "wide text 你好").unwrap();
+	assert_eq!(ctx.get_buffer_utf16(format).unwrap(), "wide text 你好");
+}
@@ -0,0 +1,42 @@
+use clipboard_rs::mock::MockClipboard;
+use clipboard_rs::polling::PollingWatcher;
+use clipboard_rs::{Clipboard, ClipboardHandler, ClipboardWatcher, ContentFormat};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+struct Counter(Arc<AtomicUsize>);
+
+impl ClipboardHandler for Counter {
+	fn on_clipboard_change(&mut self) {
+		self.0.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+#[test]
+fn test_start_watch_thread() {
+	let clipboard = MockClipboard::new();
+	let count = Arc::new(AtomicUsize::new(0));
+	let mut watcher =
+		PollingWatcher::new(clipboard.clone()).with_formats(vec![ContentFormat::Text]);
+	watcher.add_handler(Counter(count.clone()));
+
+	let handle = watcher.start_watch_thread(Duration::from_millis(10));
+	thread::sleep(Duration::from_millis(50));
+	assert!(!handle.is_finished());
+
+	clipboard.set_text("changed".to_string()).unwrap();
+	for _ in 0..200 {
+		if count.load(Ordering::SeqCst) > 0 {
+			break;
+		}
+		thread::sleep(Duration::from_millis(5));
+	}
+	assert_eq!(count.load(Ordering::SeqCst), 1);
+
+	handle.stop();
+	clipboard.set_text("after stop".to_string()).unwrap();
+	thread::sleep(Duration::from_millis(50));
+	assert_eq!(count.load(Ordering::SeqCst), 1);
+}
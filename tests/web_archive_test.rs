@@ -0,0 +1,49 @@
+#![cfg(target_os = "macos")]
+use clipboard_rs::{Clipboard, ClipboardContext, Rtfd, WebArchive};
+
+// web archives are usually binary property lists, the XML form decodes the same way
+const ARCHIVE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>WebMainResource</key>
+	<dict>
+		<key>WebResourceData</key>
+		<data>PHA+aGk8aW1nIHNyYz0iYS5wbmciPjwvcD4=</data>
+		<key>WebResourceMIMEType</key>
+		<string>text/html</string>
+		<key>WebResourceURL</key>
+		<string>https://example.com/</string>
+	</dict>
+	<key>WebSubresources</key>
+	<array>
+		<dict>
+			<key>WebResourceData</key>
+			<data>iVBORw0K</data>
+			<key>WebResourceMIMEType</key>
+			<string>image/png</string>
+			<key>WebResourceURL</key>
+			<string>https://example.com/a.png</string>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#;
+
+#[test]
+fn test_web_archive() {
+	assert!(WebArchive::from_bytes(b"not an archive").is_err());
+	assert!(Rtfd::from_bytes(b"not an rtfd").is_err());
+
+	let ctx = ClipboardContext::new().unwrap();
+	ctx.set_buffer("com.apple.webarchive", ARCHIVE.as_bytes().to_vec())
+		.unwrap();
+	let archive = ctx.get_web_archive().unwrap();
+	assert_eq!(archive.html, r#"<p>hi<img src="a.png"></p>"#);
+	assert_eq!(archive.url.as_deref(), Some("https://example.com/"));
+	assert_eq!(archive.resources.len(), 1);
+	assert_eq!(archive.resources[0].name, "https://example.com/a.png");
+	assert_eq!(archive.resources[0].mime.as_deref(), Some("image/png"));
+	assert_eq!(archive.resources[0].data, b"\x89PNG\r\n");
+	assert!(ctx.get_rtfd().is_err());
+}
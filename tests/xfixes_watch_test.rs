@@ -0,0 +1,46 @@
+#![cfg(all(
+	unix,
+	not(any(
+		target_os = "macos",
+		target_os = "ios",
+		target_os = "android",
+		target_os = "emscripten"
+	))
+))]
+use clipboard_rs::{
+	Clipboard, ClipboardContext, ClipboardHandler, ClipboardWatcher, ClipboardWatcherContext,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Counter(Arc<AtomicUsize>);
+
+impl ClipboardHandler for Counter {
+	fn on_clipboard_change(&mut self) {
+		self.0.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+#[test]
+fn test_xfixes_watch() {
+	let ctx = ClipboardContext::new().unwrap();
+	let count = Arc::new(AtomicUsize::new(0));
+	let mut watcher = ClipboardWatcherContext::new().unwrap();
+	watcher.add_handler(Counter(count.clone()));
+	let shutdown = watcher.get_shutdown_channel();
+	let handle = thread::spawn(move || watcher.start_watch(Duration::from_secs(2)));
+	thread::sleep(Duration::from_millis(200));
+
+	// the change arrives long before the two seconds a poll would take
+	let start = Instant::now();
+	ctx.set_text("xfixes".to_string()).unwrap();
+	while count.load(Ordering::SeqCst) == 0 && start.elapsed() < Duration::from_secs(1) {
+		thread::sleep(Duration::from_millis(5));
+	}
+	assert!(count.load(Ordering::SeqCst) >= 1);
+
+	shutdown.stop();
+	handle.join().unwrap();
+}